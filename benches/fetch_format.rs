@@ -0,0 +1,134 @@
+//! Benchmarks of the fetch and format path: item bodies deserialized into
+//! [`Story`], and a listing of them rendered as the pretty and JSON outputs.
+//!
+//! Run with `cargo bench`; criterion keeps each run's results under
+//! `target/criterion` and reports the change against the previous one.
+//!
+//! Comments: the renderers live in the binary, so the `listing/*` benchmarks
+//! run it `--offline` against a cache of the fixtures. `listing/urls` formats
+//! next to nothing and gives the cost of the run around the rendering, which
+//! the other listing benchmarks include too.
+
+use std::{fs, hint::black_box, path::Path, process::Command};
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use hn_cli::Story;
+use serde_json::json;
+
+/// Stories in the fixture set.
+const STORIES: u32 = 500;
+
+/// The item bodies the API answers for the fixture set: links with a few
+/// top-level comments, and an Ask HN text post every tenth story.
+fn items() -> Vec<String> {
+    (1..=STORIES)
+        .map(|id| {
+            let mut item = json!({
+                "id": id,
+                "type": "story",
+                "by": format!("user{}", id % 37),
+                "time": 1_700_000_000 + i64::from(id) * 60,
+                "title": format!("Story {id}: a title about as long as those on the front page"),
+                "score": id * 7919 % 900,
+                "descendants": id * 31 % 400,
+                "kids": (0..8).map(|kid| id * 100 + kid).collect::<Vec<_>>(),
+            });
+            if id % 10 == 0 {
+                item["text"] = json!(
+                    "What do you use for this? I&#x27;ve tried <i>several</i> tools.<p>A second \
+                     paragraph, with <a href=\"https://example.com\">a link</a>."
+                );
+            } else {
+                item["url"] = json!(format!("https://www.example{}.com/articles/{id}", id % 23));
+            }
+            item.to_string()
+        })
+        .collect()
+}
+
+fn stories(items: &[String]) -> Vec<Story> {
+    items
+        .iter()
+        .map(|body| serde_json::from_str(body).expect("fixtures parse as stories"))
+        .collect()
+}
+
+/// Fills `dir` the way an online run of the top stories would: the feed's
+/// ids and one file per story.
+fn fill_cache(dir: &Path, stories: &[Story]) {
+    fs::create_dir_all(dir.join("lists")).unwrap();
+    fs::create_dir_all(dir.join("items")).unwrap();
+    let ids: Vec<u32> = stories.iter().map(|story| story.id).collect();
+    fs::write(
+        dir.join("lists").join("topstories.json"),
+        serde_json::to_vec(&ids).unwrap(),
+    )
+    .unwrap();
+    for story in stories {
+        fs::write(
+            dir.join("items").join(format!("{}.json", story.id)),
+            serde_json::to_vec(story).unwrap(),
+        )
+        .unwrap();
+    }
+}
+
+fn deserialize(c: &mut Criterion) {
+    let items = items();
+    let mut group = c.benchmark_group("deserialize");
+    group.throughput(Throughput::Elements(STORIES.into()));
+    group.bench_function("items", |b| b.iter(|| stories(black_box(&items))));
+    group.finish();
+}
+
+fn render(c: &mut Criterion) {
+    let stories = stories(&items());
+    let home = std::env::temp_dir().join(format!("hn-cli-bench-{}", std::process::id()));
+    let cache = home.join("cache");
+    fill_cache(&cache, &stories);
+    listing(c, &home, &cache, &stories);
+    fs::remove_dir_all(&home).unwrap();
+}
+
+/// The `listing/*` benchmarks, with `home` for the binary's config and state
+/// and `cache` filled with `stories`.
+fn listing(c: &mut Criterion, home: &Path, cache: &Path, stories: &[Story]) {
+    let count = STORIES.to_string();
+    let mut group = c.benchmark_group("listing");
+    group.throughput(Throughput::Elements(STORIES.into()));
+    group.bench_function("json_serialize", |b| {
+        b.iter(|| serde_json::to_string_pretty(black_box(stories)).unwrap());
+    });
+    for (name, args) in [
+        ("pretty", &["--format", "pretty"][..]),
+        ("compact", &["--compact"]),
+        ("json", &["--format", "json"]),
+        ("urls", &["--url-only"]),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+                    .args([
+                        "--offline",
+                        "--no-pager",
+                        "--deterministic",
+                        "--count",
+                        &count,
+                    ])
+                    .arg("--cache-dir")
+                    .arg(cache)
+                    .args(args)
+                    .env("XDG_CONFIG_HOME", home)
+                    .env("XDG_STATE_HOME", home)
+                    .output()
+                    .expect("hn-cli binary runs");
+                assert!(output.status.success(), "{output:?}");
+                output.stdout.len()
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, deserialize, render);
+criterion_main!(benches);
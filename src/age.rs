@@ -0,0 +1,37 @@
+//! Ages given on the command line, e.g. `30m`, `12h` or `2w`: `log top
+//! --since`, `--max-age`, `--min-age` and `age` in `--where`.
+
+use std::time::Duration;
+
+/// Seconds in one `unit` of an age: `s`, `m`, `h`, `d` or `w`.
+pub fn unit_seconds(unit: &str) -> Option<u64> {
+    match unit {
+        "s" => Some(1),
+        "m" => Some(60),
+        "h" => Some(60 * 60),
+        "d" => Some(24 * 60 * 60),
+        "w" => Some(7 * 24 * 60 * 60),
+        _ => None,
+    }
+}
+
+/// Parses an age: a number followed by `s`, `m`, `h`, `d` or `w`.
+pub fn parse(age: &str) -> Result<Duration, String> {
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (count, unit) = age.split_at(split);
+    let count: u64 = count
+        .parse()
+        .map_err(|_| format!("expected an age like '7d', got '{age}'"))?;
+    let seconds = unit_seconds(unit)
+        .ok_or_else(|| format!("unknown unit in '{age}'; use s, m, h, d or w"))?;
+    Ok(Duration::from_secs(count.saturating_mul(seconds)))
+}
+
+/// A `--max-age` or `--min-age`: [`parse`], above zero, in seconds.
+pub fn parse_seconds(age: &str) -> Result<i64, String> {
+    let duration = parse(age)?;
+    if duration.is_zero() {
+        return Err(format!("expected an age above zero, got '{age}'"));
+    }
+    i64::try_from(duration.as_secs()).map_err(|_| format!("'{age}' is too large"))
+}
@@ -0,0 +1,148 @@
+//! Batched item lookups and full-text search through the Algolia HN search
+//! API.
+
+use serde::Deserialize;
+use tracing::debug_span;
+
+use crate::{HnClient, ItemType, Story, client, error::Error};
+
+/// A story as returned by the Algolia HN API, either as a search hit or from
+/// the items endpoint.
+#[derive(Deserialize, Debug)]
+struct AlgoliaStory {
+    #[serde(alias = "objectID", deserialize_with = "deserialize_algolia_id")]
+    id: u32,
+    /// Comments: `type` from the items endpoint; search hits only have the
+    /// type among their `_tags`.
+    #[serde(rename = "type", default)]
+    kind: ItemType,
+    #[serde(rename = "_tags", default)]
+    tags: Vec<String>,
+    title: Option<String>,
+    url: Option<String>,
+    points: Option<i32>,
+    author: Option<String>,
+    num_comments: Option<u32>,
+    /// Comments: `story_text` in search hits, `text` from the items endpoint.
+    #[serde(alias = "text")]
+    story_text: Option<String>,
+    created_at_i: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlgoliaSearch {
+    hits: Vec<AlgoliaStory>,
+}
+
+impl From<AlgoliaStory> for Story {
+    fn from(item: AlgoliaStory) -> Self {
+        let kind = if item.kind.is_unknown() {
+            item.tags
+                .iter()
+                .map(|tag| ItemType::from_name(tag))
+                .find(|kind| !kind.is_unknown())
+                .unwrap_or_default()
+        } else {
+            item.kind
+        };
+        Self {
+            id: item.id,
+            kind,
+            title: item.title.unwrap_or_default(),
+            url: item.url.filter(|url| !url.is_empty()),
+            score: item.points.unwrap_or_default(),
+            by: item.author.unwrap_or_default(),
+            descendants: item.num_comments,
+            text: item.story_text.filter(|text| !text.is_empty()),
+            time: item.created_at_i,
+            link_status: None,
+            archive_url: None,
+            parts: None,
+            poll_options: None,
+            kids: None,
+            top_comment: None,
+            cached: false,
+            duplicates: Vec::new(),
+            feeds: Vec::new(),
+        }
+    }
+}
+
+/// Algolia returns `objectID` as a string in search hits but `id` as a number
+/// from the items endpoint.
+fn deserialize_algolia_id<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(u32),
+        Text(String),
+    }
+
+    match Id::deserialize(deserializer)? {
+        Id::Number(id) => Ok(id),
+        Id::Text(text) => text.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+impl HnClient {
+    /// A single story from the Algolia items endpoint.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::get`]; ids Algolia doesn't know (it answers 404)
+    /// end in [`Error::Gone`], answers for another id in [`Error::Decode`].
+    pub fn algolia_item(&self, id: u32) -> Result<Story, Error> {
+        let _span = debug_span!("algolia_item", id).entered();
+        let url = format!("{}/items/{id}", self.algolia_url);
+        let item: AlgoliaStory = serde_json::from_value(client::gone_as(id, self.send(&url))?)?;
+        client::same_id(id, item.into())
+    }
+
+    /// Up to `ids.len()` stories with one Algolia search request. Ids Algolia
+    /// doesn't know about are simply absent from the result.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::get`], for the search request as a whole.
+    /// Comments: the `(story_1,story_2)` tag syntax ORs the ids together.
+    pub fn algolia_batch(&self, ids: &[u32]) -> Result<Vec<Story>, Error> {
+        let tags = ids
+            .iter()
+            .map(|id| format!("story_{id}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let _span = debug_span!("algolia_batch", size = ids.len()).entered();
+        let url = format!(
+            "{}/search?tags=({tags})&hitsPerPage={}",
+            self.algolia_url,
+            ids.len()
+        );
+        let search: AlgoliaSearch = serde_json::from_value(self.send(&url)?)?;
+        Ok(search.hits.into_iter().map(Story::from).collect())
+    }
+
+    /// Full-text search for stories matching `query`, most relevant first:
+    /// page `page` (from 0) of `per_page` hits.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::get`].
+    pub fn algolia_search(
+        &self,
+        query: &str,
+        page: usize,
+        per_page: usize,
+    ) -> Result<Vec<Story>, Error> {
+        let _span = debug_span!("algolia_search", query, page).entered();
+        let query: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+        let url = format!(
+            "{}/search?query={query}&tags=story&hitsPerPage={per_page}&page={page}",
+            self.algolia_url
+        );
+        let search: AlgoliaSearch = serde_json::from_value(self.send(&url)?)?;
+        Ok(search.hits.into_iter().map(Story::from).collect())
+    }
+}
@@ -0,0 +1,185 @@
+//! `--append` and the `log` subcommand: a growing JSON Lines archive of
+//! every story fetched, and queries over it.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, hash_map::Entry},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use colored::Colorize;
+use hn_cli::{Error, Story};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Args, Format, age, emit, eprint_line, header, page, render_badge, render_json, render_pretty,
+    render_table, render_yaml,
+    theme::{self, paint},
+};
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum LogQuery {
+    /// Rank the archived stories by their highest recorded score
+    Top {
+        /// Archive written by --append
+        file: PathBuf,
+
+        /// Only consider stories fetched within this long, e.g. '7d', '12h'
+        /// or '2w'
+        #[arg(long, value_name = "AGE", value_parser = age::parse)]
+        since: Option<Duration>,
+
+        /// Number of stories to show
+        #[arg(short, long, default_value_t = 30)]
+        count: usize,
+    },
+}
+
+/// One archived story: the story as fetched, plus when.
+#[derive(Deserialize, Serialize, Debug)]
+struct Record<'a> {
+    /// Unix seconds when the run fetched the story.
+    fetched_at: i64,
+    #[serde(flatten)]
+    story: Cow<'a, Story>,
+}
+
+/// Appends `stories` to the archive at `path`, one JSON object per line.
+/// Comments: the file is opened in append mode and each line goes out in a
+/// single `write_all`, so concurrent runs (e.g. overlapping cron jobs)
+/// interleave whole lines rather than corrupting each other.
+pub fn append(path: &Path, stories: &[Story]) -> Result<(), Error> {
+    let output_error = |source| Error::Output {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(output_error)?;
+
+    let fetched_at = Utc::now().timestamp();
+    for story in stories {
+        let record = Record {
+            fetched_at,
+            story: Cow::Borrowed(story),
+        };
+        let mut line = serde_json::to_vec(&record).expect("stories serialize to JSON");
+        line.push(b'\n');
+        file.write_all(&line).map_err(output_error)?;
+    }
+    Ok(())
+}
+
+/// Runs a `log` query and prints the result in the format `args` asks for.
+pub fn run(args: &Args, query: &LogQuery) -> Result<ExitCode, Error> {
+    let LogQuery::Top {
+        file,
+        since,
+        count,
+    } = query;
+    let cutoff =
+        since.map(|age| Utc::now().timestamp() - i64::try_from(age.as_secs()).unwrap_or(i64::MAX));
+    let contents = fs::read_to_string(file)
+        .map_err(|e| Error::Usage(format!("could not read archive {}: {e}", file.display())))?;
+
+    let (records, unreadable) = parse(&contents);
+    if unreadable > 0 {
+        let noun = if unreadable == 1 { "line" } else { "lines" };
+        eprint_line(
+            &format!(
+                "warning: skipped {unreadable} unreadable {noun} in {}",
+                file.display()
+            ),
+            theme::current().warn,
+        );
+    }
+    let considered = records
+        .into_iter()
+        .filter(|record| cutoff.is_none_or(|cutoff| record.fetched_at >= cutoff));
+    let mut stories = top(considered);
+    stories.truncate(*count);
+
+    let output = match args.format {
+        Format::Json => render_json(&stories, &[], false, None, None, args.link_fallback),
+        Format::Yaml => render_yaml(&stories, None, args.link_fallback),
+        Format::Html => page::render(&stories, &[], &HashMap::new(), args.now()),
+        Format::Badge => render_badge(&stories, args.badge_field.unwrap_or_default()),
+        Format::Pretty if args.quiet() => render_pretty(&stories, args.layout()),
+        Format::Table if args.quiet() => render_table(args, &stories),
+        Format::Pretty | Format::Table => format!(
+            "\n{}\n{}{}",
+            theme::current().header(&header(args)),
+            if args.format == Format::Table {
+                render_table(args, &stories)
+            } else {
+                render_pretty(&stories, args.layout())
+            },
+            summary_line(args, stories.len(), cutoff)
+        ),
+    };
+    emit(args, &output)?;
+    if stories.is_empty() {
+        return Err(Error::NoResults);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The readable records in an archive, and how many lines weren't.
+/// Comments: a run killed mid-write can leave a truncated last line; that
+/// costs one story, not the archive.
+fn parse(contents: &str) -> (Vec<Record<'static>>, usize) {
+    let mut unreadable = 0;
+    let records = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).map_err(|_| unreadable += 1).ok())
+        .collect();
+    (records, unreadable)
+}
+
+/// Each story once, as of its highest recorded score, highest first; ties
+/// keep archive order.
+fn top<'a>(records: impl Iterator<Item = Record<'a>>) -> Vec<Story> {
+    let mut stories: Vec<Story> = Vec::new();
+    let mut by_id = HashMap::new();
+    for record in records {
+        let story = record.story.into_owned();
+        match by_id.entry(story.id) {
+            Entry::Occupied(entry) => {
+                let best: &mut Story = &mut stories[*entry.get()];
+                if story.score > best.score {
+                    *best = story;
+                }
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(stories.len());
+                stories.push(story);
+            },
+        }
+    }
+    stories.sort_by_key(|story| std::cmp::Reverse(story.score));
+    stories
+}
+
+/// The closing line saying how many stories were ranked, and since when.
+fn summary_line(args: &Args, total: usize, cutoff: Option<i64>) -> String {
+    let noun = if total == 1 { "story" } else { "stories" };
+    let since = cutoff
+        .and_then(|cutoff| DateTime::from_timestamp(cutoff, 0))
+        .map_or_else(String::new, |time| {
+            format!(" since {}", args.format_time(time, "%Y-%m-%d %H:%M"))
+        });
+    format!(
+        "{} {}\n",
+        paint("Archive:", theme::current().good).bold(),
+        format!("top {total} {noun}{since}").dimmed()
+    )
+}
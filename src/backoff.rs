@@ -0,0 +1,84 @@
+//! Retry delays: capped exponential backoff with full jitter.
+
+use std::{
+    process,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Picks how long to wait before each retry.
+///
+/// Retry `n` (counting from 0) waits a uniformly random time between zero and
+/// `min(max, base * 2^n)`, so clients that failed together don't retry
+/// together.
+/// Comments: clones share one random sequence, which is seedable so tests
+/// can pin the schedule down.
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    state: Arc<AtomicU64>,
+}
+
+impl Default for Backoff {
+    /// 250 ms doubling up to 10 s.
+    fn default() -> Self {
+        Self::new(Duration::from_millis(250), Duration::from_secs(10))
+    }
+}
+
+impl Backoff {
+    /// Backoff from `base` up to `max`, with a jitter seed taken from the
+    /// clock.
+    #[must_use]
+    pub fn new(base: Duration, max: Duration) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+        Self::seeded(
+            base,
+            max,
+            u64::from(nanos) ^ (u64::from(process::id()) << 32),
+        )
+    }
+
+    /// Like [`Backoff::new`] but with a fixed jitter seed, for a reproducible
+    /// schedule.
+    #[must_use]
+    pub fn seeded(base: Duration, max: Duration, seed: u64) -> Self {
+        Self {
+            base,
+            max,
+            state: Arc::new(AtomicU64::new(seed)),
+        }
+    }
+
+    /// Upper bound of the delay before retry `attempt`.
+    #[must_use]
+    pub fn cap(&self, attempt: u32) -> Duration {
+        self.base
+            .saturating_mul(2_u32.saturating_pow(attempt))
+            .min(self.max)
+    }
+
+    /// The delay before retry `attempt`: a random point in `0..=cap(attempt)`.
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let cap = u64::try_from(self.cap(attempt).as_nanos()).unwrap_or(u64::MAX);
+        Duration::from_nanos(self.next() % cap.saturating_add(1))
+    }
+
+    /// `SplitMix64`: small, fast and good enough to spread retries out.
+    fn next(&self) -> u64 {
+        let mut z = self
+            .state
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,285 @@
+//! The blocklist: sites and authors left out of every listing, kept in a
+//! plain text file and edited with the `block` command.
+//!
+//! One entry per line, `domain:example.com` or `user:someguy`; `#` starts a
+//! comment. The file is `blocklist.txt` under [`paths::config_dir`], or the
+//! one the config file's `blocklist` key names.
+
+use std::{
+    fmt,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use clap::Subcommand;
+use colored::Colorize;
+use hn_cli::{Error, Story};
+
+use crate::{
+    eprint_line, output, paths,
+    theme::{self, paint},
+};
+
+/// File name of the default blocklist inside the config directory.
+const BLOCKLIST_FILE: &str = "blocklist.txt";
+
+/// The config file's `blocklist` path, if it has one; set once at startup.
+static PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// The blocklist once first read.
+static LOADED: OnceLock<Blocklist> = OnceLock::new();
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum BlockAction {
+    /// Block sites or authors, e.g. `domain:example.com` or `user:someguy`
+    Add {
+        #[arg(required = true, value_parser = Entry::parse)]
+        entries: Vec<Entry>,
+    },
+    /// Unblock sites or authors
+    #[command(visible_alias = "remove")]
+    Rm {
+        #[arg(required = true, value_parser = Entry::parse)]
+        entries: Vec<Entry>,
+    },
+    /// List what is blocked (the default)
+    List,
+}
+
+/// One line of the blocklist.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Entry {
+    /// A site and its subdomains, lowercase and without a leading `www.`
+    /// like [`Story::host`].
+    Domain(String),
+    /// An author, by exact username.
+    User(String),
+}
+
+impl Entry {
+    /// Parses `domain:HOST` or `user:NAME`.
+    fn parse(text: &str) -> Result<Self, String> {
+        let malformed = || format!("expected `domain:HOST` or `user:NAME`, got '{text}'");
+        let (kind, value) = text.split_once(':').ok_or_else(malformed)?;
+        let value = value.trim();
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            return Err(malformed());
+        }
+        match kind.trim() {
+            "domain" => {
+                let host = value.trim_end_matches('.').to_ascii_lowercase();
+                Ok(Self::Domain(
+                    host.strip_prefix("www.")
+                        .map_or_else(|| host.clone(), str::to_string),
+                ))
+            },
+            "user" => Ok(Self::User(value.to_string())),
+            _ => Err(malformed()),
+        }
+    }
+
+    fn blocks(&self, story: &Story) -> bool {
+        match self {
+            Self::Domain(domain) => story.host().is_some_and(|host| {
+                host.strip_suffix(domain.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+            }),
+            Self::User(name) => story.by == *name,
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Domain(domain) => write!(f, "domain:{domain}"),
+            Self::User(name) => write!(f, "user:{name}"),
+        }
+    }
+}
+
+/// The blocked sites and authors.
+#[derive(Clone, Debug, Default)]
+pub struct Blocklist {
+    entries: Vec<Entry>,
+}
+
+impl Blocklist {
+    /// Whether `story` links to a blocked site or has a blocked author.
+    pub fn blocks(&self, story: &Story) -> bool {
+        self.entries.iter().any(|entry| entry.blocks(story))
+    }
+
+    /// Whether nothing is blocked.
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// How many sites and how many authors are blocked.
+    pub fn counts(&self) -> (usize, usize) {
+        let domains = self
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry, Entry::Domain(_)))
+            .count();
+        (domains, self.entries.len() - domains)
+    }
+}
+
+/// Reads the blocklist from `path` instead of the default file.
+pub fn set_path(path: PathBuf) {
+    let _ = PATH.set(path);
+}
+
+/// The blocklist, read on first use.
+/// Comments: a file that can't be read is warned about once and the run
+/// carries on with nothing blocked, as for an unusable cache directory.
+pub fn current() -> &'static Blocklist {
+    LOADED.get_or_init(|| {
+        let Some(path) = path() else {
+            return Blocklist::default();
+        };
+        match read(&path) {
+            Ok(text) => parse(&path, &text),
+            Err(e) => {
+                eprint_line(
+                    &format!(
+                        "warning: can't read blocklist {} ({e}); continuing without it",
+                        path.display()
+                    ),
+                    theme::current().warn,
+                );
+                Blocklist::default()
+            },
+        }
+    })
+}
+
+/// Runs a `block` action.
+/// Comments: edits keep the rest of the file as it was, comments and
+/// malformed lines included.
+pub fn run(action: Option<&BlockAction>) -> Result<(), Error> {
+    let path = path().ok_or(Error::NoPlatformDir("config"))?;
+    let text = read(&path).map_err(|e| Error::Config {
+        path: path.clone(),
+        source: Box::new(e),
+    })?;
+    let mut blocked = parse(&path, &text).entries;
+    let good = theme::current().good;
+
+    match action {
+        Some(BlockAction::Add {
+            entries,
+        }) => {
+            let mut text = text;
+            for entry in entries {
+                if blocked.contains(entry) {
+                    println!("{} {entry} is already blocked", "•".dimmed());
+                    continue;
+                }
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                let _ = writeln!(text, "{entry}");
+                println!(
+                    "{} blocked {}",
+                    paint("✓", good).bold(),
+                    entry.to_string().bold()
+                );
+                blocked.push(entry.clone());
+            }
+            save(&path, &text)
+        },
+        Some(BlockAction::Rm {
+            entries,
+        }) => {
+            for entry in entries.iter().filter(|entry| !blocked.contains(entry)) {
+                println!("{} {entry} isn't blocked", "•".dimmed());
+            }
+            let kept: String = text
+                .split_inclusive('\n')
+                .filter(|line| {
+                    entry_text(line)
+                        .and_then(|entry| Entry::parse(entry).ok())
+                        .is_none_or(|entry| !entries.contains(&entry))
+                })
+                .collect();
+            for entry in entries.iter().filter(|entry| blocked.contains(entry)) {
+                println!(
+                    "{} unblocked {}",
+                    paint("✓", good).bold(),
+                    entry.to_string().bold()
+                );
+            }
+            save(&path, &kept)
+        },
+        Some(BlockAction::List) | None => {
+            if blocked.is_empty() {
+                println!("Nothing is blocked. Block a site with `block add domain:<HOST>`.");
+            }
+            for entry in &blocked {
+                println!("{entry}");
+            }
+            Ok(())
+        },
+    }
+}
+
+/// The config file's `blocklist`, or `blocklist.txt` in the config
+/// directory.
+fn path() -> Option<PathBuf> {
+    PATH.get()
+        .cloned()
+        .or_else(|| paths::config_dir().map(|dir| dir.join(BLOCKLIST_FILE)))
+}
+
+/// The file's text; a missing file is an empty blocklist.
+fn read(path: &Path) -> io::Result<String> {
+    match fs::read_to_string(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        read => read,
+    }
+}
+
+/// The entry on `line` without its comment, if there is one.
+fn entry_text(line: &str) -> Option<&str> {
+    let entry = line.split_once('#').map_or(line, |(entry, _)| entry).trim();
+    (!entry.is_empty()).then_some(entry)
+}
+
+/// The entries in `text`, warning about each malformed line by its number
+/// in `path`.
+fn parse(path: &Path, text: &str) -> Blocklist {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let Some(entry) = entry_text(line) else {
+            continue;
+        };
+        match Entry::parse(entry) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprint_line(
+                &format!(
+                    "warning: {}:{}: {e}; ignoring the line",
+                    path.display(),
+                    number + 1
+                ),
+                theme::current().warn,
+            ),
+        }
+    }
+    Blocklist {
+        entries,
+    }
+}
+
+fn save(path: &Path, text: &str) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|source| Error::Output {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+    }
+    output::write_atomic(path, text)
+}
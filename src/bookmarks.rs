@@ -0,0 +1,236 @@
+//! `bookmarks` subcommand: a local list of saved stories.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::Subcommand;
+use colored::Colorize;
+use hn_cli::{Error, HnClient, Story};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    count, paths, score_delta,
+    theme::{self, paint},
+};
+
+/// File name of the bookmark store inside the data directory.
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum BookmarkAction {
+    /// Save stories by id
+    Add {
+        #[arg(required = true)]
+        ids: Vec<u32>,
+    },
+    /// Remove saved stories by id
+    Remove {
+        #[arg(required = true)]
+        ids: Vec<u32>,
+    },
+    /// List saved stories (the default)
+    List,
+}
+
+/// A saved story, along with its score at the time it was saved.
+#[derive(Deserialize, Serialize, Debug)]
+struct Bookmark {
+    id: u32,
+    title: String,
+    url: Option<String>,
+    by: String,
+    saved_score: i32,
+    saved_at: u64,
+}
+
+impl Bookmark {
+    fn new(story: Story) -> Self {
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self {
+            id: story.id,
+            title: story.title,
+            url: story.url,
+            by: story.by,
+            saved_score: story.score,
+            saved_at,
+        }
+    }
+}
+
+/// Just enough of an item to tell whether it still exists and what it scores.
+#[derive(Deserialize, Debug)]
+struct ItemStatus {
+    #[serde(default)]
+    deleted: bool,
+    #[serde(default)]
+    dead: bool,
+    score: Option<i32>,
+}
+
+/// Runs a bookmarks action; `refresh` re-fetches every saved story first.
+pub fn run(client: &HnClient, action: Option<&BookmarkAction>, refresh: bool) -> Result<(), Error> {
+    let path = store_path()?;
+    let mut bookmarks = load(&path)?;
+
+    match action {
+        Some(BookmarkAction::Add {
+            ids,
+        }) => {
+            for &id in ids {
+                if bookmarks.iter().any(|bookmark| bookmark.id == id) {
+                    println!("{} {id} is already saved", "•".dimmed());
+                    continue;
+                }
+                let bookmark = Bookmark::new(client.item(id)?);
+                println!(
+                    "{} saved {}",
+                    paint("✓", theme::current().good).bold(),
+                    bookmark.title.bold()
+                );
+                bookmarks.push(bookmark);
+            }
+            save(&path, &bookmarks)
+        },
+        Some(BookmarkAction::Remove {
+            ids,
+        }) => {
+            let before = bookmarks.len();
+            bookmarks.retain(|bookmark| !ids.contains(&bookmark.id));
+            println!(
+                "{} removed {}",
+                paint("✓", theme::current().good).bold(),
+                before - bookmarks.len()
+            );
+            save(&path, &bookmarks)
+        },
+        Some(BookmarkAction::List) | None => {
+            if bookmarks.is_empty() {
+                println!("No bookmarks yet. Save one with `bookmarks add <ID>`.");
+            } else if refresh {
+                print_refreshed(client, &bookmarks);
+            } else {
+                print_saved(&bookmarks);
+            }
+            Ok(())
+        },
+    }
+}
+
+fn store_path() -> Result<PathBuf, Error> {
+    paths::data_dir()
+        .map(|dir| dir.join(BOOKMARKS_FILE))
+        .ok_or(Error::NoPlatformDir("data"))
+}
+
+fn load(path: &Path) -> Result<Vec<Bookmark>, Error> {
+    let cache_error = |source| Error::Cache {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| cache_error(io::Error::new(io::ErrorKind::InvalidData, e))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(cache_error(e)),
+    }
+}
+
+fn save(path: &Path, bookmarks: &[Bookmark]) -> Result<(), Error> {
+    let cache_error = |source| Error::Cache {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(cache_error)?;
+    }
+    let json = serde_json::to_vec_pretty(bookmarks).expect("bookmarks serialize to JSON");
+    fs::write(path, json).map_err(cache_error)
+}
+
+fn print_saved(bookmarks: &[Bookmark]) {
+    let theme = theme::current();
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        let index = paint(&format!("{:>2}.", i + 1), theme.rank).dimmed();
+        let score = theme.score(
+            &format!("[{:^4}]", count(bookmark.saved_score)),
+            bookmark.saved_score,
+        );
+        println!(
+            "{index} {score} {}",
+            paint(&bookmark.title, theme.title).bold()
+        );
+        print_details(bookmark);
+    }
+}
+
+/// Re-fetches each bookmark and shows its current score next to the saved
+/// one, e.g. `[ 120 → 342 ] +222`.
+fn print_refreshed(client: &HnClient, bookmarks: &[Bookmark]) {
+    let theme = theme::current();
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        let index = paint(&format!("{:>2}.", i + 1), theme.rank).dimmed();
+        let title = paint(&bookmark.title, theme.title).bold();
+
+        match get_item_status(client, bookmark.id) {
+            Ok(Some(current)) => {
+                let delta = score_delta(current - bookmark.saved_score);
+                let scores = theme.score(
+                    &format!(
+                        "[{:>4} → {:<4}]",
+                        count(bookmark.saved_score),
+                        count(current)
+                    ),
+                    current,
+                );
+                println!("{index} {scores} {delta} {title}");
+            },
+            Ok(None) => {
+                let scores =
+                    format!("[{:>4} → {:<4}]", count(bookmark.saved_score), "gone").dimmed();
+                println!(
+                    "{index} {scores} {} {title}",
+                    paint("deleted", theme.bad).bold()
+                );
+            },
+            Err(e) => {
+                let scores = format!("[{:>4} → {:<4}]", count(bookmark.saved_score), "?").dimmed();
+                println!(
+                    "{index} {scores} {} {title}",
+                    paint(&e.category(), theme.bad)
+                );
+            },
+        }
+        print_details(bookmark);
+    }
+}
+
+fn print_details(bookmark: &Bookmark) {
+    let theme = theme::current();
+    if let Some(url) = &bookmark.url {
+        println!(
+            "      {} {}",
+            "🔗".dimmed(),
+            paint(url, theme.url).underline()
+        );
+    }
+    println!(
+        "      {}\n",
+        paint(&format!("by {}", bookmark.by), theme.author)
+    );
+}
+
+/// Current score of an item, or `None` if it has since been deleted or
+/// killed.
+fn get_item_status(client: &HnClient, id: u32) -> Result<Option<i32>, Error> {
+    let item: Option<ItemStatus> = client.get(&format!("item/{id}.json"))?;
+    Ok(item
+        .filter(|item| !item.deleted && !item.dead)
+        .map(|item| item.score.unwrap_or_default()))
+}
@@ -0,0 +1,237 @@
+//! On-disk copy of fetched feeds and items, read back by `--offline`.
+//!
+//! Layout under [`paths::cache_dir`]:
+//! `lists/<feed>.json` holds the last id list of a feed,
+//! `items/<id>.json` the last copy of each story, and of every other item
+//! `crawl` fetched,
+//! `users/<name>.json` the profiles `--min-karma` fetched,
+//! `wayback/snapshots.json` the Wayback Machine copies found so far, by link,
+//! and `favicons/icons.json` the site icons of `--with-favicons`, by origin.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Subcommand;
+use colored::Colorize;
+use hn_cli::{Error, ItemType, Story, User};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    paths,
+    theme::{self, paint},
+};
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum CacheAction {
+    /// Print the cache directory (the default)
+    Path,
+    /// Delete every cached feed, story, user profile, Wayback Machine snapshot
+    /// and favicon
+    Clear,
+}
+
+/// Runs a `cache` action.
+pub fn run(action: Option<&CacheAction>) -> Result<(), Error> {
+    let dir = paths::cache_dir().ok_or(Error::NoPlatformDir("cache"))?;
+    match action {
+        None | Some(CacheAction::Path) => println!("{}", dir.display()),
+        Some(CacheAction::Clear) => {
+            for entry in
+                ["lists", "items", "users", "wayback", "favicons"].map(|name| dir.join(name))
+            {
+                match fs::remove_dir_all(&entry) {
+                    Ok(()) => {},
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+                    Err(source) => {
+                        return Err(Error::Cache {
+                            path: entry,
+                            source,
+                        });
+                    },
+                }
+            }
+            println!(
+                "{} cleared {}",
+                paint("✓", theme::current().good).bold(),
+                dir.display()
+            );
+        },
+    }
+    Ok(())
+}
+
+/// Saves the id list of a feed. Best effort: a cache that can't be written
+/// never fails an online run.
+pub fn store_list(feed: &str, ids: &[u32]) {
+    if let Some(path) = list_path(feed) {
+        store(&path, ids);
+    }
+}
+
+/// Saves a fetched story, best effort like [`store_list`].
+pub fn store_item(story: &Story) {
+    if let Some(path) = item_path(story.id) {
+        store(&path, story);
+    }
+}
+
+/// Saves an item of any type as the API returned it, best effort like
+/// [`store_list`].
+pub fn store_raw_item(id: u32, item: &Value) {
+    if let Some(path) = item_path(id) {
+        store(&path, item);
+    }
+}
+
+/// Whether an item is in the cache, whatever its type.
+pub fn has_item(id: u32) -> bool {
+    item_path(id).is_some_and(|path| path.exists())
+}
+
+/// The id list of a feed as last seen online.
+pub fn load_list(feed: &str) -> Result<Vec<u32>, Error> {
+    let path = list_path(feed).ok_or(Error::NoPlatformDir("cache"))?;
+    load(&path, || format!("feed {feed}"))
+}
+
+/// A story as last seen online.
+/// Comments: `crawl` caches deleted items, comments and poll options too,
+/// which end as they would online: gone, or not a story.
+pub fn load_item(id: u32) -> Result<Story, Error> {
+    let path = item_path(id).ok_or(Error::NoPlatformDir("cache"))?;
+    let item: Value = load(&path, || format!("item {id}"))?;
+    if item["deleted"] == true {
+        return Err(Error::Gone(id));
+    }
+    let story: Story = serde_json::from_value(item).map_err(|e| Error::Cache {
+        path,
+        source: io::Error::new(io::ErrorKind::InvalidData, e),
+    })?;
+    if matches!(story.kind, ItemType::Comment | ItemType::PollOpt) {
+        return Err(Error::Decode(
+            format!("item {id} is a {}, not a story", story.kind.name()).into(),
+        ));
+    }
+    if story.id != id {
+        return Err(Error::Decode(
+            format!("asked for item {id}, got item {}", story.id).into(),
+        ));
+    }
+    Ok(story)
+}
+
+/// Saves a fetched user profile, best effort like [`store_list`].
+pub fn store_user(user: &User) {
+    if let Some(path) = user_path(&user.id) {
+        store(&path, user);
+    }
+}
+
+/// A user profile as last seen online, if saved within `max_age`, or at any
+/// time for `None`.
+/// Comments: an older copy counts as not cached, so karma is fetched again
+/// now and then.
+pub fn load_user(name: &str, max_age: Option<Duration>) -> Result<User, Error> {
+    let path = user_path(name).ok_or_else(|| Error::NotCached(format!("user {name}")))?;
+    let stale = max_age.is_some_and(|max| {
+        fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|saved| saved.elapsed().unwrap_or_default() > max)
+    });
+    if stale {
+        return Err(Error::NotCached(format!("user {name}")));
+    }
+    load(&path, || format!("user {name}"))
+}
+
+/// Wayback Machine snapshots by link, as found by earlier runs; empty when
+/// none were saved or the file can't be read.
+/// Comments: only snapshots that exist are kept, since a link without one
+/// today may have one tomorrow.
+pub fn load_snapshots() -> HashMap<String, String> {
+    snapshots_path()
+        .and_then(|path| load(&path, String::new).ok())
+        .unwrap_or_default()
+}
+
+/// Saves the snapshots by link, best effort like [`store_list`].
+pub fn store_snapshots(snapshots: &HashMap<String, String>) {
+    if let Some(path) = snapshots_path() {
+        store(&path, snapshots);
+    }
+}
+
+/// Site icons as `data:` URIs by origin, as fetched by earlier runs; empty
+/// when none were saved or the file can't be read.
+/// Comments: like snapshots, only icons that were found are kept.
+pub fn load_favicons() -> HashMap<String, String> {
+    favicons_path()
+        .and_then(|path| load(&path, String::new).ok())
+        .unwrap_or_default()
+}
+
+/// Saves the icons by origin, best effort like [`store_list`].
+pub fn store_favicons(icons: &HashMap<String, String>) {
+    if let Some(path) = favicons_path() {
+        store(&path, icons);
+    }
+}
+
+fn favicons_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("favicons").join("icons.json"))
+}
+
+fn snapshots_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("wayback").join("snapshots.json"))
+}
+
+fn list_path(feed: &str) -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("lists").join(format!("{feed}.json")))
+}
+
+fn item_path(id: u32) -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join("items").join(format!("{id}.json")))
+}
+
+/// `None` for names that aren't safe as a file name, which no real account
+/// has.
+fn user_path(name: &str) -> Option<PathBuf> {
+    let safe = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'));
+    safe.then(|| paths::cache_dir().map(|dir| dir.join("users").join(format!("{name}.json"))))
+        .flatten()
+}
+
+fn store(path: &Path, value: &(impl Serialize + ?Sized)) {
+    let json = serde_json::to_vec(value).expect("cache entries serialize to JSON");
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(path, json));
+    if let Err(e) = result {
+        debug!(path = %path.display(), error = %e, "cache write failed");
+    }
+}
+
+/// Reads a cache entry; `what` names it in the error when it isn't there.
+fn load<T: DeserializeOwned>(path: &Path, what: impl FnOnce() -> String) -> Result<T, Error> {
+    let cache_error = |source| Error::Cache {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| cache_error(io::Error::new(io::ErrorKind::InvalidData, e))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(Error::NotCached(what())),
+        Err(e) => Err(cache_error(e)),
+    }
+}
@@ -0,0 +1,92 @@
+//! `--changed-only`: prints only the stories an earlier run of the same
+//! feeds didn't, for cron jobs that should stay silent when nothing is new.
+//!
+//! The ids shown are kept per feed under [`paths::state_dir`], e.g.
+//! `changed/topstories.json`. A story that was listed before but filtered
+//! out, for instance by `--min-score`, was never shown, so it is printed the
+//! first run it passes the filters. Ids drop out of the snapshot once they
+//! have left the feed.
+
+use std::{collections::BTreeSet, fs, io, path::PathBuf};
+
+use hn_cli::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{Args, fetch::Listing, output, paths};
+
+/// Directory of the snapshots inside the state directory.
+const CHANGED_DIR: &str = "changed";
+
+/// The stories shown so far, as saved to disk.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct Snapshot {
+    #[serde(skip)]
+    path: PathBuf,
+    shown: BTreeSet<u32>,
+}
+
+impl Snapshot {
+    /// Saves the snapshot for the next run.
+    /// Comments: unlike `last.json` this isn't best effort, since a snapshot
+    /// that wasn't saved makes the next run print every story again.
+    pub fn save(&self) -> Result<(), Error> {
+        let json = serde_json::to_string(self).expect("snapshots serialize to JSON");
+        self.path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|source| Error::Output {
+                path: self.path.clone(),
+                source,
+            })?;
+        output::write_atomic(&self.path, &json)
+    }
+}
+
+/// Leaves the stories of `listing` that an earlier run of its feeds already
+/// showed out of it, and returns the snapshot to save once the rest are
+/// printed.
+///
+/// # Errors
+///
+/// No state directory, or a snapshot that can't be read.
+pub fn keep_new(args: &Args, listing: &mut Listing) -> Result<Snapshot, Error> {
+    let feed: Vec<&str> = args.list.sort.iter().map(|sort| sort.feed()).collect();
+    let path = paths::state_dir()
+        .ok_or(Error::NoPlatformDir("state"))?
+        .join(CHANGED_DIR)
+        .join(format!("{}.json", feed.join("+")));
+    let cache_error = |source| Error::Cache {
+        path: path.clone(),
+        source,
+    };
+    let before: Snapshot = match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| cache_error(io::Error::new(io::ErrorKind::InvalidData, e)))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Snapshot::default(),
+        Err(e) => return Err(cache_error(e)),
+    };
+
+    let mut shown: BTreeSet<u32> = before
+        .shown
+        .iter()
+        .copied()
+        .filter(|id| listing.requested.contains(id))
+        .collect();
+    shown.extend(listing.stories.iter().map(|story| story.id));
+
+    let mut stories = std::mem::take(&mut listing.stories).into_iter();
+    for section in &mut listing.sections {
+        let listed = section.len;
+        section.len = 0;
+        for story in stories.by_ref().take(listed) {
+            if !before.shown.contains(&story.id) {
+                listing.stories.push(story);
+                section.len += 1;
+            }
+        }
+    }
+    Ok(Snapshot {
+        path,
+        shown,
+    })
+}
@@ -0,0 +1,609 @@
+//! The Firebase API client.
+
+use std::{
+    num::NonZeroU32,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tracing::{debug, debug_span, info_span, trace};
+use ureq::{
+    Agent,
+    config::{self, AutoHeaderValue},
+    http::{HeaderName, HeaderValue},
+};
+
+use crate::{
+    API_HOST, Backoff, Comment, IpFamily, ItemType, PollOpt, SortMode, Story, User,
+    error::Error,
+    rate_limit::RateLimit,
+    transport::{self, ExtraHeaders, Response, Transport, UreqTransport},
+};
+
+/// Base URL of the Algolia HN API.
+const ALGOLIA_URL: &str = "https://hn.algolia.com/api/v1";
+
+/// Base URL of the Wayback Machine API.
+const WAYBACK_URL: &str = "https://archive.org/wayback";
+
+/// Longest total `Retry-After` wait per request unless
+/// [`HnClientBuilder::max_retry_after`] says otherwise.
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_mins(1);
+
+/// Longest a link check may take, or the client timeout if shorter.
+const LINK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Redirects a link check follows before giving up.
+const MAX_LINK_REDIRECTS: u32 = 5;
+
+/// `tracing` target of the raw body of every API response, logged at trace
+/// level before it is decoded, e.g. with `RUST_LOG=hn_cli::raw=trace`; for
+/// telling what the API sent when it no longer decodes.
+pub const RAW_TARGET: &str = "hn_cli::raw";
+
+/// User agent sent unless [`HnClientBuilder::user_agent`] says otherwise.
+const DEFAULT_USER_AGENT: &str = concat!("hn-cli/", env!("CARGO_PKG_VERSION"));
+
+/// Blocking client for the Firebase HN API and Algolia batch lookups.
+///
+/// Comments: cheap to clone, so each worker thread can own one; clones share
+/// the connection pool, the rate limit and the retry counter.
+#[derive(Clone, Debug)]
+pub struct HnClient {
+    transport: Arc<dyn Transport>,
+    base_url: String,
+    pub(crate) algolia_url: String,
+    pub(crate) wayback_url: String,
+    /// Agent for [`HnClient::check_link`], separate from the transport for
+    /// its shorter timeout and redirect limit.
+    pub(crate) links: Agent,
+    ip_family: IpFamily,
+    retries: u32,
+    retry_empty_lists: bool,
+    backoff: Backoff,
+    max_retry_after: Duration,
+    rate_limit: Option<RateLimit>,
+    retries_performed: Arc<AtomicUsize>,
+}
+
+/// Configures an [`HnClient`]; see [`HnClient::builder`].
+#[derive(Debug)]
+pub struct HnClientBuilder {
+    base_url: String,
+    algolia_url: String,
+    wayback_url: String,
+    timeout: Option<Duration>,
+    user_agent: String,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    compression: bool,
+    ip_family: IpFamily,
+    retries: u32,
+    retry_empty_lists: bool,
+    backoff: Backoff,
+    max_retry_after: Duration,
+    rate_limit: Option<NonZeroU32>,
+    max_body_bytes: u64,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+impl Default for HnClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HnClient {
+    /// A client for the public API at <https://hacker-news.firebaseio.com/v0>,
+    /// with no timeout and no retries.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Starts configuring a client, e.g.
+    /// `HnClient::builder().timeout(Duration::from_secs(5)).retries(3).
+    /// build()`.
+    #[must_use]
+    pub fn builder() -> HnClientBuilder {
+        HnClientBuilder {
+            base_url: format!("https://{API_HOST}/v0"),
+            algolia_url: ALGOLIA_URL.to_string(),
+            wayback_url: WAYBACK_URL.to_string(),
+            timeout: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: Vec::new(),
+            compression: true,
+            ip_family: IpFamily::Any,
+            retries: 0,
+            retry_empty_lists: false,
+            backoff: Backoff::default(),
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+            rate_limit: None,
+            max_body_bytes: transport::DEFAULT_MAX_BODY_BYTES,
+            transport: None,
+        }
+    }
+
+    /// The Firebase API base URL requests are made against.
+    #[must_use]
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The Algolia API base URL searches and item batches are made against.
+    #[must_use]
+    pub fn algolia_url(&self) -> &str {
+        &self.algolia_url
+    }
+
+    /// The address families connections are limited to.
+    #[must_use]
+    pub const fn ip_family(&self) -> IpFamily {
+        self.ip_family
+    }
+
+    /// Retries performed so far by this client and all of its clones.
+    #[must_use]
+    pub fn retries_performed(&self) -> usize {
+        self.retries_performed.load(Ordering::Relaxed)
+    }
+
+    /// Requests so far that the rate limit held back, and how long they waited
+    /// in total, across this client and all of its clones.
+    #[must_use]
+    pub fn throttled(&self) -> (usize, Duration) {
+        self.rate_limit
+            .as_ref()
+            .map_or((0, Duration::ZERO), RateLimit::throttled)
+    }
+
+    /// Ids of the stories in a feed, in feed order.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::get`], and [`Error::EmptyFeed`] when the list
+    /// stays empty under [`HnClientBuilder::retry_empty_lists`].
+    pub fn story_ids(&self, sort: SortMode) -> Result<Vec<u32>, Error> {
+        let feed = sort.feed();
+        let _span = info_span!("list", feed).entered();
+        let mut attempt = 0;
+        loop {
+            let ids: Vec<u32> = self.get(&format!("{feed}.json"))?;
+            if !ids.is_empty() || !self.retry_empty_lists {
+                return Ok(ids);
+            }
+            if attempt == self.retries {
+                return Err(Error::EmptyFeed(feed, attempt + 1));
+            }
+            // Comments: Firebase now and then answers a feed with `[]` for a
+            // moment; that isn't a front page worth showing.
+            let delay = self.backoff.delay(attempt);
+            debug!(
+                feed,
+                attempt = attempt + 1,
+                delay_ms = delay.as_millis(),
+                "empty list, retrying"
+            );
+            self.retries_performed.fetch_add(1, Ordering::Relaxed);
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// The first `limit` stories of the front page, fetched one by one.
+    ///
+    /// # Errors
+    ///
+    /// Fails on the first request that fails, list or item.
+    pub fn top_stories(&self, limit: usize) -> Result<Vec<Story>, Error> {
+        self.stories(SortMode::Hottest, limit)
+    }
+
+    /// The `limit` newest stories, fetched one by one.
+    ///
+    /// # Errors
+    ///
+    /// Like [`HnClient::top_stories`].
+    pub fn new_stories(&self, limit: usize) -> Result<Vec<Story>, Error> {
+        self.stories(SortMode::Latest, limit)
+    }
+
+    /// A single story by id.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::get`]. Deleted and nonexistent ids end in
+    /// [`Error::Gone`]; items that aren't stories, live stories without a
+    /// title, and answers for another id, in [`Error::Decode`].
+    pub fn item(&self, id: u32) -> Result<Story, Error> {
+        let _span = debug_span!("item", id).entered();
+        let item = self.live_item(id)?;
+        // Comments: the API withholds most fields of dead items, which then
+        // read as empty; a live story without a title is the one that's broken.
+        let titled = item.get("title").is_some_and(Value::is_string) || item["dead"] == true;
+        let story = same_id(id, serde_json::from_value(item)?)?;
+        // Comments: every field but `id` may be missing, so it's the type
+        // that tells a comment or poll option apart from a story.
+        if matches!(story.kind, ItemType::Comment | ItemType::PollOpt) {
+            return Err(Error::Decode(
+                format!("item {id} is a {}, not a story", story.kind.name()).into(),
+            ));
+        }
+        if !titled {
+            return Err(Error::Decode(format!("item {id} has no title").into()));
+        }
+        Ok(story)
+    }
+
+    /// An item of any type by id, as the API returns it: a story, comment or
+    /// any other, deleted items included as the API keeps them, e.g. `{"id":
+    /// 3, "deleted": true}`.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::get`]. Ids that were never used end in
+    /// [`Error::Gone`].
+    pub fn raw_item(&self, id: u32) -> Result<Value, Error> {
+        let _span = debug_span!("raw_item", id).entered();
+        let url = format!("{}/item/{id}.json", self.base_url);
+        let item = gone_as(id, self.send(&url))?;
+        // Comments: Firebase answers `null` for ids that were never used.
+        if item.is_null() {
+            return Err(Error::Gone(id));
+        }
+        Ok(item)
+    }
+
+    /// Every option of `poll` with its votes, in the poll's order; none for
+    /// stories that aren't polls.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::item`], for the first option that fails.
+    pub fn poll_options(&self, poll: &Story) -> Result<Vec<PollOpt>, Error> {
+        let _span = debug_span!("poll_options", id = poll.id).entered();
+        poll.parts
+            .iter()
+            .flatten()
+            .map(|&id| Ok(serde_json::from_value(self.live_item(id)?)?))
+            .collect()
+    }
+
+    /// A comment by id.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::item`].
+    pub fn comment(&self, id: u32) -> Result<Comment, Error> {
+        let _span = debug_span!("comment", id).entered();
+        Ok(serde_json::from_value(self.live_item(id)?)?)
+    }
+
+    /// Item `id` as JSON, or [`Error::Gone`] if it was deleted or never
+    /// existed.
+    fn live_item(&self, id: u32) -> Result<Value, Error> {
+        let item = self.raw_item(id)?;
+        if item["deleted"] == true {
+            return Err(Error::Gone(id));
+        }
+        Ok(item)
+    }
+
+    /// A user profile by username.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::get`]; an unknown username ends in
+    /// [`Error::Decode`].
+    pub fn user(&self, name: &str) -> Result<User, Error> {
+        let _span = debug_span!("user", name).entered();
+        self.get(&format!("user/{name}.json"))
+    }
+
+    /// Fetches any API path relative to the base URL, e.g. `maxitem.json`,
+    /// and decodes it as `T`.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Network`] if the API can't be reached or answers with an
+    /// HTTP error, [`Error::Decode`] if the body isn't JSON matching `T`.
+    pub fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let url = format!("{}/{path}", self.base_url);
+        Ok(serde_json::from_value(self.send(&url)?)?)
+    }
+
+    /// Issues a GET once the rate limit allows, retrying timeouts, connection
+    /// failures, 429s and 5xx responses after a [`Backoff`] delay.
+    ///
+    /// A 429 or 503 with `Retry-After` waits as long as the server asks
+    /// instead, without using up a retry, until the waits for this request
+    /// would exceed the `Retry-After` cap.
+    pub(crate) fn send(&self, url: &str) -> Result<Value, Error> {
+        let mut attempt = 0;
+        let mut waited = Duration::ZERO;
+        loop {
+            if let Some(rate_limit) = &self.rate_limit {
+                rate_limit.acquire();
+            }
+            let error = match self.transport.get(url) {
+                Ok(response) if response.is_success() => {
+                    trace!(target: RAW_TARGET, url, "{}", String::from_utf8_lossy(&response.body));
+                    return Ok(serde_json::from_slice(&response.body)?);
+                },
+                Ok(response) => {
+                    if let Some(delay) = retry_after(&response) {
+                        if waited + delay > self.max_retry_after {
+                            return Err(Error::RateLimited {
+                                retry_after: Some(delay),
+                            });
+                        }
+                        debug!(
+                            url,
+                            status = response.status,
+                            delay_ms = delay.as_millis(),
+                            "server asked to retry after"
+                        );
+                        self.retries_performed.fetch_add(1, Ordering::Relaxed);
+                        thread::sleep(delay);
+                        waited += delay;
+                        continue;
+                    }
+                    Error::Network(ureq::Error::StatusCode(response.status))
+                },
+                Err(e) => e,
+            };
+
+            match error {
+                Error::Network(e) if attempt < self.retries && retryable(&e) => {
+                    let delay = self.backoff.delay(attempt);
+                    debug!(url, attempt = attempt + 1, delay_ms = delay.as_millis(), error = %e, "retrying");
+                    self.retries_performed.fetch_add(1, Ordering::Relaxed);
+                    thread::sleep(delay);
+                    attempt += 1;
+                },
+                Error::Network(ureq::Error::StatusCode(429)) => {
+                    return Err(Error::RateLimited {
+                        retry_after: None,
+                    });
+                },
+                error => return Err(error),
+            }
+        }
+    }
+
+    fn stories(&self, sort: SortMode, limit: usize) -> Result<Vec<Story>, Error> {
+        self.story_ids(sort)?
+            .into_iter()
+            .take(limit)
+            .map(|id| self.item(id))
+            .collect()
+    }
+}
+
+impl HnClientBuilder {
+    /// Firebase API base URL, e.g. a local mock server or a proxy.
+    #[must_use]
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Algolia API base URL, for the batched lookups.
+    #[must_use]
+    pub fn algolia_url(mut self, url: impl Into<String>) -> Self {
+        self.algolia_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Wayback Machine API base URL, for [`HnClient::wayback_snapshot`].
+    #[must_use]
+    pub fn wayback_url(mut self, url: impl Into<String>) -> Self {
+        self.wayback_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Upper bound on each request, connecting and reading included.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// `User-Agent` header, so bots can identify themselves.
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Adds a header to every request, Algolia lookups included, e.g. one an
+    /// egress proxy requires. Adding a name twice sends both values.
+    #[must_use]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Whether to ask for gzipped responses (the default). Gzipped responses
+    /// are decoded either way; turning this off helps when debugging through
+    /// a proxy.
+    #[must_use]
+    pub const fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Limits connections to one address family, e.g. [`IpFamily::V4`] on
+    /// networks where IPv6 is advertised but broken. Defaults to
+    /// [`IpFamily::Any`].
+    #[must_use]
+    pub const fn ip_family(mut self, family: IpFamily) -> Self {
+        self.ip_family = family;
+        self
+    }
+
+    /// How many times a failed request is retried before giving up.
+    #[must_use]
+    pub const fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Treats an empty feed id list as a transient failure: it is fetched
+    /// again after a [`Backoff`] delay, up to the [`retries`](Self::retries),
+    /// and [`HnClient::story_ids`] ends in [`Error::EmptyFeed`] if it stays
+    /// empty. Off by default, when an empty list is returned as it is.
+    #[must_use]
+    pub const fn retry_empty_lists(mut self, retry: bool) -> Self {
+        self.retry_empty_lists = retry;
+        self
+    }
+
+    /// Delays between retries; defaults to [`Backoff::default`].
+    #[must_use]
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Longest a single request may spend honoring `Retry-After`, all waits
+    /// together; a server asking for more ends in [`Error::RateLimited`].
+    /// Defaults to 60 seconds.
+    #[must_use]
+    pub const fn max_retry_after(mut self, max: Duration) -> Self {
+        self.max_retry_after = max;
+        self
+    }
+
+    /// Sends at most `per_second` requests per second, retries included,
+    /// across the client and all of its clones. Unlimited by default.
+    #[must_use]
+    pub const fn rate_limit(mut self, per_second: NonZeroU32) -> Self {
+        self.rate_limit = Some(per_second);
+        self
+    }
+
+    /// Fails requests whose response body is over `limit` bytes with
+    /// [`Error::BodyTooLarge`] instead of reading on. Defaults to
+    /// [`DEFAULT_MAX_BODY_BYTES`](crate::DEFAULT_MAX_BODY_BYTES).
+    #[must_use]
+    pub const fn max_body_bytes(mut self, limit: u64) -> Self {
+        self.max_body_bytes = limit;
+        self
+    }
+
+    /// Sends requests through `transport` instead of ureq, e.g. a
+    /// `MockTransport` in tests. The timeout, user agent, headers, IP family
+    /// and body size limit then no longer apply; retries and the rate limit
+    /// still do. [`HnClient::check_link`] always goes through ureq.
+    #[must_use]
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Builds the client.
+    #[must_use]
+    pub fn build(self) -> HnClient {
+        let agent_config = |timeout| {
+            let config = Agent::config_builder()
+                .timeout_global(timeout)
+                .user_agent(self.user_agent.clone())
+                .http_status_as_error(false)
+                .tls_config(transport::tls_config())
+                .ip_family(match self.ip_family {
+                    IpFamily::Any => config::IpFamily::Any,
+                    IpFamily::V4 => config::IpFamily::Ipv4Only,
+                    IpFamily::V6 => config::IpFamily::Ipv6Only,
+                });
+            if self.headers.is_empty() {
+                config
+            } else {
+                config.middleware(ExtraHeaders(self.headers.clone()))
+            }
+        };
+        let links = agent_config(Some(
+            self.timeout
+                .map_or(LINK_TIMEOUT, |timeout| timeout.min(LINK_TIMEOUT)),
+        ))
+        .max_redirects(MAX_LINK_REDIRECTS)
+        .save_redirect_history(true)
+        .build()
+        .into();
+        let transport = self.transport.clone().unwrap_or_else(|| {
+            let config = agent_config(self.timeout).accept_encoding(if self.compression {
+                AutoHeaderValue::Default
+            } else {
+                AutoHeaderValue::None
+            });
+            Arc::new(UreqTransport::new(config.build().into()).max_body_bytes(self.max_body_bytes))
+        });
+        HnClient {
+            transport,
+            base_url: self.base_url,
+            algolia_url: self.algolia_url,
+            wayback_url: self.wayback_url,
+            links,
+            ip_family: self.ip_family,
+            retries: self.retries,
+            retry_empty_lists: self.retry_empty_lists,
+            backoff: self.backoff,
+            max_retry_after: self.max_retry_after,
+            rate_limit: self.rate_limit.map(RateLimit::new),
+            retries_performed: Arc::default(),
+        }
+    }
+}
+
+/// `story` if it is item `id`, or [`Error::Decode`] when the server answered
+/// for another one.
+pub fn same_id(id: u32, story: Story) -> Result<Story, Error> {
+    if story.id != id {
+        return Err(Error::Decode(
+            format!("asked for item {id}, got item {}", story.id).into(),
+        ));
+    }
+    Ok(story)
+}
+
+/// Turns a 404 for item `id` into [`Error::Gone`].
+pub fn gone_as<T>(id: u32, result: Result<T, Error>) -> Result<T, Error> {
+    match result {
+        Err(Error::Network(ureq::Error::StatusCode(404))) => Err(Error::Gone(id)),
+        result => result,
+    }
+}
+
+/// The wait a 429 or 503 response asks for in its `Retry-After` header,
+/// given either in seconds or as an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    if !matches!(response.status, 429 | 503) {
+        return None;
+    }
+    let value = response.header("retry-after")?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    // Comments: a date in the past means "now".
+    Some(
+        SystemTime::from(date)
+            .duration_since(SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// Failures that may well succeed on a second try.
+const fn retryable(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::StatusCode(status) => *status == 429 || *status >= 500,
+        ureq::Error::Timeout(_) | ureq::Error::Io(_) | ureq::Error::ConnectionFailed => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,201 @@
+//! `comments` subcommand and the repl's `comments N`: a story's discussion
+//! as an indented tree.
+//!
+//! The tree is fetched a level at a time, each level's comments with up to
+//! `--jobs` requests at once, down to `--depth` if given.
+//! Comments: a deleted or dead comment with replies stays as `[deleted]`, so
+//! the replies keep their place; without replies it is left out, as on the
+//! site. A comment that fails to fetch is left out with its replies and
+//! reported after the tree.
+
+use std::{collections::HashMap, fmt::Write as _, num::NonZeroUsize, process::ExitCode};
+
+use colored::Colorize;
+use hn_cli::{Comment, Error, HnClient, Story};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    Args, Format, Row, age_text, count, emit,
+    fetch::{self, FetchFailure},
+    interrupt, report_failures,
+    theme::{self, paint},
+    title_style,
+};
+
+/// Spaces each level of replies is indented by.
+const INDENT: usize = 4;
+
+/// A comment and the replies under it, in the order the site shows them.
+#[derive(Serialize, Debug)]
+pub struct Thread {
+    #[serde(flatten)]
+    comment: Comment,
+    /// Deleted or dead, kept only for its replies.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    deleted: bool,
+    replies: Vec<Self>,
+}
+
+/// A story with its comment tree, as the JSON and YAML formats print it.
+#[derive(Serialize, Debug)]
+struct Discussion<'a> {
+    #[serde(flatten)]
+    story: Row<'a>,
+    comments: &'a [Thread],
+}
+
+/// Prints the discussion of story `id`, down to `depth` levels of replies.
+pub fn run(
+    args: &Args,
+    client: &HnClient,
+    id: u32,
+    depth: Option<NonZeroUsize>,
+) -> Result<ExitCode, Error> {
+    let story = client.item(id)?;
+    let (threads, failures) = fetch_tree(args, client, &story, depth);
+    let output = match args.format {
+        Format::Json => {
+            serde_json::to_string_pretty(&discussion(args, &story, &threads))
+                .expect("comments serialize to JSON")
+                + "\n"
+        },
+        Format::Yaml => serde_yaml::to_string(&discussion(args, &story, &threads))
+            .expect("comments serialize to YAML"),
+        Format::Pretty => render(args, &story, &threads),
+        Format::Table | Format::Html | Format::Badge => {
+            unreachable!("validate() rejects the story-only formats for comments")
+        },
+    };
+    emit(args, &output)?;
+    report_failures(&failures, false);
+    if interrupt::requested() {
+        return Err(Error::Interrupted);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn discussion<'a>(args: &Args, story: &'a Story, threads: &'a [Thread]) -> Discussion<'a> {
+    Discussion {
+        story: Row::new(story, args.link_fallback),
+        comments: threads,
+    }
+}
+
+/// The comment tree under `story`, and the comments that failed to fetch.
+/// Comments: Ctrl-C stops after the level in flight, with the tree so far.
+pub fn fetch_tree(
+    args: &Args,
+    client: &HnClient,
+    story: &Story,
+    depth: Option<NonZeroUsize>,
+) -> (Vec<Thread>, Vec<FetchFailure>) {
+    let mut items: HashMap<u32, Value> = HashMap::new();
+    let mut failures = Vec::new();
+    let mut level: Vec<u32> = story.kids.clone().unwrap_or_default();
+    let mut levels = 0;
+    while !level.is_empty()
+        && !interrupt::requested()
+        && depth.is_none_or(|depth| levels < depth.get())
+    {
+        let jobs: Vec<(usize, u32)> = level.iter().copied().enumerate().collect();
+        let fetched =
+            fetch::for_each_job(args, &jobs, "fetching comments", |id| client.raw_item(id));
+        let mut next = Vec::new();
+        for (index, item) in fetched {
+            match item {
+                Ok(item) => {
+                    next.extend(kids(&item));
+                    items.insert(level[index], item);
+                },
+                Err(Error::Gone(_)) => {},
+                Err(e) => failures.push(FetchFailure::new(level[index], &e)),
+            }
+        }
+        level = next;
+        levels += 1;
+    }
+    let roots = story.kids.as_deref().unwrap_or_default();
+    (build(roots, &mut items), failures)
+}
+
+/// The reply ids of a raw comment item.
+fn kids(item: &Value) -> Vec<u32> {
+    serde_json::from_value(item["kids"].clone()).unwrap_or_default()
+}
+
+/// The threads under `ids` among the fetched `items`.
+fn build(ids: &[u32], items: &mut HashMap<u32, Value>) -> Vec<Thread> {
+    ids.iter()
+        .filter_map(|id| {
+            let item = items.remove(id)?;
+            let replies = build(&kids(&item), items);
+            let deleted = item["deleted"] == true || item["dead"] == true;
+            if deleted && replies.is_empty() {
+                return None;
+            }
+            let comment = if deleted {
+                Comment {
+                    id: *id,
+                    by: String::new(),
+                    text: String::new(),
+                    time: None,
+                }
+            } else {
+                serde_json::from_value(item).ok()?
+            };
+            Some(Thread {
+                comment,
+                deleted,
+                replies,
+            })
+        })
+        .collect()
+}
+
+/// The pretty discussion: the story's title and author, then each comment
+/// under its author and age, replies indented under their parent.
+pub fn render(args: &Args, story: &Story, threads: &[Thread]) -> String {
+    let theme = theme::current();
+    let comments = story.descendants.map_or_else(String::new, |comments| {
+        format!(" — {} comments", count(comments))
+    });
+    let mut output = format!(
+        "{}\n{}\n",
+        title_style(&story.title, story.score, args.hot_at),
+        paint(&format!("by {}{comments}", story.by), theme.author)
+    );
+    for thread in threads {
+        write_thread(&mut output, args, thread, 0);
+    }
+    output
+}
+
+fn write_thread(output: &mut String, args: &Args, thread: &Thread, level: usize) {
+    let theme = theme::current();
+    let indent = " ".repeat(level * INDENT);
+    output.push('\n');
+    if thread.deleted {
+        let _ = writeln!(output, "{indent}{}", "[deleted]".dimmed());
+    } else {
+        let age = thread.comment.time.map_or_else(String::new, |time| {
+            format!(" · {}", age_text(time, args.now()))
+        });
+        let _ = writeln!(
+            output,
+            "{indent}{}{}",
+            paint(&thread.comment.by, theme.author).bold(),
+            age.dimmed()
+        );
+        for line in thread.comment.body().lines() {
+            if line.trim().is_empty() {
+                output.push('\n');
+            } else {
+                let _ = writeln!(output, "{indent}{}", line.trim_end());
+            }
+        }
+    }
+    for reply in &thread.replies {
+        write_thread(output, args, reply, level + 1);
+    }
+}
@@ -0,0 +1,123 @@
+//! The optional config file, for settings that belong to a machine rather
+//! than a single run (e.g. a cron job's rate limit).
+//!
+//! Read from `--config FILE`, or `config.toml` under [`paths::config_dir`]
+//! when that exists. Command-line flags win over the file.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use colored::Colorize;
+use hn_cli::Error;
+use serde::Deserialize;
+
+use crate::{
+    paths,
+    profile::Profile,
+    theme::{self, Preset, ThemeConfig, Thresholds, paint},
+};
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum ConfigAction {
+    /// Load the config file and report the first problem in it, such as an
+    /// unknown key or a value of the wrong type, with its line; exits 2 if
+    /// there is one
+    Check,
+}
+
+/// Settings read from the config file; every key is optional.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Requests per second, as `--rate-limit`.
+    pub rate_limit: Option<NonZeroU32>,
+    /// Where score badges turn warm and then hot.
+    pub score_colors: Thresholds,
+    /// Theme preset and color overrides.
+    pub theme: ThemeConfig,
+    /// The blocklist file, instead of `blocklist.txt` in the config
+    /// directory.
+    pub blocklist: Option<PathBuf>,
+    /// Named listings for `--profile`.
+    pub profiles: BTreeMap<String, Profile>,
+    /// Whether titles get type icons, as `--type-icons`.
+    pub type_icons: bool,
+    /// Type icons in ASCII, as `--ascii`.
+    pub ascii: bool,
+}
+
+/// Loads `path`, or the default config file if there is one.
+/// Comments: a missing default file is an empty config, but a missing
+/// `--config` file is an error.
+pub fn load(path: Option<&Path>) -> Result<Config, Error> {
+    let Some((path, required)) = location(path) else {
+        return Ok(Config::default());
+    };
+    let config_error = |source: Box<dyn std::error::Error + Send + Sync>| Error::Config {
+        path: path.clone(),
+        source,
+    };
+
+    let config: Config = match fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| config_error(Box::new(e)))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !required => Config::default(),
+        Err(e) => return Err(config_error(Box::new(e))),
+    };
+    let Thresholds {
+        warm,
+        hot,
+    } = config.score_colors;
+    if warm > hot {
+        return Err(config_error(
+            format!("score_colors: warm ({warm}) is above hot ({hot})").into(),
+        ));
+    }
+    // Comments: checked here so a bad color is reported with the file.
+    config
+        .theme
+        .resolve(Preset::default(), config.score_colors)
+        .map_err(|e| config_error(e.into()))?;
+    Ok(config)
+}
+
+/// The config file to read and whether it must exist: `path` if given,
+/// otherwise the default file, if there is a config directory.
+fn location(path: Option<&Path>) -> Option<(PathBuf, bool)> {
+    path.map_or_else(
+        || paths::config_dir().map(|dir| (dir.join("config.toml"), false)),
+        |path| Some((path.to_path_buf(), true)),
+    )
+}
+
+/// Runs `config check`: loads the config file as every run does, so a file
+/// that passes is one a run accepts.
+/// Comments: a missing default file isn't a problem, since every key has a
+/// default.
+pub fn check(path: Option<&Path>) -> Result<(), Error> {
+    let Some((file, required)) = location(path) else {
+        println!("No config directory; the defaults apply.");
+        return Ok(());
+    };
+    if !required && !file.exists() {
+        println!("No config file at {}; the defaults apply.", file.display());
+        return Ok(());
+    }
+    let config = load(Some(&file))?;
+    let profiles = match config.profiles.len() {
+        0 => String::new(),
+        1 => ", 1 profile".to_string(),
+        n => format!(", {n} profiles"),
+    };
+    println!(
+        "{} {} is valid{}",
+        paint("✓", theme::current().good).bold(),
+        file.display(),
+        profiles.dimmed()
+    );
+    Ok(())
+}
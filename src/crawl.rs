@@ -0,0 +1,161 @@
+//! `crawl`: fetching a contiguous range of item ids into the cache, for a
+//! local dataset that `--offline` and the cached copies of failed fetches
+//! read from.
+//!
+//! Every item of the range is kept as the API returns it, comments and
+//! deleted items included; ids already in the cache are skipped, so a crawl
+//! stopped by Ctrl-C, a failure or the rate limit resumes where it left off
+//! when run again.
+//! Comments: requests go through the client like a listing's, so
+//! `--rate-limit`, `--retries` and the backoff apply; only ids that were never
+//! used are left uncached, and fetched again by the next crawl of the range.
+
+use std::{
+    num::NonZeroUsize,
+    process::ExitCode,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+};
+
+use colored::Colorize;
+use hn_cli::{Error, HnClient};
+
+use crate::{
+    Args, cache,
+    fetch::{self, FetchFailure, INTERRUPT_POLL},
+    interrupt, logging, paths, report_failures,
+    theme::{self, paint},
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct CrawlArgs {
+    /// First item id to fetch
+    #[arg(long, value_name = "ID")]
+    from: u32,
+
+    /// Last item id to fetch, inclusive
+    #[arg(long, value_name = "ID")]
+    to: u32,
+
+    /// Number of items to fetch concurrently
+    #[arg(short, long, default_value = "8")]
+    jobs: NonZeroUsize,
+}
+
+/// How the ids of a crawl ended up.
+#[derive(Default)]
+struct Tally {
+    /// Fetched and written to the cache.
+    fetched: usize,
+    /// Never used, so there was nothing to cache.
+    missing: usize,
+    failures: Vec<FetchFailure>,
+}
+
+/// Fetches every id of the range that isn't cached yet and caches it, behind
+/// a progress bar, then says how the range went.
+pub fn run(args: &Args, client: &HnClient, crawl: &CrawlArgs) -> Result<ExitCode, Error> {
+    if crawl.from > crawl.to {
+        return Err(Error::Usage(format!(
+            "--from {} is past --to {}; give the lower id first",
+            crawl.from, crawl.to
+        )));
+    }
+    paths::cache_dir().ok_or(Error::NoPlatformDir("cache"))?;
+    let ids: Arc<[u32]> = (crawl.from..=crawl.to)
+        .filter(|&id| !cache::has_item(id))
+        .collect();
+    let cached = (crawl.to - crawl.from) as usize + 1 - ids.len();
+
+    interrupt::install();
+    let pb = fetch::progress_bar(args, ids.len());
+    logging::attach(&pb);
+    let tally = fetch_range(client, &ids, crawl.jobs, &pb);
+    pb.finish_and_clear();
+    logging::detach();
+    let tally = tally?;
+
+    if !args.quiet {
+        println!(
+            "{} {}",
+            paint("✓", theme::current().good).bold(),
+            format!(
+                "crawled {}–{}: {} fetched, {cached} already cached, {} never used, {} failed",
+                crawl.from,
+                crawl.to,
+                tally.fetched,
+                tally.missing,
+                tally.failures.len()
+            )
+            .dimmed()
+        );
+    }
+    report_failures(&tally.failures, false);
+    if interrupt::requested() {
+        return Err(Error::Interrupted);
+    }
+    if !tally.failures.is_empty() {
+        return Err(Error::CrawlIncomplete(tally.failures.len()));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Fetches `ids` with up to `jobs` concurrent requests, caching each item as
+/// it arrives.
+/// Comments: like a listing's fetch, the workers are detached, so a rate
+/// limit or Ctrl-C stops the crawl without waiting on requests in flight;
+/// what arrived before stays cached.
+fn fetch_range(
+    client: &HnClient,
+    ids: &Arc<[u32]>,
+    jobs: NonZeroUsize,
+    pb: &indicatif::ProgressBar,
+) -> Result<Tally, Error> {
+    let (tx, rx) = mpsc::channel();
+    let next = Arc::new(AtomicUsize::new(0));
+    for _ in 0..jobs.get().min(ids.len()) {
+        let (client, tx, next, ids) = (client.clone(), tx.clone(), next.clone(), ids.clone());
+        thread::spawn(move || {
+            while !interrupt::requested() {
+                let Some(&id) = ids.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                    break;
+                };
+                if tx.send((id, client.raw_item(id))).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let mut tally = Tally::default();
+    loop {
+        let (id, result) = match rx.recv_timeout(INTERRUPT_POLL) {
+            Ok(received) => received,
+            Err(RecvTimeoutError::Timeout) if interrupt::requested() => break,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        pb.inc(1);
+        pb.set_message(format!("#{id}"));
+        match result {
+            Ok(item) => {
+                cache::store_raw_item(id, &item);
+                tally.fetched += 1;
+            },
+            Err(Error::Gone(_)) => tally.missing += 1,
+            Err(e) if fetch::aborts(&e, false) => {
+                return Err(Error::Item {
+                    id,
+                    source: Box::new(e),
+                });
+            },
+            Err(e) => tally.failures.push(FetchFailure::new(id, &e)),
+        }
+    }
+    Ok(tally)
+}
@@ -0,0 +1,225 @@
+//! `diff`: what changed between two saved runs of a feed, e.g. while you
+//! were away: the stories that entered the list, those that dropped off and
+//! those that moved by more than `--moved-by` places.
+
+use std::{collections::HashMap, fmt::Write as _, path::PathBuf, process::ExitCode};
+
+use chrono::DateTime;
+use colored::Colorize;
+use hn_cli::{Error, SortMode, Story};
+use serde::Serialize;
+
+use crate::{
+    Args, Format, count, emit, header,
+    last::{self, LastRun},
+    theme::{self, paint},
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct DiffArgs {
+    /// Saved runs to compare, older first, e.g. copies of last.json; by
+    /// default the two most recent runs of --sort
+    #[arg(num_args = 2, value_names = ["OLD", "NEW"])]
+    files: Vec<PathBuf>,
+
+    /// Feed whose saved runs to compare
+    #[arg(short, long, value_enum, default_value_t = SortMode::Hottest, conflicts_with = "files")]
+    sort: SortMode,
+
+    /// Only report stories whose rank changed by more than N places
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    moved_by: usize,
+}
+
+/// A story in one of the sections, with its rank in each run it is in.
+#[derive(Serialize, Debug)]
+struct Change<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<usize>,
+    story: &'a Story,
+}
+
+/// The differences between two runs, as `--format json` prints them.
+#[derive(Serialize, Debug)]
+struct Diff<'a> {
+    feed: &'a str,
+    /// Unix seconds when each run finished.
+    old_fetched_at: i64,
+    new_fetched_at: i64,
+    entered: Vec<Change<'a>>,
+    dropped: Vec<Change<'a>>,
+    moved: Vec<Change<'a>>,
+}
+
+/// Compares two saved runs and prints what changed.
+pub fn run(args: &Args, diff: &DiffArgs) -> Result<ExitCode, Error> {
+    if !matches!(args.format, Format::Pretty | Format::Json) {
+        return Err(Error::Usage(
+            "diff only prints '--format pretty' or '--format json'".to_string(),
+        ));
+    }
+    let (old, new) = if let [old, new] = diff.files.as_slice() {
+        (last::load(old)?, last::load(new)?)
+    } else {
+        let feed = diff.sort.feed();
+        let dir = last::runs_dir(feed).ok_or(Error::NoPlatformDir("state"))?;
+        let runs = last::saved_runs(&dir);
+        let [.., old, new] = runs.as_slice() else {
+            return Err(Error::Usage(format!(
+                "diff needs two saved runs of {feed}, found {}; list it again later, or name \
+                 two saved runs",
+                runs.len()
+            )));
+        };
+        (last::load(old)?, last::load(new)?)
+    };
+
+    let changes = compare(&old, &new, diff.moved_by);
+    let output = match args.format {
+        Format::Json => {
+            serde_json::to_string_pretty(&changes).expect("diffs serialize to JSON") + "\n"
+        },
+        _ => render(args, &changes, diff.moved_by),
+    };
+    emit(args, &output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The stories new to `new`, those missing from it, and those whose rank
+/// changed by more than `moved_by`, each in the order of the run it is
+/// ranked in; moved stories as of `new`.
+fn compare<'a>(old: &'a LastRun, new: &'a LastRun, moved_by: usize) -> Diff<'a> {
+    let ranks = |run: &'a LastRun| -> HashMap<u32, usize> {
+        run.stories
+            .iter()
+            .enumerate()
+            .map(|(index, story)| (story.id, index + 1))
+            .collect()
+    };
+    let (old_ranks, new_ranks) = (ranks(old), ranks(new));
+
+    let mut entered = Vec::new();
+    let mut moved = Vec::new();
+    for (index, story) in new.stories.iter().enumerate() {
+        let to = index + 1;
+        match old_ranks.get(&story.id) {
+            None => entered.push(Change {
+                from: None,
+                to: Some(to),
+                story,
+            }),
+            Some(&from) if from.abs_diff(to) > moved_by => moved.push(Change {
+                from: Some(from),
+                to: Some(to),
+                story,
+            }),
+            Some(_) => {},
+        }
+    }
+    let dropped = old
+        .stories
+        .iter()
+        .enumerate()
+        .filter(|(_, story)| !new_ranks.contains_key(&story.id))
+        .map(|(index, story)| Change {
+            from: Some(index + 1),
+            to: None,
+            story,
+        })
+        .collect();
+
+    Diff {
+        feed: &new.feed,
+        old_fetched_at: old.fetched_at,
+        new_fetched_at: new.fetched_at,
+        entered,
+        dropped,
+        moved,
+    }
+}
+
+/// The pretty diff: a heading and one line per story for each section,
+/// with the header and a closing line saying which runs were compared
+/// unless quiet.
+fn render(args: &Args, diff: &Diff, moved_by: usize) -> String {
+    let theme = theme::current();
+    let mut output = String::new();
+    if !args.quiet() {
+        let _ = writeln!(output, "\n{}\n", theme.header(&header(args)));
+    }
+    for (title, changes) in [
+        ("Entered the list".to_string(), &diff.entered),
+        ("Dropped off".to_string(), &diff.dropped),
+        (format!("Moved more than {moved_by} places"), &diff.moved),
+    ] {
+        let noun = if changes.len() == 1 {
+            "story"
+        } else {
+            "stories"
+        };
+        let _ = writeln!(
+            output,
+            "{} {}",
+            paint(&format!("▸ {title}"), theme.badge).bold(),
+            format!("{} {noun}", changes.len()).dimmed()
+        );
+        for change in changes {
+            output.push_str(&line(change));
+        }
+        output.push('\n');
+    }
+    if !args.quiet() {
+        let when = |time| {
+            DateTime::from_timestamp(time, 0).map_or_else(
+                || "an unknown time".to_string(),
+                |time| args.format_time(time, "%Y-%m-%d %H:%M"),
+            )
+        };
+        let _ = writeln!(
+            output,
+            "{} {}",
+            paint("Compared:", theme.good).bold(),
+            format!(
+                "{} fetched {} and {}",
+                diff.feed,
+                when(diff.old_fetched_at),
+                when(diff.new_fetched_at)
+            )
+            .dimmed()
+        );
+    }
+    output
+}
+
+/// One story of a section: its rank (in the old run for a dropped story),
+/// title, site and score, and how far a moved story went.
+fn line(change: &Change) -> String {
+    let theme = theme::current();
+    let story = change.story;
+    let rank = change.to.or(change.from).unwrap_or_default();
+    let mut line = format!(
+        "{} {}",
+        format!("{rank:>3}.").dimmed(),
+        paint(&story.title, theme.title).bold()
+    );
+    if let Some(host) = story.host() {
+        let _ = write!(line, " {}", paint(&format!("({host})"), theme.url));
+    }
+    let _ = write!(
+        line,
+        " {}",
+        format!("{} points", count(story.score)).dimmed()
+    );
+    if let (Some(from), Some(to)) = (change.from, change.to) {
+        let movement = if to < from {
+            paint(&format!("↑{}", from - to), theme.good)
+        } else {
+            paint(&format!("↓{}", to - from), theme.bad)
+        };
+        let _ = write!(line, " {movement} {}", format!("from {from}").dimmed());
+    }
+    line.push('\n');
+    line
+}
@@ -0,0 +1,327 @@
+//! `doctor` subcommand: step-by-step connectivity diagnostics.
+
+#[cfg(feature = "tls-rustls")]
+use std::sync::Arc;
+use std::{
+    error, fs,
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use hn_cli::{Error, HnClient, IpFamily, SortMode, TLS_BACKEND};
+#[cfg(feature = "tls-rustls")]
+use rustls::{ClientConfig, ClientConnection, RootCertStore, pki_types::ServerName};
+use url::Url;
+
+use crate::{
+    paths,
+    theme::{self, paint},
+};
+
+/// Timeout applied to the raw TCP and TLS checks.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An item that is known to exist: the first YC application story.
+const KNOWN_ITEM: u32 = 8863;
+
+/// Runs every check in order and returns whether all of them passed.
+/// `tls_info` adds what the handshake negotiated, and with which backend,
+/// under the TLS check.
+/// Comments: checks that depend on an earlier failed step are reported as
+/// skipped rather than run against nothing.
+pub fn run(client: &HnClient, tls_info: bool) -> bool {
+    println!(
+        "\n{}\n",
+        theme::current().header(" 🩺 Hacker News CLI doctor ")
+    );
+
+    let mut failures = 0;
+
+    let target = check(&mut failures, "API base URL", || target(client.base_url()));
+    let host = target
+        .as_ref()
+        .map_or("API host", |target| target.host.as_str());
+    let port = target.as_ref().map_or(443, |target| target.port);
+    let tls = target.as_ref().is_some_and(|target| target.tls);
+
+    let addrs = check_after(
+        &mut failures,
+        &format!("DNS resolution of {host}"),
+        target.as_ref(),
+        |target| resolve(&target.host, target.port, client.ip_family()),
+    );
+    let stream = check_after(
+        &mut failures,
+        &format!("TCP connect on port {port}"),
+        addrs,
+        |addrs| connect(&addrs),
+    );
+    // Comments: a plain-http base URL (e.g. a local mock) has no handshake.
+    let negotiated = check_after(
+        &mut failures,
+        "TLS handshake",
+        stream.filter(|_| tls),
+        |stream| handshake(stream, host),
+    );
+    if tls_info {
+        println!("     {}", format!("backend: {TLS_BACKEND}").dimmed());
+        for (name, value) in negotiated.iter().flatten() {
+            println!("     {}", format!("{name}: {value}").dimmed());
+        }
+    }
+
+    check(&mut failures, "GET topstories.json", || {
+        let ids = client.story_ids(SortMode::Hottest)?;
+        Ok(((), format!("{} ids", ids.len())))
+    });
+    check(&mut failures, &format!("GET item {KNOWN_ITEM}"), || {
+        let story = client.item(KNOWN_ITEM)?;
+        Ok(((), story.title))
+    });
+
+    check(&mut failures, "Cache directory writable", || {
+        writable(paths::cache_dir())
+    });
+    check(&mut failures, "State directory writable", || {
+        writable(paths::state_dir())
+    });
+
+    println!();
+    if failures == 0 {
+        println!(
+            "{}",
+            paint("All checks passed!", theme::current().good).bold()
+        );
+    } else {
+        let noun = if failures == 1 { "check" } else { "checks" };
+        println!(
+            "{}",
+            paint(&format!("{failures} {noun} failed"), theme::current().bad).bold()
+        );
+    }
+
+    failures == 0
+}
+
+/// Runs one check, printing a ✓/✗ line with its timing and detail.
+fn check<T>(
+    failures: &mut usize,
+    name: &str,
+    f: impl FnOnce() -> Result<(T, String), Box<dyn error::Error>>,
+) -> Option<T> {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = format!("({} ms)", start.elapsed().as_millis()).dimmed();
+
+    match result {
+        Ok((value, detail)) => {
+            println!(
+                " {} {name} {elapsed} {}",
+                paint("✓", theme::current().good).bold(),
+                detail.dimmed()
+            );
+            Some(value)
+        },
+        Err(e) => {
+            *failures += 1;
+            println!(
+                " {} {name} {elapsed}",
+                paint("✗", theme::current().bad).bold()
+            );
+            println!("     {}", paint(&e.to_string(), theme::current().bad));
+            None
+        },
+    }
+}
+
+/// Runs a check that needs the output of an earlier one, or reports it as
+/// skipped (without counting a separate failure) when that one failed.
+fn check_after<D, T>(
+    failures: &mut usize,
+    name: &str,
+    dependency: Option<D>,
+    f: impl FnOnce(D) -> Result<(T, String), Box<dyn error::Error>>,
+) -> Option<T> {
+    let Some(dependency) = dependency else {
+        println!(" {} {name} {}", "-".dimmed(), "(skipped)".dimmed());
+        return None;
+    };
+    check(failures, name, || f(dependency))
+}
+
+/// Where the API lives, from the client's base URL.
+struct Target {
+    host: String,
+    port: u16,
+    tls: bool,
+}
+
+fn target(base_url: &str) -> Result<(Target, String), Box<dyn error::Error>> {
+    let url = Url::parse(base_url)?;
+    let target = Target {
+        host: url.host_str().ok_or("base URL has no host")?.to_string(),
+        port: url.port_or_known_default().ok_or("base URL has no port")?,
+        tls: url.scheme() == "https",
+    };
+    Ok((target, base_url.to_string()))
+}
+
+/// Resolves `host`, keeping only the addresses `family` allows, the way the
+/// client does.
+fn resolve(
+    host: &str,
+    port: u16,
+    family: IpFamily,
+) -> Result<(Vec<SocketAddr>, String), Box<dyn error::Error>> {
+    let resolved: Vec<SocketAddr> = (host, port).to_socket_addrs()?.collect();
+    let addrs: Vec<SocketAddr> = resolved
+        .iter()
+        .copied()
+        .filter(|addr| family.allows(addr))
+        .collect();
+    if addrs.is_empty() {
+        return Err(match family {
+            IpFamily::Any => format!("{host} resolved to no addresses"),
+            IpFamily::V4 | IpFamily::V6 => format!(
+                "{host} resolved to no {} addresses ({} of other families)",
+                family_name(family),
+                resolved.len()
+            ),
+        }
+        .into());
+    }
+    let detail = addrs
+        .iter()
+        .map(|addr| addr.ip().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok((addrs, detail))
+}
+
+/// Connects to the first reachable address, in resolver order like the
+/// client, and says which family the connection used.
+/// Comments: addresses that failed first are listed too; a slow IPv6 failure
+/// before an IPv4 success is the usual sign of broken IPv6.
+fn connect(addrs: &[SocketAddr]) -> Result<(TcpStream, String), Box<dyn error::Error>> {
+    let mut failed = Vec::new();
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, CHECK_TIMEOUT) {
+            Ok(stream) => {
+                let family = if addr.is_ipv4() { "IPv4" } else { "IPv6" };
+                let detail = if failed.is_empty() {
+                    format!("{addr} over {family}")
+                } else {
+                    format!("{addr} over {family}, after {} failed", failed.join(", "))
+                };
+                return Ok((stream, detail));
+            },
+            Err(e) => failed.push(format!("{addr} ({e})")),
+        }
+    }
+    Err(failed
+        .pop()
+        .unwrap_or_else(|| "no addresses to connect to".to_string())
+        .into())
+}
+
+const fn family_name(family: IpFamily) -> &'static str {
+    match family {
+        IpFamily::Any => "IP",
+        IpFamily::V4 => "IPv4",
+        IpFamily::V6 => "IPv6",
+    }
+}
+
+/// Facts about a completed handshake, for `--tls-info`.
+type Negotiated = Vec<(&'static str, String)>;
+
+/// Completes a TLS handshake against the API host using the same rustls
+/// provider and webpki roots as ureq.
+#[cfg(feature = "tls-rustls")]
+fn handshake(
+    mut stream: TcpStream,
+    host: &str,
+) -> Result<(Negotiated, String), Box<dyn error::Error>> {
+    stream.set_read_timeout(Some(CHECK_TIMEOUT))?;
+    stream.set_write_timeout(Some(CHECK_TIMEOUT))?;
+
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+    let server_name = ServerName::try_from(host.to_string())?;
+    let mut conn = ClientConnection::new(Arc::new(config), server_name)?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut stream)?;
+    }
+
+    let version = conn
+        .protocol_version()
+        .map_or_else(|| "unknown version".to_string(), |v| format!("{v:?}"));
+    let negotiated = vec![
+        ("protocol", version.clone()),
+        (
+            "cipher suite",
+            conn.negotiated_cipher_suite().map_or_else(
+                || "unknown".to_string(),
+                |suite| format!("{:?}", suite.suite()),
+            ),
+        ),
+        (
+            "certificate chain",
+            conn.peer_certificates().map_or(0, <[_]>::len).to_string(),
+        ),
+        ("roots", "bundled webpki roots".to_string()),
+    ];
+    Ok((negotiated, version))
+}
+
+/// Completes a TLS handshake against the API host through the platform TLS
+/// library and certificate store, as ureq does in this build.
+/// Comments: native-tls doesn't expose the negotiated protocol version.
+#[cfg(all(feature = "tls-native", not(feature = "tls-rustls")))]
+fn handshake(stream: TcpStream, host: &str) -> Result<(Negotiated, String), Box<dyn error::Error>> {
+    stream.set_read_timeout(Some(CHECK_TIMEOUT))?;
+    stream.set_write_timeout(Some(CHECK_TIMEOUT))?;
+
+    let connector = native_tls::TlsConnector::new()?;
+    let tls = connector.connect(host, stream).map_err(|e| e.to_string())?;
+
+    let certificate = tls
+        .peer_certificate()?
+        .map_or_else(|| "none".to_string(), |_| "presented".to_string());
+    let negotiated = vec![
+        ("protocol", "not exposed by native-tls".to_string()),
+        ("server certificate", certificate),
+        ("roots", "platform certificate store".to_string()),
+    ];
+    Ok((negotiated, "verified by the platform store".to_string()))
+}
+
+/// Creates the directory if needed and round-trips a probe file through it.
+fn writable(dir: Option<impl AsRef<Path>>) -> Result<((), String), Box<dyn error::Error>> {
+    let dir = dir.ok_or("no platform directory available")?;
+    let dir = dir.as_ref();
+    let cache_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| Error::Cache {
+            path,
+            source,
+        }
+    };
+
+    fs::create_dir_all(dir).map_err(cache_error(dir))?;
+
+    let probe = dir.join(".doctor-probe");
+    fs::write(&probe, b"ok").map_err(cache_error(&probe))?;
+    fs::remove_file(&probe).map_err(cache_error(&probe))?;
+
+    Ok(((), dir.display().to_string()))
+}
@@ -0,0 +1,284 @@
+//! Error type shared by every command, with stable process exit codes.
+
+use std::{io, path::PathBuf, time::Duration};
+
+use thiserror::Error;
+
+/// Everything that can make a run fail.
+/// Comments: each variant maps to a documented exit code (see
+/// [`Error::exit_code`]) so scripts can tell failures apart.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Invalid combination of arguments that clap can't express.
+    #[error("{0}")]
+    Usage(String),
+
+    /// The API could not be reached or answered with an HTTP error.
+    #[error("network error: {0}")]
+    Network(#[source] ureq::Error),
+
+    /// The server kept answering 429, or asked to wait longer than the
+    /// client's `Retry-After` cap allows.
+    #[error("rate limited by server{}", wait_hint(*.retry_after))]
+    RateLimited {
+        /// The wait the server last asked for, if it said.
+        retry_after: Option<Duration>,
+    },
+
+    /// The API answered with a body that isn't the JSON we expect.
+    #[error("could not decode API response: {0}")]
+    Decode(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// A response body was larger than the transport's limit, in bytes; see
+    /// [`HnClientBuilder::max_body_bytes`](crate::HnClientBuilder::max_body_bytes).
+    #[error("response body exceeds the {0}-byte limit")]
+    BodyTooLarge(u64),
+
+    /// The item was deleted, or there is no item with that id.
+    #[error("item {0} is deleted or doesn't exist")]
+    Gone(u32),
+
+    /// The run succeeded but nothing was left to show.
+    #[error("no stories matched")]
+    NoResults,
+
+    /// The feed's id list stayed empty through every retry of
+    /// [`HnClientBuilder::retry_empty_lists`](crate::HnClientBuilder::retry_empty_lists).
+    #[error("no stories returned by {0} after {1} attempts")]
+    EmptyFeed(&'static str, u32),
+
+    /// The platform has no directory of the given kind (e.g. `$HOME` unset).
+    #[error("no platform {0} directory available")]
+    NoPlatformDir(&'static str),
+
+    /// Fewer stories than `--require` asked for made it to the output.
+    #[error("only {found} of the {required} required stories were fetched")]
+    TooFewResults {
+        /// The `--require` minimum.
+        required: usize,
+        /// Stories that were actually shown.
+        found: usize,
+    },
+
+    /// A single item failed and `--fail-fast` aborted the run.
+    #[error("item {id} failed to fetch: {source}")]
+    Item {
+        /// The item that failed.
+        id: u32,
+        /// Why it failed.
+        #[source]
+        source: Box<Self>,
+    },
+
+    /// Ctrl-C stopped the run early; partial results were already printed.
+    #[error("interrupted")]
+    Interrupted,
+
+    /// Some items failed to fetch and `--strict` was given.
+    #[error("{0} item(s) failed to fetch (--strict)")]
+    Incomplete(usize),
+
+    /// Some items of a `crawl` failed to fetch; running it again retries
+    /// them.
+    #[error("{0} item(s) failed to fetch; run the crawl again to retry them")]
+    CrawlIncomplete(usize),
+
+    /// A feed lists fewer stories than `--count` asked for and `--strict`
+    /// was given.
+    #[error("{feed} lists {available} stories, fewer than the {requested} asked for (--strict)")]
+    ShortFeed {
+        /// The feed's endpoint, e.g. `topstories`.
+        feed: &'static str,
+        /// The `--count`.
+        requested: usize,
+        /// The ids the feed listed.
+        available: usize,
+    },
+
+    /// `--offline` needed something that was never fetched online.
+    #[error("{0} is not in the offline cache; run once without --offline first")]
+    NotCached(String),
+
+    /// The config file could not be read or isn't valid.
+    #[error("config file {}: {source}", path.display())]
+    Config {
+        /// The config file.
+        path: PathBuf,
+        /// The read or parse error.
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The `--output` or `--append` file, or stdout, could not be written.
+    #[error("could not write {}: {source}", path.display())]
+    Output {
+        /// The output file, or `<stdout>`.
+        path: PathBuf,
+        /// The underlying I/O error.
+        #[source]
+        source: io::Error,
+    },
+
+    /// The `--pipe-each` command failed for this many stories.
+    #[error("--pipe-each failed for {0} story(s)")]
+    PipeFailed(usize),
+
+    /// The `--exec` command failed for this many stories.
+    #[error("--exec failed for {0} story(s)")]
+    ExecFailed(usize),
+
+    /// The terminal could not be read, or a browser could not be started,
+    /// in `repl` or for `--pick`.
+    #[error("{action}: {source}")]
+    Interactive {
+        /// What was being done, e.g. `can't read the command`.
+        action: String,
+        /// The terminal or launcher error.
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A cache or state directory could not be read or written.
+    #[error("cache error at {}: {source}", path.display())]
+    Cache {
+        /// The file or directory involved.
+        path: PathBuf,
+        /// The underlying I/O error.
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// Process exit code for this error.
+    ///
+    /// | Code | Meaning |
+    /// | :--- | :--- |
+    /// | 1 | generic failure (e.g. a `doctor` check failed, or `repl` or `--pick` couldn't read the terminal or open a browser) |
+    /// | 2 | usage error or invalid config file |
+    /// | 3 | network error |
+    /// | 4 | no results, fewer than `--require`, or the item is gone |
+    /// | 5 | API decode error, or a response body over the size limit |
+    /// | 6 | cache error, or not cached under `--offline` |
+    /// | 7 | some items failed to fetch, or a feed listed fewer than `--count`, under `--strict`; or some items of a `crawl` failed |
+    /// | 8 | rate limited by the server |
+    /// | 9 | the `--output` or `--append` file could not be written |
+    /// | 10 | the `--pipe-each` or `--exec` command failed for some stories |
+    /// | 130 | interrupted by Ctrl-C |
+    ///
+    /// An item error under `--fail-fast` uses the code of its underlying
+    /// error.
+    #[must_use]
+    pub const fn exit_code(&self) -> u8 {
+        match self {
+            Self::Interactive {
+                ..
+            } => 1,
+            Self::Usage(_)
+            | Self::Config {
+                ..
+            } => 2,
+            Self::Network(_) => 3,
+            Self::NoResults
+            | Self::EmptyFeed(..)
+            | Self::Gone(_)
+            | Self::TooFewResults {
+                ..
+            } => 4,
+            Self::Decode(_) | Self::BodyTooLarge(_) => 5,
+            Self::Cache {
+                ..
+            }
+            | Self::NoPlatformDir(_)
+            | Self::NotCached(_) => 6,
+            Self::Incomplete(_)
+            | Self::CrawlIncomplete(_)
+            | Self::ShortFeed {
+                ..
+            } => 7,
+            Self::RateLimited {
+                ..
+            } => 8,
+            Self::Output {
+                ..
+            } => 9,
+            Self::PipeFailed(_) | Self::ExecFailed(_) => 10,
+            // Conventional exit code for a process terminated by SIGINT.
+            Self::Interrupted => 130,
+            Self::Item {
+                source, ..
+            } => source.exit_code(),
+        }
+    }
+
+    /// Short label used in per-item failure summaries, e.g. `timeout`,
+    /// `decode` or `HTTP 500`.
+    #[must_use]
+    pub fn category(&self) -> String {
+        match self {
+            Self::Network(ureq::Error::Timeout(_)) => "timeout".to_string(),
+            Self::Network(ureq::Error::StatusCode(status)) => format!("HTTP {status}"),
+            Self::Network(_) => "network".to_string(),
+            Self::Decode(_) => "decode".to_string(),
+            Self::BodyTooLarge(_) => "too large".to_string(),
+            Self::Gone(_) => "missing".to_string(),
+            Self::RateLimited {
+                ..
+            } => "rate limited".to_string(),
+            Self::Usage(_) => "usage".to_string(),
+            Self::Interactive {
+                ..
+            } => "interactive".to_string(),
+            Self::Config {
+                ..
+            } => "config".to_string(),
+            Self::NoResults
+            | Self::EmptyFeed(..)
+            | Self::TooFewResults {
+                ..
+            } => "no results".to_string(),
+            Self::Output {
+                ..
+            } => "output".to_string(),
+            Self::Cache {
+                ..
+            } => "cache".to_string(),
+            Self::NoPlatformDir(_) => "no directory".to_string(),
+            Self::NotCached(_) => "not cached".to_string(),
+            Self::Incomplete(_)
+            | Self::CrawlIncomplete(_)
+            | Self::ShortFeed {
+                ..
+            } => "incomplete".to_string(),
+            Self::PipeFailed(_) => "pipe".to_string(),
+            Self::ExecFailed(_) => "exec".to_string(),
+            Self::Interrupted => "interrupted".to_string(),
+            Self::Item {
+                source, ..
+            } => source.category(),
+        }
+    }
+}
+
+/// ` (asked to wait 120s)` when the server named a wait.
+fn wait_hint(retry_after: Option<Duration>) -> String {
+    retry_after.map_or_else(String::new, |wait| {
+        format!(" (asked to wait {}s)", wait.as_secs())
+    })
+}
+
+impl From<ureq::Error> for Error {
+    fn from(error: ureq::Error) -> Self {
+        match error {
+            ureq::Error::Json(_) => Self::Decode(Box::new(error)),
+            ureq::Error::BodyExceedsLimit(limit) => Self::BodyTooLarge(limit),
+            _ => Self::Network(error),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Decode(Box::new(error))
+    }
+}
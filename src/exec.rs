@@ -0,0 +1,295 @@
+//! `--exec`: runs a command once per listed story, with the story's fields
+//! substituted into its arguments, e.g. `notify-send {title} {url}`.
+//!
+//! The command is split into arguments once, without a shell: arguments are
+//! separated by whitespace, `'…'` keeps everything inside as it is, `"…"`
+//! too except for `\"` and `\\`, and a backslash outside quotes keeps the
+//! next character. Placeholders are then replaced within each argument, so
+//! a title with spaces or quotes stays a single argument and is never
+//! interpreted. `{{` and `}}` stand for literal braces.
+
+use std::{
+    fmt::Write as _,
+    num::NonZeroUsize,
+    process::{Command, Stdio},
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use hn_cli::{Error, Story};
+
+use crate::{eprint_line, interrupt, pipe, theme};
+
+/// A story field a placeholder stands for.
+#[derive(Copy, Clone, Debug)]
+enum Field {
+    /// `{id}`.
+    Id,
+    /// `{title}`.
+    Title,
+    /// `{url}`: the link, or the discussion page of a text post.
+    Url,
+    /// `{hn_url}`: the discussion page.
+    HnUrl,
+    /// `{by}`.
+    By,
+    /// `{score}`.
+    Score,
+    /// `{comments}`: the comment count, 0 when the API reported none.
+    Comments,
+    /// `{time}`: submission time in Unix seconds, empty when unknown.
+    Time,
+}
+
+impl Field {
+    const ALL: [(&str, Self); 8] = [
+        ("id", Self::Id),
+        ("title", Self::Title),
+        ("url", Self::Url),
+        ("hn_url", Self::HnUrl),
+        ("by", Self::By),
+        ("score", Self::Score),
+        ("comments", Self::Comments),
+        ("time", Self::Time),
+    ];
+
+    fn value(self, story: &Story) -> String {
+        match self {
+            Self::Id => story.id.to_string(),
+            Self::Title => story.title.clone(),
+            Self::Url => story.link(),
+            Self::HnUrl => story.discussion_url(),
+            Self::By => story.by.clone(),
+            Self::Score => story.score.to_string(),
+            Self::Comments => story.descendants.unwrap_or_default().to_string(),
+            Self::Time => story.time.map_or_else(String::new, |time| time.to_string()),
+        }
+    }
+}
+
+/// Part of an argument.
+#[derive(Clone, Debug)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+/// A parsed `--exec` command.
+#[derive(Clone, Debug)]
+pub struct Template {
+    /// The command as given, for messages.
+    source: String,
+    /// The program and its arguments.
+    argv: Vec<Vec<Piece>>,
+}
+
+impl Template {
+    /// Splits `source` into arguments and finds their placeholders.
+    ///
+    /// # Errors
+    ///
+    /// An unterminated quote, an unknown or unclosed placeholder, or no
+    /// command at all.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let argv = split(source)?
+            .iter()
+            .map(|arg| pieces(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        if argv.is_empty() {
+            return Err("expected a command".to_string());
+        }
+        Ok(Self {
+            source: source.to_string(),
+            argv,
+        })
+    }
+
+    /// The command as given.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The command for `story`, its fields substituted.
+    fn command(&self, story: &Story) -> Command {
+        let mut argv = self.argv.iter().map(|pieces| {
+            pieces
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Text(text) => text.clone(),
+                    Piece::Field(field) => field.value(story),
+                })
+                .collect::<String>()
+        });
+        let mut command = Command::new(argv.next().unwrap_or_default());
+        command.args(argv);
+        command
+    }
+}
+
+/// `source` split into arguments, quotes and escapes resolved.
+fn split(source: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(arg.take()),
+            '\'' => {
+                let quoted = arg.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => quoted.push(c),
+                        None => return Err("unterminated ' quote".to_string()),
+                    }
+                }
+            },
+            '"' => {
+                let quoted = arg.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => quoted.push(c),
+                            Some(c) => {
+                                quoted.push('\\');
+                                quoted.push(c);
+                            },
+                            None => return Err("unterminated \" quote".to_string()),
+                        },
+                        Some(c) => quoted.push(c),
+                        None => return Err("unterminated \" quote".to_string()),
+                    }
+                }
+            },
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or("nothing to escape after a trailing \\")?;
+                arg.get_or_insert_default().push(escaped);
+            },
+            c => arg.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+/// The text and placeholders of one argument.
+fn pieces(arg: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut rest = arg;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("{{") {
+            text.push('{');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("}}") {
+            text.push('}');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let (name, after) = after
+                .split_once('}')
+                .ok_or_else(|| format!("unclosed placeholder in '{arg}'"))?;
+            let field = Field::ALL
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map(|&(_, field)| field)
+                .ok_or_else(|| {
+                    let known: Vec<String> = Field::ALL
+                        .iter()
+                        .map(|(known, _)| format!("{{{known}}}"))
+                        .collect();
+                    format!(
+                        "unknown placeholder {{{name}}}; expected one of {}",
+                        known.join(", ")
+                    )
+                })?;
+            if !text.is_empty() {
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+            }
+            pieces.push(Piece::Field(field));
+            rest = after;
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if !text.is_empty() || pieces.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// Runs `template` once per story, up to `jobs` at a time, with stdin closed
+/// and the story's id in `HN_STORY_ID`; with `fail_fast`, no command starts
+/// after the first one fails.
+/// Comments: the commands' own output goes straight to the terminal, so
+/// concurrent ones may interleave. Failures are reported in listing order,
+/// and Ctrl-C stops before the next command.
+pub fn run(
+    template: &Template,
+    stories: &[Story],
+    jobs: NonZeroUsize,
+    fail_fast: bool,
+) -> Result<(), Error> {
+    let next = AtomicUsize::new(0);
+    let started = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let failures = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..jobs.get().min(stories.len()) {
+            scope.spawn(|| {
+                while !(interrupt::requested() || fail_fast && failed.load(Ordering::SeqCst)) {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(story) = stories.get(index) else {
+                        break;
+                    };
+                    started.fetch_add(1, Ordering::SeqCst);
+                    let outcome = match template
+                        .command(story)
+                        .env("HN_STORY_ID", story.id.to_string())
+                        .stdin(Stdio::null())
+                        .status()
+                    {
+                        Ok(status) if status.success() => continue,
+                        Ok(status) => pipe::describe(status),
+                        Err(e) => format!("couldn't run: {e}"),
+                    };
+                    failed.store(true, Ordering::SeqCst);
+                    failures
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push((index, format!("{} ({outcome})", story.id)));
+                }
+            });
+        }
+    });
+
+    let mut failures = failures
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+    if failures.is_empty() {
+        return Ok(());
+    }
+    failures.sort_by_key(|&(index, _)| index);
+    let started = started.into_inner();
+    let listed: Vec<String> = failures.into_iter().map(|(_, failure)| failure).collect();
+    let mut message = format!(
+        "--exec failed for {} of {started} stories: {}",
+        listed.len(),
+        listed.join(", ")
+    );
+    if fail_fast && started < stories.len() {
+        let _ = write!(
+            message,
+            "; {} not run (--exec-fail-fast)",
+            stories.len() - started
+        );
+    }
+    eprint_line(&message, theme::current().warn);
+    Err(Error::ExecFailed(listed.len()))
+}
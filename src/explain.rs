@@ -0,0 +1,264 @@
+//! `--explain`: what a listing would fetch and show, worked out from the
+//! flags and the config file, without fetching a single story.
+
+use std::{fmt::Write as _, num::NonZeroU32, process::ExitCode};
+
+use clap::ValueEnum;
+use colored::Colorize;
+use hn_cli::{Error, HnClient};
+
+use crate::{
+    Args, Backend, Format, OrderBy,
+    fetch::{self, ALGOLIA_BATCH_SIZE, FeedIds},
+};
+
+/// Prints the plan of the listing `args` asks for: the feeds and how many
+/// stories they give, the item requests, filters, order and output.
+/// Comments: the id lists are fetched (or read from the cache offline), so
+/// the counts are those of a real run a moment from now.
+pub fn run(
+    args: &Args,
+    client: &HnClient,
+    rate_limit: Option<NonZeroU32>,
+) -> Result<ExitCode, Error> {
+    let FeedIds {
+        ids,
+        section_of,
+        sections,
+        ..
+    } = fetch::feed_ids(args, client)?;
+
+    let mut output = String::new();
+    for (index, section) in sections.iter().enumerate() {
+        let source = if args.list.offline {
+            "the cache".to_string()
+        } else {
+            format!("{}/{}.json", client.base_url(), section.sort.feed())
+        };
+        let count = section_of.values().filter(|&&of| of == index).count();
+        let mut line = format!("{} from {source}: {}", name(&section.sort), stories(count));
+        if section.repeats > 0 {
+            let _ = write!(line, ", {} already in an earlier feed", section.repeats);
+        }
+        entry(&mut output, "Feed", &line);
+    }
+    entry(
+        &mut output,
+        "Items",
+        &items(args, client, rate_limit, ids.len()),
+    );
+    entry(&mut output, "Filters", &filters(args));
+    entry(&mut output, "Order", &order(args));
+    entry(&mut output, "Output", &destination(args));
+    for extra in extras(args) {
+        entry(&mut output, "Also", &extra);
+    }
+    print!("{output}");
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Appends `value` under `label`, labels lined up in a column.
+fn entry(output: &mut String, label: &str, value: &str) {
+    let _ = writeln!(output, "{} {value}", format!("{label:<8}").dimmed());
+}
+
+/// The command-line spelling of `value`.
+fn name(value: &impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn stories(count: usize) -> String {
+    format!("{count} {}", if count == 1 { "story" } else { "stories" })
+}
+
+/// How the `count` stories would be fetched.
+fn items(args: &Args, client: &HnClient, rate_limit: Option<NonZeroU32>, count: usize) -> String {
+    if args.list.offline {
+        return format!("{} from the cache, no requests", stories(count));
+    }
+    let (requests, url, concurrency) = match args.list.backend {
+        Backend::Firebase => (
+            count,
+            format!("{}/item/{{id}}.json", client.base_url()),
+            format!("{} at a time", args.list.jobs),
+        ),
+        Backend::Algolia => (
+            count.div_ceil(ALGOLIA_BATCH_SIZE),
+            format!("{}/search", client.algolia_url()),
+            format!("up to {ALGOLIA_BATCH_SIZE} stories each, one at a time"),
+        ),
+    };
+    let rate = rate_limit.map_or_else(
+        || "no rate limit".to_string(),
+        |per_second| format!("at most {per_second} per second"),
+    );
+    format!(
+        "{requests} {} to {url}, {concurrency}, up to {} retries each, {rate}",
+        if requests == 1 { "request" } else { "requests" },
+        args.retries
+    )
+}
+
+/// An `--max-age` or `--min-age` of `seconds` in its largest whole unit, e.g.
+/// `36h`.
+fn age(seconds: i64) -> String {
+    match seconds {
+        _ if seconds % 86_400 == 0 => format!("{}d", seconds / 86_400),
+        _ if seconds % 3600 == 0 => format!("{}h", seconds / 3600),
+        _ => format!("{}m", seconds / 60),
+    }
+}
+
+/// The filters that would leave stories out, in the order they apply.
+fn filters(args: &Args) -> String {
+    let criteria = args.criteria();
+    let mut filters = Vec::new();
+    if let Some(min) = criteria.min_score {
+        filters.push(format!("score at least {min}"));
+    }
+    if let Some(min) = criteria.min_comments {
+        filters.push(format!("at least {min} comments"));
+    }
+    if let Some(max) = criteria.max_age {
+        filters.push(format!("submitted in the last {}", age(max)));
+    }
+    if let Some(min) = criteria.min_age {
+        filters.push(format!("submitted at least {} ago", age(min)));
+    }
+    if criteria.text_only {
+        filters.push("text posts only".to_string());
+    }
+    if criteria.links_only {
+        filters.push("links only".to_string());
+    }
+    if criteria.no_jobs {
+        filters.push("no job postings".to_string());
+    }
+    if !criteria.keywords.is_empty() {
+        let terms: Vec<String> = criteria
+            .keywords
+            .iter()
+            .map(|term| format!("'{term}'"))
+            .collect();
+        filters.push(format!("title contains {}", terms.join(" or ")));
+    }
+    if let Some(expr) = &criteria.expr {
+        filters.push(format!("where {}", expr.source()));
+    }
+    let (domains, users) = criteria.blocklist.counts();
+    let blocked: Vec<String> = [(domains, "site"), (users, "author")]
+        .into_iter()
+        .filter(|&(count, _)| count > 0)
+        .map(|(count, noun)| format!("{count} {noun}{}", if count == 1 { "" } else { "s" }))
+        .collect();
+    if !blocked.is_empty() {
+        filters.push(format!(
+            "not from the {} in the blocklist",
+            blocked.join(" and ")
+        ));
+    }
+    if let Some(min) = args.list.min_karma {
+        filters.push(format!(
+            "submitter karma at least {min}, one profile request per submitter"
+        ));
+    }
+    if let Some(max) = args.list.max_per_domain {
+        filters.push(format!(
+            "at most {max} per domain, backfilled from deeper in the feed"
+        ));
+    }
+    if args.list.changed_only {
+        filters.push("only stories no earlier --changed-only run showed".to_string());
+    }
+    if filters.is_empty() {
+        "none".to_string()
+    } else {
+        filters.join(", ")
+    }
+}
+
+fn order(args: &Args) -> String {
+    let mut order = format!("by {}", name(&args.order_by()));
+    if args.order_by() == OrderBy::Hot {
+        let _ = write!(order, " (score / (age_hours + 2)^{})", args.list.gravity);
+    }
+    if let Some(group_by) = args.list.group_by
+        && args.format == Format::Pretty
+    {
+        let _ = write!(order, ", grouped by {}", name(&group_by));
+    }
+    if !args.list.digest {
+        return order;
+    }
+    let sites = args
+        .list
+        .digest_domains
+        .map_or_else(|| "most".to_string(), |max| format!("{max} most"));
+    format!("{order}, then a digest of the top story from each of the {sites} common sites")
+}
+
+/// The format and where it goes.
+fn destination(args: &Args) -> String {
+    let mut format = name(&args.format);
+    if args.list.url_only {
+        format = if args.list.print0 {
+            "links, NUL-terminated".to_string()
+        } else {
+            "links, one per line".to_string()
+        };
+    } else if args.format == Format::Pretty && args.compact {
+        format.push_str(" (compact)");
+    }
+    let to = args
+        .output_file()
+        .map_or_else(|| "stdout".to_string(), |path| path.display().to_string());
+    format!("{format} to {to}")
+}
+
+/// What else the run would do besides printing.
+fn extras(args: &Args) -> Vec<String> {
+    let list = &args.list;
+    let mut extras = Vec::new();
+    if args.merged() {
+        extras.push("merge the feeds into one list, interleaved by rank".to_string());
+    }
+    if list.check_links {
+        extras.push("check each link's HTTP status".to_string());
+    }
+    if list.include_poll_results {
+        extras.push("fetch the options of each poll".to_string());
+    }
+    if list.top_comment {
+        extras.push("fetch the first comment of each story".to_string());
+    }
+    if list.archive_links {
+        extras.push("look up a Wayback Machine copy of each link".to_string());
+    }
+    if list.with_favicons {
+        extras.push("embed each site's favicon in the page".to_string());
+    }
+    if let Some(path) = &list.append {
+        extras.push(format!("append the stories to {}", path.display()));
+    }
+    if let Some(command) = &list.pipe_each {
+        extras.push(format!("run '{command}' once per story"));
+    }
+    if let Some(template) = &list.exec {
+        extras.push(format!(
+            "run '{}' once per story, {} at a time",
+            template.source(),
+            list.jobs
+        ));
+    }
+    if list.pick {
+        let then = if list.pick_print { "print" } else { "open" };
+        extras.push(format!("pick stories in $HN_PICKER and {then} their links"));
+    }
+    if let Some(interval) = list.watch {
+        extras.push(format!("refresh every {interval}s until Ctrl-C"));
+    }
+    extras
+}
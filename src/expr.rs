@@ -0,0 +1,462 @@
+//! `--where`: a small expression language over story fields, e.g.
+//! `score > 100 and domain == "github.com"`.
+//!
+//! Conditions compare a field with a value and combine with `and`, `or`,
+//! `not` and parentheses; `not` binds tightest and `or` loosest. Numbers
+//! compare with `==`, `!=`, `<`, `<=`, `>` and `>=`; text compares with `==`
+//! and `!=`, or with `~` for "contains", always ignoring case, and is quoted
+//! with `"…"` or `'…'`.
+
+use std::fmt;
+
+use hn_cli::Story;
+
+use crate::{age, filter};
+
+/// A story field a condition tests.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Field {
+    /// `score`.
+    Score,
+    /// `comments`: 0 when the API reported none.
+    Comments,
+    /// `author`.
+    Author,
+    /// `domain`: the link's host without `www.`, empty for text posts.
+    Domain,
+    /// `title`.
+    Title,
+    /// `age`: time since submission; never matches stories without one.
+    Age,
+}
+
+impl Field {
+    const ALL: [(&str, Self); 6] = [
+        ("score", Self::Score),
+        ("comments", Self::Comments),
+        ("author", Self::Author),
+        ("domain", Self::Domain),
+        ("title", Self::Title),
+        ("age", Self::Age),
+    ];
+
+    const fn is_numeric(self) -> bool {
+        matches!(self, Self::Score | Self::Comments | Self::Age)
+    }
+
+    fn name(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|&&(_, field)| field == self)
+            .map_or("", |&(name, _)| name)
+    }
+}
+
+/// A comparison operator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `~`: the text contains the value.
+    Contains,
+}
+
+impl Op {
+    const ALL: [(&str, Self); 7] = [
+        ("==", Self::Eq),
+        ("!=", Self::Ne),
+        ("<=", Self::Le),
+        (">=", Self::Ge),
+        ("<", Self::Lt),
+        (">", Self::Gt),
+        ("~", Self::Contains),
+    ];
+
+    fn symbol(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|&&(_, op)| op == self)
+            .map_or("", |&(symbol, _)| symbol)
+    }
+
+    fn compare<T: PartialOrd>(self, left: &T, right: &T) -> bool {
+        match self {
+            Self::Eq => left == right,
+            Self::Ne => left != right,
+            Self::Lt => left < right,
+            Self::Le => left <= right,
+            Self::Gt => left > right,
+            Self::Ge => left >= right,
+            Self::Contains => false,
+        }
+    }
+}
+
+/// What a field is compared with.
+#[derive(Clone, Debug)]
+enum Value {
+    /// A number; for `age`, in seconds.
+    Number(i64),
+    Text(String),
+}
+
+/// A parsed expression.
+#[derive(Clone, Debug)]
+enum Node {
+    Or(Box<Self>, Box<Self>),
+    And(Box<Self>, Box<Self>),
+    Not(Box<Self>),
+    Compare(Field, Op, Value),
+}
+
+/// A parsed `--where` expression.
+#[derive(Clone, Debug)]
+pub struct Expr {
+    /// The expression as given, for messages.
+    source: String,
+    root: Node,
+}
+
+impl Expr {
+    /// Parses `source`.
+    ///
+    /// # Errors
+    ///
+    /// A syntax error, an unknown field, or a value or operator the field
+    /// can't be compared with, each naming the column it was found at.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokens(source)?;
+        let mut parser = Parser {
+            tokens,
+            next: 0,
+            depth: 0,
+        };
+        let root = parser.or()?;
+        if let Some((column, token)) = parser.tokens.get(parser.next) {
+            return Err(format!("unexpected {token} at column {column}"));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    /// The expression as given.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether `story` matches, with ages measured from `now` (Unix seconds).
+    pub fn matches(&self, story: &Story, now: i64) -> bool {
+        eval(&self.root, story, now)
+    }
+}
+
+fn eval(node: &Node, story: &Story, now: i64) -> bool {
+    match node {
+        Node::Or(left, right) => eval(left, story, now) || eval(right, story, now),
+        Node::And(left, right) => eval(left, story, now) && eval(right, story, now),
+        Node::Not(inner) => !eval(inner, story, now),
+        Node::Compare(field, op, Value::Number(value)) => {
+            let actual = match field {
+                Field::Score => i64::from(story.score),
+                Field::Comments => i64::from(story.descendants.unwrap_or_default()),
+                Field::Age => match story.time {
+                    Some(time) => now.saturating_sub(time),
+                    None => return false,
+                },
+                Field::Author | Field::Domain | Field::Title => return false,
+            };
+            op.compare(&actual, value)
+        },
+        Node::Compare(field, op, Value::Text(value)) => {
+            let actual = match field {
+                Field::Author => story.by.clone(),
+                Field::Domain => story.host().unwrap_or_default(),
+                Field::Title => story.title.clone(),
+                Field::Score | Field::Comments | Field::Age => return false,
+            };
+            match op {
+                Op::Contains => filter::contains(&actual, value),
+                op => op.compare(&actual.to_lowercase(), &value.to_lowercase()),
+            }
+        },
+    }
+}
+
+/// A lexical token.
+#[derive(Clone, Debug)]
+enum Token {
+    /// A field name or `and`, `or`, `not`; also unquoted text, for a
+    /// helpful error.
+    Word(String),
+    /// A number and its unit letter, if any.
+    Number(i64, Option<char>),
+    Text(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Word(word) => write!(f, "'{word}'"),
+            Self::Number(number, unit) => {
+                write!(
+                    f,
+                    "'{number}{}'",
+                    unit.map(String::from).unwrap_or_default()
+                )
+            },
+            Self::Text(text) => write!(f, "\"{text}\""),
+            Self::Op(op) => write!(f, "'{}'", op.symbol()),
+            Self::Open => f.write_str("'('"),
+            Self::Close => f.write_str("')'"),
+        }
+    }
+}
+
+/// `source` split into tokens, each with the column it starts at.
+fn tokens(source: &str) -> Result<Vec<(usize, Token)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let column = source[..start].chars().count() + 1;
+        let rest = &source[start..];
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = if c == '(' || c == ')' {
+            chars.next();
+            if c == '(' { Token::Open } else { Token::Close }
+        } else if let Some(&(symbol, op)) =
+            Op::ALL.iter().find(|(symbol, _)| rest.starts_with(symbol))
+        {
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            Token::Op(op)
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, end)) if end == c => break,
+                    Some((_, c)) => text.push(c),
+                    None => return Err(format!("unterminated {c} quote at column {column}")),
+                }
+            }
+            Token::Text(text)
+        } else if c.is_ascii_digit() {
+            let mut digits = String::new();
+            while let Some(&(_, c)) = chars.peek().filter(|(_, c)| c.is_ascii_digit()) {
+                digits.push(c);
+                chars.next();
+            }
+            let unit = chars
+                .peek()
+                .map(|&(_, c)| c)
+                .filter(char::is_ascii_alphabetic);
+            if unit.is_some() {
+                chars.next();
+            }
+            let number = digits
+                .parse()
+                .map_err(|_| format!("number too large at column {column}"))?;
+            Token::Number(number, unit)
+        } else if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            {
+                word.push(c);
+                chars.next();
+            }
+            Token::Word(word)
+        } else {
+            return Err(format!("unexpected '{c}' at column {column}"));
+        };
+        tokens.push((column, token));
+    }
+    Ok(tokens)
+}
+
+/// How deep `not`s and parentheses may nest, so a long enough `--where`
+/// can't overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// A recursive-descent parser over the tokens.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// `not`s and parentheses open around the next token.
+    depth: usize,
+}
+
+impl Parser {
+    /// Parses `inner` one level deeper, or fails past [`MAX_DEPTH`] with the
+    /// column of the token that nests it.
+    fn nested(
+        &mut self,
+        column: usize,
+        inner: impl FnOnce(&mut Self) -> Result<Node, String>,
+    ) -> Result<Node, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("expression nested too deeply at column {column}"));
+        }
+        self.depth += 1;
+        let node = inner(self);
+        self.depth -= 1;
+        node
+    }
+
+    /// Takes the next token if it is the keyword `word`.
+    fn keyword(&mut self, word: &str) -> bool {
+        let found = matches!(
+            self.tokens.get(self.next),
+            Some((_, Token::Word(next))) if next.eq_ignore_ascii_case(word)
+        );
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    /// The next token, or an error saying `expected` is missing at the end.
+    fn take(&mut self, expected: &str) -> Result<(usize, Token), String> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .cloned()
+            .ok_or_else(|| format!("expected {expected} at the end"))?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    /// `and ("or" and)*`
+    fn or(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.keyword("or") {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    /// `not ("and" not)*`
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.not()?;
+        while self.keyword("and") {
+            node = Node::And(Box::new(node), Box::new(self.not()?));
+        }
+        Ok(node)
+    }
+
+    /// `"not" not | "(" or ")" | comparison`
+    fn not(&mut self) -> Result<Node, String> {
+        let column = self.tokens.get(self.next).map_or(0, |&(column, _)| column);
+        if self.keyword("not") {
+            return self.nested(column, |parser| Ok(Node::Not(Box::new(parser.not()?))));
+        }
+        if matches!(self.tokens.get(self.next), Some((_, Token::Open))) {
+            self.next += 1;
+            let node = self.nested(column, Self::or)?;
+            return match self.take("')'")? {
+                (_, Token::Close) => Ok(node),
+                (column, token) => Err(format!("expected ')', got {token} at column {column}")),
+            };
+        }
+        self.comparison()
+    }
+
+    /// `field op value`
+    fn comparison(&mut self) -> Result<Node, String> {
+        let field = match self.take("a field")? {
+            (column, Token::Word(name)) => Field::ALL
+                .iter()
+                .find(|(known, _)| name.eq_ignore_ascii_case(known))
+                .map(|&(_, field)| field)
+                .ok_or_else(|| {
+                    let known: Vec<&str> = Field::ALL.iter().map(|&(known, _)| known).collect();
+                    format!(
+                        "unknown field '{name}' at column {column}; expected one of {}",
+                        known.join(", ")
+                    )
+                })?,
+            (column, token) => {
+                return Err(format!("expected a field, got {token} at column {column}"));
+            },
+        };
+        let op = match self.take("an operator")? {
+            (_, Token::Op(op)) => op,
+            (column, token) => {
+                return Err(format!(
+                    "expected an operator after {}, got {token} at column {column}",
+                    field.name()
+                ));
+            },
+        };
+        let (column, token) = self.take("a value")?;
+        let value = match token {
+            Token::Number(number, unit) if field.is_numeric() => {
+                Value::Number(number.saturating_mul(unit_seconds(field, unit, column)?))
+            },
+            Token::Text(text) if !field.is_numeric() => Value::Text(text),
+            Token::Number(..) | Token::Text(_) => {
+                let kind = if field.is_numeric() {
+                    "a number"
+                } else {
+                    "quoted text"
+                };
+                return Err(format!(
+                    "{} compares with {kind}, got {token} at column {column}",
+                    field.name()
+                ));
+            },
+            Token::Word(word) if !field.is_numeric() => {
+                return Err(format!(
+                    "expected a value, got '{word}' at column {column}; quote text as \"{word}\""
+                ));
+            },
+            token => return Err(format!("expected a value, got {token} at column {column}")),
+        };
+        let ordering = matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge);
+        if field.is_numeric() && op == Op::Contains || !field.is_numeric() && ordering {
+            let allowed = if field.is_numeric() {
+                "==, !=, <, <=, > or >="
+            } else {
+                "==, != or ~"
+            };
+            return Err(format!(
+                "{} can't be compared with {}; use {allowed}",
+                field.name(),
+                op.symbol()
+            ));
+        }
+        Ok(Node::Compare(field, op, value))
+    }
+}
+
+/// Seconds per `unit` of an `age`, hours without one; other fields take
+/// plain numbers.
+fn unit_seconds(field: Field, unit: Option<char>, column: usize) -> Result<i64, String> {
+    match (field, unit) {
+        (Field::Age, None) => Ok(3600),
+        (Field::Age, Some(unit)) => age::unit_seconds(unit.encode_utf8(&mut [0; 4]))
+            .and_then(|seconds| i64::try_from(seconds).ok())
+            .ok_or_else(|| {
+                format!("unknown unit '{unit}' at column {column}; expected s, m, h, d or w")
+            }),
+        (_, None) => Ok(1),
+        (field, Some(_)) => Err(format!(
+            "{} takes a plain number, without a unit, at column {column}",
+            field.name()
+        )),
+    }
+}
@@ -0,0 +1,1109 @@
+//! Fetching a feed and its stories: concurrently from Firebase, in batches
+//! from Algolia, or from the cache when offline.
+
+use std::{
+    collections::{HashMap, HashSet, hash_map::Entry},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use colored::Color;
+use hn_cli::{Error, HnClient, LinkStatus, SortMode, Story};
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    Args, Backend, cache, eprint_line,
+    filter::{self, DomainCap},
+    interrupt, logging, print_stdout, progress_style,
+    reorder::Reorder,
+    theme,
+    timing::Timing,
+    write_story,
+};
+
+/// A story that could not be fetched, reported next to the results in JSON.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct FetchFailure {
+    pub id: u32,
+    pub category: String,
+    reason: String,
+}
+
+impl FetchFailure {
+    pub fn new(id: u32, error: &Error) -> Self {
+        Self {
+            id,
+            category: error.category(),
+            reason: error.to_string(),
+        }
+    }
+}
+
+/// Maximum number of ids packed into a single Algolia search query.
+/// Comments: keeps the query string well below common URL length limits.
+pub const ALGOLIA_BATCH_SIZE: usize = 50;
+
+/// Stories that were fetched, plus the ids that failed along the way.
+pub type Fetched = (Vec<Story>, Vec<FetchFailure>);
+
+/// Whether a failed item ends the whole run: under `--fail-fast` unless the
+/// item is merely deleted or missing, and always once the server rate limits
+/// us, since every further request would be refused too.
+pub const fn aborts(error: &Error, fail_fast: bool) -> bool {
+    match error {
+        Error::RateLimited {
+            ..
+        } => true,
+        Error::Gone(_) => false,
+        _ => fail_fast,
+    }
+}
+
+/// Records a failed item, or aborts with it when [`aborts`] says so.
+fn record_failure(
+    failures: &mut Vec<FetchFailure>,
+    id: u32,
+    error: Error,
+    fail_fast: bool,
+) -> Result<(), Error> {
+    if aborts(&error, fail_fast) {
+        return Err(Error::Item {
+            id,
+            source: Box::new(error),
+        });
+    }
+    failures.push(FetchFailure::new(id, &error));
+    Ok(())
+}
+
+/// The copy an earlier run cached of a story that failed to fetch with
+/// `error`, marked as such, unless `--prefer-fresh` or the item is deleted,
+/// missing or would abort the run anyway.
+fn cached_fallback(args: &Args, id: u32, error: &Error) -> Option<Story> {
+    if args.list.prefer_fresh
+        || matches!(error, Error::Gone(_))
+        || aborts(error, args.list.fail_fast)
+    {
+        return None;
+    }
+    let mut story = cache::load_item(id).ok()?;
+    debug!(id, error = %error, "showing the cached copy");
+    story.cached = true;
+    Some(story)
+}
+
+/// How often the collecting thread checks for Ctrl-C while requests are in
+/// flight.
+pub const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+/// Fetches stories with up to `--jobs` concurrent requests.
+/// Comments: `on_story` sees each result in completion order together with its
+/// index in `ids` (`None` for a failure); the returned stories keep the order
+/// of `ids`. Workers are
+/// detached so a fail-fast abort or Ctrl-C doesn't wait on in-flight
+/// requests.
+fn get_stories_firebase(
+    client: &HnClient,
+    ids: &[u32],
+    pb: &ProgressBar,
+    args: &Args,
+    timing: &mut Timing,
+    on_story: &mut dyn FnMut(usize, Option<&Story>),
+) -> Result<Fetched, Error> {
+    let (tx, rx) = mpsc::channel();
+    let next = Arc::new(AtomicUsize::new(0));
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let shared: Arc<[u32]> = ids.into();
+
+    for _ in 0..args.list.jobs.get().min(ids.len()) {
+        let (client, tx, next, in_flight, stop, ids, pb) = (
+            client.clone(),
+            tx.clone(),
+            next.clone(),
+            in_flight.clone(),
+            stop.clone(),
+            shared.clone(),
+            pb.clone(),
+        );
+        thread::spawn(move || {
+            while !stop.load(Ordering::SeqCst) && !interrupt::requested() {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(&id) = ids.get(index) else {
+                    break;
+                };
+
+                let active = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                pb.set_message(format!("[{active} in flight] fetching #{id}"));
+
+                let start = Instant::now();
+                let result = client.item(id);
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                if tx.send((index, id, result, start.elapsed())).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let mut slots: Vec<Option<Result<Story, FetchFailure>>> = ids.iter().map(|_| None).collect();
+    let mut tally = Tally::new(pb, args);
+    loop {
+        let (index, id, result, elapsed) = match rx.recv_timeout(INTERRUPT_POLL) {
+            Ok(received) => received,
+            Err(RecvTimeoutError::Timeout) if interrupt::requested() => break,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        timing.item(elapsed);
+        let active = in_flight.load(Ordering::SeqCst);
+        let label = result.as_ref().map_or_else(
+            |e| format!("#{id} failed: {}", e.category()),
+            |story| story.title.clone(),
+        );
+        pb.set_message(if args.verbose > 0 {
+            format!("[{active} in flight] {label} ({} ms)", elapsed.as_millis())
+        } else {
+            format!("[{active} in flight] {label}")
+        });
+        tally.advance(result.is_err());
+
+        slots[index] = Some(match result {
+            Ok(story) => {
+                on_story(index, Some(&story));
+                Ok(story)
+            },
+            Err(e) if aborts(&e, args.list.fail_fast) => {
+                stop.store(true, Ordering::SeqCst);
+                return Err(Error::Item {
+                    id,
+                    source: Box::new(e),
+                });
+            },
+            Err(e) => {
+                if let Some(story) = cached_fallback(args, id, &e) {
+                    on_story(index, Some(&story));
+                    Ok(story)
+                } else {
+                    on_story(index, None);
+                    Err(FetchFailure::new(id, &e))
+                }
+            },
+        });
+    }
+
+    let mut stories = Vec::with_capacity(ids.len());
+    let mut failures = Vec::new();
+    for slot in slots.into_iter().flatten() {
+        match slot {
+            Ok(story) => stories.push(story),
+            Err(failure) => failures.push(failure),
+        }
+    }
+    Ok((stories, failures))
+}
+
+/// Fetches stories through Algolia in batches, falling back to the items
+/// endpoint for any id a batch didn't return. Results keep the order of `ids`,
+/// and `on_story` sees each batch's results once the batch is complete.
+fn get_stories_algolia(
+    client: &HnClient,
+    ids: &[u32],
+    pb: &ProgressBar,
+    args: &Args,
+    timing: &mut Timing,
+    on_story: &mut dyn FnMut(usize, Option<&Story>),
+) -> Result<Fetched, Error> {
+    let mut found: HashMap<u32, Story> = HashMap::with_capacity(ids.len());
+    let mut failures = Vec::new();
+    let mut tally = Tally::new(pb, args);
+
+    let batches = ids.len().div_ceil(ALGOLIA_BATCH_SIZE);
+    for (chunk_index, chunk) in ids.chunks(ALGOLIA_BATCH_SIZE).enumerate() {
+        if interrupt::requested() {
+            break;
+        }
+        pb.set_message(format!("batch {}/{batches}", chunk_index + 1));
+        let start = Instant::now();
+        let batch = client.algolia_batch(chunk);
+        timing.item(start.elapsed());
+        if let Ok(hits) = batch {
+            for hit in hits {
+                if chunk.contains(&hit.id) {
+                    found.insert(hit.id, hit);
+                }
+            }
+        }
+
+        for &id in chunk {
+            if interrupt::requested() {
+                break;
+            }
+            let failed = failures.len();
+            if let Entry::Vacant(entry) = found.entry(id) {
+                pb.set_message(format!(
+                    "batch {}/{batches}: fetching #{id}",
+                    chunk_index + 1
+                ));
+                let start = Instant::now();
+                let result = client.algolia_item(id);
+                timing.item(start.elapsed());
+                match result {
+                    Ok(story) => {
+                        entry.insert(story);
+                    },
+                    Err(e) => {
+                        if let Some(story) = cached_fallback(args, id, &e) {
+                            entry.insert(story);
+                        } else {
+                            record_failure(&mut failures, id, e, args.list.fail_fast)?;
+                        }
+                    },
+                }
+            }
+            tally.advance(failures.len() > failed);
+        }
+
+        let offset = chunk_index * ALGOLIA_BATCH_SIZE;
+        for (i, id) in chunk.iter().enumerate() {
+            on_story(offset + i, found.get(id));
+        }
+    }
+
+    let stories = ids.iter().filter_map(|id| found.remove(id)).collect();
+    Ok((stories, failures))
+}
+
+/// Reads stories from the on-disk cache; ids that were never fetched online
+/// are recorded as failures.
+fn get_stories_offline(
+    ids: &[u32],
+    pb: &ProgressBar,
+    args: &Args,
+    timing: &mut Timing,
+    on_story: &mut dyn FnMut(usize, Option<&Story>),
+) -> Result<Fetched, Error> {
+    let mut stories = Vec::with_capacity(ids.len());
+    let mut failures = Vec::new();
+    let mut tally = Tally::new(pb, args);
+
+    for (index, &id) in ids.iter().enumerate() {
+        if interrupt::requested() {
+            break;
+        }
+        let failed = match cache::load_item(id) {
+            Ok(story) => {
+                timing.cache_hit();
+                on_story(index, Some(&story));
+                stories.push(story);
+                false
+            },
+            Err(e) => {
+                record_failure(&mut failures, id, e, args.list.fail_fast)?;
+                on_story(index, None);
+                true
+            },
+        };
+        tally.advance(failed);
+    }
+    Ok((stories, failures))
+}
+
+/// Fetches the ids of the selected feed, or reads them from the cache when
+/// `offline`.
+pub fn get_story_ids(client: &HnClient, sort: SortMode, offline: bool) -> Result<Vec<u32>, Error> {
+    if offline {
+        return cache::load_list(sort.feed());
+    }
+
+    let ids = client.story_ids(sort)?;
+    cache::store_list(sort.feed(), &ids);
+    Ok(ids)
+}
+
+/// A progress bar for `len` steps in the `--progress-template` style,
+/// hidden when progress isn't shown.
+pub fn progress_bar(args: &Args, len: usize) -> ProgressBar {
+    let pb = ProgressBar::new(len as u64);
+    pb.set_style(progress_style(
+        args.list.progress_template.as_deref(),
+        theme::current().good,
+    ));
+    if !args.show_progress() {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    pb
+}
+
+/// Counts the fetches behind a progress bar as it advances: the default bar
+/// turns from the theme's good color to its warning color at the first
+/// failure, and to its bad color once a quarter of the fetches so far have
+/// failed. A `--progress-template` keeps its own colors.
+struct Tally<'a> {
+    pb: &'a ProgressBar,
+    args: &'a Args,
+    done: u64,
+    failed: u64,
+    color: Option<Color>,
+}
+
+impl<'a> Tally<'a> {
+    fn new(pb: &'a ProgressBar, args: &'a Args) -> Self {
+        Self {
+            pb,
+            args,
+            done: 0,
+            failed: 0,
+            color: theme::current().good,
+        }
+    }
+
+    /// Advances the bar by one fetch, which `failed` or not.
+    fn advance(&mut self, failed: bool) {
+        self.pb.inc(1);
+        self.done += 1;
+        self.failed += u64::from(failed);
+        if self.args.list.progress_template.is_some() {
+            return;
+        }
+        let theme = theme::current();
+        let color = match self.failed {
+            0 => theme.good,
+            failed if failed * 4 < self.done => theme.warn,
+            _ => theme.bad,
+        };
+        if color != self.color {
+            self.color = color;
+            self.pb.set_style(progress_style(None, color));
+        }
+    }
+}
+
+/// Fetches `ids` with `--backend`, or from the cache under `--offline`.
+fn get_stories(
+    args: &Args,
+    client: &HnClient,
+    ids: &[u32],
+    pb: &ProgressBar,
+    timing: &mut Timing,
+    on_story: &mut dyn FnMut(usize, Option<&Story>),
+) -> Result<Fetched, Error> {
+    match args.list.backend {
+        _ if args.list.offline => get_stories_offline(ids, pb, args, timing, on_story),
+        Backend::Firebase => get_stories_firebase(client, ids, pb, args, timing, on_story),
+        Backend::Algolia => get_stories_algolia(client, ids, pb, args, timing, on_story),
+    }
+}
+
+/// Fetches `ids` with the `--backend` a listing would use, or from the cache
+/// under `--offline`, behind the usual progress bar but without filters, and
+/// caches what arrived.
+pub fn fetch_ids(args: &Args, client: &HnClient, ids: &[u32]) -> Result<Fetched, Error> {
+    let pb = progress_bar(args, ids.len());
+    let mut timing = Timing::start();
+    let fetched = get_stories(args, client, ids, &pb, &mut timing, &mut |_, _| {});
+    pb.finish_and_clear();
+    let fetched = fetched?;
+    if !args.list.offline {
+        fetched
+            .0
+            .iter()
+            .filter(|story| !story.cached)
+            .for_each(cache::store_item);
+    }
+    Ok(fetched)
+}
+
+/// The stories of one `--sort` feed within a [`Listing`].
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub sort: SortMode,
+    /// How many of the listing's stories, following those of the earlier
+    /// sections, belong to this one.
+    pub len: usize,
+    /// Stories of this feed left out because an earlier section shows them.
+    pub repeats: usize,
+    /// Stories of this feed `--max-per-domain` left out, after backfilling.
+    pub capped: usize,
+    /// Ids the feed offered towards `--count`: at most `--count`, fewer when
+    /// the feed ran out or `--since-id` stopped it.
+    pub available: usize,
+}
+
+/// The fetched feeds, filtered and ready to print.
+pub struct Listing {
+    /// Every section's stories, one section after the other.
+    pub stories: Vec<Story>,
+    pub sections: Vec<Section>,
+    /// Stories that arrived, before the filters.
+    pub fetched: usize,
+    /// Reposts `--dedupe-url` left out.
+    pub collapsed: usize,
+    /// Stories `--min-karma` left out, backfills included.
+    pub low_karma: usize,
+    pub failures: Vec<FetchFailure>,
+    /// Highest id among the stories that were requested.
+    pub newest: Option<u32>,
+    /// The ids requested, across all feeds, in feed order.
+    pub requested: Vec<u32>,
+    /// Up to `--count` ids per feed after those fetched, for
+    /// `--prefetch-next-page`.
+    pub next_page: Vec<u32>,
+    pub timing: Timing,
+}
+
+/// The ids a listing fetches, across all of its `--sort` feeds.
+pub struct FeedIds {
+    /// Each id once, in feed order.
+    pub ids: Vec<u32>,
+    /// Index of the section each id belongs to.
+    pub section_of: HashMap<u32, usize>,
+    /// The sections, still empty.
+    pub sections: Vec<Section>,
+    /// Per section, the unseen ids past `--count`, in feed order, to backfill
+    /// the places `--max-per-domain` empties.
+    pub spare: Vec<Vec<u32>>,
+    /// Per section, the ids its feed offered towards `--count`, in feed
+    /// order, those of earlier sections included.
+    pub listed: Vec<Vec<u32>>,
+}
+
+/// Fetches the id list of every `--sort` feed.
+/// Comments: a story listed by several feeds belongs to the first of them.
+///
+/// # Errors
+///
+/// [`Error::ShortFeed`] under `--strict` when a feed lists fewer ids than
+/// `--count`; without it, that is only warned about.
+pub fn feed_ids(args: &Args, client: &HnClient) -> Result<FeedIds, Error> {
+    let count = args.list.count.get();
+    let mut ids = Vec::new();
+    let mut section_of = HashMap::new();
+    let mut sections = Vec::with_capacity(args.list.sort.len());
+    let mut spare = Vec::with_capacity(args.list.sort.len());
+    let mut listed = Vec::with_capacity(args.list.sort.len());
+    for (section, &sort) in args.list.sort.iter().enumerate() {
+        let story_ids = get_story_ids(client, sort, args.list.offline)?;
+        short_feed(args, sort, story_ids.len())?;
+        // newstories is ordered newest first, so everything after the first id
+        // at or below --since-id has already been seen
+        let unseen = story_ids
+            .iter()
+            .take_while(|&&id| args.list.since_id.is_none_or(|since| id > since))
+            .count();
+        let mut repeats = 0;
+        for &id in &story_ids[..count.min(unseen)] {
+            match section_of.entry(id) {
+                Entry::Occupied(_) => repeats += 1,
+                Entry::Vacant(entry) => {
+                    entry.insert(section);
+                    ids.push(id);
+                },
+            }
+        }
+        sections.push(Section {
+            sort,
+            len: 0,
+            repeats,
+            capped: 0,
+            available: count.min(unseen),
+        });
+        spare.push(story_ids[count.min(unseen)..unseen].to_vec());
+        listed.push(story_ids[..count.min(unseen)].to_vec());
+    }
+    Ok(FeedIds {
+        ids,
+        section_of,
+        sections,
+        spare,
+        listed,
+    })
+}
+
+impl FeedIds {
+    /// Turns the sections into one for `--feeds`: the ids interleaved by
+    /// rank, the first story of each feed, then the second of each, and so
+    /// on, each once. Returns the feeds listing each id, spare ones included.
+    pub fn merge(&mut self) -> HashMap<u32, Vec<SortMode>> {
+        let mut feeds: HashMap<u32, Vec<SortMode>> = HashMap::new();
+        for (section, (listed, spare)) in self
+            .sections
+            .iter()
+            .zip(self.listed.iter().zip(&self.spare))
+        {
+            for &id in listed.iter().chain(spare) {
+                let of = feeds.entry(id).or_default();
+                if !of.contains(&section.sort) {
+                    of.push(section.sort);
+                }
+            }
+        }
+        self.ids = interleave(&self.listed, |_| true);
+        self.section_of = self.ids.iter().map(|&id| (id, 0)).collect();
+        self.spare = vec![interleave(&self.spare, |id| {
+            !self.section_of.contains_key(&id)
+        })];
+        self.sections = vec![Section {
+            sort: self.sections[0].sort,
+            len: 0,
+            repeats: self.sections.iter().map(|section| section.repeats).sum(),
+            capped: 0,
+            available: self.sections.iter().map(|section| section.available).sum(),
+        }];
+        feeds
+    }
+}
+
+/// Tags each of `stories` with the feeds listing it, under `--feeds`.
+fn tag_feeds(stories: &mut [Story], feeds: Option<&HashMap<u32, Vec<SortMode>>>) {
+    let Some(feeds) = feeds else {
+        return;
+    };
+    for story in stories {
+        story.feeds = feeds.get(&story.id).cloned().unwrap_or_default();
+    }
+}
+
+/// The ids of `lists` that `keep` accepts, each once: the first of every
+/// list, then the second of every list, and so on.
+fn interleave(lists: &[Vec<u32>], keep: impl Fn(u32) -> bool) -> Vec<u32> {
+    let longest = lists.iter().map(Vec::len).max().unwrap_or_default();
+    let mut seen = HashSet::new();
+    (0..longest)
+        .flat_map(|rank| lists.iter().filter_map(move |ids| ids.get(rank).copied()))
+        .filter(|&id| keep(id) && seen.insert(id))
+        .collect()
+}
+
+/// Warns that the `sort` feed lists only `listed` ids when `--count` asks
+/// for more, or fails under `--strict`.
+/// Comments: only a `--count` that was asked for is checked, not the default
+/// of 30; and a `--since-id` that stops the feed early is expected, so only
+/// the length of the feed counts.
+fn short_feed(args: &Args, sort: SortMode, listed: usize) -> Result<(), Error> {
+    let requested = args.list.count.get();
+    if !args.list.count_asked || listed >= requested {
+        return Ok(());
+    }
+    let feed = sort.feed();
+    if args.list.strict {
+        return Err(Error::ShortFeed {
+            feed,
+            requested,
+            available: listed,
+        });
+    }
+    eprint_line(
+        &format!("warning: {feed} lists {listed} stories, fewer than the {requested} asked for"),
+        theme::current().warn,
+    );
+    Ok(())
+}
+
+/// Adds what the flags ask for beyond the stories themselves: link
+/// statuses, archived copies, poll results and top comments.
+fn annotate(args: &Args, client: &HnClient, stories: &mut [Story]) {
+    if args.list.check_links {
+        check_links(args, client, stories);
+    }
+    if args.list.check_links || args.list.archive_links {
+        archive_links(args, client, stories);
+    }
+    if args.list.include_poll_results && !args.list.offline {
+        poll_results(args, client, stories);
+    }
+    if args.list.top_comment && !args.list.offline {
+        top_comments(args, client, stories);
+    }
+}
+
+/// Fetches the selected feeds and their stories, then applies the filters
+/// to each feed's section.
+/// Comments: under `--stream` matching stories are printed as they arrive,
+/// and in incremental mode as soon as they are next in rank order.
+pub fn fetch_listing(args: &Args, client: &HnClient) -> Result<Listing, Error> {
+    // 1. Fetch story IDs
+    let mut timing = Timing::start();
+    let retries_before = client.retries_performed();
+    let (throttled_before, waited_before) = client.throttled();
+    let start = Instant::now();
+    let mut ids = feed_ids(args, client)?;
+    let feeds = args.merged().then(|| ids.merge());
+    let FeedIds {
+        ids: target_ids,
+        mut section_of,
+        mut sections,
+        spare,
+        ..
+    } = ids;
+    timing.list(start.elapsed());
+
+    // 2. Set up Progress Bar
+    // Comments: indicatif helps manage user expectations during blocking I/O
+    let pb = progress_bar(args, target_ids.len());
+    logging::attach(&pb);
+
+    // 3. Fetch stories concurrently, in batches through Algolia, or from the cache
+    //    when offline
+    // Comments: Ctrl-C stops issuing requests; whatever arrived is still shown
+    interrupt::install();
+    let criteria = args.criteria();
+    let mut in_order = args.incremental().then(|| Reorder::new(args, &pb));
+    let mut block = String::new();
+    let mut on_story = |index: usize, story: Option<&Story>| {
+        if let Some(story) = story
+            && args.list.stream
+            && criteria.matches(story)
+        {
+            block.clear();
+            write_story(&mut block, index + 1, story, None, args.layout());
+            pb.suspend(|| print_stdout(&block));
+        }
+        if let Some(in_order) = &mut in_order {
+            in_order.push(index, story);
+        }
+    };
+    let fetched = get_stories(args, client, &target_ids, &pb, &mut timing, &mut on_story);
+
+    if let Some(in_order) = in_order
+        && fetched.is_ok()
+    {
+        in_order.finish();
+    }
+    pb.finish_and_clear();
+    logging::detach();
+    let (mut stories, mut failures) = fetched?;
+    let fetched = stories.len();
+    if !args.list.offline {
+        stories
+            .iter()
+            .filter(|story| !story.cached)
+            .for_each(cache::store_item);
+    }
+
+    stories.retain(|story| criteria.matches(story));
+    let mut karma = args.list.min_karma.map(Karma::new);
+    if let Some(karma) = &mut karma {
+        karma.retain(args, client, &mut stories);
+    }
+    let collapsed = args
+        .list
+        .dedupe_url
+        .then(|| filter::dedupe_urls(&mut stories));
+    let grouped = group(stories, sections.len(), &section_of);
+    let mut stories = Vec::new();
+    for (index, ((section, mut shown), spare)) in
+        sections.iter_mut().zip(grouped).zip(&spare).enumerate()
+    {
+        if let Some(max) = args.list.max_per_domain {
+            let mut backfill = Backfill {
+                spare,
+                section: index,
+                section_of: &mut section_of,
+                failures: &mut failures,
+                karma: karma.as_mut(),
+            };
+            section.capped = cap_per_domain(args, client, &mut shown, max, &mut backfill)?;
+        }
+        let shown = refine(args, shown);
+        section.len = shown.len();
+        stories.extend(shown);
+    }
+    tag_feeds(&mut stories, feeds.as_ref());
+    annotate(args, client, &mut stories);
+    let next_page = next_page(args.list.count.get(), &spare, &section_of);
+
+    timing.retries(client.retries_performed() - retries_before);
+    let (throttled, waited) = client.throttled();
+    timing.throttled(
+        throttled - throttled_before,
+        waited.saturating_sub(waited_before),
+    );
+
+    Ok(Listing {
+        stories,
+        sections,
+        fetched,
+        collapsed: collapsed.unwrap_or_default(),
+        low_karma: karma.map_or(0, |karma| karma.dropped),
+        failures,
+        newest: target_ids.iter().copied().max(),
+        requested: target_ids,
+        next_page,
+        timing,
+    })
+}
+
+/// `stories` split by the section each belongs to, in order; any story
+/// outside every section is left out.
+fn group(
+    stories: Vec<Story>,
+    sections: usize,
+    section_of: &HashMap<u32, usize>,
+) -> Vec<Vec<Story>> {
+    let mut grouped: Vec<Vec<Story>> = (0..sections).map(|_| Vec::new()).collect();
+    for story in stories {
+        if let Some(&section) = section_of.get(&story.id) {
+            grouped[section].push(story);
+        } else {
+            debug!(id = story.id, "left out a story no feed listed");
+        }
+    }
+    grouped
+}
+
+/// Up to `count` ids of each section past those it fetched, each once and in
+/// feed order.
+fn next_page(count: usize, spare: &[Vec<u32>], fetched: &HashMap<u32, usize>) -> Vec<u32> {
+    let mut next = Vec::new();
+    for id in spare.iter().flat_map(|ids| &ids[..count.min(ids.len())]) {
+        if !fetched.contains_key(id) && !next.contains(id) {
+            next.push(*id);
+        }
+    }
+    next
+}
+
+/// Where [`cap_per_domain`] finds stories for the places it empties.
+struct Backfill<'a> {
+    /// The section's unseen ids past `--count`, in feed order.
+    spare: &'a [u32],
+    /// Index of the section.
+    section: usize,
+    /// The section of every id fetched so far; backfilled ids join it.
+    section_of: &'a mut HashMap<u32, usize>,
+    /// Backfilled stories that fail to fetch join these.
+    failures: &'a mut Vec<FetchFailure>,
+    /// `--min-karma`, which backfilled stories must pass too.
+    karma: Option<&'a mut Karma>,
+}
+
+/// Keeps the first `max` stories of `stories` per registrable domain, then
+/// fills each place left out with the next story of the feed that passes the
+/// filters and the cap, while the feed lasts. Returns how many stories the
+/// cap left out in the end.
+/// Comments: spare ids another section already lists are skipped, as in
+/// [`feed_ids`]; each round fetches only as many as places are still open.
+fn cap_per_domain(
+    args: &Args,
+    client: &HnClient,
+    stories: &mut Vec<Story>,
+    max: usize,
+    backfill: &mut Backfill,
+) -> Result<usize, Error> {
+    let mut cap = DomainCap::new(max);
+    let listed = stories.len();
+    stories.retain(|story| cap.admit(story));
+    let mut capped = listed - stories.len();
+
+    let criteria = args.criteria();
+    let mut spare = backfill.spare.iter();
+    let mut open = capped;
+    while open > 0 && !interrupt::requested() {
+        let ids: Vec<u32> = spare
+            .by_ref()
+            .filter(|id| !backfill.section_of.contains_key(id))
+            .take(open)
+            .copied()
+            .collect();
+        if ids.is_empty() {
+            break;
+        }
+        for &id in &ids {
+            backfill.section_of.insert(id, backfill.section);
+        }
+        let (mut fetched, failed) = fetch_ids(args, client, &ids)?;
+        backfill.failures.extend(failed);
+        fetched.retain(|story| criteria.matches(story));
+        if let Some(karma) = &mut backfill.karma {
+            karma.retain(args, client, &mut fetched);
+        }
+        for story in fetched {
+            if cap.admit(&story) {
+                stories.push(story);
+                open -= 1;
+            } else {
+                capped += 1;
+            }
+        }
+    }
+    Ok(capped)
+}
+
+/// How long a cached profile's karma is trusted before `--min-karma`
+/// fetches it again.
+const KARMA_MAX_AGE: Duration = Duration::from_hours(24);
+
+/// `--min-karma`: the karma of every submitter looked up so far, and how
+/// many stories it left out.
+struct Karma {
+    min: i32,
+    /// `None` for submitters whose profile couldn't be had.
+    known: HashMap<String, Option<i32>>,
+    dropped: usize,
+}
+
+impl Karma {
+    fn new(min: i32) -> Self {
+        Self {
+            min,
+            known: HashMap::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Leaves out the stories of `stories` whose submitter has less than
+    /// `--min-karma`, first looking up the submitters not seen before: from
+    /// the cache, then with up to `--jobs` profile requests at a time.
+    /// Comments: a lookup fails open, keeping the stories, since a filter
+    /// this crude shouldn't hide a listing behind an outage; offline, only
+    /// cached profiles count. Stories without a submitter are kept.
+    fn retain(&mut self, args: &Args, client: &HnClient, stories: &mut Vec<Story>) {
+        let mut unseen: Vec<&str> = stories
+            .iter()
+            .map(|story| story.by.as_str())
+            .filter(|by| !by.is_empty() && !self.known.contains_key(*by))
+            .collect();
+        unseen.sort_unstable();
+        unseen.dedup();
+        let max_age = (!args.list.offline).then_some(KARMA_MAX_AGE);
+        let mut missing = Vec::new();
+        for name in unseen {
+            match cache::load_user(name, max_age) {
+                Ok(user) => {
+                    self.known.insert(name.to_string(), Some(user.karma));
+                },
+                Err(e) if args.list.offline => {
+                    debug!(user = name, error = %e, "no cached karma; keeping their stories");
+                    self.known.insert(name.to_string(), None);
+                },
+                Err(_) => missing.push(name.to_string()),
+            }
+        }
+
+        let names: Vec<(usize, &str)> = missing.iter().map(String::as_str).enumerate().collect();
+        let fetched = for_each_job(args, &names, "fetching submitter karma", |name| {
+            client.user(name)
+        });
+        for (index, user) in fetched {
+            let karma = user
+                .inspect(cache::store_user)
+                .inspect_err(|e| {
+                    debug!(
+                        user = missing[index],
+                        error = %e,
+                        "karma lookup failed; keeping their stories"
+                    );
+                })
+                .ok()
+                .map(|user| user.karma);
+            self.known.insert(missing[index].clone(), karma);
+        }
+
+        let listed = stories.len();
+        stories.retain(|story| {
+            self.known
+                .get(&story.by)
+                .copied()
+                .flatten()
+                .is_none_or(|karma| karma >= self.min)
+        });
+        self.dropped += listed - stories.len();
+    }
+}
+
+/// Applies `--digest` and `--order-by` to the stories of one section.
+fn refine(args: &Args, mut stories: Vec<Story>) -> Vec<Story> {
+    if args.list.digest {
+        stories = filter::digest(stories, args.list.digest_domains);
+    }
+    filter::order_by(&mut stories, args.order_by(), args.list.gravity, args.now());
+    stories
+}
+
+/// Checks the links of `stories` with up to `--jobs` concurrent requests and
+/// records each outcome in the story's `link_status`.
+/// Comments: text posts have no link to check, and a dead link only marks
+/// its story; after Ctrl-C the remaining links simply stay unchecked.
+fn check_links(args: &Args, client: &HnClient, stories: &mut [Story]) {
+    let links: Vec<(usize, &str)> = stories
+        .iter()
+        .enumerate()
+        .filter_map(|(index, story)| Some((index, story.url.as_deref()?)))
+        .collect();
+    let checked = for_each_job(args, &links, "checking links", |url| client.check_link(url));
+    for (index, status) in checked {
+        stories[index].link_status = Some(status);
+    }
+}
+
+/// Fetches the options of every poll among `stories` with up to `--jobs`
+/// polls at a time and records them in the poll's `poll_options`.
+/// Comments: a poll whose options fail to fetch is shown without results.
+fn poll_results(args: &Args, client: &HnClient, stories: &mut [Story]) {
+    let polls: Vec<(usize, &Story)> = stories
+        .iter()
+        .enumerate()
+        .filter(|(_, story)| story.parts.as_ref().is_some_and(|parts| !parts.is_empty()))
+        .collect();
+    let fetched = for_each_job(args, &polls, "fetching poll results", |poll| {
+        client.poll_options(poll)
+    });
+    for (index, options) in fetched {
+        match options {
+            Ok(options) => stories[index].poll_options = Some(options),
+            Err(e) => debug!(id = stories[index].id, error = %e, "poll results failed"),
+        }
+    }
+}
+
+/// Fetches the first comment of each story that has any, for
+/// `--top-comment`.
+/// Comments: a comment that fails (or was deleted) just leaves the story
+/// without a preview.
+fn top_comments(args: &Args, client: &HnClient, stories: &mut [Story]) {
+    let firsts: Vec<(usize, u32)> = stories
+        .iter()
+        .enumerate()
+        .filter_map(|(index, story)| Some((index, *story.kids.as_ref()?.first()?)))
+        .collect();
+    let fetched = for_each_job(args, &firsts, "fetching top comments", |id| {
+        client.comment(id)
+    });
+    for (index, comment) in fetched {
+        match comment {
+            Ok(comment) => stories[index].top_comment = Some(comment),
+            Err(e) => debug!(id = stories[index].id, error = %e, "top comment failed"),
+        }
+    }
+}
+
+/// Looks up Wayback Machine copies of dead links, or of every link under
+/// `--archive-links`, and records them in the stories' `archive_url`.
+/// Comments: snapshots found once are cached and reused; offline, only those
+/// are shown. A failed lookup just leaves the story without one.
+fn archive_links(args: &Args, client: &HnClient, stories: &mut [Story]) {
+    let mut snapshots = cache::load_snapshots();
+    let mut missing = Vec::new();
+    for (index, story) in stories.iter_mut().enumerate() {
+        let Some(url) = &story.url else {
+            continue;
+        };
+        if !args.list.archive_links && !story.link_status.as_ref().is_some_and(LinkStatus::is_dead)
+        {
+            continue;
+        }
+        match snapshots.get(url) {
+            Some(snapshot) => story.archive_url = Some(snapshot.clone()),
+            None if !args.list.offline => missing.push((index, url.clone())),
+            None => {},
+        }
+    }
+    if missing.is_empty() {
+        return;
+    }
+
+    let links: Vec<(usize, &str)> = missing
+        .iter()
+        .map(|(index, url)| (*index, url.as_str()))
+        .collect();
+    let found = for_each_job(args, &links, "looking up archived copies", |url| {
+        client
+            .wayback_snapshot(url)
+            .inspect_err(|e| debug!(url, error = %e, "Wayback Machine lookup failed"))
+            .ok()
+            .flatten()
+    });
+    for (index, snapshot) in found {
+        if let Some(snapshot) = snapshot {
+            if let Some(url) = &stories[index].url {
+                snapshots.insert(url.clone(), snapshot.clone());
+            }
+            stories[index].archive_url = Some(snapshot);
+        }
+    }
+    cache::store_snapshots(&snapshots);
+}
+
+/// The site icons of `stories` for `--with-favicons`, as `data:` URIs by
+/// origin (see [`origin`]), fetched once per site with up to `--jobs`
+/// requests at a time.
+/// Comments: icons found once are cached and reused; offline, only those are
+/// shown. A site whose icon fails to fetch is shown without one.
+pub fn favicons(args: &Args, client: &HnClient, stories: &[Story]) -> HashMap<String, String> {
+    let mut icons = cache::load_favicons();
+    let mut missing: Vec<String> = stories
+        .iter()
+        .filter_map(origin)
+        .filter(|origin| !icons.contains_key(origin))
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+    if missing.is_empty() || args.list.offline {
+        return icons;
+    }
+
+    let sites: Vec<(usize, &str)> = missing.iter().map(String::as_str).enumerate().collect();
+    let found = for_each_job(args, &sites, "fetching favicons", |origin| {
+        client.favicon(origin)
+    });
+    for (index, icon) in found {
+        if let Some(icon) = icon {
+            icons.insert(missing[index].clone(), icon);
+        }
+    }
+    cache::store_favicons(&icons);
+    icons
+}
+
+/// The scheme, host and port of a story's link, e.g. `https://example.com`,
+/// which its favicon is looked up by.
+pub fn origin(story: &Story) -> Option<String> {
+    let origin = url::Url::parse(story.url.as_deref()?).ok()?.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+/// Runs `work` on each job (a link, a poll, a comment) with up to `--jobs`
+/// threads behind a progress bar showing `message`, and returns each result
+/// with the index it came with.
+/// Comments: stops handing out jobs after Ctrl-C.
+pub fn for_each_job<J: Copy + Sync, T: Send>(
+    args: &Args,
+    jobs: &[(usize, J)],
+    message: &'static str,
+    work: impl Fn(J) -> T + Sync,
+) -> Vec<(usize, T)> {
+    let pb = progress_bar(args, jobs.len());
+    pb.set_message(message);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(jobs.len()));
+    thread::scope(|scope| {
+        for _ in 0..args.list.jobs.get().min(jobs.len()) {
+            scope.spawn(|| {
+                while !interrupt::requested() {
+                    let Some(&(index, job)) = jobs.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                        break;
+                    };
+                    let result = work(job);
+                    pb.inc(1);
+                    results
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .push((index, result));
+                }
+            });
+        }
+    });
+    pb.finish_and_clear();
+    results.into_inner().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,318 @@
+//! Post-fetch filters and ordering applied to the story list before it is
+//! printed.
+
+use std::{cmp::Reverse, collections::HashMap, ops::Range, sync::OnceLock};
+
+use chrono::Utc;
+use hn_cli::{ItemType, Story};
+
+use crate::{OrderBy, blocklist::Blocklist, expr::Expr};
+
+/// `--filter` terms, for highlighting in titles; set once at startup.
+static KEYWORDS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Per-story conditions a story must meet to be shown.
+#[derive(Debug, Default)]
+pub struct Criteria {
+    pub min_score: Option<i32>,
+    /// Compared against `descendants`; stories without it count as 0.
+    pub min_comments: Option<u32>,
+    /// Oldest a story may be, in seconds; stories without a `time` are left
+    /// out.
+    pub max_age: Option<i64>,
+    /// Youngest a story may be, in seconds; stories without a `time` are
+    /// kept.
+    pub min_age: Option<i64>,
+    /// Keep only text posts, i.e. stories without a link.
+    pub text_only: bool,
+    /// Keep only stories with a link.
+    pub links_only: bool,
+    /// Leave out job postings.
+    pub no_jobs: bool,
+    /// Keep only stories whose title contains one of these, ignoring case.
+    pub keywords: Vec<String>,
+    /// Keep only stories matching this `--where` expression.
+    pub expr: Option<Expr>,
+    /// Leave out blocked sites and authors.
+    pub blocklist: Blocklist,
+}
+
+impl Criteria {
+    /// Whether any filter is set, so that some stories may not match.
+    pub const fn is_active(&self) -> bool {
+        self.min_score.is_some()
+            || self.min_comments.is_some()
+            || self.max_age.is_some()
+            || self.min_age.is_some()
+            || self.text_only
+            || self.links_only
+            || self.no_jobs
+            || !self.keywords.is_empty()
+            || self.expr.is_some()
+            || !self.blocklist.is_empty()
+    }
+
+    pub fn matches(&self, story: &Story) -> bool {
+        let age = story
+            .time
+            .map(|time| Utc::now().timestamp().saturating_sub(time));
+        self.min_score.is_none_or(|min| story.score >= min)
+            && self
+                .min_comments
+                .is_none_or(|min| story.descendants.unwrap_or_default() >= min)
+            && self
+                .max_age
+                .is_none_or(|max| age.is_some_and(|age| age <= max))
+            && self
+                .min_age
+                .is_none_or(|min| age.is_none_or(|age| age >= min))
+            && (!self.text_only || story.is_text_post())
+            && (!self.links_only || !story.is_text_post())
+            && !(self.no_jobs && story.kind == ItemType::Job)
+            && (self.keywords.is_empty()
+                || self
+                    .keywords
+                    .iter()
+                    .any(|term| contains(&story.title, term)))
+            && self
+                .expr
+                .as_ref()
+                .is_none_or(|expr| expr.matches(story, Utc::now().timestamp()))
+            && !self.blocklist.blocks(story)
+    }
+}
+
+/// Highlights the `terms` in titles from now on.
+pub fn set_keywords(terms: Vec<String>) {
+    let _ = KEYWORDS.set(terms);
+}
+
+/// The `--filter` terms.
+pub fn keywords() -> &'static [String] {
+    KEYWORDS.get().map_or(&[], Vec::as_slice)
+}
+
+/// Byte ranges of `title` matching any of `terms`, ignoring case, in order.
+/// Comments: overlapping and touching matches are merged, so each
+/// highlighted run is styled once rather than nested.
+pub fn keyword_ranges(title: &str, terms: &[String]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = terms
+        .iter()
+        .filter(|term| !term.is_empty())
+        .flat_map(|term| {
+            title.char_indices().filter_map(move |(start, _)| {
+                match_len(&title[start..], term).map(|len| start..start + len)
+            })
+        })
+        .collect();
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Whether `title` contains `term`, ignoring case.
+pub fn contains(title: &str, term: &str) -> bool {
+    term.is_empty()
+        || title
+            .char_indices()
+            .any(|(start, _)| match_len(&title[start..], term).is_some())
+}
+
+/// Length in bytes of the start of `text` that equals `term` ignoring case.
+/// Comments: compares lowercased chars rather than lowercased strings, whose
+/// byte offsets wouldn't line up with the original title.
+fn match_len(text: &str, term: &str) -> Option<usize> {
+    let mut expected = term.chars().flat_map(char::to_lowercase).peekable();
+    for (index, c) in text.char_indices() {
+        for lower in c.to_lowercase() {
+            if expected.next() != Some(lower) {
+                return None;
+            }
+        }
+        if expected.peek().is_none() {
+            return Some(index + c.len_utf8());
+        }
+    }
+    None
+}
+
+/// Sorts the stories by `order`, highest (or newest) first; the sort is
+/// stable, so ties keep their feed order. [`OrderBy::Hot`] ranks with
+/// `gravity` and ages measured from `now`, or without one (under
+/// `--deterministic`) from the newest story.
+pub fn order_by(stories: &mut [Story], order: OrderBy, gravity: f64, now: Option<i64>) {
+    match order {
+        OrderBy::Rank => {},
+        OrderBy::Score => stories.sort_by_key(|story| Reverse(story.score)),
+        OrderBy::Comments => {
+            stories.sort_by_key(|story| Reverse(story.descendants.unwrap_or_default()));
+        },
+        OrderBy::Age => stories.sort_by_key(|story| Reverse(story.time)),
+        OrderBy::Hot => {
+            let now = now.or_else(|| stories.iter().filter_map(|story| story.time).max());
+            stories.sort_by(|a, b| hot_rank(b, now, gravity).total_cmp(&hot_rank(a, now, gravity)));
+        },
+    }
+}
+
+/// The front page's ranking formula, `score / (age_hours + 2)^gravity`: the
+/// score decays with age, faster the higher `gravity` is.
+/// Comments: stories without a submission time rank below all others.
+fn hot_rank(story: &Story, now: Option<i64>, gravity: f64) -> f64 {
+    let (Some(time), Some(now)) = (story.time, now) else {
+        return f64::NEG_INFINITY;
+    };
+    let seconds = u32::try_from(now.saturating_sub(time).max(0)).map_or(f64::MAX, f64::from);
+    f64::from(story.score) / (seconds / 3600.0 + 2.0).powf(gravity)
+}
+
+/// The best-scoring story from each of the `domains` most common hosts in
+/// `stories`, most common host first. Ties go to the host that appeared
+/// first and to the higher-ranked story; stories without a URL have no host
+/// and are left out.
+pub fn digest(stories: Vec<Story>, domains: Option<usize>) -> Vec<Story> {
+    struct Source {
+        count: usize,
+        best: Story,
+    }
+
+    let mut sources: Vec<Source> = Vec::new();
+    let mut by_host = HashMap::new();
+    for story in stories {
+        let Some(host) = story.host() else {
+            continue;
+        };
+        if let Some(&i) = by_host.get(&host) {
+            let source: &mut Source = &mut sources[i];
+            source.count += 1;
+            if story.score > source.best.score {
+                source.best = story;
+            }
+        } else {
+            by_host.insert(host, sources.len());
+            sources.push(Source {
+                count: 1,
+                best: story,
+            });
+        }
+    }
+
+    sources.sort_by_key(|source| Reverse(source.count));
+    sources
+        .into_iter()
+        .take(domains.unwrap_or(usize::MAX))
+        .map(|source| source.best)
+        .collect()
+}
+
+/// Keeps one story of those linking to the same page, as
+/// [`Story::normalized_url`] tells: the best-scoring one, in the place of
+/// the first of them, with the ids of the others in its `duplicates` in
+/// rank order. Ties go to the higher-ranked story; text posts are never
+/// duplicates. Returns how many stories were left out.
+pub fn dedupe_urls(stories: &mut Vec<Story>) -> usize {
+    let mut groups: Vec<Vec<Story>> = Vec::new();
+    let mut by_url: HashMap<String, usize> = HashMap::new();
+    for story in stories.drain(..) {
+        match story.normalized_url() {
+            Some(url) if let Some(&i) = by_url.get(&url) => groups[i].push(story),
+            Some(url) => {
+                by_url.insert(url, groups.len());
+                groups.push(vec![story]);
+            },
+            None => groups.push(vec![story]),
+        }
+    }
+
+    let mut collapsed = 0;
+    for mut group in groups {
+        let best = group
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, story)| (story.score, Reverse(i)))
+            .map_or(0, |(i, _)| i);
+        let mut kept = group.remove(best);
+        collapsed += group.len();
+        kept.duplicates = group.iter().map(|story| story.id).collect();
+        stories.push(kept);
+    }
+    collapsed
+}
+
+/// A running tally of stories per registrable domain, for `--max-per-domain`
+/// over stories seen one at a time in rank order.
+#[derive(Debug)]
+pub struct DomainCap {
+    max: usize,
+    seen: HashMap<String, usize>,
+}
+
+impl DomainCap {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Counts `story` and tells whether it is still within the cap. Stories
+    /// without a URL always are.
+    pub fn admit(&mut self, story: &Story) -> bool {
+        let Some(host) = story.host() else {
+            return true;
+        };
+        let count = self
+            .seen
+            .entry(registrable_domain(&host).to_string())
+            .or_default();
+        *count += 1;
+        *count <= self.max
+    }
+}
+
+/// Second-level labels under which country domains register names, as in
+/// `bbc.co.uk` or `abc.net.au`.
+const COUNTRY_SECOND_LEVELS: [&str; 10] = [
+    "ac", "co", "com", "edu", "gov", "go", "ne", "net", "or", "org",
+];
+
+/// Hosting domains whose subdomains belong to different owners, as in
+/// `alice.github.io`.
+const SHARED_HOSTS: [&str; 7] = [
+    "blogspot.com",
+    "github.io",
+    "gitlab.io",
+    "herokuapp.com",
+    "netlify.app",
+    "pages.dev",
+    "vercel.app",
+];
+
+/// The part of `host` its owner registered, e.g. `bbc.co.uk` for
+/// `news.bbc.co.uk` and `alice.github.io` for `blog.alice.github.io`; IP
+/// addresses stay as they are.
+/// Comments: an approximation of the Public Suffix List covering the common
+/// cases, without shipping the list.
+pub fn registrable_domain(host: &str) -> &str {
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+    let labels: Vec<&str> = host.rsplit('.').collect();
+    let suffix = match labels.as_slice() {
+        [tld, second, ..] if SHARED_HOSTS.contains(&format!("{second}.{tld}").as_str()) => 2,
+        [tld, second, ..] if tld.len() == 2 && COUNTRY_SECOND_LEVELS.contains(second) => 2,
+        _ => 1,
+    };
+    if labels.len() <= suffix + 1 {
+        return host;
+    }
+    let keep: usize = labels[..=suffix].iter().map(|label| label.len() + 1).sum();
+    &host[host.len() + 1 - keep..]
+}
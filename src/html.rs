@@ -0,0 +1,96 @@
+//! Plain-text rendering of the small HTML subset HN uses in item text.
+
+/// Converts HN item HTML to plain text.
+///
+/// `<p>` starts a new paragraph, links are replaced by their target (HN
+/// shortens long link text with `...`), other tags are dropped and entities
+/// are decoded.
+pub fn to_plain(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..start]));
+        let Some(len) = rest[start..].find('>') else {
+            // Comments: a stray `<` is text, not a tag.
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start + 1..start + len];
+        rest = &rest[start + len + 1..];
+
+        let name = tag
+            .split(|c: char| c.is_ascii_whitespace())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "p" => text.push_str("\n\n"),
+            "br" | "br/" => text.push('\n'),
+            "a" => {
+                if let Some(href) = attribute(tag, "href") {
+                    text.push_str(&decode_entities(href));
+                    // Skip the link text up to `</a>`.
+                    rest = rest.find("</a>").map_or(rest, |end| &rest[end + 4..]);
+                }
+            },
+            _ => {},
+        }
+    }
+    text.push_str(&decode_entities(rest));
+    text.trim().to_string()
+}
+
+/// The double-quoted value of attribute `name` in a tag's source.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{name}=\""))? + name.len() + 2;
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+/// Decodes named (`&amp;`, `&quot;`, ...) and numeric (`&#x27;`, `&#39;`)
+/// entities; unknown ones are left as they are.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .and_then(|end| Some((end, decode_entity(&rest[1..end])?)));
+        if let Some((end, c)) = entity {
+            decoded.push(c);
+            rest = &rest[end + 1..];
+        } else {
+            decoded.push('&');
+            rest = &rest[1..];
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The character named by an entity body such as `amp` or `#x2F`.
+fn decode_entity(entity: &str) -> Option<char> {
+    let code = if let Some(hex) = entity
+        .strip_prefix("#x")
+        .or_else(|| entity.strip_prefix("#X"))
+    {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if let Some(decimal) = entity.strip_prefix('#') {
+        decimal.parse().ok()?
+    } else {
+        return match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => None,
+        };
+    };
+    char::from_u32(code)
+}
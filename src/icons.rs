@@ -0,0 +1,82 @@
+//! `--type-icons`: a glyph before each title telling what kind of post it
+//! is, so a mix of feeds reads at a glance: 📰 story, ❓ Ask HN, 🎬 Show HN,
+//! 💼 job and 📊 poll, or `[story]`, `[ask]` and so on under `--ascii`.
+//!
+//! Off unless `--type-icons` or `type_icons = true` in the config file asks
+//! for it, and fixed for the run like the theme.
+
+use std::sync::OnceLock;
+
+use hn_cli::{ItemType, Story, TitlePrefix};
+
+/// The icons of this run, if any; set once at startup.
+static ICONS: OnceLock<Icons> = OnceLock::new();
+
+/// How the type of a story is written.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum Icons {
+    /// No icon.
+    #[default]
+    Off,
+    /// 📰, ❓ and the rest.
+    Emoji,
+    /// Bracketed words, for terminals without emoji.
+    Ascii,
+}
+
+/// What a story is, as far as its icon goes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Kind {
+    Story,
+    Ask,
+    Show,
+    Job,
+    Poll,
+}
+
+impl Kind {
+    /// Jobs and polls by their `type`; Ask HN and Show HN posts, which the
+    /// API types as stories, by their title.
+    /// Comments: items cached before the `type` field was kept have
+    /// [`ItemType::Unknown`]; those with poll options are polls, and the
+    /// rest stories, as everything in a feed but jobs and polls is.
+    fn of(story: &Story) -> Self {
+        match story.kind {
+            ItemType::Job => Self::Job,
+            ItemType::Poll => Self::Poll,
+            ItemType::Unknown if story.parts.is_some() => Self::Poll,
+            _ => match story.title_prefix() {
+                Some(TitlePrefix::Ask) => Self::Ask,
+                Some(TitlePrefix::Show) => Self::Show,
+                Some(TitlePrefix::Tell) | None => Self::Story,
+            },
+        }
+    }
+}
+
+/// Uses `icons` for the rest of the run.
+pub fn set(icons: Icons) {
+    let _ = ICONS.set(icons);
+}
+
+/// The icon of `story` in this run's style, or `None` when icons are off.
+pub fn of(story: &Story) -> Option<&'static str> {
+    let kind = Kind::of(story);
+    match ICONS.get().copied().unwrap_or_default() {
+        Icons::Off => None,
+        Icons::Emoji => Some(match kind {
+            Kind::Story => "📰",
+            Kind::Ask => "❓",
+            Kind::Show => "🎬",
+            Kind::Job => "💼",
+            Kind::Poll => "📊",
+        }),
+        Icons::Ascii => Some(match kind {
+            Kind::Story => "[story]",
+            Kind::Ask => "[ask]",
+            Kind::Show => "[show]",
+            Kind::Job => "[job]",
+            Kind::Poll => "[poll]",
+        }),
+    }
+}
@@ -0,0 +1,36 @@
+//! Ctrl-C handling that lets a run stop early and still print its results.
+
+use std::{
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use hn_cli::Error;
+
+/// Conventional exit code for a process terminated by SIGINT.
+pub const EXIT_CODE: u8 = Error::Interrupted.exit_code();
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the handler: the first Ctrl-C asks the fetch loops to stop, a
+/// second one exits immediately.
+pub fn install() {
+    let installed = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            process::exit(i32::from(EXIT_CODE));
+        }
+    });
+    // Comments: without a handler Ctrl-C still terminates the process, just
+    // without partial results, so a failure here isn't fatal.
+    drop(installed);
+}
+
+/// Whether Ctrl-C has been pressed since [`install`].
+pub fn requested() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Forgets an earlier Ctrl-C, for sessions that outlive one fetch.
+pub fn clear() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
@@ -0,0 +1,198 @@
+//! `last` subcommand: reprints the most recent listing without touching the
+//! network.
+//!
+//! Every listing run saves what it fetched to `last.json` under
+//! [`paths::state_dir`], whole stories included, so `last` can render it in
+//! any format. A copy also goes to `runs/<feed>/`, where the most recent
+//! runs of each feed are kept for `diff`.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use hn_cli::{Error, Story};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    Args, Format, emit,
+    fetch::FetchFailure,
+    header, page, paths, render_badge, render_json, render_pretty, render_table, render_yaml,
+    report_failures,
+    theme::{self, paint},
+};
+
+/// File name of the saved listing inside the state directory.
+const LAST_FILE: &str = "last.json";
+
+/// Directory of the saved runs of each feed inside the state directory.
+const RUNS_DIR: &str = "runs";
+
+/// Runs kept per feed; saving another removes the oldest.
+const RUNS_KEPT: usize = 20;
+
+/// One listing run as saved to disk.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LastRun<'a> {
+    /// Unix seconds when the run finished.
+    pub fetched_at: i64,
+    /// Feeds the stories came from, e.g. `topstories` or
+    /// `topstories + newstories`.
+    pub feed: Cow<'a, str>,
+    pub stories: Cow<'a, [Story]>,
+    failures: Cow<'a, [FetchFailure]>,
+}
+
+/// Saves a listing for `last`. Best effort: a state directory that can't be
+/// written never fails the run that produced the listing.
+pub fn save(args: &Args, stories: &[Story], failures: &[FetchFailure]) {
+    let Some(path) = last_path() else {
+        return;
+    };
+    let run = LastRun {
+        fetched_at: Utc::now().timestamp(),
+        feed: Cow::Owned(
+            args.list
+                .sort
+                .iter()
+                .map(|sort| sort.feed())
+                .collect::<Vec<_>>()
+                .join(" + "),
+        ),
+        stories: Cow::Borrowed(stories),
+        failures: Cow::Borrowed(failures),
+    };
+    let json = serde_json::to_vec(&run).expect("listings serialize to JSON");
+    let feed: Vec<&str> = args.list.sort.iter().map(|sort| sort.feed()).collect();
+    let runs = runs_dir(&feed.join("+"));
+    let copy = runs
+        .as_ref()
+        .map(|dir| dir.join(format!("{}.json", Utc::now().timestamp_millis())));
+    for path in [Some(path), copy].into_iter().flatten() {
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, &json));
+        if let Err(e) = result {
+            debug!(path = %path.display(), error = %e, "saving the last listing failed");
+        }
+    }
+    if let Some(dir) = runs {
+        for old in saved_runs(&dir).iter().rev().skip(RUNS_KEPT) {
+            let _ = fs::remove_file(old);
+        }
+    }
+}
+
+/// Where the runs of `feed` (e.g. `topstories`) are kept.
+pub fn runs_dir(feed: &str) -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join(RUNS_DIR).join(feed))
+}
+
+/// The runs saved in `dir`, oldest first.
+pub fn saved_runs(dir: &Path) -> Vec<PathBuf> {
+    let mut runs: Vec<(u64, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let millis = path.file_stem()?.to_str()?.parse().ok()?;
+            (path.extension()? == "json").then_some((millis, path))
+        })
+        .collect();
+    runs.sort_unstable();
+    runs.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Reads a saved run, `last.json` or one of [`saved_runs`].
+///
+/// # Errors
+///
+/// [`Error::Cache`] if the file can't be read or isn't a saved run.
+pub fn load(path: &Path) -> Result<LastRun<'static>, Error> {
+    let cache_error = |source| Error::Cache {
+        path: path.to_path_buf(),
+        source,
+    };
+    let bytes = fs::read(path).map_err(cache_error)?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| cache_error(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// Reprints the saved listing in the format `args` asks for.
+pub fn run(args: &Args) -> Result<ExitCode, Error> {
+    let path = last_path().ok_or(Error::NoPlatformDir("state"))?;
+    let run = match load(&path) {
+        Ok(run) => run,
+        Err(Error::Cache {
+            source, ..
+        }) if source.kind() == io::ErrorKind::NotFound => {
+            println!("No saved listing yet. Run `hn-cli` once to save one.");
+            return Ok(ExitCode::SUCCESS);
+        },
+        Err(e) => return Err(e),
+    };
+
+    let output = match args.format {
+        Format::Json => render_json(
+            &run.stories,
+            &run.failures,
+            args.errors_only,
+            None,
+            None,
+            args.link_fallback,
+        ),
+        Format::Yaml => render_yaml(&run.stories, None, args.link_fallback),
+        Format::Html => page::render(&run.stories, &[], &HashMap::new(), args.now()),
+        Format::Badge => render_badge(&run.stories, args.badge_field.unwrap_or_default()),
+        Format::Pretty if args.quiet() => render_pretty(&run.stories, args.layout()),
+        Format::Table if args.quiet() => render_table(args, &run.stories),
+        Format::Pretty | Format::Table => format!(
+            "\n{}\n{}{}",
+            theme::current().header(&header(args)),
+            if args.format == Format::Table {
+                render_table(args, &run.stories)
+            } else {
+                render_pretty(&run.stories, args.layout())
+            },
+            fetched_line(args, &run)
+        ),
+    };
+    emit(args, &output)?;
+    report_failures(&run.failures, false);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn last_path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join(LAST_FILE))
+}
+
+/// The closing line saying where and when the listing was fetched.
+fn fetched_line(args: &Args, run: &LastRun) -> String {
+    let noun = if run.stories.len() == 1 {
+        "story"
+    } else {
+        "stories"
+    };
+    let when = DateTime::from_timestamp(run.fetched_at, 0).map_or_else(
+        || "at an unknown time".to_string(),
+        |time| args.format_time(time, "%Y-%m-%d %H:%M"),
+    );
+    format!(
+        "{} {}\n",
+        paint("Saved listing:", theme::current().good).bold(),
+        format!(
+            "{} {noun} from {}, fetched {when}",
+            run.stories.len(),
+            run.feed
+        )
+        .dimmed()
+    )
+}
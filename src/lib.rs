@@ -0,0 +1,478 @@
+//! A small blocking client for the official Hacker News API, the library
+//! behind the `hn-cli` binary.
+//!
+//! ```no_run
+//! use hn_cli::HnClient;
+//!
+//! let client = HnClient::new();
+//! for story in client.top_stories(10)? {
+//!     println!("[{}] {}", story.score, story.title);
+//! }
+//! # Ok::<(), hn_cli::Error>(())
+//! ```
+
+#![warn(missing_docs)]
+// Transitive dependencies (ureq, clap) pull in duplicate platform crates that
+// can't be unified from this manifest.
+#![allow(clippy::multiple_crate_versions)]
+
+#[cfg(all(feature = "tls-rustls", feature = "tls-native"))]
+compile_error!(
+    "features `tls-rustls` and `tls-native` are mutually exclusive; build `tls-native` with \
+     `--no-default-features --features tls-native`"
+);
+#[cfg(not(any(feature = "tls-rustls", feature = "tls-native")))]
+compile_error!("enable a TLS backend: feature `tls-rustls` (the default) or `tls-native`");
+
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize};
+
+mod algolia;
+mod backoff;
+mod client;
+mod error;
+mod html;
+mod links;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod numbers;
+mod rate_limit;
+mod transport;
+
+pub use backoff::Backoff;
+pub use client::{HnClient, HnClientBuilder, RAW_TARGET};
+pub use error::Error;
+pub use links::{LinkStatus, MAX_FAVICON_BYTES};
+pub use numbers::NumberStyle;
+pub use transport::{DEFAULT_MAX_BODY_BYTES, Response, TLS_BACKEND, Transport, UreqTransport};
+
+/// Host serving the official Firebase HN API.
+pub const API_HOST: &str = "hacker-news.firebaseio.com";
+
+/// Discussion page of an item on the HN website, by id.
+pub const ITEM_URL: &str = "https://news.ycombinator.com/item?id=";
+
+/// Which story feed to read.
+#[derive(Copy, Clone, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    /// `newstories`: the newest submissions first.
+    Latest,
+    /// `topstories`: the front page ranking.
+    Hottest,
+    /// `askstories`: the latest Ask HN posts.
+    Ask,
+}
+
+impl SortMode {
+    /// Name of the feed endpoint, e.g. `topstories`.
+    #[must_use]
+    pub const fn feed(self) -> &'static str {
+        match self {
+            Self::Hottest => "topstories",
+            Self::Latest => "newstories",
+            Self::Ask => "askstories",
+        }
+    }
+
+    /// The mode as `--sort` spells it, e.g. `hottest`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Hottest => "hottest",
+            Self::Latest => "latest",
+            Self::Ask => "ask",
+        }
+    }
+}
+
+/// A title convention marking what kind of post a story is, e.g. `Ask HN:`;
+/// the API types all of them as stories.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TitlePrefix {
+    /// `Ask HN`: a question to the community.
+    Ask,
+    /// `Show HN`: something the submitter made.
+    Show,
+    /// `Tell HN`: an announcement to the community.
+    Tell,
+}
+
+impl TitlePrefix {
+    /// The prefix `title` opens with, if any.
+    /// Comments: submitters aren't consistent, so case, leading whitespace
+    /// and the space before `HN` are forgiven, and any punctuation may
+    /// follow, or nothing; more letters may not, so `Ask HNers` or
+    /// `Showing HN` is no prefix.
+    #[must_use]
+    pub fn of(title: &str) -> Option<Self> {
+        let (word, rest) = title.trim_start().split_once(char::is_whitespace)?;
+        let prefix = [
+            ("ask", Self::Ask),
+            ("show", Self::Show),
+            ("tell", Self::Tell),
+        ]
+        .into_iter()
+        .find_map(|(name, prefix)| word.eq_ignore_ascii_case(name).then_some(prefix))?;
+        let rest = rest.trim_start();
+        let after = rest.get(2..)?;
+        (rest[..2].eq_ignore_ascii_case("hn") && !after.starts_with(char::is_alphanumeric))
+            .then_some(prefix)
+    }
+
+    /// Lowercase name, as the JSON output's booleans spell it, e.g. `ask`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Ask => "ask",
+            Self::Show => "show",
+            Self::Tell => "tell",
+        }
+    }
+}
+
+/// Which IP address families connections may use.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub enum IpFamily {
+    /// Whatever the resolver returns, in its order.
+    #[default]
+    Any,
+    /// IPv4 addresses only.
+    V4,
+    /// IPv6 addresses only.
+    V6,
+}
+
+impl IpFamily {
+    /// Whether `addr` belongs to this family.
+    #[must_use]
+    pub const fn allows(self, addr: &std::net::SocketAddr) -> bool {
+        match self {
+            Self::Any => true,
+            Self::V4 => addr.is_ipv4(),
+            Self::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+/// The `type` of an item.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Serialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemType {
+    /// A link or text post.
+    Story,
+    /// A comment.
+    Comment,
+    /// A job posting.
+    Job,
+    /// A poll, whose options are its `parts`.
+    Poll,
+    /// One option of a poll.
+    PollOpt,
+    /// No `type`, or one this version doesn't know.
+    #[default]
+    Unknown,
+}
+
+impl ItemType {
+    /// The type named `name`, as the API spells it, e.g. `pollopt`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "story" => Self::Story,
+            "comment" => Self::Comment,
+            "job" => Self::Job,
+            "poll" => Self::Poll,
+            "pollopt" => Self::PollOpt,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// The type as the API spells it; `unknown` for [`ItemType::Unknown`].
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Story => "story",
+            Self::Comment => "comment",
+            Self::Job => "job",
+            Self::Poll => "poll",
+            Self::PollOpt => "pollopt",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// Whether the type is [`ItemType::Unknown`].
+    #[must_use]
+    pub const fn is_unknown(&self) -> bool {
+        matches!(self, Self::Unknown)
+    }
+}
+
+/// Comments: any value is a type, so an odd `type` never fails the item.
+impl<'de> Deserialize<'de> for ItemType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::String(name) => Self::from_name(&name),
+            _ => Self::Unknown,
+        })
+    }
+}
+
+/// Reads a field as `T`, or as its default when the value has another type.
+fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned + Default,
+{
+    Ok(T::deserialize(serde_json::Value::deserialize(deserializer)?).unwrap_or_default())
+}
+
+/// A story item, or a job or poll listed among the stories.
+/// Comments: only `id` is required; the API leaves fields out of dead,
+/// flagged and some old items, which then read as empty rather than failing.
+///
+/// A field of the API's holding a value of another type than expected reads
+/// as empty too, as do fields this version doesn't know, so a change to the
+/// schema costs that field rather than the item; [`HnClient::item`] rejects
+/// the one item that is no use, a live story without a title.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct Story {
+    /// Item id, also the key of its discussion page.
+    pub id: u32,
+    /// What kind of item this is.
+    #[serde(rename = "type", default, skip_serializing_if = "ItemType::is_unknown")]
+    pub kind: ItemType,
+    /// Story title; empty if the API left it out.
+    #[serde(default, deserialize_with = "lenient")]
+    pub title: String,
+    /// Link, absent for text posts such as Ask HN.
+    #[serde(default, deserialize_with = "lenient")]
+    pub url: Option<String>,
+    /// Current score.
+    #[serde(default, deserialize_with = "lenient")]
+    pub score: i32,
+    /// Username of the submitter; empty if the API left it out.
+    #[serde(default, deserialize_with = "lenient")]
+    pub by: String,
+    /// Total comment count, if the API reported one.
+    #[serde(default, deserialize_with = "lenient")]
+    pub descendants: Option<u32>,
+    /// Body of a text post, as HTML.
+    #[serde(default, deserialize_with = "lenient")]
+    pub text: Option<String>,
+    /// Submission time, in Unix seconds.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub time: Option<i64>,
+    /// Whether the link still answers, once [`HnClient::check_link`] was
+    /// asked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_status: Option<LinkStatus>,
+    /// A Wayback Machine copy of the link, once
+    /// [`HnClient::wayback_snapshot`] found one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_url: Option<String>,
+    /// Ids of the options, for a poll.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub parts: Option<Vec<u32>>,
+    /// The options of a poll, once [`HnClient::poll_options`] fetched them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_options: Option<Vec<PollOpt>>,
+    /// Ids of the top-level comments, in ranked order.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub kids: Option<Vec<u32>>,
+    /// The first of `kids`, once [`HnClient::comment`] fetched it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_comment: Option<Comment>,
+    /// Whether fetching the story failed and this is the copy an earlier
+    /// run cached, so it may be out of date.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// Ids of other stories linking to the same page, by [`normalize_url`],
+    /// that were left out in favor of this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<u32>,
+    /// The feeds listing this story, in a listing that merged several of
+    /// them into one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feeds: Vec<SortMode>,
+}
+
+impl Story {
+    /// Host of the story's link without a leading `www.`, e.g. `github.com`.
+    pub fn host(&self) -> Option<String> {
+        let url = url::Url::parse(self.url.as_deref()?).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        Some(
+            host.strip_prefix("www.")
+                .map_or_else(|| host.clone(), str::to_string),
+        )
+    }
+
+    /// The story's link as [`normalize_url`] puts it, to tell reposts of
+    /// the same page; `None` for text posts.
+    #[must_use]
+    pub fn normalized_url(&self) -> Option<String> {
+        normalize_url(self.url.as_deref()?)
+    }
+
+    /// The story's discussion page on the HN website.
+    #[must_use]
+    pub fn discussion_url(&self) -> String {
+        format!("{ITEM_URL}{}", self.id)
+    }
+
+    /// Where the story points: its link, or its discussion page for a text
+    /// post.
+    #[must_use]
+    pub fn link(&self) -> String {
+        self.url.clone().unwrap_or_else(|| self.discussion_url())
+    }
+
+    /// Whether the story is a text post (Ask HN and most Show HN posts)
+    /// rather than a link.
+    #[must_use]
+    pub const fn is_text_post(&self) -> bool {
+        self.url.is_none()
+    }
+
+    /// The `Ask HN`, `Show HN` or `Tell HN` convention the title opens with,
+    /// if any.
+    #[must_use]
+    pub fn title_prefix(&self) -> Option<TitlePrefix> {
+        TitlePrefix::of(&self.title)
+    }
+
+    /// The text post body as plain text, with paragraphs separated by blank
+    /// lines.
+    #[must_use]
+    pub fn body(&self) -> Option<String> {
+        self.text
+            .as_deref()
+            .map(html::to_plain)
+            .filter(|body| !body.is_empty())
+    }
+}
+
+/// Query parameters [`normalize_url`] drops besides the `utm_*` ones: click
+/// ids and newsletter tags that don't change the page.
+const TRACKING_PARAMS: [&str; 8] = [
+    "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid", "ref_src",
+];
+
+/// `url` reduced so that links to the same page compare equal.
+///
+/// That is the host lowercased and without `www.`, the port only if it isn't
+/// the scheme's default, then the path without a trailing slash and the
+/// query without tracking parameters (`utm_*`, `fbclid` and the like). The
+/// scheme and fragment are left out, so `http` and `https` links match.
+/// E.g. `https://www.Example.com:443/post/?utm_source=hn&id=2#top` becomes
+/// `example.com/post?id=2`. `None` when `url` doesn't parse or has no host.
+#[must_use]
+pub fn normalize_url(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    let mut normalized = host.strip_prefix("www.").unwrap_or(host).to_string();
+    if let Some(port) = url.port() {
+        let _ = write!(normalized, ":{port}");
+    }
+    normalized.push_str(url.path().trim_end_matches('/'));
+    let query: Vec<&str> = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| {
+            let name = pair.split('=').next().unwrap_or_default();
+            !name.is_empty() && !name.starts_with("utm_") && !TRACKING_PARAMS.contains(&name)
+        })
+        .collect();
+    if !query.is_empty() {
+        normalized.push('?');
+        normalized.push_str(&query.join("&"));
+    }
+    Some(normalized)
+}
+
+/// One option of a poll, with its votes.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct PollOpt {
+    /// Item id.
+    pub id: u32,
+    /// The option, as HTML.
+    #[serde(default)]
+    pub text: String,
+    /// Votes for the option.
+    #[serde(default)]
+    pub score: i32,
+}
+
+impl PollOpt {
+    /// The option as plain text, like [`Story::body`].
+    #[must_use]
+    pub fn label(&self) -> String {
+        html::to_plain(&self.text)
+    }
+}
+
+/// A comment item.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct Comment {
+    /// Item id.
+    pub id: u32,
+    /// Username of the commenter.
+    #[serde(default)]
+    pub by: String,
+    /// The comment, as HTML.
+    #[serde(default)]
+    pub text: String,
+    /// Posting time, in Unix seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<i64>,
+}
+
+impl Comment {
+    /// The comment as plain text, like [`Story::body`].
+    #[must_use]
+    pub fn body(&self) -> String {
+        html::to_plain(&self.text)
+    }
+}
+
+/// A user profile.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct User {
+    /// Username.
+    pub id: String,
+    /// Account creation time, in Unix seconds.
+    pub created: i64,
+    /// Karma at the time of fetching.
+    pub karma: i32,
+    /// Self-description, as HTML.
+    pub about: Option<String>,
+    /// Ids of the user's stories, comments and polls, newest first.
+    #[serde(default)]
+    pub submitted: Vec<u32>,
+}
+
+impl User {
+    /// The self-description as plain text, like [`Story::body`].
+    #[must_use]
+    pub fn about_text(&self) -> Option<String> {
+        self.about
+            .as_deref()
+            .map(html::to_plain)
+            .filter(|about| !about.is_empty())
+    }
+}
@@ -0,0 +1,185 @@
+//! Link health checks: whether a story's article still answers, where the
+//! Wayback Machine keeps a copy, and the site's icon.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span};
+use ureq::{Body, ResponseExt, http};
+
+use crate::{HnClient, error::Error};
+
+/// Outcome of [`HnClient::check_link`].
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize, Debug)]
+pub struct LinkStatus {
+    /// HTTP status of the final response; absent when none arrived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Where redirects led, when the link was redirected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+    /// Why no response arrived, e.g. `timeout` or `too many redirects`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LinkStatus {
+    /// Whether the link is dead: no response, or an error status.
+    #[must_use]
+    pub const fn is_dead(&self) -> bool {
+        !matches!(self.status, Some(..400))
+    }
+}
+
+/// Answer of the Wayback Machine availability API.
+#[derive(Deserialize, Debug)]
+struct Availability {
+    archived_snapshots: Snapshots,
+}
+
+#[derive(Deserialize, Debug)]
+struct Snapshots {
+    /// Absent when nothing was archived.
+    closest: Option<Snapshot>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Snapshot {
+    available: bool,
+    url: String,
+}
+
+impl HnClient {
+    /// Checks that `url` still answers, with a HEAD request that falls back
+    /// to a one-byte ranged GET when the server refuses HEAD.
+    ///
+    /// Checks use the client's user agent, headers, IP family and proxy
+    /// settings, follow a few redirects and give up after a few seconds;
+    /// they are neither retried nor rate limited, since they go to other
+    /// hosts than the API.
+    #[must_use]
+    pub fn check_link(&self, url: &str) -> LinkStatus {
+        let _span = debug_span!("check_link", url).entered();
+        let result = match self.links.head(url).call() {
+            Ok(response) if response.status().as_u16() >= 400 => {
+                // Comments: plenty of servers answer HEAD with 403, 404 or
+                // 405 yet serve the page; a GET is what a reader's browser
+                // would send.
+                debug!(
+                    url,
+                    status = response.status().as_u16(),
+                    "HEAD refused, trying GET"
+                );
+                self.links.get(url).header("Range", "bytes=0-0").call()
+            },
+            result => result,
+        };
+        match result {
+            Ok(response) => resolved(&response),
+            Err(e) => {
+                debug!(url, error = %e, "link check failed");
+                LinkStatus {
+                    status: None,
+                    final_url: None,
+                    error: Some(failure_label(&e).to_string()),
+                }
+            },
+        }
+    }
+}
+
+impl HnClient {
+    /// The Wayback Machine's closest snapshot of `url`, if it archived one.
+    ///
+    /// # Errors
+    ///
+    /// As for [`HnClient::get`].
+    pub fn wayback_snapshot(&self, url: &str) -> Result<Option<String>, Error> {
+        let _span = debug_span!("wayback_snapshot", url).entered();
+        let query: String = url::form_urlencoded::byte_serialize(url.as_bytes()).collect();
+        let lookup = format!("{}/available?url={query}", self.wayback_url);
+        let availability: Availability = serde_json::from_value(self.send(&lookup)?)?;
+        Ok(availability
+            .archived_snapshots
+            .closest
+            .filter(|snapshot| snapshot.available)
+            .map(|snapshot| snapshot.url))
+    }
+}
+
+/// Largest favicon [`HnClient::favicon`] accepts, in bytes; bigger ones
+/// would bloat a page that embeds them.
+pub const MAX_FAVICON_BYTES: u64 = 64 * 1024;
+
+impl HnClient {
+    /// The `/favicon.ico` of the site at `origin` (e.g.
+    /// `https://example.com`), as a `data:` URI ready to embed, or `None`
+    /// when it's missing, isn't an image or is over [`MAX_FAVICON_BYTES`].
+    ///
+    /// Fetched like [`HnClient::check_link`]: not retried, not rate limited.
+    #[must_use]
+    pub fn favicon(&self, origin: &str) -> Option<String> {
+        let url = format!("{}/favicon.ico", origin.trim_end_matches('/'));
+        let _span = debug_span!("favicon", url).entered();
+        let mut response = self
+            .links
+            .get(&url)
+            .call()
+            .inspect_err(|e| debug!(url, error = %e, "favicon fetch failed"))
+            .ok()?;
+        if !response.status().is_success() {
+            debug!(url, status = response.status().as_u16(), "no favicon");
+            return None;
+        }
+        let mime = response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .map_or("image/x-icon", |value| {
+                value.split(';').next().unwrap_or_default().trim()
+            })
+            .to_ascii_lowercase();
+        if !mime.starts_with("image/") || !mime.bytes().all(is_mime_byte) {
+            debug!(url, mime, "favicon isn't an image");
+            return None;
+        }
+        let bytes = response
+            .body_mut()
+            .with_config()
+            .limit(MAX_FAVICON_BYTES)
+            .read_to_vec()
+            .inspect_err(|e| debug!(url, error = %e, "favicon unreadable or too large"))
+            .ok()
+            .filter(|bytes| !bytes.is_empty())?;
+        Some(format!("data:{mime};base64,{}", STANDARD.encode(bytes)))
+    }
+}
+
+/// Whether `byte` may appear in a media type such as `image/svg+xml`.
+const fn is_mime_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'/' | b'+' | b'-' | b'.')
+}
+
+/// The status of a response that arrived, with its URL if redirects led
+/// there.
+fn resolved(response: &http::Response<Body>) -> LinkStatus {
+    let redirected = response
+        .get_redirect_history()
+        .is_some_and(|history| history.len() > 1);
+    LinkStatus {
+        status: Some(response.status().as_u16()),
+        final_url: redirected.then(|| response.get_uri().to_string()),
+        error: None,
+    }
+}
+
+/// Short label for a check that got no response.
+const fn failure_label(error: &ureq::Error) -> &'static str {
+    match error {
+        ureq::Error::Timeout(_) => "timeout",
+        ureq::Error::TooManyRedirects => "too many redirects",
+        ureq::Error::HostNotFound => "host not found",
+        ureq::Error::ConnectionFailed => "connection failed",
+        ureq::Error::BadUri(_) | ureq::Error::Http(_) => "invalid URL",
+        _ => "network",
+    }
+}
@@ -0,0 +1,87 @@
+//! `-v`/`-vv` diagnostics through `tracing`, written to stderr.
+
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::Mutex,
+};
+
+use hn_cli::RAW_TARGET;
+use indicatif::ProgressBar;
+use tracing_subscriber::{EnvFilter, fmt};
+
+/// Progress bar that log lines are printed around, if one is being drawn.
+static PROGRESS: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Installs the subscriber. `-v` logs requests, status codes and timings,
+/// `-vv` adds response headers; without either flag `RUST_LOG` decides.
+/// `dump_raw` adds the body of every API response, which `-vv` leaves out.
+pub fn init(verbosity: u8, dump_raw: bool) {
+    let filter = match verbosity {
+        0 => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("off")),
+        1 => EnvFilter::new("hn_cli=debug"),
+        _ => EnvFilter::new(format!("hn_cli=trace,{RAW_TARGET}=off")),
+    };
+    let filter = if dump_raw {
+        filter.add_directive(
+            format!("{RAW_TARGET}=trace")
+                .parse()
+                .expect("the raw body directive parses"),
+        )
+    } else {
+        filter
+    };
+
+    fmt()
+        .with_env_filter(filter)
+        .with_timer(fmt::time::uptime())
+        .with_ansi(io::stderr().is_terminal())
+        .with_writer(|| SuspendingWriter(Vec::new()))
+        .init();
+}
+
+/// Routes log lines around `pb` until [`detach`] is called, so they don't
+/// tear through the bar.
+pub fn attach(pb: &ProgressBar) {
+    *PROGRESS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(pb.clone());
+}
+
+pub fn detach() {
+    *PROGRESS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+/// Buffers one formatted event and writes it to stderr on drop, hiding the
+/// progress bar for the duration of the write.
+struct SuspendingWriter(Vec<u8>);
+
+impl Write for SuspendingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for SuspendingWriter {
+    fn drop(&mut self) {
+        if self.0.is_empty() {
+            return;
+        }
+        let pb = PROGRESS
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+        // There is nowhere left to report a failed log write.
+        let write = || drop(io::stderr().write_all(&self.0));
+        match pb {
+            Some(pb) => pb.suspend(write),
+            None => write(),
+        }
+    }
+}
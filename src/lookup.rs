@@ -0,0 +1,107 @@
+//! `item` and `user` subcommands: one story or profile, in any format.
+
+use std::{collections::HashMap, process::ExitCode};
+
+use chrono::DateTime;
+use colored::Colorize;
+use hn_cli::{Error, HnClient, Story, User};
+
+use crate::{
+    Args, Format, Row, count, emit, indent, page, render_badge, render_table, story_age,
+    theme::{self, paint},
+    title_style,
+};
+
+/// Prints story `id` with its text post body and discussion link.
+pub fn item(args: &Args, client: &HnClient, id: u32) -> Result<ExitCode, Error> {
+    let story = client.item(id)?;
+    let output = match args.format {
+        Format::Json => to_json(&Row::new(&story, args.link_fallback)),
+        Format::Yaml => serde_yaml::to_string(&Row::new(&story, args.link_fallback))
+            .expect("stories serialize to YAML"),
+        Format::Pretty => format_item(&story, args.hot_at, args.now()),
+        Format::Table => render_table(args, std::slice::from_ref(&story)),
+        Format::Badge => render_badge(
+            std::slice::from_ref(&story),
+            args.badge_field.unwrap_or_default(),
+        ),
+        Format::Html => page::render(
+            std::slice::from_ref(&story),
+            &[],
+            &HashMap::new(),
+            args.now(),
+        ),
+    };
+    emit(args, &output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints the profile of `name`.
+pub fn user(args: &Args, client: &HnClient, name: &str) -> Result<ExitCode, Error> {
+    let user = client.user(name)?;
+    let output = match args.format {
+        Format::Json => to_json(&user),
+        Format::Yaml => serde_yaml::to_string(&user).expect("users serialize to YAML"),
+        Format::Pretty => format_user(args, &user),
+        Format::Table | Format::Html | Format::Badge => {
+            unreachable!("validate() rejects the story-only formats for users")
+        },
+    };
+    emit(args, &output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn to_json(value: &impl serde::Serialize) -> String {
+    serde_json::to_string_pretty(value).expect("API values serialize to JSON") + "\n"
+}
+
+/// The pretty story block, as in a listing but without a rank and with the
+/// body and discussion link always shown.
+fn format_item(story: &Story, hot_at: i32, now: Option<i64>) -> String {
+    let theme = theme::current();
+    let score = theme.score(&format!("[{:^4}]", count(story.score)), story.score);
+    let link = story.url.as_ref().map_or_else(String::new, |url| {
+        format!(
+            "      {} {}\n",
+            "🔗".dimmed(),
+            paint(url, theme.url).underline()
+        )
+    });
+    let comments = story.descendants.map_or_else(String::new, |comments| {
+        format!(" — {} comments", count(comments))
+    });
+    let author = paint(&format!("by {}{comments}", story.by), theme.author);
+    let age = story_age(story, now).map_or_else(String::new, |age| format!(" · {age}"));
+    let discussion = story.discussion_url().dimmed();
+    let body = story
+        .body()
+        .map_or_else(String::new, |text| format!("\n{}\n", indent(&text)));
+
+    format!(
+        "{score} {}\n{link}      {author}{age}\n      {} {discussion}\n{body}",
+        title_style(&story.title, story.score, hot_at),
+        "💬".dimmed()
+    )
+}
+
+/// The pretty profile: name and karma, then when the account was created,
+/// its submission count and the about text.
+fn format_user(args: &Args, user: &User) -> String {
+    let created = DateTime::from_timestamp(user.created, 0).map_or_else(
+        || "at an unknown time".to_string(),
+        |time| args.format_time(time, "%Y-%m-%d"),
+    );
+    let about = user
+        .about_text()
+        .map_or_else(String::new, |text| format!("\n{}\n", indent(&text)));
+    let theme = theme::current();
+    format!(
+        "{} {}\n      {}\n{about}",
+        paint(&user.id, theme.title).bold(),
+        paint(&format!("[{} karma]", count(user.karma)), theme.score).bold(),
+        paint(
+            &format!("joined {created}, {} submissions", user.submitted.len()),
+            theme.author
+        )
+    )
+}
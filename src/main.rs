@@ -1,106 +1,2472 @@
-use std::error::Error;
+// Transitive dependencies (ureq, clap) pull in duplicate platform crates that
+// can't be unified from this manifest.
+#![allow(clippy::multiple_crate_versions)]
 
-use clap::{Parser, ValueEnum};
-use colored::*;
-use indicatif::{ProgressBar, ProgressStyle};
-use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Write as _,
+    io::{self, IsTerminal, Write as _},
+    num::{NonZeroU32, NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::OnceLock,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, Utc};
+use clap::{
+    ArgAction, Args as _, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+    error::ErrorKind, parser::ValueSource,
+};
+use colored::{Color, ColoredString, Colorize};
+use console::{Term, measure_text_width, truncate_str};
+use hn_cli::{
+    Backoff, Error, HnClient, IpFamily, ItemType, LinkStatus, NumberStyle, SortMode, Story,
+    TitlePrefix,
+};
+use indicatif::ProgressStyle;
+use serde::{Deserialize, Serialize};
+use ureq::http::{HeaderName, HeaderValue};
+
+mod age;
+mod archive;
+mod blocklist;
+mod bookmarks;
+mod cache;
+mod changed;
+mod comments;
+mod config;
+mod crawl;
+mod diff;
+mod doctor;
+mod exec;
+mod explain;
+mod expr;
+mod fetch;
+mod filter;
+mod icons;
+mod interrupt;
+mod last;
+mod logging;
+mod lookup;
+mod output;
+mod page;
+mod pager;
+mod paths;
+mod pick;
+mod pipe;
+mod prefetch;
+mod profile;
+mod reorder;
+mod repl;
+mod search;
+#[cfg(feature = "serve")]
+mod serve;
+mod table;
+mod theme;
+mod timing;
+mod watch;
+
+use archive::LogQuery;
+use blocklist::{BlockAction, Blocklist};
+use bookmarks::BookmarkAction;
+use cache::CacheAction;
+use config::{Config, ConfigAction};
+use fetch::{FetchFailure, Listing, Section};
+use filter::Criteria;
+use icons::Icons;
+use prefetch::Prefetch;
+use table::Field;
+use theme::{Preset, paint};
 
 // --- Data Models ---
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Clone, Debug)]
 #[command(author, version, about = "A stylish HN CLI fetcher")]
+#[allow(clippy::struct_excessive_bools)] // independent CLI switches
 struct Args {
-    /// Sort mode: 'latest' for new stories, 'hottest' for top stories
-    #[arg(short, long, value_enum, default_value_t = SortMode::Hottest)]
-    sort: SortMode,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    list: ListArgs,
+
+    /// Output format: 'pretty' for humans, 'table' for dense scanning, 'json'
+    /// for scripts, 'yaml' for config-driven pipelines, 'badge' for one value
+    /// of the top story
+    #[arg(short, long, value_enum, default_value_t = Format::Pretty, global = true)]
+    format: Format,
+
+    /// The `url` the 'json' and 'yaml' formats give a text post: its
+    /// 'discussion' page, or 'none' for null; `is_self` marks text posts
+    /// either way
+    #[arg(long, value_enum, value_name = "LINK", default_value_t = LinkFallback::Discussion, global = true)]
+    link_fallback: LinkFallback,
+
+    /// Columns of '--format table', comma-separated, in order [default:
+    /// rank,score,comments,age,title,domain]
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "FIELDS",
+        global = true
+    )]
+    fields: Vec<Field>,
+
+    /// What '--format badge' prints of the top story [default: score]
+    #[arg(long, value_enum, value_name = "FIELD", global = true)]
+    badge_field: Option<BadgeField>,
+
+    /// Write the results to PATH instead of stdout, replacing it atomically;
+    /// '-' means stdout. A failed run leaves an existing file untouched
+    #[arg(short, long, value_name = "PATH", global = true)]
+    output: Option<PathBuf>,
+
+    /// Only emit the list of failed fetches (requires '--format json')
+    #[arg(long, global = true)]
+    errors_only: bool,
+
+    /// Firebase API base URL, e.g. a local mock server or a proxy
+    #[arg(long, visible_alias = "api-base", value_name = "URL", global = true)]
+    base_url: Option<String>,
+
+    /// Wayback Machine API base URL for the archived copies of links, e.g. a
+    /// local mock server
+    #[arg(long, value_name = "URL", global = true)]
+    wayback_url: Option<String>,
+
+    /// Algolia API base URL for 'search' and '--backend algolia', e.g. a
+    /// local mock server
+    #[arg(long, value_name = "URL", global = true)]
+    algolia_url: Option<String>,
+
+    /// Give up on a request after this many seconds
+    #[arg(long, value_name = "SECS", default_value_t = 10, global = true)]
+    timeout: u64,
+
+    /// User-Agent header sent with every request
+    #[arg(long, value_name = "UA", global = true)]
+    user_agent: Option<String>,
+
+    /// Extra header sent with every request, as 'Name: value'; repeatable
+    #[arg(
+        long = "header",
+        value_name = "HEADER",
+        value_parser = parse_header,
+        global = true
+    )]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// Don't ask for gzipped responses, e.g. to read traffic through a
+    /// debugging proxy
+    #[arg(long, global = true)]
+    no_compression: bool,
+
+    /// Connect over IPv4 only, e.g. when IPv6 is advertised but broken and
+    /// connections hang until the timeout
+    #[arg(long, global = true, conflicts_with = "ipv6")]
+    ipv4: bool,
+
+    /// Connect over IPv6 only
+    #[arg(long, global = true)]
+    ipv6: bool,
+
+    /// Retry failed requests (timeouts, dropped connections, 429s, 5xx) this
+    /// many times
+    #[arg(long, value_name = "N", default_value_t = 2, global = true)]
+    retries: u32,
+
+    /// Treat an empty feed as a transient failure and fetch it again, up to
+    /// --retries times, failing with "no stories returned" if it stays empty
+    #[arg(long, global = true)]
+    retry_on_empty_list: bool,
+
+    /// Cap of the first retry delay in milliseconds; it doubles per retry and
+    /// the actual wait is a random point below the cap
+    #[arg(long, value_name = "MS", default_value_t = 250, global = true)]
+    retry_base_ms: u64,
+
+    /// Largest cap a retry delay can grow to, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 10_000, global = true)]
+    retry_max_ms: u64,
+
+    /// Honor a 429/503 `Retry-After` for at most this many seconds per
+    /// request before giving up as rate limited
+    #[arg(long, value_name = "SECS", default_value_t = 60, global = true)]
+    max_retry_after: u64,
+
+    /// Send at most N requests per second across all workers (default:
+    /// unlimited, or `rate_limit` from the config file)
+    #[arg(long, value_name = "N", global = true)]
+    rate_limit: Option<NonZeroU32>,
+
+    /// Fail a request whose response body is larger than this many bytes,
+    /// e.g. from a broken or hostile --base-url
+    #[arg(long, value_name = "BYTES", default_value_t = hn_cli::DEFAULT_MAX_BODY_BYTES, global = true)]
+    max_body_bytes: u64,
+
+    /// Read settings from this config file instead of the default one
+    #[arg(long, value_name = "FILE", global = true)]
+    config: Option<PathBuf>,
+
+    /// Keep the on-disk cache in PATH instead of the platform cache
+    /// directory; created on first use, and the run goes uncached if it
+    /// can't be
+    #[arg(long, value_name = "PATH", env = "HN_CACHE_DIR", global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// One line per story, `rank. [score] title (site) by author · age — Nc`,
+    /// with titles cut to fit the terminal (pretty format)
+    #[arg(long, global = true)]
+    compact: bool,
+
+    /// Include the local fetch time in the header
+    #[arg(long, global = true)]
+    timestamp: bool,
+
+    /// Color theme; `light` suits light terminal backgrounds, `colorblind`
+    /// swaps green and red for blue and orange, `mono` uses no colors
+    /// (default: `preset` from the config file's [theme], or `dark`)
+    #[arg(long, value_enum, value_name = "THEME", global = true)]
+    theme: Option<Preset>,
+
+    /// How scores and comment counts are written: 'plain' (1834), 'grouped'
+    /// (1,834) or 'compact' (1.8k); JSON and YAML always use plain numbers
+    /// [default: grouped]
+    #[arg(long, value_enum, value_name = "STYLE", global = true)]
+    numbers: Option<NumberStyle>,
+
+    /// Short for '--numbers compact': scores and comment counts like 1.8k
+    #[arg(long, conflicts_with = "numbers", global = true)]
+    humanize: bool,
+
+    /// Put an icon before each title telling its type: 📰 story, ❓ Ask HN,
+    /// 🎬 Show HN, 💼 job or 📊 poll (pretty format; default: `type_icons`
+    /// from the config file, or off)
+    #[arg(long, overrides_with = "no_type_icons", global = true)]
+    type_icons: bool,
+
+    /// Leave the type icons out, whatever the config file says
+    #[arg(long, overrides_with = "type_icons", global = true)]
+    no_type_icons: bool,
+
+    /// Write the type icons in ASCII, e.g. [story] or [ask], for terminals
+    /// without emoji
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Highlight the titles of stories with at least this score (pretty
+    /// format, when colors are on)
+    #[arg(long, value_name = "SCORE", default_value_t = 300, global = true)]
+    hot_at: i32,
+
+    /// Show the --timestamp time in UTC instead of local time
+    #[arg(long, requires = "timestamp", global = true)]
+    utc: bool,
+
+    /// Never pipe long output through $PAGER; pretty stories are then printed
+    /// in order as soon as they arrive instead of all at the end
+    #[arg(long, global = true)]
+    no_pager: bool,
+
+    /// Never show the progress bar
+    #[arg(long, overrides_with = "progress", global = true)]
+    no_progress: bool,
+
+    /// Show the progress bar even when it would be hidden automatically
+    #[arg(long, overrides_with = "no_progress", global = true)]
+    progress: bool,
+
+    /// When to color the output: 'auto' when stdout is a terminal and
+    /// `NO_COLOR` isn't set, 'always' even when piped, or 'never';
+    /// --deterministic means 'never'
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
+
+    /// Byte-stable output for snapshot tests: no progress bar, color or
+    /// fitting to the terminal width, raw Unix timestamps and a fixed retry
+    /// jitter seed
+    #[arg(long, global = true)]
+    deterministic: bool,
+
+    /// Only print story data: no header, progress bar or closing summary
+    #[arg(short, long, overrides_with = "no_quiet", global = true)]
+    quiet: bool,
+
+    /// Keep the header and summary even for machine formats or piped output
+    #[arg(long, overrides_with = "quiet", global = true)]
+    no_quiet: bool,
+
+    /// Log requests and timings to stderr, and show per-item timing in the
+    /// progress bar (-vv also logs response headers; `RUST_LOG` works too)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Print the raw body of every API response to stderr before it is
+    /// decoded, e.g. to see what changed when items stop parsing
+    #[arg(long, global = true)]
+    dump_raw: bool,
+
+    /// Ids of the flags given on the command line that a `--profile` may
+    /// also set.
+    #[arg(skip)]
+    given: Vec<&'static str>,
+}
+
+/// Flags of the default `list` command, accepted bare (`hn-cli --sort
+/// latest`) or after `list`.
+#[derive(clap::Args, Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)] // independent CLI switches
+struct ListArgs {
+    /// Run the named profile from the config file's [profiles] table;
+    /// flags given alongside it override its own
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Sort mode: 'latest' for new stories, 'hottest' for top stories, 'ask'
+    /// for Ask HN; repeat it to show several feeds, each under its own
+    /// heading
+    #[arg(short, long, value_enum, default_values_t = [SortMode::Hottest])]
+    sort: Vec<SortMode>,
+
+    /// Feeds to merge into a single list, e.g. 'hottest,ask': each story once,
+    /// tagged with the feeds listing it and interleaved by its rank in them
+    /// unless --order-by says otherwise; --count applies per feed
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "SORTS",
+        conflicts_with = "sort"
+    )]
+    feeds: Vec<SortMode>,
+
+    /// Number of results to return, per feed; a feed lists at most 500 (200
+    /// for Ask HN), and asking for more warns, or fails under --strict
+    #[arg(short, long, default_value = "30")]
+    count: NonZeroUsize,
+
+    /// Whether --count was asked for, on the command line or by the
+    /// profile, rather than left at its default.
+    #[arg(skip)]
+    count_asked: bool,
+
+    /// Item backend: 'firebase' fetches one item per request, 'algolia'
+    /// batches items through the Algolia search API
+    #[arg(short, long, value_enum, default_value_t = Backend::Firebase)]
+    backend: Backend,
+
+    /// Only return stories newer than this id (requires '--sort latest')
+    #[arg(long, value_name = "ID")]
+    since_id: Option<u32>,
+
+    /// Only print the stories an earlier --changed-only run of the same
+    /// feeds didn't, e.g. for cron jobs; prints nothing at all and exits 0
+    /// when nothing is new. A story filtered out before, e.g. by
+    /// --min-score, counts as new once it passes
+    #[arg(long, conflicts_with_all = ["stream", "watch", "pick", "no_quiet"])]
+    changed_only: bool,
+
+    /// Also append each story, stamped with the fetch time, to FILE as one
+    /// JSON object per line; query the archive with `log`
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    append: Option<PathBuf>,
+
+    /// Run this shell command once per listed story, with the story as JSON
+    /// on stdin and its id in `HN_STORY_ID`; failures are reported at the end
+    #[arg(long, value_name = "CMD", conflicts_with = "watch")]
+    pipe_each: Option<String>,
+
+    /// Run CMD once per listed story, up to --jobs at a time, with `{id}`,
+    /// `{title}`, `{url}`, `{hn_url}`, `{by}`, `{score}`, `{comments}` and
+    /// `{time}` replaced in its arguments; no shell is involved, so quote as
+    /// in 'cmd "two words" {title}'. Failures are reported at the end
+    #[arg(long, value_name = "CMD", value_parser = exec::Template::parse, conflicts_with = "watch")]
+    exec: Option<exec::Template>,
+
+    /// Start no more --exec commands once one has failed
+    #[arg(long, requires = "exec")]
+    exec_fail_fast: bool,
+
+    /// Exit non-zero if any story failed to fetch, after fetching and
+    /// printing the rest
+    #[arg(long)]
+    strict: bool,
+
+    /// Stop fetching and exit non-zero at the first story that fails to
+    /// fetch; deleted or missing items are still just reported
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Show the copy an earlier run cached, marked '(cached)', of a story
+    /// that fails to fetch instead of leaving it out; on without the flag,
+    /// which only undoes an earlier --prefer-fresh
+    #[arg(long, overrides_with = "prefer_fresh")]
+    merge_cache_on_partial_failure: bool,
+
+    /// Leave out stories that fail to fetch rather than showing a cached,
+    /// possibly out-of-date copy
+    #[arg(long, overrides_with = "merge_cache_on_partial_failure")]
+    prefer_fresh: bool,
+
+    /// Exit non-zero if fewer than N stories were fetched (output is still
+    /// printed)
+    #[arg(long, value_name = "N")]
+    require: Option<usize>,
+
+    /// Only show stories with at least this score
+    #[arg(long, value_name = "N")]
+    min_score: Option<i32>,
+
+    /// Only show stories with at least this many comments
+    #[arg(long, value_name = "N")]
+    min_comments: Option<u32>,
+
+    /// Only show stories whose submitter has at least this much karma, a
+    /// crude spam filter for '--sort latest'; costs one request per
+    /// submitter, cached for a day, and a profile that fails to fetch keeps
+    /// its stories
+    #[arg(long, value_name = "N", conflicts_with = "stream")]
+    min_karma: Option<i32>,
+
+    /// Only show stories submitted within this long, e.g. '30m', '12h' or
+    /// '2w'; leaves out stories without a submission time
+    #[arg(long, value_name = "AGE", value_parser = age::parse_seconds)]
+    max_age: Option<i64>,
+
+    /// Only show stories submitted at least this long ago, e.g. '2d'; keeps
+    /// stories without a submission time
+    #[arg(long, value_name = "AGE", value_parser = age::parse_seconds)]
+    min_age: Option<i64>,
+
+    /// Order the shown stories by feed rank, score, comment count or age
+    /// (newest first) [default: rank]
+    #[arg(long, value_enum, value_name = "KEY")]
+    order_by: Option<OrderBy>,
+
+    /// Split the pretty listing under a dated heading per submission day,
+    /// newest day first, keeping the --order-by order within each day
+    #[arg(long, value_enum, value_name = "KEY", conflicts_with_all = ["stream", "watch"])]
+    group_by: Option<GroupBy>,
+
+    /// Preset for finding active discussions: '--order-by comments
+    /// --min-comments 20' unless those are given explicitly
+    #[arg(long)]
+    hot_discussions: bool,
+
+    /// Re-rank like the front page, by score / (hours since submission +
+    /// 2)^gravity, e.g. to surface rising stories of '--sort latest'; the same
+    /// as '--order-by hot'
+    #[arg(long, conflicts_with = "order_by")]
+    hot_rank: bool,
+
+    /// Exponent of the age in the --hot-rank formula; higher values sink
+    /// older stories faster, 0 ranks by score alone
+    #[arg(long, value_name = "G", default_value_t = 1.8, value_parser = parse_gravity)]
+    gravity: f64,
+
+    /// Only show text posts (Ask HN, Show HN and other posts without a link),
+    /// with their body (pretty format)
+    #[arg(long, visible_alias = "only-self-posts")]
+    text_only: bool,
+
+    /// Only show stories with a link, leaving out Ask HN, Show HN and job
+    /// posts without one
+    #[arg(long, visible_alias = "no-url-stories", conflicts_with = "text_only")]
+    links_only: bool,
+
+    /// Leave out job postings
+    #[arg(long)]
+    no_jobs: bool,
+
+    /// Only show stories whose title contains TERM, ignoring case, and
+    /// highlight it; repeat it to match any of several terms
+    #[arg(long, value_name = "TERM")]
+    filter: Vec<String>,
+
+    /// Only show stories matching EXPR, e.g. 'score > 100 and domain ==
+    /// "github.com"', over score, comments, author, domain, title and age
+    /// (in hours, or with an s, m, h, d or w suffix); see the README for the
+    /// grammar
+    #[arg(long = "where", visible_alias = "filter-expr", value_name = "EXPR", value_parser = expr::Expr::parse)]
+    where_expr: Option<expr::Expr>,
+
+    /// Also show the sites and authors the blocklist file leaves out (see
+    /// the `block` command)
+    #[arg(long)]
+    no_blocklist: bool,
+
+    /// Show at most N stories from any single domain (e.g. bbc.co.uk, with
+    /// its subdomains), filling their places from deeper in the feed;
+    /// link-less stories are exempt
+    #[arg(long, value_name = "N", conflicts_with = "stream")]
+    max_per_domain: Option<usize>,
+
+    /// Show one story per linked page, the best-scoring of its reposts:
+    /// links count as the same once the host is lowercased and the default
+    /// port, tracking parameters such as utm_* and a trailing slash are
+    /// left out; --format json lists the others' ids as `duplicates`
+    #[arg(long, conflicts_with = "stream")]
+    dedupe_url: bool,
+
+    /// "Best from each source": the top-scoring story from each of the most
+    /// common sites in the fetched stories, most common site first
+    #[arg(long, visible_alias = "top-n-by-domain", conflicts_with = "stream")]
+    digest: bool,
+
+    /// Show at most N sites in the --digest
+    #[arg(long, value_name = "N", requires = "digest")]
+    digest_domains: Option<usize>,
+
+    /// Number of stories to fetch concurrently
+    #[arg(short, long, default_value = "8")]
+    jobs: NonZeroUsize,
+
+    /// Print each story as soon as it arrives, in completion order, with its
+    /// true rank (pretty format only)
+    #[arg(long)]
+    stream: bool,
+
+    /// Print only each story's link (its discussion page for text posts),
+    /// one per line, instead of a formatted listing
+    #[arg(long, conflicts_with_all = ["stream", "watch"])]
+    url_only: bool,
+
+    /// End each --url-only record with a NUL byte instead of a newline, for
+    /// `xargs -0`
+    #[arg(long, requires = "url_only")]
+    print0: bool,
+
+    /// Pick stories in `$HN_PICKER` (fzf by default) and open each one
+    /// picked in the browser
+    #[arg(long, conflicts_with_all = ["stream", "watch", "url_only"])]
+    pick: bool,
+
+    /// Print the links of the stories --pick picked instead of opening them
+    #[arg(long, requires = "pick")]
+    pick_print: bool,
+
+    /// Check that each story's link still answers and show its HTTP status
+    /// (a `link_status` field in JSON)
+    #[arg(long, conflicts_with_all = ["stream", "watch", "offline"])]
+    check_links: bool,
+
+    /// Fetch the options of each poll and show its leading option with a
+    /// vote bar (pretty format; a `poll_options` field in JSON); costs one
+    /// request per option, and needs the Firebase backend
+    #[arg(long, conflicts_with = "stream")]
+    include_poll_results: bool,
+
+    /// Fetch the first comment of each story and show a one-line preview of
+    /// it under the story (a `top_comment` object in JSON); costs one request
+    /// per story with comments, and needs the Firebase backend
+    #[arg(long, conflicts_with = "stream")]
+    top_comment: bool,
+
+    /// Embed each site's favicon in the page of '--format html'; costs one
+    /// request per site, cached for later runs (offline, only cached icons
+    /// are shown)
+    #[arg(long, visible_alias = "save-html-assets")]
+    with_favicons: bool,
+
+    /// Show a Wayback Machine copy of every link, not just of the dead ones
+    /// --check-links finds (an `archive_url` field in JSON); offline, only
+    /// copies found by earlier runs
+    #[arg(long, conflicts_with_all = ["stream", "watch"])]
+    archive_links: bool,
+
+    /// Serve the feed and stories from the on-disk cache of earlier runs,
+    /// without touching the network
+    #[arg(long)]
+    offline: bool,
+
+    /// Fetch the next page of stories into the cache in the background, as
+    /// --watch does unless --no-prefetch (and repl unless 'repl
+    /// --no-prefetch'); the run waits for it before exiting
+    #[arg(
+        long,
+        visible_alias = "prefetch",
+        overrides_with = "no_prefetch",
+        conflicts_with = "offline"
+    )]
+    prefetch_next_page: bool,
+
+    /// Don't fetch the next page ahead in --watch
+    #[arg(long, overrides_with = "prefetch_next_page")]
+    no_prefetch: bool,
+
+    /// Re-fetch and redraw the listing every SECS seconds until Ctrl-C
+    #[arg(long, value_name = "SECS", conflicts_with_all = ["stream", "timing", "type_breakdown"])]
+    watch: Option<NonZeroU64>,
+
+    /// In watch mode, show each story's score change since the previous
+    /// refresh and tag stories that just entered the list
+    #[arg(long, requires = "watch")]
+    score_delta: bool,
+
+    /// Print how long the id list and item fetches took after the results
+    #[arg(long)]
+    timing: bool,
+
+    /// Print how many of each item type the results hold after them, e.g.
+    /// 'Types: 28 stories, 2 jobs'
+    #[arg(long)]
+    type_breakdown: bool,
+
+    /// Custom indicatif template for the progress bar, e.g. '{bar} {pos}/{len}'
+    #[arg(long, value_name = "STR")]
+    progress_template: Option<String>,
 
-    /// Number of results to return
-    #[arg(short, long, default_value_t = 30)]
-    count: usize,
+    /// Print what the listing would fetch and show (feed URLs, story and
+    /// request counts, filters, output) and exit; only the id lists are
+    /// fetched
+    #[arg(long, visible_alias = "dry-run")]
+    explain: bool,
 }
 
+impl Args {
+    /// Parses the command line, moving the flags of an explicit `list`
+    /// command to [`Args::list`] so `hn-cli list --sort latest` and `hn-cli
+    /// --sort latest` look alike.
+    /// Comments: bare listing flags are rejected next to any command, since
+    /// they would be silently ignored, or replaced by those after `list`.
+    fn parse_normalized() -> Self {
+        let matches = Self::command().get_matches();
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        if let Some(command) = &args.command {
+            let listing = ListArgs::augment_args(clap::Command::new("list"));
+            if let Some(arg) = listing.get_arguments().find(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            }) {
+                let flag = arg.get_long().unwrap_or_else(|| arg.get_id().as_str());
+                let message = match command {
+                    Command::List(_) => format!("--{flag} must come after 'list'"),
+                    Command::Profile {
+                        ..
+                    } => format!("--{flag} must come after 'profile NAME'"),
+                    #[cfg(feature = "serve")]
+                    Command::Serve {
+                        ..
+                    } => format!("--{flag} must come after 'serve'"),
+                    _ => format!("--{flag} only applies to listing stories"),
+                };
+                Self::command()
+                    .error(ErrorKind::ArgumentConflict, message)
+                    .exit();
+            }
+        }
+        let listing = match matches.subcommand() {
+            Some(("list" | "profile" | "serve", listing)) => listing,
+            _ => &matches,
+        };
+        args.given = profile::FLAGS
+            .into_iter()
+            .filter(|id| {
+                [&matches, listing]
+                    .iter()
+                    .any(|matches| matches.value_source(id) == Some(ValueSource::CommandLine))
+            })
+            .collect();
+        match args.command.take() {
+            Some(Command::List(list)) => args.list = *list,
+            Some(Command::Profile {
+                name: Some(name),
+                list,
+            }) if name != profile::LIST => {
+                if list.profile.is_some() {
+                    Self::command()
+                        .error(
+                            ErrorKind::ArgumentConflict,
+                            "give either 'profile NAME' or --profile, not both",
+                        )
+                        .exit();
+                }
+                args.list = *list;
+                args.list.profile = Some(name);
+            },
+            #[cfg(feature = "serve")]
+            Some(Command::Serve {
+                serve,
+                list,
+            }) => {
+                args.list = (*list).clone();
+                args.command = Some(Command::Serve {
+                    serve,
+                    list,
+                });
+            },
+            command => args.command = command,
+        }
+        if !args.list.feeds.is_empty() {
+            args.list.sort.clone_from(&args.list.feeds);
+        }
+        dedup_sorts(&mut args.list.sort);
+        args.list.count_asked = args.given.contains(&"count");
+        args
+    }
+
+    /// Whether decorations (header, closing summary) are suppressed.
+    /// Comments: implied by machine formats, non-TTY stdout and `--output`
+    /// files unless `--no-quiet` says otherwise.
+    fn quiet(&self) -> bool {
+        if self.quiet || self.no_quiet {
+            return self.quiet;
+        }
+        !self.format.is_human()
+            || self.list.url_only
+            || self.list.pick
+            || self.list.changed_only
+            || !io::stdout().is_terminal()
+            || self.output_file().is_some()
+    }
+
+    /// The `--output` file, unless it is `-` for stdout.
+    fn output_file(&self) -> Option<&Path> {
+        self.output
+            .as_deref()
+            .filter(|path| path.as_os_str() != "-")
+    }
+
+    /// The API client configured by the network flags, falling back to
+    /// `config` for those not given.
+    fn client(&self, config: &Config) -> HnClient {
+        let mut builder = HnClient::builder()
+            .timeout(Duration::from_secs(self.timeout))
+            .compression(!self.no_compression)
+            .ip_family(self.ip_family())
+            .retries(self.retries)
+            .retry_empty_lists(self.retry_on_empty_list)
+            .backoff(self.backoff())
+            .max_retry_after(Duration::from_secs(self.max_retry_after))
+            .max_body_bytes(self.max_body_bytes);
+        if let Some(url) = &self.base_url {
+            builder = builder.base_url(url);
+        }
+        if let Some(url) = &self.wayback_url {
+            builder = builder.wayback_url(url);
+        }
+        if let Some(url) = &self.algolia_url {
+            builder = builder.algolia_url(url);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(name.clone(), value.clone());
+        }
+        if let Some(per_second) = self.rate_limit.or(config.rate_limit) {
+            builder = builder.rate_limit(per_second);
+        }
+        builder.build()
+    }
+
+    /// Per-story filters selected on the command line.
+    fn criteria(&self) -> Criteria {
+        Criteria {
+            min_score: self.list.min_score,
+            min_comments: self
+                .list
+                .min_comments
+                .or_else(|| self.list.hot_discussions.then_some(HOT_DISCUSSION_COMMENTS)),
+            max_age: self.list.max_age,
+            min_age: self.list.min_age,
+            text_only: self.list.text_only,
+            links_only: self.list.links_only,
+            no_jobs: self.list.no_jobs,
+            keywords: self.list.filter.clone(),
+            expr: self.list.where_expr.clone(),
+            blocklist: if self.list.no_blocklist {
+                Blocklist::default()
+            } else {
+                blocklist::current().clone()
+            },
+        }
+    }
+
+    /// The order stories are shown in.
+    fn order_by(&self) -> OrderBy {
+        self.list.order_by.unwrap_or(if self.list.hot_rank {
+            OrderBy::Hot
+        } else if self.list.hot_discussions {
+            OrderBy::Comments
+        } else {
+            OrderBy::Rank
+        })
+    }
+
+    /// Whether `--feeds` merges several feeds into one list, rather than
+    /// each `--sort` getting its own section.
+    const fn merged(&self) -> bool {
+        !self.list.feeds.is_empty() && self.list.sort.len() > 1
+    }
+
+    /// Whether a listing fetches the next page ahead: by default only when
+    /// `interactive`, i.e. under `--watch`, and never offline.
+    const fn prefetch(&self, interactive: bool) -> bool {
+        !self.list.offline
+            && !self.list.no_prefetch
+            && (self.list.prefetch_next_page || interactive)
+    }
+
+    /// Whether the pretty listing is printed story by story as it arrives,
+    /// in rank order.
+    /// Comments: only when the pager is out of the picture, since paging needs
+    /// the whole output up front, and for a single feed in feed order, since
+    /// sections, any other order, a `--digest`, day groups, the
+    /// `--max-per-domain` backfill, poll results, top comments or the link
+    /// lookups need every story first; `--stream` and `--watch` print their
+    /// own way.
+    fn incremental(&self) -> bool {
+        self.format == Format::Pretty
+            && !self.list.url_only
+            && !self.list.pick
+            && !self.list.changed_only
+            && self.list.sort.len() == 1
+            && self.order_by() == OrderBy::Rank
+            && !self.list.digest
+            && self.list.group_by.is_none()
+            && self.list.max_per_domain.is_none()
+            && self.list.min_karma.is_none()
+            && !self.list.dedupe_url
+            && !self.list.include_poll_results
+            && !self.list.top_comment
+            && !self.list.check_links
+            && !self.list.archive_links
+            && !self.list.stream
+            && self.list.watch.is_none()
+            && self.output_file().is_none()
+            && (self.no_pager || !io::stdout().is_terminal())
+    }
+
+    /// Address family from `--ipv4`/`--ipv6`.
+    const fn ip_family(&self) -> IpFamily {
+        if self.ipv4 {
+            IpFamily::V4
+        } else if self.ipv6 {
+            IpFamily::V6
+        } else {
+            IpFamily::Any
+        }
+    }
+
+    /// Retry backoff; `--deterministic` fixes its jitter seed.
+    fn backoff(&self) -> Backoff {
+        let base = Duration::from_millis(self.retry_base_ms);
+        let max = Duration::from_millis(self.retry_max_ms);
+        if self.deterministic {
+            Backoff::seeded(base, max, DETERMINISTIC_SEED)
+        } else {
+            Backoff::new(base, max)
+        }
+    }
+
+    /// Formats `time` with `format` in local time, or in UTC under `--utc`;
+    /// `--deterministic` prints raw Unix seconds instead.
+    fn format_time(&self, time: DateTime<Utc>, format: &str) -> String {
+        if self.deterministic {
+            time.timestamp().to_string()
+        } else if self.utc {
+            time.format(&format!("{format} UTC")).to_string()
+        } else {
+            time.with_timezone(&Local).format(format).to_string()
+        }
+    }
+
+    /// How each pretty story is laid out.
+    fn layout(&self) -> Layout {
+        if self.compact {
+            Layout::Compact {
+                width: self.terminal_width(),
+                hot_at: self.hot_at,
+                score_width: MIN_SCORE_WIDTH,
+                now: self.now(),
+            }
+        } else {
+            Layout::Full {
+                body: self.list.text_only,
+                hot_at: self.hot_at,
+                score_width: MIN_SCORE_WIDTH,
+                now: self.now(),
+            }
+        }
+    }
+
+    /// Width of the terminal in columns, when output is fitted to it.
+    fn terminal_width(&self) -> Option<usize> {
+        Term::stdout()
+            .size_checked()
+            .filter(|_| !self.deterministic)
+            .map(|(_, cols)| usize::from(cols))
+    }
+
+    /// The time story ages are measured from, in Unix seconds.
+    /// Comments: none under `--deterministic`, where raw submission times
+    /// keep the output byte-stable.
+    fn now(&self) -> Option<i64> {
+        (!self.deterministic).then(|| Utc::now().timestamp())
+    }
+
+    /// The day of `time` (Unix seconds) as `YYYY-MM-DD`, in local time or
+    /// in UTC under `--utc`.
+    /// Comments: always UTC under `--deterministic`, so the output doesn't
+    /// depend on the machine's time zone.
+    fn day(&self, time: i64) -> Option<String> {
+        let time = DateTime::from_timestamp(time, 0)?;
+        Some(if self.utc || self.deterministic {
+            time.format("%Y-%m-%d").to_string()
+        } else {
+            time.with_timezone(&Local).format("%Y-%m-%d").to_string()
+        })
+    }
+
+    /// Whether the progress bar is drawn.
+    fn show_progress(&self) -> bool {
+        if self.deterministic {
+            return false;
+        }
+        if self.no_progress || self.progress {
+            return self.progress;
+        }
+        // Comments: with results going to a file the terminal is free for
+        // the bar.
+        if self.output_file().is_some() {
+            return io::stderr().is_terminal();
+        }
+        !self.quiet() && io::stdout().is_terminal()
+    }
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum Command {
+    /// Fetch a feed and print it (the default when no command is given)
+    List(Box<ListArgs>),
+
+    /// Show one story by id, with its text and discussion link
+    Item { id: u32 },
+
+    /// Show a user's profile
+    User { name: String },
+
+    /// Show the comments of a story as an indented tree
+    Comments {
+        /// Id of the story
+        id: u32,
+
+        /// Only show replies this many levels deep; top-level comments are
+        /// level 1
+        #[arg(long, value_name = "N")]
+        depth: Option<NonZeroUsize>,
+    },
+
+    /// Search stories by their text through Algolia, most relevant first
+    Search {
+        /// Words to search for
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        /// Number of stories to show
+        #[arg(short, long, default_value = "30")]
+        count: NonZeroUsize,
+    },
+
+    /// Run connectivity diagnostics against the HN API
+    Doctor {
+        /// Also print the TLS backend and what the handshake negotiated
+        #[arg(long, hide = true)]
+        tls_info: bool,
+    },
+
+    /// Manage locally saved stories
+    Bookmarks {
+        #[command(subcommand)]
+        action: Option<BookmarkAction>,
+
+        /// Re-fetch each bookmark and show how its score changed since saving
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Leave sites and authors out of every listing, or list what is left
+    /// out
+    Block {
+        #[command(subcommand)]
+        action: Option<BlockAction>,
+    },
+
+    /// Run a named profile from the config file, or list them
+    #[command(visible_alias = "p")]
+    Profile {
+        /// Profile to run; 'list' (the default) prints every profile and the
+        /// flags it stands for
+        name: Option<String>,
+
+        #[command(flatten)]
+        list: Box<ListArgs>,
+    },
+
+    /// Reprint the most recent listing, in any format, without network
+    /// access
+    Last,
+
+    /// Compare two saved runs of a feed: the stories that entered the list,
+    /// dropped off or moved by more than --moved-by places
+    Diff {
+        #[command(flatten)]
+        diff: diff::DiffArgs,
+    },
+
+    /// Query an archive written by --append
+    Log {
+        #[command(subcommand)]
+        query: LogQuery,
+    },
+
+    /// Browse interactively: feeds, search and paging at a prompt
+    Repl {
+        /// Stories per page, until 'top N' or 'latest N' changes it
+        #[arg(short, long, default_value = "10")]
+        count: NonZeroUsize,
+
+        /// Don't fetch the next page of a feed while you read the current one
+        #[arg(long)]
+        no_prefetch: bool,
+    },
+
+    /// Check the config file for mistakes without fetching anything
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Fetch every item in a range of ids into the cache, skipping those
+    /// already there, so a stopped crawl resumes when run again
+    Crawl {
+        #[command(flatten)]
+        crawl: crawl::CrawlArgs,
+    },
+
+    /// Show where the on-disk cache lives, or clear it
+    Cache {
+        #[command(subcommand)]
+        action: Option<CacheAction>,
+    },
+
+    /// Serve the top, new and Ask HN feeds as JSON over local HTTP,
+    /// refreshed in the background
+    #[cfg(feature = "serve")]
+    Serve {
+        #[command(flatten)]
+        serve: serve::ServeArgs,
+
+        #[command(flatten)]
+        list: Box<ListArgs>,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum Backend {
+    Firebase,
+    Algolia,
+}
+
+/// Sort keys for `--order-by`; ties keep feed order.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum OrderBy {
+    /// The feed's own order.
+    Rank,
+    /// Highest score first.
+    Score,
+    /// Most comments first.
+    Comments,
+    /// Newest first; stories without a submission time go last.
+    Age,
+    /// Highest `score / (age_hours + 2)^gravity` first, as `--hot-rank`.
+    Hot,
+}
+
+/// Keys for `--group-by`.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum GroupBy {
+    /// The day each story was submitted.
+    Day,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Pretty,
+    Table,
+    Json,
+    Yaml,
+    /// A self-contained page, for saving or mailing as a digest.
+    Html,
+    /// A single value of the top story, for status badges and other
+    /// one-line artifacts.
+    Badge,
+}
+
+impl Format {
+    /// Whether the format is meant to be read rather than parsed; these get
+    /// the header, summary and pager.
+    const fn is_human(self) -> bool {
+        matches!(self, Self::Pretty | Self::Table)
+    }
+}
+
+/// How each story of the pretty output is laid out; either way, titles of
+/// stories scoring at least `hot_at` are highlighted, scores are centered in
+/// a `score_width` column and ages are measured from `now` (Unix seconds;
+/// `None` shows raw submission times). Comments: stories printed as they arrive
+/// can't know the widest score, so they keep [`MIN_SCORE_WIDTH`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Layout {
+    /// Title, link and author lines, followed by the text post body when
+    /// `body` is set.
+    Full {
+        body: bool,
+        hot_at: i32,
+        score_width: usize,
+        now: Option<i64>,
+    },
+    /// One line per story, with titles cut to fit `width` columns when known.
+    Compact {
+        width: Option<usize>,
+        hot_at: i32,
+        score_width: usize,
+        now: Option<i64>,
+    },
+}
+
+/// Narrowest score column, enough for scores up to 9999.
+const MIN_SCORE_WIDTH: usize = 4;
+
+/// The `--numbers` style of this run; set once at startup.
+static NUMBERS: OnceLock<NumberStyle> = OnceLock::new();
+
+impl Layout {
+    /// This layout with the score column widened to the largest score of
+    /// `stories`, so every bracket of the listing lines up.
+    /// Comments: never narrows, so sections fitted to the whole listing
+    /// stay aligned with each other.
+    fn fit_scores(mut self, stories: &[Story]) -> Self {
+        let widest = stories
+            .iter()
+            .map(|story| count(story.score).len())
+            .max()
+            .unwrap_or_default();
+        let (Self::Full {
+            score_width, ..
+        }
+        | Self::Compact {
+            score_width, ..
+        }) = &mut self;
+        *score_width = (*score_width).max(widest);
+        self
+    }
+}
+
+/// What `--link-fallback` puts in a text post's `url` in the machine
+/// formats.
 #[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
-enum SortMode {
-    Latest,
-    Hottest,
+enum LinkFallback {
+    /// Its discussion page, as for `--url-only`.
+    Discussion,
+    /// Nothing, i.e. `null`.
+    None,
+}
+
+/// Choices of `--color`.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+enum ColorChoice {
+    /// Color a terminal, unless `NO_COLOR` says otherwise.
+    Auto,
+    Always,
+    Never,
+}
+
+/// What `--format badge` prints of the top story.
+#[derive(Copy, Clone, PartialEq, Eq, Default, ValueEnum, Debug)]
+enum BadgeField {
+    /// Its score, as a plain number.
+    #[default]
+    Score,
+    /// Its title.
+    Title,
+    /// Its comment count, 0 when the API reported none.
+    Comments,
+    /// Its link, or its discussion page for a text post.
+    Url,
+    /// Its item id.
+    Id,
+    /// Its author.
+    By,
+}
+
+/// A story as the JSON and YAML formats print it.
+#[derive(Serialize, Debug)]
+struct Row<'a> {
+    #[serde(flatten)]
+    story: Cow<'a, Story>,
+    /// Whether it is a text post, whatever `url` says.
+    is_self: bool,
+    /// Whether the title opens with `Ask HN`.
+    ask: bool,
+    /// Whether the title opens with `Show HN`.
+    show: bool,
+}
+
+impl<'a> Row<'a> {
+    fn new(story: &'a Story, links: LinkFallback) -> Self {
+        let is_self = story.is_text_post();
+        let prefix = story.title_prefix();
+        let story = if is_self && links == LinkFallback::Discussion {
+            Cow::Owned(Story {
+                url: Some(story.link()),
+                ..story.clone()
+            })
+        } else {
+            Cow::Borrowed(story)
+        };
+        Self {
+            story,
+            is_self,
+            ask: prefix == Some(TitlePrefix::Ask),
+            show: prefix == Some(TitlePrefix::Show),
+        }
+    }
+}
+
+/// JSON document emitted when some fetches failed, or `--timing` or
+/// `--since-id` was given.
+#[derive(Serialize, Debug)]
+struct JsonReport<'a> {
+    stories: Vec<Row<'a>>,
+    errors: &'a [FetchFailure],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timing: Option<&'a timing::Summary>,
+    /// The id to pass as `--since-id` next run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    newest: Option<u32>,
 }
 
-#[derive(Deserialize, Debug)]
-struct Story {
-    title: String,
-    url: Option<String>,
-    score: i32,
-    by: String,
+/// YAML document emitted under `--since-id`.
+#[derive(Serialize, Debug)]
+struct YamlReport<'a> {
+    stories: Vec<Row<'a>>,
+    /// The id to pass as `--since-id` next run.
+    newest: u32,
 }
 
 // --- Logic ---
 
-/// Fetches details for a single story.
-/// Comments: Using ureq 3.x body_mut() pattern.
-fn get_story_details(id: u32) -> Result<Story, Box<dyn Error>> {
-    let url = format!("https://hacker-news.firebaseio.com/v0/item/{}.json", id);
-    let mut response = ureq::get(&url).call()?;
-    let story: Story = response.body_mut().read_json()?;
-    Ok(story)
+/// `--min-comments` implied by `--hot-discussions`.
+const HOT_DISCUSSION_COMMENTS: u32 = 20;
+
+/// Retry jitter seed under `--deterministic`.
+const DETERMINISTIC_SEED: u64 = 0x5EED;
+
+fn main() -> ExitCode {
+    let mut args = Args::parse_normalized();
+    logging::init(args.verbose, args.dump_raw);
+
+    match run(&mut args) {
+        Ok(code) => code,
+        Err(e) => {
+            report(&e);
+            ExitCode::from(e.exit_code())
+        },
+    }
+}
+
+/// Drops repeated feeds.
+/// Comments: `--sort latest --sort latest` is one feed, not two sections of
+/// the same stories.
+fn dedup_sorts(sorts: &mut Vec<SortMode>) {
+    let mut seen = Vec::new();
+    sorts.retain(|sort| {
+        if seen.contains(sort) {
+            return false;
+        }
+        seen.push(*sort);
+        true
+    });
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+/// Prints a single-line error to stderr.
+fn report(error: &Error) {
+    eprint_line(&format!("error: {error}"), theme::current().bad);
+}
+
+/// Writes a diagnostic line to stderr, colored only when stderr is a TTY.
+fn eprint_line(message: &str, color: Option<Color>) {
+    if io::stderr().is_terminal() {
+        eprintln!("{}", paint(message, color));
+    } else {
+        eprintln!("{message}");
+    }
+}
+
+fn run(args: &mut Args) -> Result<ExitCode, Error> {
+    // Comments: before loading the config, which would fail on the very
+    // problems `config check` is meant to describe.
+    if matches!(
+        args.command,
+        Some(Command::Config {
+            action: ConfigAction::Check,
+        })
+    ) {
+        config::check(args.config.as_deref())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let config = config::load(args.config.as_deref())?;
+    profile::apply(args, &config.profiles)?;
+    let args = &*args;
+    validate(args)?;
+    set_globals(args, &config);
+    let client = args.client(&config);
+
+    match &args.command {
+        Some(Command::Doctor {
+            tls_info,
+        }) => Ok(if doctor::run(&client, *tls_info) {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }),
+        Some(Command::Bookmarks {
+            action,
+            refresh,
+        }) => {
+            bookmarks::run(&client, action.as_ref(), *refresh)?;
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(Command::Block {
+            action,
+        }) => {
+            blocklist::run(action.as_ref())?;
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(Command::Profile {
+            ..
+        }) => {
+            profile::list(&config.profiles);
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(Command::Item {
+            id,
+        }) => lookup::item(args, &client, *id),
+        Some(Command::User {
+            name,
+        }) => lookup::user(args, &client, name),
+        Some(Command::Comments {
+            id,
+            depth,
+        }) => comments::run(args, &client, *id, *depth),
+        Some(Command::Search {
+            query,
+            count,
+        }) => search::run(args, &client, query, *count),
+        Some(Command::Last) => last::run(args),
+        Some(Command::Diff {
+            diff,
+        }) => diff::run(args, diff),
+        Some(Command::Log {
+            query,
+        }) => archive::run(args, query),
+        Some(Command::Repl {
+            count,
+            no_prefetch,
+        }) => repl::run(args, &client, count.get(), !no_prefetch),
+        Some(Command::Crawl {
+            crawl,
+        }) => crawl::run(args, &client, crawl),
+        Some(Command::Cache {
+            action,
+        }) => {
+            cache::run(action.as_ref())?;
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(Command::Config {
+            ..
+        }) => unreachable!("handled before the config is loaded"),
+        #[cfg(feature = "serve")]
+        Some(Command::Serve {
+            serve, ..
+        }) => serve::run(args, &client, serve),
+        Some(Command::List(_)) | None if args.list.explain => {
+            explain::run(args, &client, args.rate_limit.or(config.rate_limit))
+        },
+        Some(Command::List(_)) | None => args.list.watch.map_or_else(
+            || list(args, &client),
+            |interval| watch::run(args, &client, Duration::from_secs(interval.get())),
+        ),
+    }
+}
+
+/// Sets up what every command reads from process-wide state: colors, the
+/// cache directory, `--filter` keywords, the theme, number style and
+/// blocklist.
+fn set_globals(args: &Args, config: &Config) {
+    match args.color {
+        _ if args.deterministic => colored::control::set_override(false),
+        ColorChoice::Auto => {},
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+    }
+    if let Some(dir) = &args.cache_dir {
+        paths::set_cache_dir(dir.clone());
+    }
+    filter::set_keywords(args.list.filter.clone());
+    let preset = args.theme.or(config.theme.preset).unwrap_or_default();
+    theme::set(
+        config
+            .theme
+            .resolve(preset, config.score_colors)
+            .expect("config::load checks the theme"),
+    );
+    let _ = NUMBERS.set(
+        args.numbers
+            .or_else(|| args.humanize.then_some(NumberStyle::Compact))
+            .unwrap_or_default(),
+    );
+    if let Some(path) = &config.blocklist {
+        blocklist::set_path(path.clone());
+    }
+    icons::set(
+        if args.no_type_icons || !(args.type_icons || config.type_icons) {
+            Icons::Off
+        } else if args.ascii || config.ascii {
+            Icons::Ascii
+        } else {
+            Icons::Emoji
+        },
+    );
+}
+
+/// Rejects the formats that only lay out stories for the commands that print
+/// something else.
+fn story_formats(args: &Args) -> Result<(), Error> {
+    if matches!(args.format, Format::Table | Format::Html | Format::Badge)
+        && let Some(what) = match args.command {
+            Some(Command::User {
+                ..
+            }) => Some("a user"),
+            Some(Command::Comments {
+                ..
+            }) => Some("a comment thread"),
+            _ => None,
+        }
+    {
+        let format = args.format.to_possible_value().expect("formats are named");
+        return Err(Error::Usage(format!(
+            "'--format {}' lists stories; pick another format for {what}",
+            format.get_name()
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects flag combinations clap can't express on its own.
+fn validate(args: &Args) -> Result<(), Error> {
+    if let (Some(min), Some(max)) = (args.list.min_age, args.list.max_age)
+        && min >= max
+    {
+        return Err(Error::Usage(
+            "--min-age must be shorter than --max-age, or no story could match".to_string(),
+        ));
+    }
+
+    if args.list.since_id.is_some() && args.list.sort != [SortMode::Latest] {
+        return Err(Error::Usage(
+            "--since-id only applies to '--sort latest'".to_string(),
+        ));
+    }
+
+    if args.errors_only && args.format != Format::Json {
+        return Err(Error::Usage(
+            "--errors-only requires '--format json'".to_string(),
+        ));
+    }
+
+    if args.output.is_some() && (args.list.watch.is_some() || args.list.stream) {
+        return Err(Error::Usage(
+            "--output can't be combined with --watch or --stream".to_string(),
+        ));
+    }
+
+    if args.list.sort.len() > 1 && (args.list.watch.is_some() || args.list.stream) {
+        return Err(Error::Usage(
+            "--watch and --stream show a single feed; pass one --sort".to_string(),
+        ));
+    }
+
+    if args.list.watch.is_some() && args.format != Format::Pretty {
+        return Err(Error::Usage(
+            "--watch only applies to '--format pretty'".to_string(),
+        ));
+    }
+
+    story_formats(args)?;
+
+    if args.list.url_only && args.given.contains(&"format") {
+        return Err(Error::Usage(
+            "--url-only prints bare links and can't be combined with --format".to_string(),
+        ));
+    }
+
+    if args.list.pick && (args.given.contains(&"format") || args.output.is_some()) {
+        return Err(Error::Usage(
+            "--pick hands the stories to a picker and can't be combined with --format or \
+             --output"
+                .to_string(),
+        ));
+    }
 
+    #[cfg(feature = "serve")]
+    if matches!(args.command, Some(Command::Serve { .. }))
+        && let Some(flag) = ["sort", "feeds"]
+            .into_iter()
+            .find(|flag| args.given.contains(flag))
+    {
+        return Err(Error::Usage(format!(
+            "'serve' serves every feed; drop --{flag}"
+        )));
+    }
+
+    if args.list.with_favicons && args.format != Format::Html {
+        return Err(Error::Usage(
+            "--with-favicons only applies to '--format html'".to_string(),
+        ));
+    }
+
+    if !args.fields.is_empty() && args.format != Format::Table {
+        return Err(Error::Usage(
+            "--fields only applies to '--format table'".to_string(),
+        ));
+    }
+
+    if args.badge_field.is_some() && args.format != Format::Badge {
+        return Err(Error::Usage(
+            "--badge-field only applies to '--format badge'".to_string(),
+        ));
+    }
+
+    if args.compact && args.format != Format::Pretty {
+        return Err(Error::Usage(
+            "--compact only applies to '--format pretty'".to_string(),
+        ));
+    }
+
+    if args.list.stream && args.order_by() != OrderBy::Rank {
+        return Err(Error::Usage(
+            "--stream prints in arrival order and can't be combined with --order-by".to_string(),
+        ));
+    }
+
+    if args.list.stream && args.format != Format::Pretty {
+        return Err(Error::Usage(
+            "--stream only applies to '--format pretty'".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses a `--header` value, `Name: value`.
+fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("expected 'Name: value', got '{header}'"))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|_| format!("invalid header name '{}'", name.trim()))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|_| format!("invalid value for header '{name}'"))?;
+    Ok((name, value))
+}
+
+/// A `--gravity`: a finite number, at least 0.
+fn parse_gravity(text: &str) -> Result<f64, String> {
+    let gravity: f64 = text
+        .parse()
+        .map_err(|_| format!("expected a number, got '{text}'"))?;
+    if gravity.is_finite() && gravity >= 0.0 {
+        Ok(gravity)
+    } else {
+        Err(format!(
+            "expected a finite number of at least 0, got '{text}'"
+        ))
+    }
+}
+
+/// Header banner text, with the fetch time under `--timestamp`.
+fn header(args: &Args) -> String {
+    if !args.timestamp {
+        return " 🧡 Hacker News CLI ".to_string();
+    }
+
+    let time = args.format_time(Utc::now(), "%Y-%m-%d %H:%M");
+    format!(" 🧡 Hacker News CLI — {time} ")
+}
+
+/// The default command: fetch a feed and print it.
+fn list(args: &Args, client: &HnClient) -> Result<ExitCode, Error> {
     // Visual header
-    println!("\n{}", " 🧡 Hacker News CLI ".on_cyan().black().bold());
+    if !args.quiet() && args.output_file().is_none() {
+        println!("\n{}", theme::current().header(&header(args)));
+    }
+
+    let mut listing = fetch::fetch_listing(args, client)?;
+    let mut prefetch = Prefetch::default();
+    if args.prefetch(false) {
+        prefetch.start(args, client, &listing.next_page);
+    }
+    last::save(args, &listing.stories, &listing.failures);
+    if let Some(path) = &args.list.append {
+        archive::append(path, &listing.stories)?;
+    }
+    let snapshot = args
+        .list
+        .changed_only
+        .then(|| changed::keep_new(args, &mut listing))
+        .transpose()?;
+    if args.list.pick {
+        report_failures(&listing.failures, args.list.offline);
+        let outcome = outcome(args, &listing.stories, &listing.failures);
+        if outcome.is_ok() {
+            pick::run(&listing.stories, args.list.pick_print)?;
+        }
+        return outcome;
+    }
+
+    // 4. Print Results
+    let timing = args.list.timing.then(|| listing.timing.summary());
+    let output = render_listing(args, client, &listing, timing.as_ref());
+    let Listing {
+        stories,
+        failures,
+        ..
+    } = listing;
+    let outcome = outcome(args, &stories, &failures);
+    // Comments: `--changed-only` with nothing new prints nothing at all, not
+    // even an empty JSON listing, so `ifne`-style tools can tell.
+    if (outcome.is_ok() || args.output_file().is_none())
+        && (snapshot.is_none() || !stories.is_empty())
+    {
+        emit(args, &output)?;
+    }
+    if let Some(snapshot) = &snapshot
+        && !interrupt::requested()
+    {
+        snapshot.save()?;
+    }
+    report_failures(&failures, args.list.offline);
+    if let Some(timing) = &timing
+        && args.format != Format::Json
+    {
+        eprintln!("{}", timing.render());
+    }
+    if args.list.type_breakdown {
+        eprintln!("{}", type_breakdown(&stories));
+    }
+    if let Some(command) = &args.list.pipe_each
+        && let Err(e) = pipe::run(command, &stories)
+        && outcome.is_ok()
+    {
+        return Err(e);
+    }
+    if let Some(template) = &args.list.exec
+        && let Err(e) = exec::run(template, &stories, args.list.jobs, args.list.exec_fail_fast)
+        && outcome.is_ok()
+    {
+        return Err(e);
+    }
+    prefetch.finish();
+    outcome
+}
 
-    let endpoint = match args.sort {
-        SortMode::Hottest => "topstories",
-        SortMode::Latest => "newstories",
+/// Renders the listing in the `--format` asked for, with the summary
+/// lines of the human formats.
+fn render_listing(
+    args: &Args,
+    client: &HnClient,
+    listing: &Listing,
+    timing: Option<&timing::Summary>,
+) -> String {
+    let Listing {
+        stories,
+        sections,
+        failures,
+        newest,
+        ..
+    } = listing;
+    let newest = args.list.since_id.map(|since| newest.unwrap_or(since));
+    let mut output = if !args.quiet() && args.output_file().is_some() {
+        format!("\n{}\n", header(args))
+    } else {
+        String::new()
     };
+    match args.format {
+        _ if args.list.url_only => output.push_str(&render_urls(stories, args.list.print0)),
+        Format::Json => {
+            output.push_str(&render_json(
+                stories,
+                failures,
+                args.errors_only,
+                timing,
+                newest,
+                args.link_fallback,
+            ));
+        },
+        Format::Yaml => output.push_str(&render_yaml(stories, newest, args.link_fallback)),
+        Format::Badge => output = render_badge(stories, args.badge_field.unwrap_or_default()),
+        Format::Html => {
+            let icons = if args.list.with_favicons {
+                fetch::favicons(args, client, stories)
+            } else {
+                HashMap::new()
+            };
+            output = page::render(stories, sections, &icons, args.now());
+        },
+        Format::Pretty | Format::Table if !stories.is_empty() => {
+            if args.format == Format::Table {
+                output.push_str(&render_sections(stories, sections, |shown| {
+                    render_table(args, shown)
+                }));
+            } else if !args.list.stream && !args.incremental() {
+                let layout = args.layout().fit_scores(stories);
+                output.push_str(&render_sections(stories, sections, |shown| {
+                    match args.list.group_by {
+                        Some(GroupBy::Day) => render_days(args, shown, layout),
+                        None => render_pretty(shown, layout),
+                    }
+                }));
+            }
+
+            if !args.quiet() {
+                if let Some(newest) = newest {
+                    output.push_str(&since_line(newest));
+                }
+                output.push_str(&done_line(args, listing));
+            }
+        },
+        Format::Pretty | Format::Table => {},
+    }
+    output
+}
+
+/// Writes the rendered results to the `--output` file, or prints them,
+/// through the pager for the pretty format.
+/// Comments: a reader that stops early, like `head`, closes the pipe; that's
+/// not an error worth reporting.
+fn emit(args: &Args, output: &str) -> Result<(), Error> {
+    match args.output_file() {
+        Some(path) => output::write_atomic(path, &console::strip_ansi_codes(output)),
+        None if args.format.is_human() && !args.list.url_only => {
+            pager::print(output, !args.no_pager && !args.list.stream);
+            Ok(())
+        },
+        None => match io::stdout().lock().write_all(output.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(Error::Output {
+                path: PathBuf::from("<stdout>"),
+                source: e,
+            }),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Prints `text` in one write through the stdout lock, rather than the
+/// line-buffered, per-call locking of `print!`.
+/// Comments: as in [`emit`], a reader that stopped early isn't an error.
+fn print_stdout(text: &str) {
+    let _ = io::stdout().lock().write_all(text.as_bytes());
+}
+
+/// The `--url-only` records: each story's link, or its discussion page for
+/// text posts, ended by a newline or, with `print0`, a NUL byte.
+fn render_urls(stories: &[Story], print0: bool) -> String {
+    let end = if print0 { '\0' } else { '\n' };
+    let mut output = String::new();
+    for story in stories {
+        output.push_str(&story.link());
+        output.push(end);
+    }
+    output
+}
+
+/// The `--format badge` line: `field` of the first story, or nothing when
+/// there is none.
+fn render_badge(stories: &[Story], field: BadgeField) -> String {
+    stories.first().map_or_else(String::new, |story| {
+        let value = match field {
+            BadgeField::Score => story.score.to_string(),
+            BadgeField::Title => story.title.clone(),
+            BadgeField::Comments => story.descendants.unwrap_or_default().to_string(),
+            BadgeField::Url => story.link(),
+            BadgeField::Id => story.id.to_string(),
+            BadgeField::By => story.by.clone(),
+        };
+        value + "\n"
+    })
+}
+
+/// Turns the end state of a listing run into its exit status.
+fn outcome(args: &Args, stories: &[Story], failures: &[FetchFailure]) -> Result<ExitCode, Error> {
+    if interrupt::requested() {
+        return Err(Error::Interrupted);
+    }
+    if args.list.strict && !failures.is_empty() {
+        return Err(Error::Incomplete(failures.len()));
+    }
+    if stories.is_empty() && !args.errors_only && !args.list.changed_only {
+        return Err(Error::NoResults);
+    }
+    if let Some(required) = args.list.require
+        && stories.len() < required
+    {
+        return Err(Error::TooFewResults {
+            required,
+            found: stories.len(),
+        });
+    }
+    Ok(ExitCode::SUCCESS)
+}
 
-    let list_url = format!("https://hacker-news.firebaseio.com/v0/{}.json", endpoint);
-
-    // 1. Fetch story IDs
-    let mut list_response = ureq::get(&list_url).call()?;
-    let story_ids: Vec<u32> = list_response.body_mut().read_json()?;
-    let limit = args.count.min(story_ids.len());
-    let target_ids = &story_ids[..limit];
-
-    // 2. Set up Progress Bar
-    // Comments: indicatif helps manage user expectations during blocking I/O
-    let pb = ProgressBar::new(limit as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template(
-                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
-            )?
-            .progress_chars("#>-"),
+/// The `--since-id` hint line telling the user where to resume next run.
+fn since_line(newest: u32) -> String {
+    format!(
+        "{} {}\n",
+        "Newest id:".dimmed(),
+        format!("{newest} (pass --since-id {newest} next run)").bold()
+    )
+}
+
+/// The closing `Done!` line with the story total and how many stories
+/// `--max-per-domain` and `--dedupe-url` left out; when filters are set or
+/// fewer stories than `--count` came back, also how many were requested,
+/// available (listed by the feeds), fetched and shown.
+fn done_line(args: &Args, listing: &Listing) -> String {
+    let shown = listing.stories.len();
+    let noun = if shown == 1 { "story" } else { "stories" };
+    let mut summary = format!("{shown} {noun}");
+    let capped: usize = listing.sections.iter().map(|section| section.capped).sum();
+    if capped > 0 {
+        let _ = write!(summary, ", {capped} more left out by --max-per-domain");
+    }
+    if listing.collapsed > 0 {
+        let noun = if listing.collapsed == 1 {
+            "repost"
+        } else {
+            "reposts"
+        };
+        let _ = write!(
+            summary,
+            ", {} {noun} collapsed by --dedupe-url",
+            listing.collapsed
+        );
+    }
+    if args.list.min_karma.is_some() {
+        let _ = write!(summary, ", {} left out by --min-karma", listing.low_karma);
+    }
+    let requested = args.list.count.get() * args.list.sort.len();
+    let available = listing
+        .sections
+        .iter()
+        .map(|section| section.available)
+        .sum();
+    if args.criteria().is_active()
+        || args.list.min_karma.is_some()
+        || [available, listing.fetched, shown]
+            .iter()
+            .any(|&n| n != requested)
+    {
+        let _ = write!(
+            summary,
+            " · {requested} requested, {available} available, {} fetched, {shown} shown",
+            listing.fetched
+        );
+    }
+    format!(
+        "{} {}\n",
+        paint("Done!", theme::current().good).bold(),
+        summary.dimmed()
+    )
+}
+
+/// The `--type-breakdown` line: how many of `stories` are of each type, the
+/// most common first, e.g. `Types: 28 stories, 2 jobs`.
+/// Comments: Ask HN and Show HN posts are stories to the API; items cached
+/// before the `type` field was kept are of unknown type.
+fn type_breakdown(stories: &[Story]) -> String {
+    let mut counts: Vec<(ItemType, usize)> = [
+        ItemType::Story,
+        ItemType::Job,
+        ItemType::Poll,
+        ItemType::Comment,
+        ItemType::PollOpt,
+        ItemType::Unknown,
+    ]
+    .into_iter()
+    .map(|kind| {
+        (
+            kind,
+            stories.iter().filter(|story| story.kind == kind).count(),
+        )
+    })
+    .filter(|&(_, count)| count > 0)
+    .collect();
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+    if counts.is_empty() {
+        return "Types: none".to_string();
+    }
+    let counts: Vec<String> = counts
+        .into_iter()
+        .map(|(kind, count)| {
+            let noun = match (kind, count) {
+                (ItemType::Story, 1) => "story",
+                (ItemType::Story, _) => "stories",
+                (ItemType::Job, 1) => "job",
+                (ItemType::Job, _) => "jobs",
+                (ItemType::Poll, 1) => "poll",
+                (ItemType::Poll, _) => "polls",
+                (ItemType::Comment, 1) => "comment",
+                (ItemType::Comment, _) => "comments",
+                (ItemType::PollOpt, 1) => "poll option",
+                (ItemType::PollOpt, _) => "poll options",
+                (ItemType::Unknown, _) => "of unknown type",
+            };
+            format!("{count} {noun}")
+        })
+        .collect();
+    format!("Types: {}", counts.join(", "))
+}
+
+/// The default progress bar template, its bar in `color` or, without one,
+/// the terminal's own color.
+fn progress_template(color: Option<Color>) -> String {
+    let bar = color.map_or_else(
+        || "bar:40".to_string(),
+        |color| format!("bar:40.{}/blue", theme::style_name(color)),
     );
+    format!(
+        "{{spinner:.green}} [{{elapsed_precise}}] [{{{bar}}}] {{pos}}/{{len}} ({{eta}}) {{wide_msg}}"
+    )
+}
 
-    let mut stories = Vec::new();
+/// Builds the progress bar style from a user template, falling back to
+/// [`progress_template`] in `color` with a warning when it doesn't parse.
+fn progress_style(template: Option<&str>, color: Option<Color>) -> ProgressStyle {
+    let default = progress_template(color);
+    let style = template.map_or_else(
+        || ProgressStyle::with_template(&default),
+        |template| {
+            ProgressStyle::with_template(template).or_else(|e| {
+                eprint_line(
+                    &format!("warning: invalid --progress-template ({e}); using the default"),
+                    theme::current().warn,
+                );
+                ProgressStyle::with_template(&default)
+            })
+        },
+    );
+    style
+        .expect("built-in progress template is valid")
+        .progress_chars("#>-")
+}
 
-    // 3. Fetch stories sequentially
-    for &id in target_ids {
-        if let Ok(story) = get_story_details(id) {
-            stories.push(story);
-        }
-        pb.inc(1);
+/// Summarizes failed fetches on stderr, e.g.
+/// `2 items failed to fetch: 41231 (timeout), 41260 (HTTP 500)`.
+fn report_failures(failures: &[FetchFailure], offline: bool) {
+    if failures.is_empty() {
+        return;
     }
 
-    pb.finish_and_clear();
+    let noun = if failures.len() == 1 { "item" } else { "items" };
+    let items = failures
+        .iter()
+        .map(|failure| format!("{} ({})", failure.id, failure.category))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let problem = if offline {
+        "unavailable offline"
+    } else {
+        "failed to fetch"
+    };
+    eprint_line(
+        &format!("{} {noun} {problem}: {items}", failures.len()),
+        theme::current().warn,
+    );
+}
 
-    // 4. Pretty Print Results
+/// Renders the stories in the colored, human-friendly layout.
+fn render_pretty(stories: &[Story], layout: Layout) -> String {
+    let layout = layout.fit_scores(stories);
+    let mut output = String::with_capacity(stories.len() * STORY_CAPACITY);
     for (i, story) in stories.iter().enumerate() {
-        let index = format!("{:>2}.", i + 1).dimmed();
-        let score = format!("[{:^4}]", story.score).yellow().bold();
-        let title = story.title.white().bold();
-        let author = format!("by {}", story.by).bright_black();
+        write_story(&mut output, i + 1, story, None, layout);
+    }
+    output
+}
 
-        println!("{} {} {}", index, score, title);
+/// Bytes reserved per story when rendering a listing, about one full block
+/// with its color codes, so the buffer seldom grows.
+const STORY_CAPACITY: usize = 256;
 
-        if let Some(url) = &story.url {
-            println!("      {} {}", "🔗".dimmed(), url.cyan().underline());
+/// [`render_pretty`] under a `── YYYY-MM-DD ──` heading per submission day,
+/// newest day first, and stories without a time under `── unknown ──` last.
+/// Comments: the sort is stable, so each day keeps the `--order-by` order;
+/// ranks follow the grouped order.
+fn render_days(args: &Args, stories: &[Story], layout: Layout) -> String {
+    let layout = layout.fit_scores(stories);
+    let mut by_day: Vec<(Option<String>, &Story)> = stories
+        .iter()
+        .map(|story| (story.time.and_then(|time| args.day(time)), story))
+        .collect();
+    by_day.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    let mut output = String::with_capacity(stories.len() * STORY_CAPACITY);
+    let mut current = None;
+    for (i, (day, story)) in by_day.into_iter().enumerate() {
+        if current.as_ref() != Some(&day) {
+            if i > 0 && matches!(layout, Layout::Compact { .. }) {
+                output.push('\n');
+            }
+            let heading = format!("── {} ──", day.as_deref().unwrap_or("unknown"));
+            let _ = writeln!(output, "{}", heading.dimmed());
+            if matches!(layout, Layout::Full { .. }) {
+                output.push('\n');
+            }
+            current = Some(day);
         }
-        println!("      {}\n", author);
+        write_story(&mut output, i + 1, story, None, layout);
     }
+    output
+}
 
-    println!("{}", "Done!".green().bold());
-    Ok(())
+/// Renders the stories as an aligned table, in the `--fields` columns.
+fn render_table(args: &Args, stories: &[Story]) -> String {
+    table::render(stories, &args.fields, args.terminal_width(), args.now())
+}
+
+/// `render` for a listing of several feeds: each section under a heading
+/// naming its feed, ranked from 1.
+fn render_sections(
+    stories: &[Story],
+    sections: &[Section],
+    render: impl Fn(&[Story]) -> String,
+) -> String {
+    if let [_] = sections {
+        return render(stories);
+    }
+
+    let theme = theme::current();
+    let mut output = String::new();
+    let mut rest = stories;
+    for section in sections {
+        let (shown, after) = rest.split_at(section.len.min(rest.len()));
+        rest = after;
+        let noun = if section.len == 1 { "story" } else { "stories" };
+        let repeats = if section.repeats > 0 {
+            format!(", {} more shown above", section.repeats)
+        } else {
+            String::new()
+        };
+        let _ = writeln!(
+            output,
+            "{} {}\n",
+            paint(&format!("▸ {}", feed_title(section.sort)), theme.badge).bold(),
+            format!("{} {noun}{repeats}", section.len).dimmed()
+        );
+        output.push_str(&render(shown));
+    }
+    output
+}
+
+/// Heading of a feed's section, e.g. `Top stories`.
+const fn feed_title(sort: SortMode) -> &'static str {
+    match sort {
+        SortMode::Hottest => "Top stories",
+        SortMode::Latest => "New stories",
+        SortMode::Ask => "Ask HN",
+    }
+}
+
+/// Appends one story to `output` as its block of pretty output, trailing
+/// blank line included, or as a single line in the compact layout; `badge`,
+/// such as a score delta, goes between the score and the title.
+/// Comments: listings write every story into one buffer rather than
+/// formatting a `String` per story and joining them.
+fn write_story(
+    output: &mut String,
+    rank: usize,
+    story: &Story,
+    badge: Option<ColoredString>,
+    layout: Layout,
+) {
+    let (body, hot_at, score_width, now) = match layout {
+        Layout::Full {
+            body,
+            hot_at,
+            score_width,
+            now,
+        } => (body, hot_at, score_width, now),
+        Layout::Compact {
+            width,
+            hot_at,
+            score_width,
+            now,
+        } => {
+            write_compact(output, rank, story, badge, width, hot_at, score_width, now);
+            return;
+        },
+    };
+
+    let theme = theme::current();
+    let _ = write!(
+        output,
+        "{} {} ",
+        paint(&format!("{rank:>2}."), theme.rank).dimmed(),
+        theme.score(
+            &format!("[{:^score_width$}]", count(story.score)),
+            story.score,
+        )
+    );
+    if let Some(icon) = icons::of(story) {
+        let _ = write!(output, "{icon} ");
+    }
+    if let Some(prefix) = prefix_badge(story) {
+        let _ = write!(output, "{prefix} ");
+    }
+    if let Some(badge) = badge {
+        let _ = write!(output, "{badge} ");
+    }
+    let _ = writeln!(output, "{}", title_style(&story.title, story.score, hot_at));
+    if let Some(url) = &story.url {
+        let _ = write!(
+            output,
+            "      {} {}",
+            "🔗".dimmed(),
+            paint(url, theme.url).underline()
+        );
+        if let Some(status) = &story.link_status {
+            let _ = write!(output, " {}", link_badge(status));
+        }
+        output.push('\n');
+    }
+    if let Some((bar, result)) = poll_summary(story) {
+        let _ = writeln!(output, "      {} {bar} {result}", "📊".dimmed());
+    }
+    let _ = write!(
+        output,
+        "      {}",
+        paint(&format!("by {}", story.by), theme.author)
+    );
+    if let Some(age) = story_age(story, now) {
+        let _ = write!(output, " · {age}");
+    }
+    if !story.feeds.is_empty() {
+        let feeds: Vec<&str> = story.feeds.iter().map(|feed| feed.name()).collect();
+        let _ = write!(output, " · {}", format!("in {}", feeds.join(", ")).dimmed());
+    }
+    output.push_str(&cached_marker(story));
+    output.push('\n');
+    output.push_str(&comment_preview(story, 6, None));
+    if let Some(text) = story.body().filter(|_| body) {
+        let _ = write!(output, "\n{}\n", indent(&text));
+    }
+    output.push('\n');
+}
+
+/// ` · (cached)` after the age of a story shown from the cache because it
+/// failed to fetch; empty otherwise.
+fn cached_marker(story: &Story) -> String {
+    if story.cached {
+        format!(" · {}", paint("(cached)", theme::current().warn))
+    } else {
+        String::new()
+    }
+}
+
+/// Widest a `--top-comment` preview gets, in columns.
+const COMMENT_PREVIEW_WIDTH: usize = 100;
+
+/// The `--top-comment` line under a story, `indent` columns in, e.g.
+/// `💬 pg: The first comment, on one line…`; empty when there is no
+/// comment to show.
+/// Comments: the comment's paragraphs are joined into one line and cut to
+/// fit `width`, if known, and [`COMMENT_PREVIEW_WIDTH`] in any case.
+fn comment_preview(story: &Story, indent: usize, width: Option<usize>) -> String {
+    let Some(comment) = &story.top_comment else {
+        return String::new();
+    };
+    let text = comment
+        .body()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.is_empty() {
+        return String::new();
+    }
+    let limit = width
+        .map_or(COMMENT_PREVIEW_WIDTH, |width| {
+            width.saturating_sub(indent + 3)
+        })
+        .min(COMMENT_PREVIEW_WIDTH);
+    let room = limit
+        .saturating_sub(measure_text_width(&comment.by) + 2)
+        .max(10);
+    format!(
+        "{:indent$}{} {}: {}\n",
+        "",
+        "💬".dimmed(),
+        paint(&comment.by, theme::current().author),
+        truncate_str(&text, room, "…")
+    )
+}
+
+/// Cells of the vote bar of a poll's leading option.
+const POLL_BAR_WIDTH: u8 = 10;
+
+/// The vote bar of a poll's leading option and its result, e.g.
+/// `██████░░░░` and `62% Yes (1,234 votes)`, once `--include-poll-results`
+/// fetched the options.
+/// Comments: a tie goes to the option listed first; shares are rounded half
+/// up.
+fn poll_summary(story: &Story) -> Option<(String, String)> {
+    let options = story.poll_options.as_ref()?;
+    let top = options.iter().rev().max_by_key(|option| option.score)?;
+    let votes = |option: &hn_cli::PollOpt| i64::from(option.score.max(0));
+    let total: i64 = options.iter().map(votes).sum();
+    let noun = if total == 1 { "vote" } else { "votes" };
+    let share = |scale: i64| {
+        (votes(top) * scale + total / 2)
+            .checked_div(total)
+            .unwrap_or(0)
+    };
+    let filled = usize::try_from(share(POLL_BAR_WIDTH.into())).unwrap_or_default();
+    let bar = format!(
+        "{}{}",
+        paint(&"█".repeat(filled), theme::current().badge),
+        "░".repeat(usize::from(POLL_BAR_WIDTH) - filled).dimmed()
+    );
+    let result = format!("{}% {} ({} {noun})", share(100), top.label(), count(total));
+    Some((bar, result))
+}
+
+/// Appends the `--compact` line: `rank. [score] title (site) by author · age —
+/// Nc`, with a poll's leading option after the age and a `--top-comment`
+/// preview on the next line.
+/// Comments: only the title is shortened, so the rest of the line stays
+/// readable on narrow terminals.
+#[allow(clippy::too_many_arguments)] // the fields of `Layout::Compact`, unpacked
+fn write_compact(
+    output: &mut String,
+    rank: usize,
+    story: &Story,
+    badge: Option<ColoredString>,
+    width: Option<usize>,
+    hot_at: i32,
+    score_width: usize,
+    now: Option<i64>,
+) {
+    let index = format!("{rank:>2}.");
+    let score = format!("[{:^score_width$}]", count(story.score));
+    let icon = icons::of(story).map_or_else(String::new, |icon| format!(" {icon}"));
+    let prefix = prefix_badge(story).map_or_else(String::new, |prefix| format!(" {prefix}"));
+    let badge = badge.map_or_else(String::new, |badge| format!(" {badge}"));
+    let site = story
+        .host()
+        .map_or_else(String::new, |host| format!(" ({host})"));
+    let author = format!(" by {}", story.by);
+    let age = story_age(story, now).map_or_else(String::new, |age| format!(" · {age}"));
+    let cached = cached_marker(story);
+    let comments = story
+        .descendants
+        .map_or_else(String::new, |comments| format!(" — {}c", count(comments)));
+    let status = story
+        .link_status
+        .as_ref()
+        .map_or_else(String::new, |status| format!(" {}", link_badge(status)));
+    let poll =
+        poll_summary(story).map_or_else(String::new, |(_, result)| format!(" · 📊 {result}"));
+
+    let title = width.map_or_else(
+        || story.title.clone(),
+        |width| {
+            let fixed = [
+                &index, " ", &score, &icon, &prefix, &badge, " ", &site, &author, &age, &cached,
+                &poll, &comments, &status,
+            ]
+            .iter()
+            .map(|part| measure_text_width(part))
+            .sum::<usize>();
+            truncate_str(&story.title, width.saturating_sub(fixed).max(10), "…").into_owned()
+        },
+    );
+
+    let theme = theme::current();
+    let _ = write!(
+        output,
+        "{} {}{icon}{prefix}{badge} {}{}{}{age}{cached}{poll}{}{status}\n{}",
+        paint(&index, theme.rank).dimmed(),
+        theme.score(&score, story.score),
+        title_style(&title, story.score, hot_at),
+        paint(&site, theme.url),
+        paint(&author, theme.author),
+        comments.dimmed(),
+        comment_preview(story, 4, width)
+    );
+}
+
+/// How long ago `story` was submitted, e.g. `3h ago`, colored from fresh to
+/// old; its raw Unix time when there's no `now`.
+fn story_age(story: &Story, now: Option<i64>) -> Option<ColoredString> {
+    let time = story.time?;
+    let text = age_text(time, now);
+    Some(now.map_or_else(
+        || text.normal(),
+        |now| theme::current().age(&text, now.saturating_sub(time).max(0)),
+    ))
+}
+
+/// Submission `time` as an age from `now`, e.g. `3h ago`, or as the raw Unix
+/// time when there's no `now`.
+fn age_text(time: i64, now: Option<i64>) -> String {
+    let Some(now) = now else {
+        return time.to_string();
+    };
+    let age = now.saturating_sub(time).max(0);
+    match age {
+        ..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", age / 60),
+        3600..86_400 => format!("{}h ago", age / 3600),
+        86_400..31_536_000 => format!("{}d ago", age / 86_400),
+        _ => format!("{}y ago", age / 31_536_000),
+    }
+}
+
+/// `ASK`, `SHOW` or `TELL` for a story whose title opens with the
+/// convention, unless `--type-icons` already marks it.
+fn prefix_badge(story: &Story) -> Option<ColoredString> {
+    if icons::of(story).is_some() {
+        return None;
+    }
+    let prefix = story.title_prefix()?;
+    Some(paint(&prefix.name().to_ascii_uppercase(), theme::current().badge).bold())
+}
+
+/// The `--check-links` verdict, e.g. `✓ 200`, `✗ 404` or `⚠ timeout`,
+/// followed by where a redirected link ended up.
+fn link_badge(status: &LinkStatus) -> String {
+    let theme = theme::current();
+    let verdict = match (status.status, &status.error) {
+        (Some(code @ ..400), _) => paint(&format!("✓ {code}"), theme.good),
+        (Some(code), _) => paint(&format!("✗ {code}"), theme.bad).bold(),
+        (None, error) => paint(
+            &format!("⚠ {}", error.as_deref().unwrap_or("no response")),
+            theme.warn,
+        ),
+    };
+    let moved = status.final_url.as_ref().map_or_else(String::new, |url| {
+        format!(" {}", format!("→ {url}").dimmed())
+    });
+    format!("{verdict}{moved}")
+}
+
+/// A story title in bold, highlighted once the score reaches `hot_at`, with
+/// the `--filter` terms in reverse video.
+fn title_style(title: &str, score: i32, hot_at: i32) -> String {
+    let style = |text: &str| {
+        if score >= hot_at {
+            theme::current().hot_title(text)
+        } else {
+            paint(text, theme::current().title).bold()
+        }
+    };
+
+    let mut styled = String::new();
+    let mut plain_from = 0;
+    for range in filter::keyword_ranges(title, filter::keywords()) {
+        if range.start > plain_from {
+            let _ = write!(styled, "{}", style(&title[plain_from..range.start]));
+        }
+        let _ = write!(styled, "{}", style(&title[range.clone()]).reversed());
+        plain_from = range.end;
+    }
+    if plain_from < title.len() || title.is_empty() {
+        let _ = write!(styled, "{}", style(&title[plain_from..]));
+    }
+    styled
+}
+
+/// Indents every non-blank line of `text` to line up under the title.
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!("      {}", line.trim_end())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `n` in the `--numbers` style, e.g. `1,834`.
+fn count(n: impl Into<i64>) -> String {
+    NUMBERS.get().copied().unwrap_or_default().format(n.into())
+}
+
+/// A score change colored by direction, e.g. a green `+5`.
+fn score_delta(delta: i32) -> ColoredString {
+    match delta {
+        1.. => paint(&format!("+{}", count(delta)), theme::current().good).bold(),
+        0 => "±0".dimmed(),
+        _ => paint(&count(delta), theme::current().bad).bold(),
+    }
+}
+
+/// Renders the results as JSON: a bare array of stories when everything was
+/// fetched, or a `{ "stories", "errors" }` object when something failed, or
+/// `timing` or `newest` is given, which adds a `"timing"` or `"newest"`
+/// member. Text posts get the `url` `links` asks for.
+fn render_json(
+    stories: &[Story],
+    failures: &[FetchFailure],
+    errors_only: bool,
+    timing: Option<&timing::Summary>,
+    newest: Option<u32>,
+    links: LinkFallback,
+) -> String {
+    let rows = || -> Vec<Row> { stories.iter().map(|story| Row::new(story, links)).collect() };
+    let json = if errors_only {
+        serde_json::to_string_pretty(failures)
+    } else if failures.is_empty() && timing.is_none() && newest.is_none() {
+        serde_json::to_string_pretty(&rows())
+    } else {
+        serde_json::to_string_pretty(&JsonReport {
+            stories: rows(),
+            errors: failures,
+            timing,
+            newest,
+        })
+    };
+    json.expect("stories serialize to JSON") + "\n"
+}
+
+/// Renders the stories as a YAML sequence, or a `{ stories, newest }`
+/// mapping when `newest` is given; failures are only summarized on stderr.
+fn render_yaml(stories: &[Story], newest: Option<u32>, links: LinkFallback) -> String {
+    let stories: Vec<Row> = stories.iter().map(|story| Row::new(story, links)).collect();
+    match newest {
+        Some(newest) => serde_yaml::to_string(&YamlReport {
+            stories,
+            newest,
+        }),
+        None => serde_yaml::to_string(&stories),
+    }
+    .expect("stories serialize to YAML")
 }
@@ -0,0 +1,128 @@
+//! Canned API responses for tests (the `mock` feature).
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use serde_json::Value;
+
+use crate::{
+    error::Error,
+    transport::{Response, Transport},
+};
+
+/// A [`Transport`] that answers from fixtures instead of the network.
+///
+/// Routes match on the end of the URL, so `item/8863.json` answers for any
+/// base URL. Unrouted URLs answer HTTP 404.
+/// Comments: clones share routes and the request log, so a test can keep one
+/// to inspect after handing the other to the client.
+#[derive(Clone, Debug, Default)]
+pub struct MockTransport {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    routes: Vec<Route>,
+    requests: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Route {
+    suffix: String,
+    /// Answers given in turn; the last one repeats forever.
+    responses: VecDeque<Response>,
+}
+
+impl MockTransport {
+    /// A mock with no routes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers URLs ending in `suffix` with `body`.
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value)] // fixtures are written inline as `json!(...)`
+    pub fn with_json(self, suffix: &str, body: Value) -> Self {
+        self.push(suffix, Response {
+            status: 200,
+            headers: Vec::new(),
+            body: body.to_string().into_bytes(),
+        })
+    }
+
+    /// Answers URLs ending in `suffix` with an HTTP error `status`. Chained
+    /// after or before [`MockTransport::with_json`] on the same suffix, the
+    /// answers are given in order, e.g. a 500 followed by a success.
+    #[must_use]
+    pub fn with_status(self, suffix: &str, status: u16) -> Self {
+        self.with_headers(suffix, status, &[])
+    }
+
+    /// Like [`MockTransport::with_status`], with response headers such as
+    /// `Retry-After`.
+    #[must_use]
+    pub fn with_headers(self, suffix: &str, status: u16, headers: &[(&str, &str)]) -> Self {
+        self.push(suffix, Response {
+            status,
+            headers: headers
+                .iter()
+                .map(|&(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: Vec::new(),
+        })
+    }
+
+    /// Every URL requested so far, in order.
+    #[must_use]
+    pub fn requests(&self) -> Vec<String> {
+        self.lock().requests.clone()
+    }
+
+    fn push(self, suffix: &str, response: Response) -> Self {
+        {
+            let mut inner = self.lock();
+            if let Some(route) = inner.routes.iter_mut().find(|route| route.suffix == suffix) {
+                route.responses.push_back(response);
+            } else {
+                inner.routes.push(Route {
+                    suffix: suffix.to_string(),
+                    responses: VecDeque::from([response]),
+                });
+            }
+        }
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Transport for MockTransport {
+    fn get(&self, url: &str) -> Result<Response, Error> {
+        let response = {
+            let mut inner = self.lock();
+            inner.requests.push(url.to_string());
+            inner
+                .routes
+                .iter_mut()
+                .find(|route| url.ends_with(&route.suffix))
+                .and_then(|route| {
+                    if route.responses.len() > 1 {
+                        route.responses.pop_front()
+                    } else {
+                        route.responses.front().cloned()
+                    }
+                })
+        };
+
+        Ok(response.unwrap_or_else(|| Response {
+            status: 404,
+            ..Response::default()
+        }))
+    }
+}
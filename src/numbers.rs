@@ -0,0 +1,72 @@
+//! Locale-independent formatting of scores and counts.
+
+/// How counts such as scores and comment totals are written.
+#[derive(Copy, Clone, PartialEq, Eq, Default, clap::ValueEnum, Debug)]
+pub enum NumberStyle {
+    /// Digits only, e.g. `1834`.
+    Plain,
+    /// Thousands separated by commas, e.g. `1,834`.
+    #[default]
+    Grouped,
+    /// Abbreviated to at most 4 characters, e.g. `1.8k` or `12k`.
+    Compact,
+}
+
+/// Suffixes of [`NumberStyle::Compact`], each a thousand times the last.
+const UNITS: [(u64, char); 6] = [
+    (1_000, 'k'),
+    (1_000_000, 'M'),
+    (1_000_000_000, 'B'),
+    (1_000_000_000_000, 'T'),
+    (1_000_000_000_000_000, 'P'),
+    (1_000_000_000_000_000_000, 'E'),
+];
+
+impl NumberStyle {
+    /// `n` in this style; negative numbers get a leading `-`.
+    #[must_use]
+    pub fn format(self, n: i64) -> String {
+        let sign = if n < 0 { "-" } else { "" };
+        let digits = match self {
+            Self::Plain => n.unsigned_abs().to_string(),
+            Self::Grouped => grouped(n.unsigned_abs()),
+            Self::Compact => compact(n.unsigned_abs()),
+        };
+        format!("{sign}{digits}")
+    }
+}
+
+fn grouped(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Below 1000 as it is, then one decimal below 10 of a unit (`9.9k`) and
+/// whole units above (`10k` to `999k`), rounded half up.
+/// Comments: a value that rounds up to 1000 of a unit moves on to the next
+/// one (`999,999` is `1.0M`), so the result never exceeds 4 characters.
+fn compact(n: u64) -> String {
+    if n < 1000 {
+        return n.to_string();
+    }
+    for (unit, suffix) in UNITS {
+        let unit = u128::from(unit);
+        let n = u128::from(n);
+        let tenths = (n * 10 + unit / 2) / unit;
+        if tenths < 100 {
+            return format!("{}.{}{suffix}", tenths / 10, tenths % 10);
+        }
+        let whole = (n + unit / 2) / unit;
+        if whole < 1000 {
+            return format!("{whole}{suffix}");
+        }
+    }
+    unreachable!("u64::MAX is below 1000E")
+}
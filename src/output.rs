@@ -0,0 +1,41 @@
+//! `--output`: writing the formatted results to a file.
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use hn_cli::Error;
+
+/// Replaces `path` with `contents` atomically: the data goes to a temporary
+/// file in the same directory, which is then renamed over `path`, so readers
+/// see either the old file or the whole new one.
+/// Comments: the temporary file must share `path`'s filesystem for the
+/// rename to be atomic, hence the sibling rather than the system temp dir.
+pub fn write_atomic(path: &Path, contents: &str) -> Result<(), Error> {
+    let temp = temp_path(path);
+    let written = write_synced(&temp, contents).and_then(|()| fs::rename(&temp, path));
+    written.map_err(|source| {
+        let _ = fs::remove_file(&temp);
+        Error::Output {
+            path: path.to_path_buf(),
+            source,
+        }
+    })
+}
+
+fn write_synced(path: &Path, contents: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+/// `dir/.name.<pid>.tmp` next to `path`.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or_else(|| "output".into(), |name| name.to_string_lossy());
+    path.with_file_name(format!(".{name}.{}.tmp", process::id()))
+}
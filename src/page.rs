@@ -0,0 +1,126 @@
+//! `--format html`: the listing as a self-contained page, with the styles
+//! inline and, under `--with-favicons`, each site's icon embedded as a
+//! `data:` URI, so the file can be saved or mailed as it is.
+
+use std::{collections::HashMap, fmt::Write as _};
+
+use hn_cli::Story;
+
+use crate::{age_text, feed_title, fetch, fetch::Section};
+
+/// Inline styles, close to the HN front page.
+const STYLE: &str = "
+body { font: 10pt Verdana, Geneva, sans-serif; max-width: 60em; margin: 1em auto; background: #f6f6ef; }
+h1 { font-size: 12pt; background: #ff6600; padding: 4px 8px; margin: 0 0 1em; }
+h2 { font-size: 11pt; margin: 1.5em 0 0.5em; }
+li { margin-bottom: 0.6em; }
+a { color: #000; text-decoration: none; }
+.site, .meta, .meta a { color: #828282; font-size: 8pt; }
+.site img { vertical-align: middle; margin-right: 3px; }
+";
+
+/// Renders `stories` as a page, one list per section, headed when there are
+/// several feeds, or a single list without sections; `icons` holds the favicons
+/// by origin, empty without `--with-favicons`. Ages are measured from `now` as
+/// in the pretty output.
+pub fn render(
+    stories: &[Story],
+    sections: &[Section],
+    icons: &HashMap<String, String>,
+    now: Option<i64>,
+) -> String {
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<meta \
+         name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>Hacker \
+         News</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>Hacker News</h1>\n"
+    );
+    if sections.is_empty() {
+        push_list(&mut page, stories, icons, now);
+    }
+    let mut rest = stories;
+    for section in sections {
+        let (shown, after) = rest.split_at(section.len.min(rest.len()));
+        rest = after;
+        if sections.len() > 1 {
+            let _ = writeln!(page, "<h2>{}</h2>", feed_title(section.sort));
+        }
+        push_list(&mut page, shown, icons, now);
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// Appends `stories` as an `<ol>`.
+fn push_list(
+    page: &mut String,
+    stories: &[Story],
+    icons: &HashMap<String, String>,
+    now: Option<i64>,
+) {
+    page.push_str("<ol>\n");
+    for story in stories {
+        page.push_str(&item(story, icons, now));
+    }
+    page.push_str("</ol>\n");
+}
+
+/// One story's `<li>`: its linked title, site and icon, then points,
+/// author, age and the link to the discussion.
+fn item(story: &Story, icons: &HashMap<String, String>, now: Option<i64>) -> String {
+    let discussion = story.discussion_url();
+    let link = story.url.as_deref().unwrap_or(&discussion);
+    let mut entry = format!(
+        "<li><a class=\"title\" href=\"{}\">{}</a>",
+        escape(link),
+        escape(&story.title)
+    );
+    if let Some(host) = story.host() {
+        let icon = fetch::origin(story)
+            .and_then(|origin| icons.get(&origin))
+            .map_or_else(String::new, |icon| {
+                format!(
+                    "<img src=\"{}\" width=\"16\" height=\"16\" alt=\"\">",
+                    escape(icon)
+                )
+            });
+        let _ = write!(
+            entry,
+            " <span class=\"site\">({icon}{})</span>",
+            escape(&host)
+        );
+    }
+
+    let points = if story.score == 1 { "point" } else { "points" };
+    let age = story
+        .time
+        .map_or_else(String::new, |time| format!(" · {}", age_text(time, now)));
+    let comments = match story.descendants.unwrap_or_default() {
+        1 => "1 comment".to_string(),
+        count => format!("{count} comments"),
+    };
+    let _ = writeln!(
+        entry,
+        "\n<div class=\"meta\">{} {points} by {}{age} · <a href=\"{}\">{comments}</a></div></li>",
+        story.score,
+        escape(&story.by),
+        escape(&discussion)
+    );
+    entry
+}
+
+/// `text` with the characters HTML gives a meaning escaped, for element
+/// content and quoted attributes alike.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
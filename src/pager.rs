@@ -0,0 +1,52 @@
+//! Pipes long pretty output through the user's pager.
+
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use console::Term;
+
+/// Pager used when `$PAGER` is unset. `-R` keeps ANSI colors intact.
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Prints `output`, through the pager when it is enabled, stdout is a TTY and
+/// the output is taller than the terminal.
+/// Comments: falls back to plain printing if the pager can't be started.
+pub fn print(output: &str, enabled: bool) {
+    if enabled && io::stdout().is_terminal() && exceeds_screen(output) && page(output).is_ok() {
+        return;
+    }
+    crate::print_stdout(output);
+}
+
+fn exceeds_screen(output: &str) -> bool {
+    let (rows, _) = Term::stdout().size();
+    output.lines().count() > usize::from(rows)
+}
+
+fn page(output: &str) -> io::Result<()> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    let mut words = pager.split_whitespace();
+    let program = words.next().unwrap_or("less");
+
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Comments: quitting the pager early closes the pipe; that's not an
+        // error worth reporting.
+        match stdin.write_all(output.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {},
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
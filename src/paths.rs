@@ -0,0 +1,65 @@
+//! Platform locations for on-disk state.
+
+use std::{fs, path::PathBuf, sync::OnceLock};
+
+use crate::{eprint_line, theme};
+
+/// Directory name used under each platform base directory.
+const APP_DIR: &str = "hn-cli";
+
+/// `--cache-dir`, if given; set once at startup.
+static CACHE_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// The `--cache-dir` directory once first used: `None` if it couldn't be
+/// created.
+static CACHE_CREATED: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Uses `dir` instead of the platform cache directory from now on.
+pub fn set_cache_dir(dir: PathBuf) {
+    let _ = CACHE_OVERRIDE.set(dir);
+}
+
+/// Cache directory, e.g. `~/.cache/hn-cli` on Linux, or the `--cache-dir`
+/// one.
+/// Comments: an override is created on first use; if that fails the run
+/// warns once and carries on as if there were no cache directory at all.
+pub fn cache_dir() -> Option<PathBuf> {
+    let Some(dir) = CACHE_OVERRIDE.get() else {
+        return dirs::cache_dir().map(|dir| dir.join(APP_DIR));
+    };
+    CACHE_CREATED
+        .get_or_init(|| match fs::create_dir_all(dir) {
+            Ok(()) => Some(dir.clone()),
+            Err(e) => {
+                eprint_line(
+                    &format!(
+                        "warning: can't create cache directory {} ({e}); continuing without a cache",
+                        dir.display()
+                    ),
+                    theme::current().warn,
+                );
+                None
+            },
+        })
+        .clone()
+}
+
+/// Config directory, e.g. `~/.config/hn-cli` on Linux.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// Data directory for user-curated files, e.g. `~/.local/share/hn-cli` on
+/// Linux.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// State directory, e.g. `~/.local/state/hn-cli` on Linux.
+/// Comments: macOS and Windows have no dedicated state dir, so fall back to
+/// the local data dir there.
+pub fn state_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join(APP_DIR))
+}
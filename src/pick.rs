@@ -0,0 +1,103 @@
+//! `--pick`: hands the listing to a fuzzy finder such as fzf and opens what
+//! was picked.
+//!
+//! Each story goes to the picker as one `title<TAB>link<TAB>id` line; the
+//! picker prints the lines it selects, several with `fzf -m`, and each of
+//! those stories is opened in the browser or, with `--pick-print`, printed.
+
+use std::{
+    env,
+    fmt::Write as _,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use hn_cli::{Error, Story};
+
+use crate::{pipe, repl};
+
+/// Picker run when `$HN_PICKER` isn't set: fzf showing only the titles.
+const DEFAULT_PICKER: &str = "fzf --delimiter=\\t --with-nth=1";
+
+/// Offers `stories` in `$HN_PICKER`, then opens (or with `print`, prints)
+/// the link of every story picked.
+/// Comments: the picker draws on the terminal itself, so only its stdin and
+/// stdout are redirected. Quitting it without a choice (fzf exits 1 or 130)
+/// picks nothing and isn't an error.
+pub fn run(stories: &[Story], print: bool) -> Result<(), Error> {
+    let picker = env::var("HN_PICKER")
+        .ok()
+        .filter(|picker| !picker.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PICKER.to_string());
+    let mut words = picker.split_whitespace();
+    let program = words.next().unwrap_or("fzf");
+
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            Error::Usage(if e.kind() == io::ErrorKind::NotFound {
+                format!(
+                    "picker '{program}' not found; install it, set $HN_PICKER, or browse with \
+                     the `repl` command instead"
+                )
+            } else {
+                format!("can't start picker '{program}': {e}")
+            })
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Comments: a picker may stop reading once it has its answer.
+        match stdin.write_all(lines(stories).as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(Error::Usage(format!(
+                    "can't write to picker '{program}': {e}"
+                )));
+            },
+            _ => {},
+        }
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| Error::Usage(format!("picker '{program}' failed: {e}")))?;
+    if !output.status.success() && !matches!(output.status.code(), Some(1 | 130)) {
+        return Err(Error::Usage(format!(
+            "picker '{program}' failed ({})",
+            pipe::describe(output.status)
+        )));
+    }
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some(url) = picked(stories, line) else {
+            continue;
+        };
+        if print {
+            println!("{url}");
+        } else {
+            repl::browse(&url)?;
+        }
+    }
+    Ok(())
+}
+
+/// The picker's input, one `title<TAB>link<TAB>id` line per story.
+fn lines(stories: &[Story]) -> String {
+    let mut lines = String::new();
+    for story in stories {
+        let title: String = story
+            .title
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        let _ = writeln!(lines, "{title}\t{}\t{}", story.link(), story.id);
+    }
+    lines
+}
+
+/// The link of the listed story a picked `line` stands for, found by the id
+/// at its end, since pickers may show or trim the other fields.
+fn picked(stories: &[Story], line: &str) -> Option<String> {
+    let id: u32 = line.rsplit('\t').next()?.trim().parse().ok()?;
+    stories.iter().find(|story| story.id == id).map(Story::link)
+}
@@ -0,0 +1,87 @@
+//! `--pipe-each`: hands every listed story to an external command, for
+//! integrations the CLI doesn't have (webhooks, databases, notifiers).
+
+use std::{
+    io::{self, Write},
+    process::{Command, ExitStatus, Stdio},
+};
+
+use hn_cli::{Error, Story};
+
+use crate::{eprint_line, interrupt, theme};
+
+/// Runs `command` through the shell once per story, in listing order, with
+/// the story as JSON on stdin and its id in `HN_STORY_ID`.
+/// Comments: the command's own output goes straight to the terminal. Every
+/// story is tried even after a failure, and Ctrl-C stops before the next
+/// one.
+pub fn run(command: &str, stories: &[Story]) -> Result<(), Error> {
+    let mut failures = Vec::new();
+    for story in stories {
+        if interrupt::requested() {
+            break;
+        }
+        match pipe(command, story) {
+            Ok(status) if status.success() => {},
+            Ok(status) => failures.push(format!("{} ({})", story.id, describe(status))),
+            Err(e) => failures.push(format!("{} (couldn't run: {e})", story.id)),
+        }
+    }
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    eprint_line(
+        &format!(
+            "--pipe-each failed for {} of {} stories: {}",
+            failures.len(),
+            stories.len(),
+            failures.join(", ")
+        ),
+        theme::current().warn,
+    );
+    Err(Error::PipeFailed(failures.len()))
+}
+
+fn pipe(command: &str, story: &Story) -> io::Result<ExitStatus> {
+    let mut child = shell(command)
+        .env("HN_STORY_ID", story.id.to_string())
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let mut json = serde_json::to_vec(story).expect("stories serialize to JSON");
+        json.push(b'\n');
+        // Comments: a command that doesn't read its input closes the pipe
+        // early; only its exit status decides.
+        match stdin.write_all(&json) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {},
+        }
+    }
+    child.wait()
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// `exit 3`, or the signal that killed the command.
+pub fn describe(status: ExitStatus) -> String {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return format!("killed by signal {signal}");
+    }
+    status
+        .code()
+        .map_or_else(|| status.to_string(), |code| format!("exit {code}"))
+}
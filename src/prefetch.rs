@@ -0,0 +1,126 @@
+//! `--prefetch-next-page`: fetching the stories of the page after the one on
+//! screen in the background, so `next` in `repl` shows them at once.
+//!
+//! `repl` and `--watch` prefetch unless told `--no-prefetch`; other listings
+//! only with `--prefetch`, and then wait for it before exiting. Stories fetched
+//! ahead go to the cache too, where `--offline` and the cached copies of
+//! failed fetches find them.
+//! Comments: the extra requests are bounded by the page: at most `--count`
+//! ids, over `--jobs` workers and the client's `--rate-limit`, and an id is
+//! fetched ahead once per session, so a watch doesn't repeat the next page on
+//! every refresh. A page wanted while its stories are still on the way waits
+//! for them rather than fetching them a second time.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use hn_cli::{HnClient, Story};
+use tracing::debug;
+
+use crate::{Args, cache, interrupt};
+
+/// How often a page waiting for stories on the way checks for Ctrl-C.
+const WAIT_TICK: Duration = Duration::from_millis(100);
+
+/// What the workers share with the page that takes their stories.
+#[derive(Default)]
+struct Arrivals {
+    /// Stories that arrived, by id, until a page takes them.
+    ready: HashMap<u32, Story>,
+    /// Ids queued or in flight.
+    pending: HashSet<u32>,
+}
+
+/// The stories fetched ahead, and the workers still fetching.
+#[derive(Default)]
+pub struct Prefetch {
+    arrivals: Arc<(Mutex<Arrivals>, Condvar)>,
+    /// Every id fetched ahead so far, arrived or not.
+    started: HashSet<u32>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Prefetch {
+    /// Starts fetching those of `ids` not fetched ahead before, with up to
+    /// `--jobs` workers.
+    pub fn start(&mut self, args: &Args, client: &HnClient, ids: &[u32]) {
+        let queue: VecDeque<u32> = ids
+            .iter()
+            .copied()
+            .filter(|&id| self.started.insert(id))
+            .collect();
+        if queue.is_empty() {
+            return;
+        }
+        debug!(ids = queue.len(), "prefetching the next page");
+        self.lock().pending.extend(&queue);
+        self.workers.retain(|worker| !worker.is_finished());
+        let workers = args.list.jobs.get().min(queue.len());
+        let queue = Arc::new(Mutex::new(queue));
+        for _ in 0..workers {
+            let (client, queue, arrivals) = (client.clone(), queue.clone(), self.arrivals.clone());
+            self.workers.push(thread::spawn(move || {
+                while !interrupt::requested() {
+                    let Some(id) = queue
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .pop_front()
+                    else {
+                        break;
+                    };
+                    let story = client
+                        .item(id)
+                        .inspect(cache::store_item)
+                        .inspect_err(|e| debug!(id, error = %e, "prefetch failed"));
+                    let (lock, arrived) = &*arrivals;
+                    {
+                        let mut arrivals = lock.lock().unwrap_or_else(PoisonError::into_inner);
+                        arrivals.pending.remove(&id);
+                        if let Ok(story) = story {
+                            arrivals.ready.insert(id, story);
+                        }
+                    }
+                    arrived.notify_all();
+                }
+            }));
+        }
+    }
+
+    /// Removes the stories among `ids` that were fetched ahead, first waiting
+    /// for those still on the way unless Ctrl-C is pressed.
+    pub fn take(&self, ids: &[u32]) -> HashMap<u32, Story> {
+        let (_, arrived) = &*self.arrivals;
+        let mut arrivals = self.lock();
+        while !interrupt::requested() && ids.iter().any(|id| arrivals.pending.contains(id)) {
+            arrivals = arrived
+                .wait_timeout(arrivals, WAIT_TICK)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        let taken = ids
+            .iter()
+            .filter_map(|id| arrivals.ready.remove_entry(id))
+            .collect();
+        drop(arrivals);
+        taken
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Arrivals> {
+        self.arrivals
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits for the workers, for a run that exits once they are done;
+    /// Ctrl-C stops them after the requests in flight.
+    pub fn finish(self) {
+        for worker in self.workers {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Named listings from the config file's `[profiles]` table, run with
+//! `--profile NAME` or `profile NAME`.
+
+use std::{collections::BTreeMap, num::NonZeroUsize};
+
+use clap::ValueEnum;
+use colored::Colorize;
+use hn_cli::{Error, SortMode};
+use serde::Deserialize;
+
+use crate::{Args, Format, dedup_sorts};
+
+/// What `profile list` (like a bare `profile`) is spelled as, instead of a
+/// profile's name.
+pub const LIST: &str = "list";
+
+/// Ids of the flags a profile can set.
+pub const FLAGS: [&str; 7] = [
+    "sort",
+    "feeds",
+    "count",
+    "filter",
+    "min_score",
+    "min_comments",
+    "format",
+];
+
+/// Listing flags bundled under a name; every key is optional and sets the
+/// flag of the same name.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Feeds, as repeated `--sort`.
+    pub sort: Vec<SortMode>,
+    /// Feeds to merge into one list, as `--feeds`.
+    pub feeds: Vec<SortMode>,
+    /// As `--count`.
+    pub count: Option<NonZeroUsize>,
+    /// Title terms, as repeated `--filter`.
+    pub filter: Vec<String>,
+    /// As `--min-score`.
+    pub min_score: Option<i32>,
+    /// As `--min-comments`.
+    pub min_comments: Option<u32>,
+    /// As `--format`.
+    pub format: Option<Format>,
+}
+
+impl Profile {
+    /// The command-line flags this profile stands for, e.g. `--sort latest
+    /// --filter rust`.
+    fn flags(&self) -> String {
+        let mut flags = Vec::new();
+        for sort in &self.sort {
+            flags.push(format!("--sort {}", name(sort)));
+        }
+        if !self.feeds.is_empty() {
+            let feeds: Vec<String> = self.feeds.iter().map(name).collect();
+            flags.push(format!("--feeds {}", feeds.join(",")));
+        }
+        if let Some(count) = self.count {
+            flags.push(format!("--count {count}"));
+        }
+        for term in &self.filter {
+            if term.contains(char::is_whitespace) {
+                flags.push(format!("--filter '{term}'"));
+            } else {
+                flags.push(format!("--filter {term}"));
+            }
+        }
+        if let Some(min) = self.min_score {
+            flags.push(format!("--min-score {min}"));
+        }
+        if let Some(min) = self.min_comments {
+            flags.push(format!("--min-comments {min}"));
+        }
+        if let Some(format) = &self.format {
+            flags.push(format!("--format {}", name(format)));
+        }
+        flags.join(" ")
+    }
+}
+
+/// The command-line spelling of `value`.
+fn name(value: &impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Sets the flags of the `--profile` in `profiles` that weren't given on
+/// the command line.
+///
+/// # Errors
+///
+/// [`Error::Usage`] naming the known profiles when there is none by that
+/// name.
+pub fn apply(args: &mut Args, profiles: &BTreeMap<String, Profile>) -> Result<(), Error> {
+    let Some(name) = &args.list.profile else {
+        return Ok(());
+    };
+    let Some(profile) = profiles.get(name) else {
+        let known = if profiles.is_empty() {
+            "the config file defines none".to_string()
+        } else {
+            let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            format!("known profiles: {}", names.join(", "))
+        };
+        return Err(Error::Usage(format!("unknown profile '{name}'; {known}")));
+    };
+
+    let given = |id: &str| args.given.contains(&id);
+    if !given("sort") && !given("feeds") {
+        if !profile.feeds.is_empty() {
+            args.list.feeds.clone_from(&profile.feeds);
+            args.list.sort.clone_from(&profile.feeds);
+        } else if !profile.sort.is_empty() {
+            args.list.sort.clone_from(&profile.sort);
+        }
+        dedup_sorts(&mut args.list.sort);
+    }
+    if let Some(count) = profile.count.filter(|_| !given("count")) {
+        args.list.count = count;
+        args.list.count_asked = true;
+    }
+    if !profile.filter.is_empty() && !given("filter") {
+        args.list.filter.clone_from(&profile.filter);
+    }
+    if let Some(min) = profile.min_score.filter(|_| !given("min_score")) {
+        args.list.min_score = Some(min);
+    }
+    if let Some(min) = profile.min_comments.filter(|_| !given("min_comments")) {
+        args.list.min_comments = Some(min);
+    }
+    if let Some(format) = profile.format.filter(|_| !given("format")) {
+        args.format = format;
+    }
+    Ok(())
+}
+
+/// Prints each profile's name and the flags it stands for.
+pub fn list(profiles: &BTreeMap<String, Profile>) {
+    if profiles.is_empty() {
+        println!("No profiles yet. Define one under [profiles.NAME] in the config file.");
+        return;
+    }
+    let width = profiles.keys().map(String::len).max().unwrap_or_default();
+    for (name, profile) in profiles {
+        println!("{}  {}", format!("{name:<width$}").bold(), profile.flags());
+    }
+}
@@ -0,0 +1,71 @@
+//! Client-side request pacing: a token bucket shared by every clone.
+
+use std::{
+    num::NonZeroU32,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Caps requests per second across a client and all of its clones.
+///
+/// The bucket holds up to one second's worth of requests, so a short burst
+/// goes out at once and sustained load is paced evenly.
+/// Comments: a caller that finds the bucket empty reserves the next free
+/// slot before sleeping, so concurrent workers queue up rather than waking
+/// together.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    per_second: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// May go negative: each unit below zero is a reserved future slot.
+    tokens: f64,
+    refilled: Instant,
+    throttled: usize,
+    waited: Duration,
+}
+
+impl RateLimit {
+    pub fn new(per_second: NonZeroU32) -> Self {
+        let per_second = f64::from(per_second.get());
+        Self {
+            per_second,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: per_second,
+                refilled: Instant::now(),
+                throttled: 0,
+                waited: Duration::ZERO,
+            })),
+        }
+    }
+
+    /// Blocks until another request may go out.
+    pub fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.refilled).as_secs_f64() * self.per_second;
+            bucket.tokens = (bucket.tokens + refill).min(self.per_second) - 1.0;
+            bucket.refilled = now;
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+
+            let wait = Duration::from_secs_f64(-bucket.tokens / self.per_second);
+            bucket.throttled += 1;
+            bucket.waited += wait;
+            wait
+        };
+        thread::sleep(wait);
+    }
+
+    /// Requests that had to wait, and how long they waited in total.
+    pub fn throttled(&self) -> (usize, Duration) {
+        let bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        (bucket.throttled, bucket.waited)
+    }
+}
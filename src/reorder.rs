@@ -0,0 +1,79 @@
+//! Rank-ordered incremental printing of the pretty listing.
+
+use std::collections::BTreeMap;
+
+use hn_cli::Story;
+use indicatif::ProgressBar;
+
+use crate::{Args, Layout, filter::Criteria, print_stdout, write_story};
+
+/// Prints each story as soon as every higher-ranked one has arrived or
+/// failed, so the listing fills in top-down while the rest is in flight.
+/// Comments: applies the same filters and ranking as the buffered listing,
+/// so the final output is identical; only its timing differs.
+pub struct Reorder<'a> {
+    pb: &'a ProgressBar,
+    criteria: Criteria,
+    layout: Layout,
+    /// Arrived results waiting on a higher-ranked one; `None` marks a
+    /// failure.
+    pending: BTreeMap<usize, Option<Story>>,
+    /// Index of the next result to print.
+    next: usize,
+    /// Stories printed so far, i.e. the rank of the last one.
+    shown: usize,
+    /// The block being printed, reused from story to story.
+    block: String,
+}
+
+impl<'a> Reorder<'a> {
+    pub fn new(args: &Args, pb: &'a ProgressBar) -> Self {
+        Self {
+            pb,
+            criteria: args.criteria(),
+            layout: args.layout(),
+            pending: BTreeMap::new(),
+            next: 0,
+            shown: 0,
+            block: String::new(),
+        }
+    }
+
+    /// Takes the result for `index` (`None` if it failed) and prints every
+    /// story that is now next in line.
+    pub fn push(&mut self, index: usize, story: Option<&Story>) {
+        if index == self.next {
+            self.next += 1;
+            if let Some(story) = story {
+                self.show(story);
+            }
+        } else {
+            self.pending.insert(index, story.cloned());
+        }
+
+        while let Some(slot) = self.pending.remove(&self.next) {
+            self.next += 1;
+            if let Some(story) = slot {
+                self.show(&story);
+            }
+        }
+    }
+
+    /// Prints whatever is still waiting, skipping the gaps left by requests
+    /// that never completed (e.g. after Ctrl-C).
+    pub fn finish(mut self) {
+        for story in std::mem::take(&mut self.pending).into_values().flatten() {
+            self.show(&story);
+        }
+    }
+
+    fn show(&mut self, story: &Story) {
+        if !self.criteria.matches(story) {
+            return;
+        }
+        self.shown += 1;
+        self.block.clear();
+        write_story(&mut self.block, self.shown, story, None, self.layout);
+        self.pb.suspend(|| print_stdout(&self.block));
+    }
+}
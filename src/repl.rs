@@ -0,0 +1,336 @@
+//! `repl` subcommand: an interactive session that keeps the client, the
+//! current listing and its stories warm between commands.
+
+use std::{fmt::Write as _, process::ExitCode};
+
+use colored::Colorize;
+use hn_cli::{Error, HnClient, SortMode, Story};
+use rustyline::{DefaultEditor, error::ReadlineError};
+
+use crate::{
+    Args, comments, eprint_line,
+    fetch::{self, Fetched},
+    interrupt, paths,
+    prefetch::Prefetch,
+    report, report_failures,
+    theme::{self, paint},
+    write_story,
+};
+
+/// File name of the line history inside the state directory.
+const HISTORY_FILE: &str = "repl_history";
+
+/// Summary printed by `help` and after an unknown command.
+const HELP: &str = "\
+Commands:
+  top [N]        front page stories, N per page
+  latest [N]     newest stories, N per page
+  search WORDS   stories matching WORDS, most relevant first
+  next           the next page of the current listing
+  open N         open story N in the browser
+  comments N     read the discussion of story N
+  help           this summary
+  quit           leave the session (Ctrl-D works too)";
+
+/// What the current listing pages through.
+enum Source {
+    /// A feed, with the ids fetched when it was opened.
+    Feed { ids: Vec<u32> },
+    /// An Algolia search.
+    Search { query: String },
+}
+
+struct Session<'a> {
+    args: &'a Args,
+    client: &'a HnClient,
+    source: Option<Source>,
+    per_page: usize,
+    /// Zero-based page of `source` on screen.
+    page: usize,
+    /// The stories on screen, in rank order.
+    shown: Vec<Story>,
+    /// The next page of a feed, fetched while this one is read; none under
+    /// `--no-prefetch`.
+    prefetch: Option<Prefetch>,
+}
+
+/// Reads commands until `quit` or Ctrl-D. A failed command is reported and
+/// the session carries on.
+pub fn run(
+    args: &Args,
+    client: &HnClient,
+    per_page: usize,
+    prefetch: bool,
+) -> Result<ExitCode, Error> {
+    let mut editor = DefaultEditor::new().map_err(|e| Error::Interactive {
+        action: "can't start the interactive session".to_string(),
+        source: e.into(),
+    })?;
+    let history = paths::state_dir().map(|dir| dir.join(HISTORY_FILE));
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+    interrupt::install();
+
+    let mut session = Session {
+        args,
+        client,
+        source: None,
+        per_page,
+        page: 0,
+        shown: Vec::new(),
+        prefetch: (prefetch && !args.list.offline).then(Prefetch::default),
+    };
+    println!("Type 'help' for the commands, 'quit' or Ctrl-D to leave.");
+    loop {
+        let line = match editor.readline("hn> ") {
+            Ok(line) => line,
+            // Comments: Ctrl-C at the prompt drops the line, as in a shell.
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                return Err(Error::Interactive {
+                    action: "can't read the command".to_string(),
+                    source: e.into(),
+                });
+            },
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        if matches!(line, "quit" | "exit" | "q") {
+            break;
+        }
+        if let Err(e) = session.execute(line) {
+            report(&e);
+        }
+        interrupt::clear();
+    }
+
+    if let Some(history) = &history
+        && history
+            .parent()
+            .is_none_or(|dir| std::fs::create_dir_all(dir).is_ok())
+    {
+        let _ = editor.save_history(history);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+impl Session<'_> {
+    fn execute(&mut self, line: &str) -> Result<(), Error> {
+        let (command, rest) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(command, rest)| (command, rest.trim()));
+        match command {
+            "top" | "latest" => {
+                let per_page = parse_count(rest)?;
+                let sort = if command == "top" {
+                    SortMode::Hottest
+                } else {
+                    SortMode::Latest
+                };
+                let ids = fetch::get_story_ids(self.client, sort, false)?;
+                self.per_page = per_page.unwrap_or(self.per_page);
+                self.open_source(Source::Feed {
+                    ids,
+                })
+            },
+            "search" if !rest.is_empty() => self.open_source(Source::Search {
+                query: rest.to_string(),
+            }),
+            "next" => {
+                if self.source.is_none() {
+                    return Err(Error::Usage(
+                        "nothing to page through yet; try 'top'".to_string(),
+                    ));
+                }
+                self.page += 1;
+                let shown = self.show_page();
+                if shown.is_err() {
+                    self.page -= 1;
+                }
+                shown
+            },
+            "open" => {
+                let story = self.story(rest)?;
+                browse(&story.link())
+            },
+            "comments" => {
+                let id = self.story(rest)?.id;
+                self.show_comments(id)
+            },
+            "help" => {
+                println!("{HELP}");
+                Ok(())
+            },
+            _ => {
+                eprint_line(&format!("unknown command '{line}'"), theme::current().warn);
+                println!("{HELP}");
+                Ok(())
+            },
+        }
+    }
+
+    fn open_source(&mut self, source: Source) -> Result<(), Error> {
+        self.source = Some(source);
+        self.page = 0;
+        self.show_page()
+    }
+
+    /// Fetches and prints the current page, ranked across pages.
+    fn show_page(&mut self) -> Result<(), Error> {
+        let first = self.offset()? + 1;
+        let (stories, failures) = self.fetch_page()?;
+        if stories.is_empty() && failures.is_empty() {
+            // Comments: stay on the last page, so 'open N' keeps working.
+            self.page = self.page.saturating_sub(1);
+            println!("{}", "No more stories.".dimmed());
+            return Ok(());
+        }
+
+        let layout = self.args.layout().fit_scores(&stories);
+        let mut output = String::new();
+        for (offset, story) in stories.iter().enumerate() {
+            write_story(&mut output, first + offset, story, None, layout);
+        }
+        let _ = writeln!(
+            output,
+            "{}",
+            format!("Page {} · 'next' for more", self.page + 1).dimmed()
+        );
+        print!("{output}");
+        report_failures(&failures, false);
+        self.shown = stories;
+        self.prefetch_next();
+        Ok(())
+    }
+
+    /// Prints the discussion of story `id` as an indented tree.
+    /// Comments: the story is fetched again for its replies, which search
+    /// hits don't list, and for a current comment count.
+    fn show_comments(&self, id: u32) -> Result<(), Error> {
+        let story = self.client.item(id)?;
+        let (threads, failures) = comments::fetch_tree(self.args, self.client, &story, None);
+        print!("{}", comments::render(self.args, &story, &threads));
+        report_failures(&failures, false);
+        Ok(())
+    }
+
+    /// Starts fetching the page after the current one of a feed, unless
+    /// `--no-prefetch`.
+    fn prefetch_next(&mut self) {
+        let Ok(start) = self.offset() else {
+            return;
+        };
+        if let Some(prefetch) = &mut self.prefetch
+            && let Some(Source::Feed {
+                ids,
+            }) = &self.source
+        {
+            let next = ids.get(start + self.per_page..).unwrap_or_default();
+            prefetch.start(
+                self.args,
+                self.client,
+                &next[..self.per_page.min(next.len())],
+            );
+        }
+    }
+
+    fn fetch_page(&self) -> Result<Fetched, Error> {
+        let start = self.offset()?;
+        match &self.source {
+            Some(Source::Feed {
+                ids,
+            }) => {
+                let ids = ids.get(start..).unwrap_or_default();
+                let ids = &ids[..self.per_page.min(ids.len())];
+                let mut ready = self
+                    .prefetch
+                    .as_ref()
+                    .map(|prefetch| prefetch.take(ids))
+                    .unwrap_or_default();
+                let missing: Vec<u32> = ids
+                    .iter()
+                    .copied()
+                    .filter(|id| !ready.contains_key(id))
+                    .collect();
+                let failures = if missing.is_empty() {
+                    Vec::new()
+                } else {
+                    let (fetched, failures) = fetch::fetch_ids(self.args, self.client, &missing)?;
+                    ready.extend(fetched.into_iter().map(|story| (story.id, story)));
+                    failures
+                };
+                Ok((
+                    ids.iter().filter_map(|id| ready.remove(id)).collect(),
+                    failures,
+                ))
+            },
+            Some(Source::Search {
+                query,
+            }) => Ok((
+                self.client
+                    .algolia_search(query, self.page, self.per_page)?,
+                Vec::new(),
+            )),
+            None => Ok((Vec::new(), Vec::new())),
+        }
+    }
+
+    /// How many stories come before the current page.
+    /// Comments: checked, so a huge `--count` fails with an error instead of
+    /// overflowing; once the offset fits, so does every rank on the page.
+    fn offset(&self) -> Result<usize, Error> {
+        self.page
+            .checked_mul(self.per_page)
+            .filter(|offset| offset.checked_add(self.per_page).is_some())
+            .ok_or_else(|| {
+                Error::Usage(format!(
+                    "page {} of {} stories each is out of range; use a smaller count",
+                    self.page + 1,
+                    self.per_page
+                ))
+            })
+    }
+
+    /// The story on screen with the rank given in `rank`.
+    fn story(&self, rank: &str) -> Result<&Story, Error> {
+        let first = self.offset()? + 1;
+        let rank: usize = rank
+            .parse()
+            .map_err(|_| Error::Usage(format!("expected a story number, got '{rank}'")))?;
+        rank.checked_sub(first)
+            .and_then(|index| self.shown.get(index))
+            .ok_or_else(|| Error::Usage(format!("no story {rank} on this page")))
+    }
+}
+
+/// Parses the optional page size after `top` or `latest`.
+fn parse_count(count: &str) -> Result<Option<usize>, Error> {
+    if count.is_empty() {
+        return Ok(None);
+    }
+    match count.parse() {
+        Ok(0) | Err(_) => Err(Error::Usage(format!(
+            "expected a positive number of stories, got '{count}'"
+        ))),
+        Ok(count) => Ok(Some(count)),
+    }
+}
+
+/// Opens `url` in the default browser.
+pub fn browse(url: &str) -> Result<(), Error> {
+    open::that_detached(url).map_err(|e| Error::Interactive {
+        action: format!("can't open {url} in a browser"),
+        source: e.into(),
+    })?;
+    println!(
+        "{} {}",
+        "Opened".dimmed(),
+        paint(url, theme::current().url).underline()
+    );
+    Ok(())
+}
@@ -0,0 +1,61 @@
+//! `search` subcommand: stories matching a query, most relevant first,
+//! through Algolia's full-text search.
+
+use std::{collections::HashMap, num::NonZeroUsize, process::ExitCode};
+
+use colored::Colorize;
+use hn_cli::{Error, HnClient, Story};
+
+use crate::{
+    Args, Format, emit, header, page, render_badge, render_json, render_pretty, render_table,
+    render_yaml,
+    theme::{self, paint},
+};
+
+/// Prints the first `count` stories matching `words`, in any story format.
+pub fn run(
+    args: &Args,
+    client: &HnClient,
+    words: &[String],
+    count: NonZeroUsize,
+) -> Result<ExitCode, Error> {
+    let query = words.join(" ");
+    let stories = client.algolia_search(&query, 0, count.get())?;
+    if stories.is_empty() {
+        return Err(Error::NoResults);
+    }
+    let output = match args.format {
+        Format::Json => render_json(&stories, &[], false, None, None, args.link_fallback),
+        Format::Yaml => render_yaml(&stories, None, args.link_fallback),
+        Format::Html => page::render(&stories, &[], &HashMap::new(), args.now()),
+        Format::Badge => render_badge(&stories, args.badge_field.unwrap_or_default()),
+        Format::Pretty if args.quiet() => render_pretty(&stories, args.layout()),
+        Format::Table if args.quiet() => render_table(args, &stories),
+        Format::Pretty | Format::Table => format!(
+            "\n{}\n{}{}",
+            theme::current().header(&header(args)),
+            if args.format == Format::Table {
+                render_table(args, &stories)
+            } else {
+                render_pretty(&stories, args.layout())
+            },
+            found_line(&stories, &query)
+        ),
+    };
+    emit(args, &output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The closing line, e.g. `Found: 30 stories matching 'rust'`.
+fn found_line(stories: &[Story], query: &str) -> String {
+    let noun = if stories.len() == 1 {
+        "story"
+    } else {
+        "stories"
+    };
+    format!(
+        "{} {}\n",
+        paint("Found:", theme::current().good).bold(),
+        format!("{} {noun} matching '{query}'", stories.len()).dimmed()
+    )
+}
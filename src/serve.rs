@@ -0,0 +1,337 @@
+//! `serve`: a small local HTTP server answering the feeds as JSON, for
+//! dashboards and other pages.
+//!
+//! `GET /top.json`, `/new.json` and `/ask.json` answer what `--format json`
+//! prints for that feed, listing flags such as `--count` and `--filter`
+//! applied. Feeds are refreshed in the background every `--refresh` seconds
+//! and served from memory in between; until the first refresh lands, the
+//! copies cached by earlier runs are served.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{IpAddr, Ipv4Addr, TcpListener, TcpStream},
+    num::NonZeroU64,
+    process::ExitCode,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use hn_cli::{Error, HnClient, SortMode};
+use tracing::debug;
+
+use crate::{
+    Args, Format, eprint_line,
+    fetch::{INTERRUPT_POLL, Listing, fetch_listing},
+    interrupt, render_json,
+    theme::{self, paint},
+};
+
+/// The paths served and the feed behind each.
+const FEEDS: [(&str, SortMode); 3] = [
+    ("/top.json", SortMode::Hottest),
+    ("/new.json", SortMode::Latest),
+    ("/ask.json", SortMode::Ask),
+];
+
+/// How long a client may take to send its request, or to take in the
+/// answer.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest request or header line read, in bytes.
+const MAX_LINE: usize = 8 * 1024;
+
+/// Most header lines read with a request.
+const MAX_HEADERS: usize = 100;
+
+/// Connections answered at once; more are turned away with a 503.
+const MAX_CONNECTIONS: usize = 64;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct ServeArgs {
+    /// Port to listen on; 0 picks a free one
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+
+    /// Address to listen on; the default only accepts local connections
+    #[arg(long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    bind: IpAddr,
+
+    /// Seconds between refreshes of the feeds
+    #[arg(long, value_name = "SECS", default_value = "300")]
+    refresh: NonZeroU64,
+
+    /// Let pages from ORIGIN fetch the feeds, e.g. `http://localhost:3000`,
+    /// or '*' for any page (sets Access-Control-Allow-Origin)
+    #[arg(long, value_name = "ORIGIN", value_parser = parse_origin)]
+    cors_origin: Option<String>,
+}
+
+/// A `--cors-origin`: anything that fits in a header value.
+fn parse_origin(origin: &str) -> Result<String, String> {
+    if origin.is_empty() || origin.chars().any(char::is_control) {
+        return Err(format!(
+            "expected an origin such as 'http://localhost:3000', got '{origin}'"
+        ));
+    }
+    Ok(origin.to_string())
+}
+
+/// The JSON of each feed by path, as last fetched.
+type Feeds = Mutex<HashMap<&'static str, String>>;
+
+/// Serves the feeds until Ctrl-C, which stops accepting connections, drops
+/// the requests still being read, lets the answers in progress finish and
+/// exits successfully.
+pub fn run(args: &Args, client: &HnClient, serve: &ServeArgs) -> Result<ExitCode, Error> {
+    let listener = TcpListener::bind((serve.bind, serve.port))
+        .and_then(|listener| listener.set_nonblocking(true).map(|()| listener))
+        .map_err(|e| {
+            Error::Usage(format!(
+                "can't listen on {}:{}: {e}",
+                serve.bind, serve.port
+            ))
+        })?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| Error::Usage(e.to_string()))?;
+    interrupt::install();
+
+    let feeds = Feeds::default();
+    // Comments: the cache answers at once, where a first fetch of three
+    // feeds would keep the server unavailable for a while.
+    refresh(&feed_args(args, true), client, &feeds);
+    println!(
+        "{} http://{address}/top.json, /new.json and /ask.json, refreshed every {}s (Ctrl-C \
+         stops)",
+        paint("Serving", theme::current().good).bold(),
+        serve.refresh
+    );
+
+    let active = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let (feeds, active) = (&feeds, &active);
+        scope.spawn(|| refresh_loop(&feed_args(args, false), client, feeds, serve.refresh));
+        while !interrupt::requested() {
+            match listener.accept() {
+                // Comments: the stream is still nonblocking, so a client that
+                // doesn't read can't hold up the accept loop.
+                Ok((stream, _)) if active.load(Ordering::SeqCst) >= MAX_CONNECTIONS => {
+                    respond(
+                        &stream,
+                        "503 Service Unavailable",
+                        "Retry-After: 1\r\n",
+                        "{\"error\":\"too many connections\"}\n",
+                        false,
+                    );
+                },
+                Ok((stream, _)) => {
+                    active.fetch_add(1, Ordering::SeqCst);
+                    scope.spawn(move || {
+                        answer(&stream, feeds, serve.cors_origin.as_deref());
+                        active.fetch_sub(1, Ordering::SeqCst);
+                    });
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(INTERRUPT_POLL),
+                Err(e) => debug!(error = %e, "accept failed"),
+            }
+        }
+    });
+    println!("{}", "Stopped.".dimmed());
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The listing flags of the run, made to fetch quietly for the server, or
+/// from the cache when `offline`.
+fn feed_args(args: &Args, offline: bool) -> Args {
+    let mut args = args.clone();
+    args.format = Format::Json;
+    args.progress = false;
+    args.no_progress = true;
+    args.list.offline = offline || args.list.offline;
+    args
+}
+
+/// Fetches every feed again each `interval` until Ctrl-C.
+/// Comments: a feed that fails to refresh, e.g. while the API is down, keeps
+/// its previous copy until the next attempt.
+fn refresh_loop(args: &Args, client: &HnClient, feeds: &Feeds, interval: NonZeroU64) {
+    loop {
+        let wake = Instant::now() + Duration::from_secs(interval.get());
+        refresh(args, client, feeds);
+        while Instant::now() < wake {
+            if interrupt::requested() {
+                return;
+            }
+            thread::sleep(INTERRUPT_POLL);
+        }
+    }
+}
+
+/// Fetches each feed with `args` and stores its JSON in `feeds`.
+fn refresh(args: &Args, client: &HnClient, feeds: &Feeds) {
+    for (path, sort) in FEEDS {
+        if interrupt::requested() {
+            return;
+        }
+        let mut args = args.clone();
+        args.list.sort = vec![sort];
+        match fetch_listing(&args, client) {
+            Ok(Listing {
+                stories,
+                failures,
+                ..
+            }) if !interrupt::requested() => {
+                let json = render_json(&stories, &failures, false, None, None, args.link_fallback);
+                feeds
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(path, json);
+            },
+            Ok(_) => return,
+            Err(e) if args.list.offline => debug!(path, error = %e, "no cached copy"),
+            Err(e) => eprint_line(
+                &format!("warning: refreshing {path} failed ({e}); serving the last copy"),
+                theme::current().warn,
+            ),
+        }
+    }
+}
+
+/// Reads one line of a request, newline included, giving up at Ctrl-C or
+/// `deadline`.
+/// Comments: `Err(None)` is a client that went away or was too slow, which
+/// isn't answered; `Err(Some(status))` a line longer than [`MAX_LINE`].
+fn read_line(
+    reader: &mut impl BufRead,
+    deadline: Instant,
+    too_long: &'static str,
+) -> Result<Vec<u8>, Option<&'static str>> {
+    let mut line = Vec::new();
+    loop {
+        if interrupt::requested() || Instant::now() >= deadline {
+            return Err(None);
+        }
+        let limit = (MAX_LINE - line.len()) as u64;
+        match reader.take(limit).read_until(b'\n', &mut line) {
+            Ok(_) if line.ends_with(b"\n") => return Ok(line),
+            Ok(_) if line.len() == MAX_LINE => return Err(Some(too_long)),
+            Ok(_) => return Ok(line),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {},
+            Err(_) => return Err(None),
+        }
+    }
+}
+
+/// The request line of the request on `reader`, its headers read and
+/// skipped.
+/// Comments: the headers don't matter, but must be read before answering.
+fn read_request(reader: &mut impl BufRead) -> Result<String, Option<&'static str>> {
+    let deadline = Instant::now() + READ_TIMEOUT;
+    let request_line = read_line(reader, deadline, "400 Bad Request")?;
+    for _ in 0..=MAX_HEADERS {
+        let header = read_line(reader, deadline, "431 Request Header Fields Too Large")?;
+        if header.len() <= 2 {
+            return Ok(String::from_utf8_lossy(&request_line).into_owned());
+        }
+    }
+    Err(Some("431 Request Header Fields Too Large"))
+}
+
+/// Reads one request from `stream` and answers it.
+/// Comments: the stream is polled every [`INTERRUPT_POLL`] so that Ctrl-C
+/// doesn't wait on a slow client.
+fn answer(stream: &TcpStream, feeds: &Feeds, cors_origin: Option<&str>) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(INTERRUPT_POLL));
+    let _ = stream.set_write_timeout(Some(READ_TIMEOUT));
+    let mut headers = cors_origin.map_or_else(String::new, |origin| {
+        format!("Access-Control-Allow-Origin: {origin}\r\nVary: Origin\r\n")
+    });
+    let request_line = match read_request(&mut BufReader::new(stream)) {
+        Ok(request_line) => request_line,
+        Err(Some(status)) => {
+            debug!(status, "request too large");
+            respond(
+                stream,
+                status,
+                &headers,
+                "{\"error\":\"request too large\"}\n",
+                false,
+            );
+            return;
+        },
+        Err(None) => return,
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts
+        .next()
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default();
+    debug!(method, path, "request");
+    let (status, body) = match method {
+        "OPTIONS" => {
+            headers.push_str(
+                "Access-Control-Allow-Methods: GET, HEAD, OPTIONS\r\nAccess-Control-Allow-Headers: \
+                 *\r\n",
+            );
+            ("204 No Content", String::new())
+        },
+        "GET" | "HEAD" => match FEEDS.iter().find(|(served, _)| *served == path) {
+            Some((path, _)) => feeds
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(path)
+                .map_or_else(
+                    || {
+                        headers.push_str("Retry-After: 5\r\n");
+                        (
+                            "503 Service Unavailable",
+                            "{\"error\":\"not fetched yet\"}\n".to_string(),
+                        )
+                    },
+                    |json| ("200 OK", json.clone()),
+                ),
+            None => (
+                "404 Not Found",
+                "{\"error\":\"not found; try /top.json, /new.json or /ask.json\"}\n".to_string(),
+            ),
+        },
+        _ => {
+            headers.push_str("Allow: GET, HEAD, OPTIONS\r\n");
+            (
+                "405 Method Not Allowed",
+                "{\"error\":\"method not allowed\"}\n".to_string(),
+            )
+        },
+    };
+
+    respond(stream, status, &headers, &body, method == "HEAD");
+}
+
+/// Writes a `status` response with `headers` (each ended by `\r\n`) and
+/// `body`, left out for a `head` request, then closes the connection.
+fn respond(stream: &TcpStream, status: &str, headers: &str, body: &str, head: bool) {
+    let len = body.len();
+    let body = if head { "" } else { body };
+    let mut stream = stream;
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {len}\r\nCache-Control: \
+         no-cache\r\n{headers}Connection: close\r\n\r\n{body}"
+    );
+    let _ = stream.flush();
+}
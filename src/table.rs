@@ -0,0 +1,154 @@
+//! `--format table`: one aligned row per story, in the columns `--fields`
+//! picks.
+
+use clap::ValueEnum;
+use console::{Alignment, measure_text_width, pad_str, truncate_str};
+use hn_cli::Story;
+
+use crate::{age_text, count, theme};
+
+/// Narrowest the title column is cut to, however narrow the terminal.
+const MIN_TITLE_WIDTH: usize = 10;
+
+/// Space between two columns.
+const GAP: &str = "  ";
+
+/// A column of the table.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Field {
+    /// Position in the listing, from 1.
+    Rank,
+    /// Current score.
+    Score,
+    /// Comment count; empty when the API reported none.
+    Comments,
+    /// How long ago the story was submitted.
+    Age,
+    /// The title, cut to fit the terminal.
+    Title,
+    /// The link's host; empty for text posts.
+    Domain,
+}
+
+impl Field {
+    /// Every column, in the default order.
+    pub const ALL: [Self; 6] = [
+        Self::Rank,
+        Self::Score,
+        Self::Comments,
+        Self::Age,
+        Self::Title,
+        Self::Domain,
+    ];
+
+    const fn heading(self) -> &'static str {
+        match self {
+            Self::Rank => "Rank",
+            Self::Score => "Score",
+            Self::Comments => "Comments",
+            Self::Age => "Age",
+            Self::Title => "Title",
+            Self::Domain => "Domain",
+        }
+    }
+
+    /// Counts line up on the right, text on the left.
+    const fn alignment(self) -> Alignment {
+        match self {
+            Self::Rank | Self::Score | Self::Comments => Alignment::Right,
+            Self::Age | Self::Title | Self::Domain => Alignment::Left,
+        }
+    }
+
+    fn cell(self, rank: usize, story: &Story, now: Option<i64>) -> String {
+        match self {
+            Self::Rank => rank.to_string(),
+            Self::Score => count(story.score),
+            Self::Comments => story.descendants.map_or_else(String::new, count),
+            Self::Age => story
+                .time
+                .map_or_else(String::new, |time| age_text(time, now)),
+            Self::Title => story.title.clone(),
+            Self::Domain => story.host().unwrap_or_default(),
+        }
+    }
+}
+
+/// Renders `stories`, ranked from 1, under a header row in the theme's
+/// header style. Ages are measured from `now` as in the pretty layout, and
+/// titles cut with an ellipsis when the row would overflow `width` columns.
+/// Comments: widths are display widths, so wide (e.g. CJK) characters keep
+/// the columns aligned.
+pub fn render(
+    stories: &[Story],
+    fields: &[Field],
+    width: Option<usize>,
+    now: Option<i64>,
+) -> String {
+    let fields = if fields.is_empty() {
+        &Field::ALL
+    } else {
+        fields
+    };
+    let headings: Vec<String> = fields
+        .iter()
+        .map(|field| field.heading().to_string())
+        .collect();
+    let rows: Vec<Vec<String>> = stories
+        .iter()
+        .enumerate()
+        .map(|(i, story)| {
+            fields
+                .iter()
+                .map(|field| field.cell(i + 1, story, now))
+                .collect()
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = (0..fields.len())
+        .map(|column| {
+            rows.iter()
+                .chain([&headings])
+                .map(|row| measure_text_width(&row[column]))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    if let (Some(width), Some(title)) = (
+        width,
+        fields.iter().position(|&field| field == Field::Title),
+    ) {
+        let others = widths.iter().sum::<usize>() - widths[title] + GAP.len() * (fields.len() - 1);
+        widths[title] = widths[title].min(width.saturating_sub(others).max(MIN_TITLE_WIDTH));
+    }
+
+    let mut output = format!(
+        "{}\n",
+        theme::current().header(&line(&headings, fields, &widths))
+    );
+    for row in &rows {
+        output.push_str(&line(row, fields, &widths));
+        output.push('\n');
+    }
+    output
+}
+
+/// One row, each cell cut and padded to its column's width.
+fn line(cells: &[String], fields: &[Field], widths: &[usize]) -> String {
+    let line = cells
+        .iter()
+        .zip(fields)
+        .zip(widths)
+        .map(|((cell, field), &width)| {
+            pad_str(
+                &truncate_str(cell, width, "…"),
+                width,
+                field.alignment(),
+                None,
+            )
+            .into_owned()
+        })
+        .collect::<Vec<_>>()
+        .join(GAP);
+    line.trim_end().to_string()
+}
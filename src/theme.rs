@@ -0,0 +1,328 @@
+//! Colors that carry meaning in every view: the header bar, titles, scores
+//! and the rest, picked by a preset (`--theme`) and the `[theme]` table of
+//! the config file, then fixed for the run.
+//!
+//! Styles go through `colored`, so `--deterministic` and `NO_COLOR` turn
+//! them off along with the rest of the output.
+
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use colored::{Color, ColoredString, Colorize};
+use serde::Deserialize;
+
+/// The theme of this run; set once at startup.
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Built-in themes, as `--theme` or `preset` in the `[theme]` table.
+#[derive(Copy, Clone, PartialEq, Eq, Default, ValueEnum, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    /// For dark terminal backgrounds.
+    #[default]
+    Dark,
+    /// For light terminal backgrounds.
+    Light,
+    /// No colors, only bold, dimmed and underlined text.
+    Mono,
+    /// For dark backgrounds, telling good from bad and warm from hot by
+    /// blue against orange rather than green against red.
+    #[value(alias = "cb")]
+    #[serde(alias = "cb")]
+    Colorblind,
+}
+
+impl Preset {
+    const fn theme(self) -> Theme {
+        match self {
+            Self::Dark => Theme::DARK,
+            Self::Light => Theme::LIGHT,
+            Self::Mono => Theme::MONO,
+            Self::Colorblind => Theme::COLORBLIND,
+        }
+    }
+}
+
+/// One color per role; `None` leaves that text in the terminal's own color.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    /// Background of the header bars.
+    pub header: Option<Color>,
+    /// Story titles and other names, e.g. a user's.
+    pub title: Option<Color>,
+    /// Warm score badges, and the background of hot titles.
+    pub score: Option<Color>,
+    /// Hot score badges.
+    pub hot: Option<Color>,
+    /// Authors and other bylines.
+    pub author: Option<Color>,
+    /// Links and sites.
+    pub url: Option<Color>,
+    /// Rank numbers, always dimmed.
+    pub rank: Option<Color>,
+    /// Section headings and the `NEW` badge.
+    pub badge: Option<Color>,
+    /// Successes, e.g. a `✓` or a score gain.
+    pub good: Option<Color>,
+    /// Failures, e.g. a `✗`, an error or a score loss.
+    pub bad: Option<Color>,
+    /// Warnings, e.g. stories that failed to fetch.
+    pub warn: Option<Color>,
+    /// Where score badges turn warm and hot.
+    pub score_colors: Thresholds,
+}
+
+impl Theme {
+    const DARK: Self = Self {
+        header: Some(Color::Cyan),
+        title: Some(Color::White),
+        score: Some(Color::Yellow),
+        hot: Some(Color::BrightRed),
+        author: Some(Color::BrightBlack),
+        url: Some(Color::Cyan),
+        rank: None,
+        badge: Some(Color::Cyan),
+        good: Some(Color::Green),
+        bad: Some(Color::Red),
+        warn: Some(Color::Yellow),
+        score_colors: Thresholds::DEFAULT,
+    };
+    /// Comments: darker shades of the dark theme's colors, since yellow,
+    /// white and bright black all but vanish on white.
+    const LIGHT: Self = Self {
+        header: Some(Color::AnsiColor(117)),
+        title: Some(Color::Black),
+        score: Some(Color::AnsiColor(130)),
+        hot: Some(Color::Red),
+        author: Some(Color::AnsiColor(240)),
+        url: Some(Color::Blue),
+        rank: None,
+        badge: Some(Color::Blue),
+        good: Some(Color::AnsiColor(28)),
+        bad: Some(Color::Red),
+        warn: Some(Color::AnsiColor(130)),
+        score_colors: Thresholds::DEFAULT,
+    };
+    /// Comments: 256-color approximations of the Okabe-Ito palette, whose
+    /// blue, orange and yellow stay apart under deuteranopia and protanopia.
+    const COLORBLIND: Self = Self {
+        header: Some(Color::AnsiColor(39)),
+        title: Some(Color::White),
+        score: Some(Color::AnsiColor(39)),
+        hot: Some(Color::AnsiColor(208)),
+        author: Some(Color::BrightBlack),
+        url: Some(Color::AnsiColor(74)),
+        rank: None,
+        badge: Some(Color::AnsiColor(39)),
+        good: Some(Color::AnsiColor(39)),
+        bad: Some(Color::AnsiColor(208)),
+        warn: Some(Color::AnsiColor(227)),
+        score_colors: Thresholds::DEFAULT,
+    };
+    const MONO: Self = Self {
+        header: None,
+        title: None,
+        score: None,
+        hot: None,
+        author: None,
+        url: None,
+        rank: None,
+        badge: None,
+        good: None,
+        bad: None,
+        warn: None,
+        score_colors: Thresholds::DEFAULT,
+    };
+
+    /// `text` as a header bar, e.g. ` 🧡 Hacker News CLI `.
+    pub fn header(&self, text: &str) -> ColoredString {
+        self.header.map_or_else(
+            || text.reversed().bold(),
+            |color| text.on_color(color).black().bold(),
+        )
+    }
+
+    /// `text` as a title that reached `--hot-at`.
+    pub fn hot_title(&self, text: &str) -> ColoredString {
+        self.score.map_or_else(
+            || text.bold().underline(),
+            |color| text.on_color(color).black().bold(),
+        )
+    }
+
+    /// `text`, a badge showing `count`, colored by the score thresholds:
+    /// dimmed while cold, in the score color once warm and in the hot color
+    /// once hot.
+    /// Comments: meant for every count badge, so a score and a comment count
+    /// of the same heat look alike.
+    pub fn score(&self, text: &str, count: i32) -> ColoredString {
+        if count >= self.score_colors.hot {
+            paint(text, self.hot).bold()
+        } else if count >= self.score_colors.warm {
+            paint(text, self.score).bold()
+        } else {
+            text.dimmed()
+        }
+    }
+
+    /// `text`, a story's age of `age` seconds: bold in the good color for
+    /// the first hour, then plain good color, the terminal's own color after
+    /// six hours and dimmed after a day.
+    pub fn age(&self, text: &str, age: i64) -> ColoredString {
+        match age {
+            ..3600 => paint(text, self.good).bold(),
+            3600..21_600 => paint(text, self.good),
+            21_600..86_400 => text.normal(),
+            _ => text.dimmed(),
+        }
+    }
+}
+
+/// `text` in `color`, or as it is without one.
+pub fn paint(text: &str, color: Option<Color>) -> ColoredString {
+    color.map_or_else(|| text.normal(), |color| text.color(color))
+}
+
+/// `color` as an indicatif template style, e.g. `green.bright` or `208`.
+pub fn style_name(color: Color) -> String {
+    let (name, bright) = match color {
+        Color::Black => ("black", false),
+        Color::Red => ("red", false),
+        Color::Green => ("green", false),
+        Color::Yellow => ("yellow", false),
+        Color::Blue => ("blue", false),
+        Color::Magenta => ("magenta", false),
+        Color::Cyan => ("cyan", false),
+        Color::White => ("white", false),
+        Color::BrightBlack => ("black", true),
+        Color::BrightRed => ("red", true),
+        Color::BrightGreen => ("green", true),
+        Color::BrightYellow => ("yellow", true),
+        Color::BrightBlue => ("blue", true),
+        Color::BrightMagenta => ("magenta", true),
+        Color::BrightCyan => ("cyan", true),
+        Color::BrightWhite => ("white", true),
+        Color::AnsiColor(index) => return index.to_string(),
+        Color::TrueColor {
+            r,
+            g,
+            b,
+        } => return format!("#{r:02x}{g:02x}{b:02x}"),
+    };
+    if bright {
+        format!("{name}.bright")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Uses `theme` for the rest of the run.
+pub fn set(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+/// The theme of this run; the dark preset until [`set`] is called.
+pub fn current() -> &'static Theme {
+    THEME.get().unwrap_or(&Theme::DARK)
+}
+
+/// Where a count badge changes color, as the `[score_colors]` table of the
+/// config file.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    /// From here on the badge is warm; below it, dimmed.
+    pub warm: i32,
+    /// From here on the badge is hot.
+    pub hot: i32,
+}
+
+impl Thresholds {
+    const DEFAULT: Self = Self {
+        warm: 50,
+        hot: 200,
+    };
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The `[theme]` table of the config file: a preset, and colors replacing
+/// some of its roles.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Used when `--theme` isn't given.
+    pub preset: Option<Preset>,
+    header: Option<ColorSpec>,
+    title: Option<ColorSpec>,
+    score: Option<ColorSpec>,
+    hot: Option<ColorSpec>,
+    author: Option<ColorSpec>,
+    url: Option<ColorSpec>,
+    rank: Option<ColorSpec>,
+    badge: Option<ColorSpec>,
+    good: Option<ColorSpec>,
+    bad: Option<ColorSpec>,
+    warn: Option<ColorSpec>,
+}
+
+/// A color as written in the config file: a name such as `"bright blue"`,
+/// `"#rrggbb"`, `"none"` or a 256-color index.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum ColorSpec {
+    Name(String),
+    Index(i64),
+}
+
+impl ColorSpec {
+    fn color(&self) -> Result<Option<Color>, String> {
+        match self {
+            Self::Name(name) if name.eq_ignore_ascii_case("none") => Ok(None),
+            Self::Name(name) => name
+                .replace(['_', '-'], " ")
+                .parse()
+                .map(Some)
+                .map_err(|()| {
+                    format!(
+                        "unknown color '{name}'; expected a name like 'bright blue', \
+                         '#rrggbb', 'none' or a 256-color index"
+                    )
+                }),
+            Self::Index(index) => u8::try_from(*index)
+                .map(|index| Some(Color::AnsiColor(index)))
+                .map_err(|_| format!("color index {index} is out of range 0-255")),
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// The theme of `preset` with this table's colors and `score_colors`
+    /// applied, or what's wrong with a color, naming its key.
+    pub fn resolve(&self, preset: Preset, score_colors: Thresholds) -> Result<Theme, String> {
+        let mut theme = preset.theme();
+        theme.score_colors = score_colors;
+        for (key, spec, role) in [
+            ("header", &self.header, &mut theme.header),
+            ("title", &self.title, &mut theme.title),
+            ("score", &self.score, &mut theme.score),
+            ("hot", &self.hot, &mut theme.hot),
+            ("author", &self.author, &mut theme.author),
+            ("url", &self.url, &mut theme.url),
+            ("rank", &self.rank, &mut theme.rank),
+            ("badge", &self.badge, &mut theme.badge),
+            ("good", &self.good, &mut theme.good),
+            ("bad", &self.bad, &mut theme.bad),
+            ("warn", &self.warn, &mut theme.warn),
+        ] {
+            if let Some(spec) = spec {
+                *role = spec.color().map_err(|e| format!("theme.{key}: {e}"))?;
+            }
+        }
+        Ok(theme)
+    }
+}
@@ -0,0 +1,138 @@
+//! `--timing`: wall-clock durations of a listing run.
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Durations collected while a listing runs.
+#[derive(Debug)]
+pub struct Timing {
+    started: Instant,
+    list: Duration,
+    items: Vec<Duration>,
+    cache_hits: usize,
+    retries: usize,
+    throttled: usize,
+    throttle_wait: Duration,
+}
+
+/// The `"timing"` object of `--format json`; durations are in milliseconds.
+#[derive(Serialize, Debug)]
+pub struct Summary {
+    total_ms: f64,
+    list_ms: f64,
+    items: Option<Latency>,
+    cache_hits: usize,
+    retries: usize,
+    /// Requests the `--rate-limit` held back.
+    throttled: usize,
+    throttle_wait_ms: f64,
+}
+
+/// Latency distribution of the item requests.
+#[derive(Serialize, Debug)]
+pub struct Latency {
+    requests: usize,
+    min_ms: f64,
+    median_ms: f64,
+    p95_ms: f64,
+    max_ms: f64,
+}
+
+impl Timing {
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            list: Duration::ZERO,
+            items: Vec::new(),
+            cache_hits: 0,
+            retries: 0,
+            throttled: 0,
+            throttle_wait: Duration::ZERO,
+        }
+    }
+
+    pub const fn list(&mut self, elapsed: Duration) {
+        self.list = elapsed;
+    }
+
+    pub fn item(&mut self, elapsed: Duration) {
+        self.items.push(elapsed);
+    }
+
+    pub const fn retries(&mut self, count: usize) {
+        self.retries += count;
+    }
+
+    /// Records `count` requests that the rate limit delayed by `wait` in
+    /// total.
+    pub fn throttled(&mut self, count: usize, wait: Duration) {
+        self.throttled += count;
+        self.throttle_wait += wait;
+    }
+
+    pub const fn cache_hit(&mut self) {
+        self.cache_hits += 1;
+    }
+
+    /// Freezes the totals and computes the latency percentiles.
+    /// Comments: percentiles use the nearest-rank method, so each one is a
+    /// latency that was actually observed.
+    pub fn summary(&self) -> Summary {
+        let mut items = self.items.clone();
+        items.sort_unstable();
+        let rank = |percent: usize| items[(items.len() * percent).div_ceil(100).max(1) - 1];
+
+        Summary {
+            total_ms: millis(self.started.elapsed()),
+            list_ms: millis(self.list),
+            items: (!items.is_empty()).then(|| Latency {
+                requests: items.len(),
+                min_ms: millis(items[0]),
+                median_ms: millis(rank(50)),
+                p95_ms: millis(rank(95)),
+                max_ms: millis(items[items.len() - 1]),
+            }),
+            cache_hits: self.cache_hits,
+            retries: self.retries,
+            throttled: self.throttled,
+            throttle_wait_ms: millis(self.throttle_wait),
+        }
+    }
+}
+
+impl Summary {
+    /// The human-readable summary printed under `--format pretty`.
+    pub fn render(&self) -> String {
+        let items = self.items.as_ref().map_or_else(
+            || "no item requests".to_string(),
+            |latency| {
+                format!(
+                    "{} item requests: min {:.0} ms, median {:.0} ms, p95 {:.0} ms, max {:.0} ms",
+                    latency.requests,
+                    latency.min_ms,
+                    latency.median_ms,
+                    latency.p95_ms,
+                    latency.max_ms
+                )
+            },
+        );
+        let throttled = if self.throttled > 0 {
+            format!(
+                "\n  {} requests throttled by --rate-limit, {:.0} ms waiting",
+                self.throttled, self.throttle_wait_ms
+            )
+        } else {
+            String::new()
+        };
+        format!(
+            "Timing: {:.0} ms total, {:.0} ms for the id list\n  {items}\n  {} cache hits, {} \
+             retries{throttled}",
+            self.total_ms, self.list_ms, self.cache_hits, self.retries
+        )
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,192 @@
+//! The seam between the client and the network, so requests can be logged
+//! in one place and replaced in tests.
+
+use std::{fmt, time::Instant};
+
+use tracing::{debug, trace};
+use ureq::{
+    Agent, Body, SendBody,
+    http::{self, HeaderName, HeaderValue},
+    middleware::{Middleware, MiddlewareNext},
+    tls::{RootCerts, TlsConfig, TlsProvider},
+};
+
+use crate::error::Error;
+
+/// Fetches a URL and hands back the parts of the response the client uses.
+///
+/// [`HnClient`](crate::HnClient) talks to the API only through this trait;
+/// swap in `mock::MockTransport` (with the `mock` feature) to run without a
+/// network.
+/// Comments: HTTP error statuses are responses, not errors, so the client can
+/// look at their headers (e.g. `Retry-After`) before deciding what to do.
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// GETs `url`.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Network`] when no response arrived: DNS, connection, TLS or
+    /// timeout failures.
+    fn get(&self, url: &str) -> Result<Response, Error>;
+}
+
+/// Status, headers and body of an HTTP response.
+#[derive(Clone, Debug, Default)]
+pub struct Response {
+    /// HTTP status code.
+    pub status: u16,
+    /// Header names and values, in the order received.
+    pub headers: Vec<(String, String)>,
+    /// The raw body; JSON for successful API responses.
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// The first value of header `name`, compared case-insensitively.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the status is 2xx.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+}
+
+/// Largest response body [`UreqTransport`] reads unless
+/// [`UreqTransport::max_body_bytes`] says otherwise: 8 MiB, far above any
+/// real API response.
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Name of the TLS backend this build was compiled with, picked by the
+/// `tls-rustls` or `tls-native` feature.
+#[cfg(feature = "tls-rustls")]
+pub const TLS_BACKEND: &str = "rustls";
+/// Name of the TLS backend this build was compiled with, picked by the
+/// `tls-rustls` or `tls-native` feature.
+#[cfg(all(feature = "tls-native", not(feature = "tls-rustls")))]
+pub const TLS_BACKEND: &str = "native-tls";
+
+/// TLS settings for the backend this build was compiled with.
+/// Comments: rustls checks against the bundled webpki roots; native-tls
+/// uses the platform's certificate store.
+pub fn tls_config() -> TlsConfig {
+    let builder = TlsConfig::builder();
+    #[cfg(all(feature = "tls-native", not(feature = "tls-rustls")))]
+    let builder = builder
+        .provider(TlsProvider::NativeTls)
+        .root_certs(RootCerts::PlatformVerifier);
+    #[cfg(feature = "tls-rustls")]
+    let builder = builder
+        .provider(TlsProvider::Rustls)
+        .root_certs(RootCerts::WebPki);
+    builder.build()
+}
+
+/// Agent middleware adding the headers from
+/// [`HnClientBuilder::header`](crate::HnClientBuilder::header) to every
+/// request.
+#[derive(Debug)]
+pub struct ExtraHeaders(pub Vec<(HeaderName, HeaderValue)>);
+
+impl Middleware for ExtraHeaders {
+    fn handle(
+        &self,
+        mut request: http::Request<SendBody>,
+        next: MiddlewareNext,
+    ) -> Result<http::Response<Body>, ureq::Error> {
+        for (name, value) in &self.0 {
+            request.headers_mut().append(name, value.clone());
+        }
+        next.handle(request)
+    }
+}
+
+/// The real transport: a ureq agent.
+#[derive(Debug)]
+pub struct UreqTransport {
+    agent: Agent,
+    max_body_bytes: u64,
+}
+
+impl UreqTransport {
+    /// Wraps a configured agent.
+    ///
+    /// Comments: agents that keep ureq's default of turning error statuses
+    /// into errors still work, but their error responses arrive without
+    /// headers.
+    #[must_use]
+    pub const fn new(agent: Agent) -> Self {
+        Self {
+            agent,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+
+    /// Stops reading a body after `limit` bytes and fails the request with
+    /// [`Error::BodyTooLarge`], so a broken or hostile server can't exhaust
+    /// memory. Defaults to [`DEFAULT_MAX_BODY_BYTES`].
+    #[must_use]
+    pub const fn max_body_bytes(mut self, limit: u64) -> Self {
+        self.max_body_bytes = limit;
+        self
+    }
+}
+
+impl Transport for UreqTransport {
+    /// Logs the URL, status and timing at debug level and the response
+    /// headers at trace level.
+    fn get(&self, url: &str) -> Result<Response, Error> {
+        let start = Instant::now();
+        let result = self.agent.get(url).call();
+        let elapsed = start.elapsed().as_millis();
+
+        let mut response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::StatusCode(status)) => {
+                debug!(url, status, elapsed_ms = elapsed, "GET");
+                return Ok(Response {
+                    status,
+                    ..Response::default()
+                });
+            },
+            Err(e) => {
+                debug!(url, elapsed_ms = elapsed, error = %e, "GET failed");
+                return Err(e.into());
+            },
+        };
+
+        let status = response.status().as_u16();
+        debug!(url, status, elapsed_ms = elapsed, "GET");
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().unwrap_or("<binary>");
+                trace!(url, "{name}: {value}");
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+        // Comments: error bodies are never decoded, so don't wait for them.
+        let body = if (200..300).contains(&status) {
+            response
+                .body_mut()
+                .with_config()
+                .limit(self.max_body_bytes)
+                .read_to_vec()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
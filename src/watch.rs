@@ -0,0 +1,115 @@
+//! `--watch`: redraw the listing on an interval until Ctrl-C.
+
+use std::{
+    collections::HashMap,
+    io::{self, IsTerminal},
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use colored::{ColoredString, Colorize};
+use console::Term;
+use hn_cli::{Error, HnClient, Story};
+
+use crate::{
+    Args, STORY_CAPACITY,
+    fetch::{INTERRUPT_POLL, Listing, fetch_listing},
+    header, interrupt,
+    prefetch::Prefetch,
+    print_stdout, report, report_failures, score_delta,
+    theme::{self, paint},
+    write_story,
+};
+
+/// Polls the feed every `interval` and redraws it in place.
+/// Comments: a failed refresh (e.g. the network dropping out) is reported and
+/// retried on the next poll instead of ending the watch; only Ctrl-C does.
+pub fn run(args: &Args, client: &HnClient, interval: Duration) -> Result<ExitCode, Error> {
+    interrupt::install();
+    // Scores from the previous refresh; `None` until the first one succeeds.
+    let mut previous: Option<HashMap<u32, i32>> = None;
+    let mut prefetch = Prefetch::default();
+
+    loop {
+        match fetch_listing(args, client) {
+            Ok(listing) => {
+                if args.prefetch(true) {
+                    prefetch.start(args, client, &listing.next_page);
+                }
+                redraw(args, &listing, previous.as_ref(), interval);
+                previous = Some(
+                    listing
+                        .stories
+                        .iter()
+                        .map(|story| (story.id, story.score))
+                        .collect(),
+                );
+            },
+            Err(e) if transient(&e) => report(&e),
+            Err(e) => return Err(e),
+        }
+
+        let wake = Instant::now() + interval;
+        while Instant::now() < wake {
+            if interrupt::requested() {
+                return Err(Error::Interrupted);
+            }
+            thread::sleep(INTERRUPT_POLL);
+        }
+    }
+}
+
+/// Clears the terminal and prints one refresh of the listing.
+fn redraw(
+    args: &Args,
+    listing: &Listing,
+    previous: Option<&HashMap<u32, i32>>,
+    interval: Duration,
+) {
+    if io::stdout().is_terminal() {
+        // Nothing useful to do if the terminal refuses to clear; the new
+        // listing is printed below the old one instead.
+        drop(Term::stdout().clear_screen());
+    }
+
+    if !args.quiet() {
+        println!("\n{}", theme::current().header(&header(args)));
+    }
+    let layout = args.layout().fit_scores(&listing.stories);
+    let mut output = String::with_capacity(listing.stories.len() * STORY_CAPACITY);
+    for (i, story) in listing.stories.iter().enumerate() {
+        let badge = previous
+            .filter(|_| args.list.score_delta)
+            .map(|previous| badge(story, previous));
+        write_story(&mut output, i + 1, story, badge, layout);
+    }
+    print_stdout(&output);
+
+    report_failures(&listing.failures, args.list.offline);
+    if !args.quiet() {
+        println!(
+            "{} {}",
+            format!("Updated {}.", args.format_time(Utc::now(), "%H:%M:%S")).dimmed(),
+            format!("Refreshing every {}s, Ctrl-C to stop.", interval.as_secs()).dimmed()
+        );
+    }
+}
+
+/// `NEW` for a story that wasn't listed last time, otherwise its score
+/// change since then.
+fn badge(story: &Story, previous: &HashMap<u32, i32>) -> ColoredString {
+    previous.get(&story.id).map_or_else(
+        || paint("NEW", theme::current().badge).bold(),
+        |&score| score_delta(story.score - score),
+    )
+}
+
+/// Errors worth retrying on the next poll rather than ending the watch.
+const fn transient(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Network(_) | Error::Decode(_) | Error::Item { .. }
+    )
+}
@@ -1,85 +1,479 @@
-use std::error::Error;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
 
-use clap::{Parser, ValueEnum};
-use serde::Deserialize;
+use flate2::{Compression, write::GzEncoder};
+use hn_cli::{
+    Backoff, Error, HnClient, ItemType, NumberStyle, SortMode, Story, TitlePrefix,
+    mock::MockTransport, normalize_url,
+};
+use proptest::prelude::*;
+use serde_json::{Value, json};
 
-// --- Data Models ---
+/// A client answering from `mock`, retrying without delay.
+fn mock_client(mock: &MockTransport) -> HnClient {
+    HnClient::builder()
+        .transport(mock.clone())
+        .retries(2)
+        .backoff(Backoff::seeded(Duration::ZERO, Duration::ZERO, 0))
+        .build()
+}
 
-#[derive(Parser, Debug)]
-#[command(author, version, about = "HN CLI fetcher using ureq 3.x")]
-pub struct Args {
-    /// Sort mode: 'latest' for new stories, 'hottest' for top stories
-    #[arg(short, long, value_enum, default_value_t = SortMode::Hottest)]
-    pub sort: SortMode,
+/// One canned answer of a [`FixtureServer`] route.
+#[derive(Clone, Debug)]
+enum Reply {
+    Json(Value),
+    /// A 200 whose body isn't valid JSON.
+    Malformed,
+    Status(u16),
+    /// An error status with a `Retry-After` header.
+    RetryAfter(u16, &'static str),
+    /// Sleeps this long before answering, to trip the client timeout.
+    Stall(Duration),
+    /// Answers `body` after a delay, to reorder completions.
+    Slow(Duration, Value),
+    /// Answers `body` gzipped when the request accepts gzip, plain otherwise.
+    Gzip(Value),
+    /// A 301 to this location.
+    Redirect(&'static str),
+    /// A 200 with this content type and raw body, e.g. an image.
+    Bytes(&'static str, &'static [u8]),
+}
 
-    /// Number of results to return
-    #[arg(short, long, default_value_t = 30)]
-    pub top: usize,
+/// What the server saw of one request.
+#[derive(Clone, Debug)]
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
-pub enum SortMode {
-    Latest,
-    Hottest,
+impl Request {
+    /// The first value of header `name`, compared case-insensitively.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-pub struct Story {
-    pub title: String,
-    pub url: Option<String>,
-    pub score: i32,
-    pub by: String,
+type Routes = HashMap<String, VecDeque<Reply>>;
+
+/// A local HTTP server answering `GET /v0/<path>` from fixtures, for running
+/// the binary end to end without the real API.
+/// Comments: answers per path are given in turn and the last one repeats, as
+/// with `MockTransport`; unrouted paths answer 404.
+struct FixtureServer {
+    url: String,
+    routes: Arc<Mutex<Routes>>,
+    /// Every request, in arrival order.
+    requests: Arc<Mutex<Vec<Request>>>,
 }
 
-// --- Logic ---
+impl FixtureServer {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind a local port");
+        let url = format!("http://{}/v0", listener.local_addr().unwrap());
+        let server = Self {
+            url,
+            routes: Arc::default(),
+            requests: Arc::default(),
+        };
+
+        let routes = Arc::clone(&server.routes);
+        let requests = Arc::clone(&server.requests);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let routes = Arc::clone(&routes);
+                let requests = Arc::clone(&requests);
+                thread::spawn(move || serve(stream, &routes, &requests));
+            }
+        });
+        server
+    }
+
+    fn route(self, path: &str, reply: Reply) -> Self {
+        self.routes
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_default()
+            .push_back(reply);
+        self
+    }
+
+    /// Queues `replies` ahead of those already routed for `path`.
+    fn before(self, path: &str, replies: impl IntoIterator<Item = Reply>) -> Self {
+        let mut routes = self.routes.lock().unwrap();
+        let queue = routes.entry(path.to_string()).or_default();
+        *queue = replies.into_iter().chain(std::mem::take(queue)).collect();
+        drop(routes);
+        self
+    }
+
+    /// Answers `path` with `reply` from now on, dropping earlier routes.
+    fn replace(self, path: &str, reply: Reply) -> Self {
+        self.routes.lock().unwrap().remove(path);
+        self.route(path, reply)
+    }
+
+    fn json(self, path: &str, body: Value) -> Self {
+        self.route(path, Reply::Json(body))
+    }
+
+    fn requests(&self, path: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.path == path)
+            .count()
+    }
+
+    /// Method and header `name` of every request so far for `path`.
+    fn requests_to(&self, path: &str, name: &str) -> Vec<(String, Option<String>)> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.path == path)
+            .map(|request| {
+                (
+                    request.method.clone(),
+                    request.header(name).map(str::to_string),
+                )
+            })
+            .collect()
+    }
+
+    /// Header `name` of every request so far.
+    fn header_values(&self, name: &str) -> Vec<Option<String>> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.header(name).map(str::to_string))
+            .collect()
+    }
+
+    /// Runs the binary against this server in `--deterministic` mode, with
+    /// retries that don't wait and a throwaway cache directory.
+    fn run(&self, args: &[&str]) -> Run {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let home = std::env::temp_dir().join(format!(
+            "hn-cli-test-{}-{}",
+            std::process::id(),
+            RUNS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut run = self.run_in(&home, args);
+        run.cache = Some(home);
+        run
+    }
 
-/// Fetches individual story details from the HN Firebase API.
-/// Uses ureq 3.x response handling.
-fn get_story_details(id: u32) -> Result<Story, Box<dyn Error>> {
-    let url = format!("https://hacker-news.firebaseio.com/v0/item/{}.json", id);
-    let mut response = ureq::get(&url).call()?;
-    let story: Story = response.body_mut().read_json()?;
-    Ok(story)
+    /// Like [`Self::run`], but keeps cache, config and state in `home`, which
+    /// outlives the run, so runs can see what earlier ones saved.
+    fn run_in(&self, home: &Path, args: &[&str]) -> Run {
+        let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args([
+                "--base-url",
+                &self.url,
+                "--retry-base-ms",
+                "0",
+                "--no-pager",
+                "--deterministic",
+            ])
+            .args(args)
+            .env("XDG_CACHE_HOME", home)
+            .env("XDG_CONFIG_HOME", home)
+            .env("XDG_DATA_HOME", home)
+            .env("XDG_STATE_HOME", home)
+            .output()
+            .expect("hn-cli binary runs");
+        Run {
+            code: output.status.code(),
+            stdout: String::from_utf8(output.stdout).unwrap(),
+            stderr: String::from_utf8(output.stderr).unwrap(),
+            cache: None,
+        }
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+/// The outcome of one [`FixtureServer::run`].
+struct Run {
+    code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    /// Home directory to remove once the run is dropped.
+    cache: Option<PathBuf>,
+}
 
-    // Map the internal SortMode to the API endpoint string
-    let endpoint = match args.sort {
-        SortMode::Hottest => "topstories",
-        SortMode::Latest => "newstories",
-    };
+impl Drop for Run {
+    fn drop(&mut self) {
+        if let Some(cache) = &self.cache {
+            let _ = std::fs::remove_dir_all(cache);
+        }
+    }
+}
 
-    let list_url = format!("https://hacker-news.firebaseio.com/v0/{}.json", endpoint);
-
-    println!("--- Fetching {} stories from {} ---", args.top, endpoint);
-
-    // Fetch the list of IDs from HN
-    let mut list_response = ureq::get(&list_url).call()?;
-    let story_ids: Vec<u32> = list_response.body_mut().read_json()?;
-
-    // Ensure we don't try to take more stories than the API returned
-    let limit = args.top.min(story_ids.len());
-    let target_ids = &story_ids[..limit];
-
-    for (i, &id) in target_ids.iter().enumerate() {
-        match get_story_details(id) {
-            Ok(story) => {
-                println!(
-                    "{:>2}. [{:^4}] {}\n    Link: {}",
-                    i + 1,
-                    story.score,
-                    story.title,
-                    story.url.as_deref().unwrap_or("No URL")
-                );
-                println!("    User: {}\n", story.by);
+/// Answers one request on `stream` from `routes`, then closes it.
+fn serve(mut stream: TcpStream, routes: &Mutex<Routes>, requests: &Mutex<Vec<Request>>) {
+    let mut request_line = String::new();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Read the headers up to the blank line.
+    let mut headers = Vec::new();
+    for line in reader.lines() {
+        match line {
+            Ok(line) if !line.is_empty() => {
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.push((name.to_string(), value.trim().to_string()));
+                }
             },
-            Err(e) => eprintln!("Error fetching story {}: {}", id, e),
+            _ => break,
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches("/v0/")
+        .to_string();
+    let request = Request {
+        method,
+        path: path.clone(),
+        headers,
+    };
+    let gzip = request
+        .header("accept-encoding")
+        .is_some_and(|accepted| accepted.contains("gzip"));
+    requests.lock().unwrap().push(request);
+    let reply = routes.lock().unwrap().get_mut(&path).and_then(|replies| {
+        if replies.len() > 1 {
+            replies.pop_front()
+        } else {
+            replies.front().cloned()
         }
+    });
+
+    let mut headers = String::new();
+    let mut content_type = "application/json";
+    let (status, body) = match reply {
+        Some(Reply::Gzip(body)) if gzip => {
+            headers = "Content-Encoding: gzip\r\n".to_string();
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body.to_string().as_bytes()).unwrap();
+            (200, encoder.finish().unwrap())
+        },
+        Some(Reply::Json(body) | Reply::Gzip(body)) => (200, body.to_string().into_bytes()),
+        Some(Reply::RetryAfter(status, retry_after)) => {
+            headers = format!("Retry-After: {retry_after}\r\n");
+            (status, Vec::new())
+        },
+        Some(Reply::Malformed) => (200, b"{\"id\": 1, \"title\": ".to_vec()),
+        Some(Reply::Status(status)) => (status, Vec::new()),
+        Some(Reply::Redirect(location)) => {
+            headers = format!("Location: {location}\r\n");
+            (301, Vec::new())
+        },
+        Some(Reply::Bytes(kind, bytes)) => {
+            content_type = kind;
+            (200, bytes.to_vec())
+        },
+        Some(Reply::Stall(delay)) => {
+            thread::sleep(delay);
+            return;
+        },
+        Some(Reply::Slow(delay, body)) => {
+            thread::sleep(delay);
+            (200, body.to_string().into_bytes())
+        },
+        None => (404, Vec::new()),
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status} Fixture\r\nContent-Type: {content_type}\r\n{headers}Content-Length: \
+         {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .and_then(|()| stream.write_all(&body));
+}
+
+/// Sends `method path` to the HTTP server at `address` and returns the
+/// response head and body.
+#[cfg(feature = "serve")]
+fn http(address: &str, method: &str, path: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(address).expect("server accepts");
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: {address}\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    (head.to_string(), body.to_string())
+}
+
+/// Starts `serve` on a free port against `server`, refreshing every second,
+/// with its cache and state in `home`; returns the process, its stdout past
+/// the banner and the address it listens on.
+#[cfg(all(unix, feature = "serve"))]
+fn start_serve(
+    server: &FixtureServer,
+    home: &Path,
+) -> (
+    std::process::Child,
+    BufReader<std::process::ChildStdout>,
+    String,
+) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+        .args([
+            "--base-url",
+            &server.url,
+            "--deterministic",
+            "--retries",
+            "0",
+        ])
+        .args([
+            "serve",
+            "--port",
+            "0",
+            "--refresh",
+            "1",
+            "--cors-origin",
+            "*",
+        ])
+        .env("XDG_CACHE_HOME", home)
+        .env("XDG_STATE_HOME", home)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("hn-cli binary runs");
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut banner = String::new();
+    stdout.read_line(&mut banner).unwrap();
+    let address = banner
+        .split_once("http://")
+        .and_then(|(_, rest)| rest.split_once('/'))
+        .map_or_else(
+            || panic!("no address in {banner:?}"),
+            |(address, _)| address.to_string(),
+        );
+    (child, stdout, address)
+}
+
+/// The fixture route of a Wayback Machine lookup for `link`.
+fn wayback_path(link: &str) -> String {
+    let query: String = url::form_urlencoded::byte_serialize(link.as_bytes()).collect();
+    format!("wayback/available?url={query}")
+}
+
+/// Compares `actual` with `tests/snapshots/<name>`, or rewrites the snapshot
+/// when `UPDATE_SNAPSHOTS` is set.
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, actual).expect("snapshot is writable");
+        return;
     }
+    let expected = std::fs::read_to_string(&path).expect("snapshot exists");
+    assert_eq!(
+        actual,
+        expected,
+        "{} is stale; rerun with UPDATE_SNAPSHOTS=1 to accept the new output",
+        path.display()
+    );
+}
+
+/// A server with a small front page: a link, a text post, a job and a
+/// deleted item.
+fn front_page() -> FixtureServer {
+    FixtureServer::start()
+        .json("topstories.json", json!([8863, 121_003, 192_327, 4]))
+        .json(
+            "item/8863.json",
+            json!({
+                "id": 8863, "type": "story", "title": "My YC app: Dropbox", "score": 111,
+                "by": "dhouston", "descendants": 71,
+                "url": "http://www.getdropbox.com/u/2/screencast.html"
+            }),
+        )
+        .json(
+            "item/121003.json",
+            json!({
+                "id": 121_003, "type": "story", "title": "Ask HN: The Arc Effect", "score": 25,
+                "by": "tel", "descendants": 16, "text": "Is it <i>just</i> me?"
+            }),
+        )
+        .json(
+            "item/192327.json",
+            json!({
+                "id": 192_327, "type": "job", "title": "Justin.tv is looking for a Lead Flash Engineer!",
+                "score": 6, "by": "justin", "text": "Justin.tv is hiring."
+            }),
+        )
+        .json("item/4.json", json!({ "id": 4, "deleted": true, "type": "story" }))
+}
 
-    Ok(())
+/// Item bodies as the live API serves them: one of each type, a dead story
+/// and the very first item, as the property tests' starting points.
+fn real_items() -> Vec<Value> {
+    vec![
+        json!({
+            "by": "pg", "descendants": 15, "id": 1, "kids": [15, 234_509, 487_171],
+            "score": 57, "time": 1_160_418_111, "title": "Y Combinator", "type": "story",
+            "url": "http://ycombinator.com"
+        }),
+        json!({
+            "by": "dhouston", "descendants": 71, "id": 8863, "kids": [9224, 8917, 8952],
+            "score": 111, "time": 1_175_714_200, "title": "My YC app: Dropbox - Throw away your USB drive",
+            "type": "story", "url": "http://www.getdropbox.com/u/2/screencast.html"
+        }),
+        json!({
+            "by": "tel", "descendants": 16, "id": 121_003, "kids": [121_016, 121_109],
+            "score": 25, "text": "<i>or</i> HN: the Next Iteration<p>I get the impression...",
+            "time": 1_203_647_620, "title": "Ask HN: The Arc Effect", "type": "story"
+        }),
+        json!({
+            "by": "justin", "id": 192_327, "score": 6, "text": "Justin.tv is the biggest live video site online.",
+            "time": 1_210_981_217, "title": "Justin.tv is looking for a Lead Flash Engineer!",
+            "type": "job", "url": ""
+        }),
+        json!({
+            "by": "pg", "descendants": 54, "id": 126_809, "kids": [126_822, 126_823],
+            "parts": [126_810, 126_811, 126_812], "score": 46, "text": "",
+            "time": 1_204_403_652, "title": "Poll: What would happen if News.YC had explicit support for polls?",
+            "type": "poll"
+        }),
+        json!({
+            "by": "pg", "id": 160_705, "poll": 160_704, "score": 335, "time": 1_207_886_576,
+            "text": "Yes, ban them; I&#x27;m tired of seeing Valleywag stories on News.YC.",
+            "type": "pollopt"
+        }),
+        json!({
+            "by": "norvig", "id": 2_921_983, "kids": [2_922_097, 2_922_429], "parent": 2_921_506,
+            "text": "Aw shucks, guys ... you make me blush with your compliments.",
+            "time": 1_314_211_127, "type": "comment"
+        }),
+        json!({ "dead": true, "id": 33_345_214, "time": 1_666_969_876, "type": "story" }),
+    ]
 }
 
 // --- Tests ---
@@ -104,35 +498,4746 @@ mod tests {
         }"#;
 
         let story: Story = serde_json::from_str(json).unwrap();
+        assert_eq!(story.id, 8863);
         assert_eq!(story.title, "My YC app: Sample");
         assert_eq!(story.score, 111);
         assert_eq!(story.by, "dhouston");
+        assert_eq!(story.descendants, Some(71));
+        assert_eq!(story.host().as_deref(), Some("getdropbox.com"));
+    }
+
+    /// Test that a text post's HTML body decodes to plain paragraphs.
+    #[test]
+    fn test_story_body() {
+        let json = r#"{
+            "by": "tel",
+            "id": 121003,
+            "score": 25,
+            "text": "<i>or</i> HN: the Next Iteration<p>I&#x27;m a fan of <a href=\"http:&#x2F;&#x2F;arclanguage.org&#x2F;\" rel=\"nofollow\">http:&#x2F;&#x2F;arclanguage.o...</a> &amp; Lisp.",
+            "time": 1203647620,
+            "title": "Ask HN: The Arc Effect",
+            "type": "story"
+        }"#;
+
+        let story: Story = serde_json::from_str(json).unwrap();
+        assert!(story.is_text_post());
+        assert_eq!(
+            story.body().as_deref(),
+            Some("or HN: the Next Iteration\n\nI'm a fan of http://arclanguage.org/ & Lisp.")
+        );
     }
 
-    /// Test that the CLI argument defaults work as expected.
+    /// Test that the CLI argument defaults work as expected: 30 stories of
+    /// the hottest feed.
     #[test]
     fn test_arg_defaults() {
-        // Mocking the command line arguments
-        let args = Args::try_parse_from(&["test_bin"]).unwrap();
-        assert_eq!(args.top, 30);
-        assert_eq!(args.sort, SortMode::Hottest);
+        let ids: Vec<u32> = (1..=40).collect();
+        let server = FixtureServer::start()
+            .json("topstories.json", json!(ids))
+            .json("newstories.json", json!(ids));
+
+        let run = server.run(&["--explain"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let feed = run.stdout.lines().next().unwrap();
+        assert_eq!(
+            feed,
+            format!(
+                "Feed     hottest from {}/topstories.json: 30 stories",
+                server.url
+            )
+        );
+        assert_eq!(server.requests("newstories.json"), 0);
     }
 
-    /// Test custom CLI arguments for top and sort mode.
+    /// Test custom CLI arguments for count and sort mode.
     #[test]
     fn test_arg_customization() {
-        let args = Args::try_parse_from(&["test_bin", "--top", "5", "--sort", "latest"]).unwrap();
-        assert_eq!(args.top, 5);
-        assert_eq!(args.sort, SortMode::Latest);
+        let ids: Vec<u32> = (1..=40).collect();
+        let server = FixtureServer::start()
+            .json("topstories.json", json!(ids))
+            .json("newstories.json", json!(ids));
+
+        let run = server.run(&["--explain", "--count", "5", "--sort", "latest"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let feed = run.stdout.lines().next().unwrap();
+        assert_eq!(
+            feed,
+            format!(
+                "Feed     latest from {}/newstories.json: 5 stories",
+                server.url
+            )
+        );
+        assert_eq!(server.requests("topstories.json"), 0);
+
+        let run = server.run(&["--explain", "-c", "7", "-s", "hottest"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stdout.starts_with(&format!(
+                "Feed     hottest from {}/topstories.json: 7 stories\n",
+                server.url
+            )),
+            "{}",
+            run.stdout
+        );
+    }
+
+    /// Test the documented exit code of each error, and that an item error
+    /// under `--fail-fast` takes its cause's.
+    #[test]
+    fn test_error_exit_codes() {
+        let io = || std::io::Error::other("disk full");
+        let cases = [
+            (
+                Error::Interactive {
+                    action: "can't read the command".into(),
+                    source: "terminal closed".into(),
+                },
+                1,
+            ),
+            (Error::Usage("bad flags".into()), 2),
+            (
+                Error::Config {
+                    path: "config.toml".into(),
+                    source: "bad".into(),
+                },
+                2,
+            ),
+            (
+                Error::RateLimited {
+                    retry_after: None,
+                },
+                8,
+            ),
+            (Error::Decode("not JSON".into()), 5),
+            (Error::BodyTooLarge(10), 5),
+            (Error::Gone(4), 4),
+            (Error::NoResults, 4),
+            (Error::EmptyFeed("topstories", 3), 4),
+            (
+                Error::TooFewResults {
+                    required: 5,
+                    found: 2,
+                },
+                4,
+            ),
+            (Error::NoPlatformDir("cache"), 6),
+            (Error::NotCached("item 1".into()), 6),
+            (
+                Error::Cache {
+                    path: "cache".into(),
+                    source: io(),
+                },
+                6,
+            ),
+            (Error::Incomplete(2), 7),
+            (Error::CrawlIncomplete(2), 7),
+            (
+                Error::ShortFeed {
+                    feed: "askstories",
+                    requested: 300,
+                    available: 200,
+                },
+                7,
+            ),
+            (
+                Error::Output {
+                    path: "out".into(),
+                    source: io(),
+                },
+                9,
+            ),
+            (Error::PipeFailed(1), 10),
+            (Error::ExecFailed(1), 10),
+            (Error::Interrupted, 130),
+            (
+                Error::Item {
+                    id: 1,
+                    source: Box::new(Error::Gone(1)),
+                },
+                4,
+            ),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{error}");
+        }
+    }
+
+    /// Test that a seeded backoff repeats its schedule and stays under the
+    /// doubling, capped bound.
+    #[test]
+    fn test_backoff_schedule() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_millis(500);
+        let schedule = |backoff: &Backoff| (0..6).map(|n| backoff.delay(n)).collect::<Vec<_>>();
+
+        let first = schedule(&Backoff::seeded(base, max, 7));
+        assert_eq!(first, schedule(&Backoff::seeded(base, max, 7)));
+        assert_ne!(first, schedule(&Backoff::seeded(base, max, 8)));
+
+        let backoff = Backoff::seeded(base, max, 7);
+        let caps: Vec<u64> = (0..6)
+            .map(|n| u64::try_from(backoff.cap(n).as_millis()).unwrap())
+            .collect();
+        assert_eq!(caps, [100, 200, 400, 500, 500, 500]);
+        for (n, delay) in (0..).zip(&first) {
+            assert!(*delay <= backoff.cap(n), "retry {n} waited {delay:?}");
+        }
+    }
+
+    /// Test the number styles at the unit boundaries: grouping from 1,000,
+    /// and compact forms rounded half up that move to the next unit rather
+    /// than grow past 4 characters.
+    #[test]
+    fn test_number_styles() {
+        let format = |style: NumberStyle, numbers: &[i64]| {
+            numbers.iter().map(|&n| style.format(n)).collect::<Vec<_>>()
+        };
+        let boundaries = [0, 999, 1000, 1834, 999_999, 1_000_000, -1234];
+
+        assert_eq!(format(NumberStyle::Plain, &boundaries), [
+            "0", "999", "1000", "1834", "999999", "1000000", "-1234"
+        ]);
+        assert_eq!(format(NumberStyle::Grouped, &boundaries), [
+            "0",
+            "999",
+            "1,000",
+            "1,834",
+            "999,999",
+            "1,000,000",
+            "-1,234"
+        ]);
+        assert_eq!(format(NumberStyle::Compact, &boundaries), [
+            "0", "999", "1.0k", "1.8k", "1.0M", "1.0M", "-1.2k"
+        ]);
+        assert_eq!(
+            format(NumberStyle::Compact, &[
+                1049,
+                1050,
+                9949,
+                9950,
+                999_499,
+                999_500,
+                i64::MAX
+            ]),
+            ["1.0k", "1.1k", "9.9k", "10k", "999k", "1.0M", "9.2E"]
+        );
+        for n in (0..2_000_000).step_by(499) {
+            let compact = NumberStyle::Compact.format(n);
+            assert!(compact.len() <= 4, "{n} is {compact}");
+        }
+    }
+
+    /// Test that a built client sends its requests to the base URL with its
+    /// user agent, retries failures as many times as asked, and gives up on
+    /// a request after its timeout.
+    #[test]
+    fn test_client_builder() {
+        let server = front_page()
+            .before("item/8863.json", [Reply::Status(500)])
+            .route("item/1.json", Reply::Stall(Duration::from_secs(2)));
+        let client = HnClient::builder()
+            .base_url(&server.url)
+            .user_agent("hn-probe/2.0")
+            .retries(1)
+            .backoff(Backoff::seeded(Duration::ZERO, Duration::ZERO, 0))
+            .timeout(Duration::from_millis(200))
+            .build();
+
+        assert_eq!(client.base_url(), server.url);
+        let story = client.item(8863).unwrap();
+        assert_eq!(story.title, "My YC app: Dropbox");
+        assert_eq!(server.requests("item/8863.json"), 2);
+        assert_eq!(client.retries_performed(), 1);
+        assert_eq!(server.header_values("user-agent"), vec![
+            Some(
+                "hn-probe/2.0".to_string()
+            );
+            2
+        ]);
+
+        let error = client.item(1).unwrap_err();
+        assert_eq!(error.category(), "timeout", "{error}");
+        assert_eq!(server.requests("item/1.json"), 2);
+    }
+
+    /// Test that the client reads a feed and then each of its items.
+    #[test]
+    fn test_client_top_stories() {
+        let mock = MockTransport::new()
+            .with_json("topstories.json", json!([8863, 121_003, 1]))
+            .with_json(
+                "item/8863.json",
+                json!({
+                    "id": 8863, "title": "My YC app: Dropbox", "score": 111, "by": "dhouston",
+                    "url": "http://www.getdropbox.com/u/2/screencast.html"
+                }),
+            )
+            .with_json(
+                "item/121003.json",
+                json!({
+                    "id": 121_003, "title": "Ask HN: The Arc Effect", "score": 25, "by": "tel"
+                }),
+            );
+
+        let stories = mock_client(&mock).top_stories(2).unwrap();
+        let titles: Vec<&str> = stories.iter().map(|story| story.title.as_str()).collect();
+        assert_eq!(titles, ["My YC app: Dropbox", "Ask HN: The Arc Effect"]);
+        assert_eq!(stories[1].url, None);
+        assert_eq!(mock.requests(), [
+            "https://hacker-news.firebaseio.com/v0/topstories.json",
+            "https://hacker-news.firebaseio.com/v0/item/8863.json",
+            "https://hacker-news.firebaseio.com/v0/item/121003.json",
+        ]);
+    }
+
+    /// Test that server errors are retried and counted, and client errors
+    /// are not.
+    #[test]
+    fn test_client_retries() {
+        let mock = MockTransport::new()
+            .with_status("item/1.json", 503)
+            .with_json(
+                "item/1.json",
+                json!({
+                    "id": 1, "title": "Back", "score": 1, "by": "pg"
+                }),
+            )
+            .with_status("item/2.json", 404);
+        let client = mock_client(&mock);
+
+        assert_eq!(client.item(1).unwrap().title, "Back");
+        assert_eq!(client.retries_performed(), 1);
+
+        let missing = client.item(2).unwrap_err();
+        assert!(matches!(missing, Error::Gone(2)), "{missing:?}");
+        assert_eq!(client.retries_performed(), 1);
+    }
+
+    /// Test that an empty feed is fetched again under `retry_empty_lists`,
+    /// returned as it is without it, and ends in `EmptyFeed` once the
+    /// retries run out.
+    #[test]
+    fn test_client_retry_empty_lists() {
+        let mock = MockTransport::new()
+            .with_json("topstories.json", json!([]))
+            .with_json("topstories.json", json!([1, 2]))
+            .with_json("newstories.json", json!([]));
+        let client = HnClient::builder()
+            .transport(mock.clone())
+            .retries(2)
+            .retry_empty_lists(true)
+            .backoff(Backoff::seeded(Duration::ZERO, Duration::ZERO, 0))
+            .build();
+
+        assert_eq!(client.story_ids(SortMode::Hottest).unwrap(), [1, 2]);
+        assert_eq!(client.retries_performed(), 1);
+
+        let error = client.story_ids(SortMode::Latest).unwrap_err();
+        assert!(
+            matches!(error, Error::EmptyFeed("newstories", 3)),
+            "{error:?}"
+        );
+        assert_eq!(
+            error.to_string(),
+            "no stories returned by newstories after 3 attempts"
+        );
+        assert_eq!(error.exit_code(), 4);
+        assert_eq!(client.retries_performed(), 3);
+
+        let lenient = mock_client(&mock);
+        assert!(lenient.story_ids(SortMode::Latest).unwrap().is_empty());
+    }
+
+    /// Test that `Retry-After` is honored without using up a retry, in both
+    /// forms, and that waits past the cap or endless 429s end as rate limited.
+    #[test]
+    fn test_client_retry_after() {
+        let story = json!({ "id": 1, "title": "Later", "score": 1, "by": "pg" });
+        let mock = MockTransport::new()
+            .with_headers("item/1.json", 429, &[("Retry-After", "0")])
+            .with_json("item/1.json", story)
+            .with_headers("item/2.json", 503, &[(
+                "retry-after",
+                "Wed, 21 Oct 2015 07:28:00 GMT",
+            )])
+            .with_json(
+                "item/2.json",
+                json!({ "id": 2, "title": "Later", "score": 1, "by": "pg" }),
+            )
+            .with_headers("item/3.json", 429, &[("Retry-After", "120")])
+            .with_status("item/4.json", 429);
+        let client = HnClient::builder()
+            .transport(mock)
+            .backoff(Backoff::seeded(Duration::ZERO, Duration::ZERO, 0))
+            .max_retry_after(Duration::from_secs(5))
+            .build();
+
+        assert_eq!(client.item(1).unwrap().title, "Later");
+        assert_eq!(client.item(2).unwrap().title, "Later");
+        assert_eq!(client.retries_performed(), 2);
+
+        let error = client.item(3).unwrap_err();
+        assert!(
+            matches!(error, Error::RateLimited { retry_after: Some(wait) } if wait.as_secs() == 120),
+            "{error:?}"
+        );
+        assert_eq!(
+            error.to_string(),
+            "rate limited by server (asked to wait 120s)"
+        );
+        assert_eq!(error.exit_code(), 8);
+
+        let error = client.item(4).unwrap_err();
+        assert!(
+            matches!(error, Error::RateLimited {
+                retry_after: None
+            }),
+            "{error:?}"
+        );
+        assert_eq!(error.category(), "rate limited");
+    }
+
+    /// Test that the rate limit lets a burst through, then paces the rest.
+    #[test]
+    fn test_client_rate_limit() {
+        let mock = MockTransport::new().with_json(
+            "item/1.json",
+            json!({
+                "id": 1, "title": "Paced", "score": 1, "by": "pg"
+            }),
+        );
+        let client = HnClient::builder()
+            .transport(mock)
+            .rate_limit(std::num::NonZeroU32::new(10).unwrap())
+            .build();
+
+        let start = std::time::Instant::now();
+        for _ in 0..12 {
+            client.item(1).unwrap();
+        }
+        let (throttled, waited) = client.throttled();
+        assert_eq!(throttled, 2);
+        assert!(waited >= Duration::from_millis(150), "{waited:?}");
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    /// Test that a body that isn't a story is a decode error (exit code 5).
+    #[test]
+    fn test_client_decode_error() {
+        let mock = MockTransport::new().with_json(
+            "item/3.json",
+            json!({ "id": 3, "type": "comment", "by": "pg", "text": "Agreed." }),
+        );
+
+        let error = mock_client(&mock).item(3).unwrap_err();
+        assert!(matches!(error, Error::Decode(_)), "{error:?}");
+        assert_eq!(error.exit_code(), 5);
+    }
+
+    /// Test that every real item is either a story, job or poll with its
+    /// type, or, for comments and poll options, a decode error.
+    #[test]
+    fn test_client_item_types() {
+        for item in real_items() {
+            let id = u32::try_from(item["id"].as_u64().unwrap()).unwrap();
+            let mock = MockTransport::new().with_json(&format!("item/{id}.json"), item.clone());
+            match (mock_client(&mock).item(id), item["type"].as_str()) {
+                (Ok(story), Some(kind)) => assert_eq!(story.kind.name(), kind, "{item}"),
+                (Err(Error::Decode(_)), Some("comment" | "pollopt")) => {},
+                (result, _) => panic!("{item}: {result:?}"),
+            }
+        }
+    }
+
+    /// Test that title prefixes are found whatever their case, spacing and
+    /// punctuation, over prefixes seen on the live site, and not in titles
+    /// that only start with similar words.
+    #[test]
+    fn test_title_prefix() {
+        for (title, prefix) in [
+            ("Ask HN: The Arc Effect", TitlePrefix::Ask),
+            ("ASK HN: Is it just me?", TitlePrefix::Ask),
+            ("ask hn: what are you working on?", TitlePrefix::Ask),
+            ("  Ask HN: Leading spaces", TitlePrefix::Ask),
+            ("Ask HN? Anyone else seeing this", TitlePrefix::Ask),
+            ("Ask HN (2019): Who is hiring?", TitlePrefix::Ask),
+            ("Ask  HN: Two spaces", TitlePrefix::Ask),
+            ("Ask HN", TitlePrefix::Ask),
+            ("Show HN: Dropbox", TitlePrefix::Show),
+            ("Show HN - A dash instead", TitlePrefix::Show),
+            ("Show HN – An en dash", TitlePrefix::Show),
+            ("show hn:lowercase, no space", TitlePrefix::Show),
+            ("Tell HN: Reddit is down", TitlePrefix::Tell),
+            ("TELL HN: Shouting", TitlePrefix::Tell),
+        ] {
+            assert_eq!(TitlePrefix::of(title), Some(prefix), "{title}");
+        }
+        for title in [
+            "Asking HN for advice",
+            "Ask HNers what they think",
+            "Showcase: my portfolio",
+            "Show Hacker News",
+            "The Ask HN effect",
+            "Telling HN about it",
+            "Ask",
+            "",
+        ] {
+            assert_eq!(TitlePrefix::of(title), None, "{title}");
+        }
+        let story: Story =
+            serde_json::from_value(json!({ "id": 1, "title": " show HN: Mine" })).unwrap();
+        assert_eq!(story.title_prefix(), Some(TitlePrefix::Show));
+    }
+
+    /// Test that a sparse item, with nothing but its id and title, parses
+    /// with the other fields empty.
+    #[test]
+    fn test_story_sparse() {
+        let story: Story = serde_json::from_value(json!({ "id": 5, "title": "Sparse" })).unwrap();
+        assert_eq!(story.title, "Sparse");
+        assert_eq!(story.kind, ItemType::Unknown);
+        assert_eq!((story.score, story.by.as_str()), (0, ""));
+        assert_eq!(
+            (story.url, story.descendants, story.time),
+            (None, None, None)
+        );
+        assert!(story.kids.is_none());
+    }
+
+    /// Test that fields of unexpected types read as empty and unknown fields
+    /// are ignored, rather than failing the item.
+    #[test]
+    fn test_story_extra_and_odd_fields() {
+        let story: Story = serde_json::from_value(json!({
+            "id": 6, "type": "story", "title": "Odd fields", "by": "pg",
+            "score": "12", "descendants": -3, "kids": "many", "time": "yesterday",
+            "url": 42, "text": ["not", "html"],
+            "flair": { "color": "orange" }, "reactions": [1, 2, 3],
+        }))
+        .unwrap();
+        assert_eq!(
+            (story.title.as_str(), story.by.as_str()),
+            ("Odd fields", "pg")
+        );
+        assert_eq!(story.score, 0);
+        assert_eq!((story.descendants, story.time), (None, None));
+        assert_eq!((story.url, story.text, story.kids), (None, None, None));
+    }
+
+    /// Test that a live story without a title, or with one that isn't text,
+    /// is a decode error naming the item, while a dead one still shows.
+    #[test]
+    fn test_client_missing_title() {
+        let mock = MockTransport::new()
+            .with_json(
+                "item/7.json",
+                json!({ "id": 7, "type": "story", "by": "pg", "score": 3 }),
+            )
+            .with_json(
+                "item/8.json",
+                json!({ "id": 8, "type": "story", "title": 8 }),
+            )
+            .with_json(
+                "item/9.json",
+                json!({ "id": 9, "type": "story", "dead": true }),
+            );
+        let client = mock_client(&mock);
+
+        for id in [7, 8] {
+            let error = client.item(id).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .ends_with(&format!("item {id} has no title")),
+                "{error}"
+            );
+            assert_eq!(error.exit_code(), 5);
+        }
+        assert_eq!(client.item(9).unwrap().title, "");
+    }
+
+    /// A JSON value of any shape, to put in place of an item's field.
+    fn any_json() -> impl Strategy<Value = Value> {
+        prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            any::<f64>().prop_map(Value::from),
+            ".{0,12}".prop_map(Value::from),
+            prop::collection::vec(any::<u32>(), 0..4).prop_map(Value::from),
+            Just(json!({ "nested": [1, "two"] })),
+        ]
+    }
+
+    /// The type an item's `type` names, spelled out here rather than through
+    /// [`ItemType::from_name`].
+    fn expected_kind(item: &Value) -> ItemType {
+        match item["type"].as_str() {
+            Some("story") => ItemType::Story,
+            Some("comment") => ItemType::Comment,
+            Some("job") => ItemType::Job,
+            Some("poll") => ItemType::Poll,
+            Some("pollopt") => ItemType::PollOpt,
+            _ => ItemType::Unknown,
+        }
+    }
+
+    proptest! {
+        /// Test that a real item with any of its fields but the id left out
+        /// still parses, classified by its type.
+        #[test]
+        fn prop_items_missing_fields_parse(
+            index in 0..real_items().len(),
+            keep in prop::collection::vec(any::<bool>(), 16),
+        ) {
+            let mut item = real_items().swap_remove(index);
+            let mut keep = keep.into_iter();
+            item.as_object_mut()
+                .unwrap()
+                .retain(|key, _| key == "id" || keep.next().unwrap_or(true));
+            let story = serde_json::from_value::<Story>(item.clone())
+                .map_err(|e| TestCaseError::fail(format!("{e}: {item}")))?;
+            prop_assert_eq!(story.kind, expected_kind(&item));
+        }
+
+        /// Test that a real item with fields replaced by values of any shape,
+        /// unknown keys added or its type changed never panics, through
+        /// serde or the client, and is classified by its type whenever it
+        /// parses.
+        #[test]
+        fn prop_mutated_items_never_panic(
+            index in 0..real_items().len(),
+            edits in prop::collection::vec(
+                (any::<prop::sample::Index>(), prop::option::of(any_json())),
+                0..4,
+            ),
+            extra in prop::collection::btree_map("[a-z_]{1,10}", any_json(), 0..3),
+            kind in prop::option::of(prop_oneof![
+                prop::sample::select(vec!["story", "comment", "job", "poll", "pollopt"])
+                    .prop_map(Value::from),
+                any_json(),
+            ]),
+        ) {
+            let mut item = real_items().swap_remove(index);
+            let fields = item.as_object_mut().unwrap();
+            for (key, value) in edits {
+                let key = fields.keys().nth(key.index(fields.len())).cloned().unwrap();
+                match value {
+                    Some(value) => fields.insert(key, value),
+                    None => fields.remove(&key),
+                };
+            }
+            fields.extend(extra);
+            if let Some(kind) = kind {
+                fields.insert("type".to_string(), kind);
+            }
+
+            if let Ok(story) = serde_json::from_value::<Story>(item.clone()) {
+                prop_assert_eq!(story.kind, expected_kind(&item));
+                prop_assert_eq!(Some(u64::from(story.id)), item["id"].as_u64());
+            }
+            let mock = MockTransport::new().with_json("item/1.json", item);
+            let _ = mock_client(&mock).item(1);
+        }
+    }
+
+    /// Test that deleted and never-used ids are gone rather than undecodable.
+    #[test]
+    fn test_client_gone() {
+        let mock = MockTransport::new()
+            .with_json("item/3.json", json!({ "id": 3, "deleted": true }))
+            .with_json("item/4.json", Value::Null);
+        let client = mock_client(&mock);
+
+        for id in [3, 4] {
+            let error = client.item(id).unwrap_err();
+            assert!(
+                matches!(error, Error::Gone(gone) if gone == id),
+                "{error:?}"
+            );
+            assert_eq!(error.category(), "missing");
+        }
+    }
+
+    /// Test that an Algolia search sends the encoded query and page, and
+    /// maps hits to stories.
+    #[test]
+    fn test_client_algolia_search() {
+        let mock = MockTransport::new().with_json(
+            "search?query=rust+lang&tags=story&hitsPerPage=2&page=1",
+            json!({ "hits": [{
+                "objectID": "8863", "title": "Rust lang", "url": "https://rust-lang.org",
+                "points": 42, "author": "graydon", "num_comments": 7
+            }] }),
+        );
+        let client = mock_client(&mock);
+
+        let stories = client.algolia_search("rust lang", 1, 2).unwrap();
+        assert_eq!(stories.len(), 1);
+        assert_eq!((stories[0].id, stories[0].score), (8863, 42));
+        assert_eq!(stories[0].by, "graydon");
+    }
+
+    /// Test that a Wayback Machine lookup returns the closest snapshot, and
+    /// nothing for a link that was never archived.
+    #[test]
+    fn test_client_wayback_snapshot() {
+        let mock = MockTransport::new()
+            .with_json(
+                "available?url=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1",
+                json!({ "archived_snapshots": { "closest": {
+                    "available": true, "url": "http://web.archive.org/web/2019/example.com/a?b=1",
+                    "timestamp": "2019", "status": "200"
+                } } }),
+            )
+            .with_json(
+                "available?url=https%3A%2F%2Fexample.com%2Fnew",
+                json!({ "url": "https://example.com/new", "archived_snapshots": {} }),
+            );
+        let client = mock_client(&mock);
+
+        assert_eq!(
+            client
+                .wayback_snapshot("https://example.com/a?b=1")
+                .unwrap()
+                .as_deref(),
+            Some("http://web.archive.org/web/2019/example.com/a?b=1")
+        );
+        assert_eq!(
+            client.wayback_snapshot("https://example.com/new").unwrap(),
+            None
+        );
+    }
+
+    /// Test the pretty listing end to end: ranks follow the feed order, the
+    /// job is listed and the deleted item is reported on stderr.
+    #[test]
+    fn test_e2e_pretty() {
+        let run = front_page().run(&[]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_snapshot("pretty.txt", &run.stdout);
+        assert_eq!(run.stderr, "1 item failed to fetch: 4 (missing)\n");
+    }
+
+    /// Test the text-post listing end to end: links are dropped and each
+    /// post's body is rendered from its HTML under the title.
+    #[test]
+    fn test_e2e_text_only() {
+        let server = front_page().replace(
+            "item/121003.json",
+            Reply::Json(json!({
+                "id": 121_003, "type": "story", "title": "Ask HN: The Arc Effect", "score": 25,
+                "by": "tel", "descendants": 16,
+                "text": "Is it <i>just</i> me?<p>See <a href=\"https:&#x2F;&#x2F;arclanguage.org\">\
+                         arc</a> &amp; friends.<br>Thanks"
+            })),
+        );
+        let run = server.run(&["--text-only"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_snapshot("text_only.txt", &run.stdout);
+    }
+
+    /// Test that `--links-only` leaves out the text posts and ranks what is
+    /// left from 1.
+    #[test]
+    fn test_e2e_links_only() {
+        let server = front_page().replace(
+            "topstories.json",
+            Reply::Json(json!([121_003, 192_327, 8863, 4])),
+        );
+        let run = server.run(&["--links-only", "--compact"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let lines: Vec<_> = run
+            .stdout
+            .lines()
+            .filter(|line| line.contains('['))
+            .collect();
+        assert_eq!(lines, [
+            " 1. [111 ] My YC app: Dropbox (getdropbox.com) by dhouston — 71c"
+        ]);
+
+        let run = server.run(&["--links-only", "--text-only"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+    }
+
+    /// Test the one-line-per-story layout; piped output keeps whole titles.
+    #[test]
+    fn test_e2e_compact() {
+        let run = front_page().run(&["--compact", "--count", "3"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            " 1. [111 ] My YC app: Dropbox (getdropbox.com) by dhouston — 71c\n 2. [ 25 ] ASK \
+             Ask HN: The Arc Effect by tel — 16c\n 3. [ 6  ] Justin.tv is looking for a Lead Flash \
+             Engineer! by justin\n"
+        );
+    }
+
+    /// Test that `--hot-discussions` keeps busy threads, busiest first, and
+    /// that explicit flags override the preset.
+    #[test]
+    fn test_e2e_hot_discussions() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json(
+                "item/1.json",
+                json!({ "id": 1, "title": "Quiet", "score": 90, "by": "a", "descendants": 5 }),
+            )
+            .json(
+                "item/2.json",
+                json!({ "id": 2, "title": "Busy", "score": 10, "by": "b", "descendants": 30 }),
+            )
+            .json(
+                "item/3.json",
+                json!({ "id": 3, "title": "Lively", "score": 50, "by": "c", "descendants": 25 }),
+            );
+        let titles = |args: &[&str]| {
+            let run = server.run(&[&["--compact"], args].concat());
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            run.stdout
+                .lines()
+                .map(|line| {
+                    let (_, rest) = line.split_once("] ").unwrap();
+                    rest.split(" by ").next().unwrap().to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(titles(&["--hot-discussions"]), ["Busy", "Lively"]);
+        assert_eq!(titles(&["--hot-discussions", "--order-by", "score"]), [
+            "Lively", "Busy"
+        ]);
+        assert_eq!(titles(&["--hot-discussions", "--min-comments", "0"]), [
+            "Busy", "Lively", "Quiet"
+        ]);
+        assert_eq!(titles(&["--order-by", "score"]), [
+            "Quiet", "Lively", "Busy"
+        ]);
+    }
+
+    /// Test that `--digest` keeps the best story per site, busiest site
+    /// first, and that `--digest-domains` caps the sites.
+    #[test]
+    fn test_e2e_digest() {
+        let story = |id: u32, score: i32, url: &str| json!({ "id": id, "title": format!("Story {id}"), "score": score, "by": "a", "url": url });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3, 4, 5, 6]))
+            .json("item/1.json", story(1, 10, "https://blog.example/one"))
+            .json("item/2.json", story(2, 50, "https://github.com/a"))
+            .json("item/3.json", story(3, 70, "https://www.github.com/b"))
+            .json("item/4.json", story(4, 90, "https://news.example/x"))
+            .json("item/5.json", story(5, 20, "https://github.com/c"))
+            .json(
+                "item/6.json",
+                json!({ "id": 6, "title": "Ask HN: Digest?", "score": 99, "by": "b" }),
+            );
+        let ids = |args: &[&str]| {
+            let run = server.run(&[&["--format", "json", "--digest"], args].concat());
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            let stories: Value = serde_json::from_str(&run.stdout).unwrap();
+            stories
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|story| story["id"].as_u64().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(&[]), [3, 1, 4]);
+        assert_eq!(ids(&["--digest-domains", "2"]), [3, 1]);
+        assert_eq!(ids(&["--order-by", "score"]), [4, 3, 1]);
+    }
+
+    /// Test that repeating `--sort` lists each feed under its own heading,
+    /// fetching and showing a story the feeds share only once.
+    #[test]
+    fn test_e2e_multiple_sorts() {
+        let story = |id: u32, score: i32| json!({ "id": id, "title": format!("Story {id}"), "score": score, "by": "a" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("newstories.json", json!([4, 3, 5]))
+            .json("item/1.json", story(1, 10))
+            .json("item/2.json", story(2, 30))
+            .json("item/3.json", story(3, 20))
+            .json("item/4.json", story(4, 1))
+            .json("item/5.json", story(5, 2));
+
+        let run = server.run(&[
+            "--sort",
+            "hottest",
+            "--sort",
+            "latest",
+            "--order-by",
+            "score",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let titles: Vec<_> = run
+            .stdout
+            .lines()
+            .filter(|line| line.starts_with('▸') || line.contains("Story"))
+            .collect();
+        assert_eq!(titles, [
+            "▸ Top stories 3 stories",
+            " 1. [ 30 ] Story 2",
+            " 2. [ 20 ] Story 3",
+            " 3. [ 10 ] Story 1",
+            "▸ New stories 2 stories, 1 more shown above",
+            " 1. [ 2  ] Story 5",
+            " 2. [ 1  ] Story 4",
+        ]);
+        assert_eq!(server.requests("item/3.json"), 1);
+
+        let run = server.run(&[
+            "--format", "json", "-s", "latest", "-s", "hottest", "-s", "latest",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let stories: Value = serde_json::from_str(&run.stdout).unwrap();
+        let ids: Vec<_> = stories
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|story| &story["id"])
+            .collect();
+        assert_eq!(ids, [4, 3, 5, 1, 2]);
+
+        let run = server.run(&["--sort", "hottest", "--sort", "latest", "--stream"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+    }
+
+    /// Test that `--feeds` merges the feeds into one list interleaved by rank,
+    /// fetching a shared story once and tagging each with the feeds listing
+    /// it, and that `--order-by` still orders the merged list.
+    #[test]
+    fn test_e2e_feeds() {
+        let story = |id: u32, score: i32| json!({ "id": id, "title": format!("Story {id}"), "score": score, "by": "a" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("newstories.json", json!([4, 3, 5]))
+            .json("item/1.json", story(1, 10))
+            .json("item/2.json", story(2, 30))
+            .json("item/3.json", story(3, 20))
+            .json("item/4.json", story(4, 1))
+            .json("item/5.json", story(5, 2));
+        let feeds = |extra: &[&str]| -> Vec<(u64, Value)> {
+            let run =
+                server.run(&[&["--feeds", "hottest,latest", "--format", "json"], extra].concat());
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            let stories: Value = serde_json::from_str(&run.stdout).unwrap();
+            stories
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|story| (story["id"].as_u64().unwrap(), story["feeds"].clone()))
+                .collect()
+        };
+
+        assert_eq!(feeds(&[]), [
+            (1, json!(["hottest"])),
+            (4, json!(["latest"])),
+            (2, json!(["hottest"])),
+            (3, json!(["hottest", "latest"])),
+            (5, json!(["latest"])),
+        ]);
+        assert_eq!(server.requests("item/3.json"), 1);
+        let ids: Vec<u64> = feeds(&["--order-by", "score"])
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, [2, 3, 1, 5, 4]);
+
+        let run = server.run(&["--feeds", "hottest,latest", "--count", "2"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let lines: Vec<_> = run.stdout.lines().filter(|line| !line.is_empty()).collect();
+        assert_eq!(lines, [
+            " 1. [ 10 ] Story 1",
+            "      by a · in hottest",
+            " 2. [ 1  ] Story 4",
+            "      by a · in latest",
+            " 3. [ 30 ] Story 2",
+            "      by a · in hottest",
+            " 4. [ 20 ] Story 3",
+            "      by a · in hottest, latest",
+        ]);
+
+        let run = server.run(&["--feeds", "hottest", "--sort", "latest"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+    }
+
+    /// Test that failed items are summarized on stderr after the stories,
+    /// which still exit 0 unless `--strict` makes them an error.
+    #[test]
+    fn test_e2e_strict() {
+        let server = front_page().replace("item/121003.json", Reply::Status(500));
+        let summary = "2 items failed to fetch: 121003 (HTTP 500), 4 (missing)";
+
+        let run = server.run(&["--retries", "0"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("My YC app: Dropbox"), "{}", run.stdout);
+        assert!(run.stderr.contains(summary), "{}", run.stderr);
+
+        let run = server.run(&["--retries", "0", "--strict"]);
+        assert_eq!(run.code, Some(7), "{}", run.stderr);
+        assert!(run.stdout.contains("My YC app: Dropbox"), "{}", run.stdout);
+        assert!(run.stderr.contains(summary), "{}", run.stderr);
+        assert!(
+            run.stderr.contains("2 item(s) failed to fetch (--strict)"),
+            "{}",
+            run.stderr
+        );
+
+        let run = server.run(&["--retries", "0", "--strict", "--count", "1"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stderr.is_empty(), "{}", run.stderr);
+    }
+
+    /// Test the JSON listing end to end: the failure moves it to the
+    /// `{ "stories", "errors" }` form.
+    #[test]
+    fn test_e2e_json() {
+        let run = front_page().run(&["--format", "json"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_snapshot("json.json", &run.stdout);
+    }
+
+    /// Test that each failed item is listed under `errors` with its id,
+    /// category and reason, and alone under `--errors-only`.
+    #[test]
+    fn test_e2e_json_errors() {
+        let server = front_page().replace("item/121003.json", Reply::Status(500));
+        let failures = |run: &Run, errors: &Value| -> Vec<(u64, String)> {
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            errors
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|failure| {
+                    assert!(failure["reason"].is_string(), "{failure}");
+                    (
+                        failure["id"].as_u64().unwrap(),
+                        failure["category"].as_str().unwrap().to_string(),
+                    )
+                })
+                .collect()
+        };
+        let expected = [
+            (121_003, "HTTP 500".to_string()),
+            (4, "missing".to_string()),
+        ];
+
+        let run = server.run(&["--retries", "0", "--format", "json"]);
+        let listing: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(failures(&run, &listing["errors"]), expected);
+        assert_eq!(listing["stories"].as_array().unwrap().len(), 2);
+
+        let run = server.run(&["--retries", "0", "--format", "json", "--errors-only"]);
+        let errors: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(failures(&run, &errors), expected);
+    }
+
+    /// Test that an item answered with another id is a decode failure of
+    /// that item, listed and cached under neither id, instead of a panic.
+    #[test]
+    fn test_e2e_mismatched_id() {
+        let server = front_page().replace(
+            "item/121003.json",
+            Reply::Json(
+                json!({ "id": 99, "type": "story", "title": "Not the story asked for", "score": 1 }),
+            ),
+        );
+        let dir = std::env::temp_dir().join(format!("hn-cli-mismatch-{}", std::process::id()));
+        let cache = dir.to_str().unwrap();
+
+        let run = server.run(&["-c", "2", "--format", "json", "--cache-dir", cache]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let listing: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(listing["stories"].as_array().unwrap().len(), 1);
+        assert_eq!(listing["stories"][0]["id"], 8863);
+        assert_eq!(listing["errors"].as_array().unwrap().len(), 1);
+        assert_eq!(listing["errors"][0]["id"], 121_003);
+        assert_eq!(listing["errors"][0]["category"], "decode");
+        assert!(!dir.join("items").join("99.json").exists());
+        assert!(!dir.join("items").join("121003.json").exists());
+
+        let run = server.run(&["-c", "2"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("My YC app: Dropbox"), "{}", run.stdout);
+        assert!(
+            !run.stdout.contains("Not the story asked for"),
+            "{}",
+            run.stdout
+        );
+        assert!(run.stderr.contains("121003"), "{}", run.stderr);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test that `-v` logs each request with its status to stderr, that
+    /// `-vv` adds the response headers, and that nothing is logged without
+    /// either.
+    #[test]
+    fn test_e2e_verbose() {
+        let server = front_page().before("item/8863.json", [Reply::Status(500)]);
+        let run = server.run(&["--count", "1", "-v"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let requests: Vec<&str> = run
+            .stderr
+            .lines()
+            .filter(|line| line.contains(" DEBUG ") && line.contains("GET url="))
+            .collect();
+        let item = format!("GET url=\"{}/item/8863.json\"", server.url);
+        assert_eq!(requests.len(), 3, "{}", run.stderr);
+        assert!(
+            requests[1].contains(&format!("{item} status=500")),
+            "{}",
+            requests[1]
+        );
+        assert!(
+            requests[2].contains(&format!("{item} status=200")),
+            "{}",
+            requests[2]
+        );
+        assert!(!run.stderr.contains(" TRACE "), "{}", run.stderr);
+
+        let run = server.run(&["--count", "1", "-vv"]);
+        assert!(
+            run.stderr
+                .lines()
+                .any(|line| line.contains(" TRACE ")
+                    && line.contains("content-type: application/json")),
+            "{}",
+            run.stderr
+        );
+        assert!(server.run(&["--count", "1"]).stderr.is_empty());
+    }
+
+    /// Test that `--dump-raw` prints each response body to stderr, leaving
+    /// stdout as it is, and that `-vv` doesn't.
+    #[test]
+    fn test_e2e_dump_raw() {
+        let server = front_page();
+        let plain = server.run(&["--compact"]);
+        let run = server.run(&["--compact", "--dump-raw"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout, plain.stdout);
+        let dropbox = run
+            .stderr
+            .lines()
+            .find(|line| line.contains("/item/8863.json"))
+            .unwrap_or_else(|| panic!("{}", run.stderr));
+        assert!(dropbox.contains("hn_cli::raw"), "{dropbox}");
+        assert!(dropbox.contains("My YC app: Dropbox"), "{dropbox}");
+        assert!(run.stderr.contains("/topstories.json"), "{}", run.stderr);
+
+        let run = server.run(&["--compact", "-vv"]);
+        assert!(!run.stderr.contains("hn_cli::raw"), "{}", run.stderr);
+    }
+
+    /// Test that `--link-fallback none` leaves a text post's `url` null in
+    /// JSON, while `is_self` marks text posts under either setting.
+    #[test]
+    fn test_e2e_link_fallback() {
+        let server = front_page();
+        let fields = |args: &[&str]| -> Vec<(Value, Value)> {
+            let run = server.run(args);
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            let report: Value = serde_json::from_str(&run.stdout).unwrap();
+            report["stories"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|story| (story["url"].clone(), story["is_self"].clone()))
+                .collect()
+        };
+        let dropbox = (
+            json!("http://www.getdropbox.com/u/2/screencast.html"),
+            json!(false),
+        );
+
+        assert_eq!(fields(&["--format", "json"]), [
+            dropbox.clone(),
+            (
+                json!("https://news.ycombinator.com/item?id=121003"),
+                json!(true)
+            ),
+            (
+                json!("https://news.ycombinator.com/item?id=192327"),
+                json!(true)
+            ),
+        ]);
+        assert_eq!(fields(&["--format", "json", "--link-fallback", "none"]), [
+            dropbox,
+            (Value::Null, json!(true)),
+            (Value::Null, json!(true))
+        ]);
+    }
+
+    /// Test the rules by which `--dedupe-url` tells links to the same page.
+    #[test]
+    fn test_normalize_url() {
+        for (url, normalized) in [
+            ("https://example.com/post", "example.com/post"),
+            ("https://EXAMPLE.com/Post", "example.com/Post"),
+            ("https://www.example.com/post", "example.com/post"),
+            ("http://example.com:80/post", "example.com/post"),
+            ("https://example.com:443/post", "example.com/post"),
+            ("https://example.com:8443/post", "example.com:8443/post"),
+            ("https://example.com/post/", "example.com/post"),
+            ("https://example.com/", "example.com"),
+            ("https://example.com/post#comments", "example.com/post"),
+            (
+                "https://example.com/post?utm_source=hn&utm_medium=social",
+                "example.com/post",
+            ),
+            (
+                "https://example.com/post?id=2&fbclid=abc&page=3",
+                "example.com/post?id=2&page=3",
+            ),
+            (
+                "https://www.Example.com:443/post/?utm_source=hn&id=2#top",
+                "example.com/post?id=2",
+            ),
+        ] {
+            assert_eq!(normalize_url(url).as_deref(), Some(normalized), "{url}");
+        }
+        assert_eq!(normalize_url("not a url"), None);
+        assert_eq!(normalize_url("mailto:pg@example.com"), None);
+        assert_ne!(
+            normalize_url("https://example.com/a?id=1"),
+            normalize_url("https://example.com/a?id=2")
+        );
+    }
+
+    /// Test that `--dedupe-url` keeps the best-scoring repost of a page in
+    /// the place of the first, lists the others as its `duplicates` and
+    /// counts them in the closing line.
+    #[test]
+    fn test_e2e_dedupe_url() {
+        let story = |id: u32, score: i32, url: &str| json!({ "id": id, "title": format!("Story {id}"), "score": score, "by": "a", "url": url });
+        let server = FixtureServer::start()
+            .json("newstories.json", json!([1, 2, 3, 4, 5]))
+            .json("item/1.json", story(1, 10, "https://example.com/post"))
+            .json("item/2.json", story(2, 5, "https://other.org/"))
+            .json(
+                "item/3.json",
+                story(3, 40, "http://www.Example.com/post/?utm_source=hn"),
+            )
+            .json(
+                "item/4.json",
+                json!({ "id": 4, "title": "Ask HN: Story 4", "score": 1, "by": "a" }),
+            )
+            .json("item/5.json", story(5, 40, "https://example.com/post#top"));
+
+        let run = server.run(&["--sort", "latest", "--format", "json", "--dedupe-url"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let stories: Value = serde_json::from_str(&run.stdout).unwrap();
+        let kept: Vec<(&Value, &Value)> = stories
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|story| (&story["id"], &story["duplicates"]))
+            .collect();
+        assert_eq!(kept, [
+            (&json!(3), &json!([1, 5])),
+            (&json!(2), &Value::Null),
+            (&json!(4), &Value::Null)
+        ]);
+
+        let run = server.run(&[
+            "--sort",
+            "latest",
+            "--no-quiet",
+            "--compact",
+            "--dedupe-url",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stdout
+                .contains("Done! 3 stories, 2 reposts collapsed by --dedupe-url"),
+            "{}",
+            run.stdout
+        );
+        let run = server.run(&["--sort", "latest", "--format", "json"]);
+        assert_eq!(run.stdout.matches("\"id\"").count(), 5, "{}", run.stdout);
+    }
+
+    /// Test that a story links to its URL, or to its discussion page when it
+    /// has none.
+    #[test]
+    fn test_story_link() {
+        let story: Story = serde_json::from_value(json!({
+            "id": 8863, "title": "My YC app: Dropbox", "url": "https://getdropbox.com/"
+        }))
+        .unwrap();
+        assert_eq!(story.link(), "https://getdropbox.com/");
+        assert_eq!(
+            story.discussion_url(),
+            "https://news.ycombinator.com/item?id=8863"
+        );
+
+        let text = Story {
+            url: None,
+            ..story
+        };
+        assert!(text.is_text_post());
+        assert_eq!(text.link(), "https://news.ycombinator.com/item?id=8863");
+    }
+
+    /// Test that `--output` writes the results to a file with nothing else
+    /// left in its directory, keeps the file when a run fails, and treats
+    /// `-` as stdout.
+    #[test]
+    fn test_e2e_output() {
+        let server = front_page();
+        let dir = std::env::temp_dir().join(format!("hn-cli-output-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("front.json");
+        let output = path.to_str().unwrap();
+
+        let run = server.run(&["--format", "json", "--output", output]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout, "");
+        assert_eq!(run.stderr, "1 item failed to fetch: 4 (missing)\n");
+        assert_snapshot("json.json", &std::fs::read_to_string(&path).unwrap());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let run = server.run(&[
+            "--format",
+            "json",
+            "--output",
+            output,
+            "--min-score",
+            "1000",
+        ]);
+        assert_eq!(run.code, Some(4));
+        assert_snapshot("json.json", &std::fs::read_to_string(&path).unwrap());
+
+        let run = server.run(&["--format", "json", "--output", "-"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_snapshot("json.json", &run.stdout);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test the YAML listing end to end.
+    #[test]
+    fn test_e2e_yaml() {
+        let run = front_page().run(&["--format", "yaml"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_snapshot("yaml.yaml", &run.stdout);
+    }
+
+    /// Test that the piped pretty listing keeps rank order when the top story
+    /// arrives last.
+    #[test]
+    fn test_e2e_incremental_order() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2]))
+            .route(
+                "item/1.json",
+                Reply::Slow(
+                    Duration::from_millis(300),
+                    json!({ "id": 1, "title": "First", "score": 2, "by": "pg" }),
+                ),
+            )
+            .json(
+                "item/2.json",
+                json!({ "id": 2, "title": "Second", "score": 1, "by": "pg" }),
+            );
+        let run = server.run(&[]);
+
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            " 1. [ 2  ] First\n      by pg\n\n 2. [ 1  ] Second\n      by pg\n\n"
+        );
+    }
+
+    /// Test that `--stream` prints each story as it arrives, a slow first
+    /// story after a fast second one, each with its true rank.
+    #[test]
+    fn test_e2e_stream() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2]))
+            .route(
+                "item/1.json",
+                Reply::Slow(
+                    Duration::from_millis(300),
+                    json!({ "id": 1, "title": "First", "score": 2, "by": "pg" }),
+                ),
+            )
+            .json(
+                "item/2.json",
+                json!({ "id": 2, "title": "Second", "score": 1, "by": "pg" }),
+            );
+        let run = server.run(&["--stream", "--jobs", "2"]);
+
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            " 2. [ 1  ] Second\n      by pg\n\n 1. [ 2  ] First\n      by pg\n\n"
+        );
+        assert_eq!(server.run(&["--stream", "--format", "json"]).code, Some(2));
+    }
+
+    /// Test that item 500s are retried up to `--retries` times, and reported
+    /// once the retries run out.
+    #[test]
+    fn test_e2e_server_errors() {
+        let server =
+            front_page().before("item/8863.json", [Reply::Status(500), Reply::Status(500)]);
+
+        let run = server.run(&["--retries", "2", "--count", "1"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("My YC app: Dropbox"), "{}", run.stdout);
+        assert_eq!(server.requests("item/8863.json"), 3);
+
+        let server = front_page().replace("item/121003.json", Reply::Status(500));
+        let run = server.run(&["--retries", "1", "--strict"]);
+        assert_eq!(run.code, Some(7));
+        assert_eq!(server.requests("item/121003.json"), 2);
+        assert!(
+            run.stderr
+                .contains("2 items failed to fetch: 121003 (HTTP 500), 4 (missing)"),
+            "{}",
+            run.stderr
+        );
+    }
+
+    /// Test that a stalled item times out, is retried, and is reported as a
+    /// timeout.
+    #[test]
+    fn test_e2e_timeout() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1]))
+            .route("item/1.json", Reply::Stall(Duration::from_secs(3)));
+        let run = server.run(&["--timeout", "1", "--retries", "1", "--format", "json"]);
+
+        assert_eq!(run.code, Some(4), "{}", run.stderr);
+        assert_eq!(server.requests("item/1.json"), 2);
+        assert_eq!(
+            run.stderr,
+            "1 item failed to fetch: 1 (timeout)\nerror: no stories matched\n"
+        );
+    }
+
+    /// Test that `--fail-fast` aborts on the first real failure but lists past
+    /// deleted items, which `--strict` only counts at the end.
+    #[test]
+    fn test_e2e_fail_fast() {
+        let run = front_page().run(&["--fail-fast", "--jobs", "1"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stderr, "1 item failed to fetch: 4 (missing)\n");
+
+        // The lone worker may already be on 192327 when the abort lands; it
+        // answers slowly, so the run stops before asking for item 4.
+        let server = front_page()
+            .replace("item/121003.json", Reply::Status(500))
+            .replace(
+                "item/192327.json",
+                Reply::Slow(Duration::from_millis(300), json!(null)),
+            );
+        let run = server.run(&["--fail-fast", "--jobs", "1"]);
+        assert_eq!(run.code, Some(3), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("error: item 121003 failed to fetch: network error"),
+            "{}",
+            run.stderr
+        );
+        assert_eq!(server.requests("item/4.json"), 0);
+    }
+
+    /// Test that an invalid `--progress-template` warns and falls back to
+    /// the default bar, and the run still succeeds.
+    #[test]
+    fn test_e2e_progress_template() {
+        let server = front_page();
+        let run = server.run(&["--progress-template", "{msg:>x}", "--count", "1"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("My YC app: Dropbox"), "{}", run.stdout);
+        assert!(
+            run.stderr
+                .contains("warning: invalid --progress-template ("),
+            "{}",
+            run.stderr
+        );
+        assert!(
+            run.stderr.contains("); using the default"),
+            "{}",
+            run.stderr
+        );
+
+        let run = server.run(&["--progress-template", "{pos}/{len} {msg}", "--count", "1"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stderr.is_empty(), "{}", run.stderr);
+    }
+
+    /// Test that Ctrl-C stops the fetch without starting another request,
+    /// prints the stories fetched so far and exits 130.
+    #[cfg(unix)]
+    #[test]
+    fn test_e2e_interrupt() {
+        let story =
+            |id: u32| json!({ "id": id, "title": format!("Story {id}"), "score": 1, "by": "a" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("item/1.json", story(1))
+            .route(
+                "item/2.json",
+                Reply::Slow(Duration::from_millis(500), story(2)),
+            )
+            .json("item/3.json", story(3));
+        let home = std::env::temp_dir().join(format!("hn-cli-interrupt-{}", std::process::id()));
+        let child = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args(["--base-url", &server.url, "--deterministic", "--jobs", "1"])
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("hn-cli binary runs");
+        while server.requests("item/2.json") == 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let killed = Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(killed.success());
+        let output = child.wait_with_output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(130), "{stderr}");
+        assert!(stdout.contains("Story 1"), "{stdout}");
+        assert!(!stdout.contains("Story 3"), "{stdout}");
+        assert_eq!(server.requests("item/3.json"), 0);
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that long output on a terminal goes through `$PAGER`, and that
+    /// piped output and `--no-pager` skip it.
+    /// Comments: `script` gives the run a terminal; the util-linux flags
+    /// differ from the BSD ones, so this only runs on Linux.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_e2e_pager() {
+        let story =
+            |id: u32| json!({ "id": id, "title": format!("Story {id}"), "score": 1, "by": "a" });
+        let mut server =
+            FixtureServer::start().json("topstories.json", json!((1..=12).collect::<Vec<_>>()));
+        for id in 1..=12 {
+            server = server.json(&format!("item/{id}.json"), story(id));
+        }
+        let home = std::env::temp_dir().join(format!("hn-cli-pager-{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+        let paged = home.join("paged");
+        let tee = format!("tee {}", paged.display());
+        let base = format!(
+            "{} --base-url {} --deterministic --no-progress",
+            env!("CARGO_BIN_EXE_hn-cli"),
+            server.url
+        );
+        let on_terminal = |extra: &str| {
+            let _ = std::fs::remove_file(&paged);
+            let output = Command::new("script")
+                .args(["-qec", &format!("{base} {extra}"), "/dev/null"])
+                .env("PAGER", &tee)
+                .env("XDG_CACHE_HOME", &home)
+                .env("XDG_STATE_HOME", &home)
+                .stdin(std::process::Stdio::null())
+                .output()
+                .expect("script runs");
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            std::fs::read_to_string(&paged).ok()
+        };
+
+        let shown = on_terminal("").expect("the pager ran");
+        assert!(shown.contains("Story 12"), "{shown}");
+        assert_eq!(on_terminal("--no-pager"), None);
+        assert_eq!(on_terminal("--count 2"), None);
+
+        let _ = std::fs::remove_file(&paged);
+        let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args(["--base-url", &server.url, "--deterministic"])
+            .env("PAGER", &tee)
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .output()
+            .expect("hn-cli binary runs");
+        assert!(String::from_utf8_lossy(&output.stdout).contains("Story 12"));
+        assert!(!paged.exists());
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that the progress bar's message counts the requests in flight
+    /// and names the story being fetched, then the one just fetched.
+    /// Comments: the bar is only drawn on a terminal; see `test_e2e_pager`.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_e2e_progress_message() {
+        let story =
+            |id: u32| json!({ "id": id, "title": format!("Story {id}"), "score": 1, "by": "a" });
+        let slow = |id: u32| Reply::Slow(Duration::from_millis(200), story(id));
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2]))
+            .route("item/1.json", slow(1))
+            .route("item/2.json", slow(2));
+        let home = std::env::temp_dir().join(format!("hn-cli-progress-{}", std::process::id()));
+        let command = format!(
+            "{} --base-url {} --no-pager --progress --jobs 2 --progress-template '{{msg}}'",
+            env!("CARGO_BIN_EXE_hn-cli"),
+            server.url
+        );
+        let output = Command::new("script")
+            .args(["-qec", &command, "/dev/null"])
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .expect("script runs");
+        let shown = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{shown}");
+        // Comments: the bar redraws at most 20 times a second, so which of
+        // the messages are drawn depends on timing.
+        assert!(shown.contains(" in flight] fetching #"), "{shown}");
+        assert!(shown.contains(" in flight] Story "), "{shown}");
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test the automatic defaults: on a terminal the header, progress bar
+    /// and closing line are shown, until `--quiet` or `--no-progress` hides
+    /// them; piped, only the stories are printed, until `--no-quiet`.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_e2e_quiet_defaults() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-quiet-{}", std::process::id()));
+        let on_terminal = |extra: &str| -> String {
+            let command = format!(
+                "{} --base-url {} --no-pager --progress-template '{{pos}} in a bar' {extra}",
+                env!("CARGO_BIN_EXE_hn-cli"),
+                server.url
+            );
+            let output = Command::new("script")
+                .args(["-qec", &command, "/dev/null"])
+                .env("XDG_CACHE_HOME", &home)
+                .env("XDG_STATE_HOME", &home)
+                .stdin(std::process::Stdio::null())
+                .output()
+                .expect("script runs");
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        let shown = on_terminal("");
+        for part in [
+            "Hacker News CLI",
+            " in a bar",
+            "Done!",
+            "My YC app: Dropbox",
+        ] {
+            assert!(shown.contains(part), "{part}: {shown}");
+        }
+        let shown = on_terminal("--no-progress");
+        assert!(!shown.contains(" in a bar"), "{shown}");
+        assert!(shown.contains("Done!"), "{shown}");
+        let shown = on_terminal("--quiet");
+        for part in ["Hacker News CLI", " in a bar", "Done!"] {
+            assert!(!shown.contains(part), "{part}: {shown}");
+        }
+        assert!(shown.contains("My YC app: Dropbox"), "{shown}");
+
+        let run = server.run(&[]);
+        assert!(!run.stdout.contains("Hacker News CLI"), "{}", run.stdout);
+        assert!(!run.stdout.contains("Done!"), "{}", run.stdout);
+        let run = server.run(&["--no-quiet"]);
+        assert!(run.stdout.contains("Hacker News CLI"), "{}", run.stdout);
+        assert!(run.stdout.contains("Done!"), "{}", run.stdout);
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `--watch --score-delta` shows nothing the first time, then
+    /// each story's score change since the previous refresh, and `NEW` for
+    /// a story that just entered the list.
+    #[cfg(unix)]
+    #[test]
+    fn test_e2e_score_delta() {
+        let story = |id: u32, score: i32| json!({ "id": id, "title": format!("Story {id}"), "score": score, "by": "a" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("topstories.json", json!([1, 2, 3, 4]))
+            .json("item/1.json", story(1, 10))
+            .json("item/1.json", story(1, 15))
+            .json("item/2.json", story(2, 7))
+            .json("item/2.json", story(2, 4))
+            .json("item/3.json", story(3, 2))
+            .json("item/4.json", story(4, 1));
+        let home = std::env::temp_dir().join(format!("hn-cli-delta-{}", std::process::id()));
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args(["--base-url", &server.url, "--deterministic", "--compact"])
+            .args(["--watch", "1", "--score-delta"])
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .expect("hn-cli binary runs");
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut lines = Vec::new();
+        while lines.len() < 7 {
+            let mut line = String::new();
+            assert!(stdout.read_line(&mut line).unwrap() > 0, "{lines:?}");
+            lines.push(line.trim_end().to_string());
+        }
+
+        let killed = Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(killed.success());
+        assert_eq!(child.wait().unwrap().code(), Some(130));
+        assert_eq!(lines, [
+            " 1. [ 10 ] Story 1 by a",
+            " 2. [ 7  ] Story 2 by a",
+            " 3. [ 2  ] Story 3 by a",
+            " 1. [ 15 ] +5 Story 1 by a",
+            " 2. [ 4  ] -3 Story 2 by a",
+            " 3. [ 2  ] ±0 Story 3 by a",
+            " 4. [ 1  ] NEW Story 4 by a",
+        ]);
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `--require` fails the run with exit code 4 when fewer
+    /// stories than asked for are shown, after printing them.
+    #[test]
+    fn test_e2e_require() {
+        let server = front_page();
+        let run = server.run(&["--require", "3"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+
+        let run = server.run(&["--require", "4"]);
+        assert_eq!(run.code, Some(4), "{}", run.stderr);
+        assert!(run.stdout.contains("My YC app: Dropbox"), "{}", run.stdout);
+        assert!(
+            run.stderr
+                .contains("only 3 of the 4 required stories were fetched"),
+            "{}",
+            run.stderr
+        );
+
+        let run = server.run(&["--require", "2", "--min-score", "100"]);
+        assert_eq!(run.code, Some(4), "{}", run.stderr);
+    }
+
+    /// Test that malformed JSON is a decode failure that isn't retried, and
+    /// that a broken feed fails the whole run with its exit code.
+    #[test]
+    fn test_e2e_malformed() {
+        let server = front_page().replace("item/8863.json", Reply::Malformed);
+        let run = server.run(&["--count", "1", "--fail-fast"]);
+        assert_eq!(run.code, Some(5), "{}", run.stderr);
+        assert_eq!(server.requests("item/8863.json"), 1);
+
+        let server = FixtureServer::start().route("topstories.json", Reply::Malformed);
+        assert_eq!(server.run(&[]).code, Some(5));
+
+        let server = FixtureServer::start().route("topstories.json", Reply::Status(503));
+        let run = server.run(&["--retries", "2"]);
+        assert_eq!(run.code, Some(3), "{}", run.stderr);
+        assert_eq!(server.requests("topstories.json"), 3);
+    }
+
+    /// Test that a server rate limiting past the `Retry-After` cap aborts the
+    /// run with its own exit code.
+    #[test]
+    fn test_e2e_rate_limited() {
+        let server =
+            FixtureServer::start().route("topstories.json", Reply::RetryAfter(429, "3600"));
+        let run = server.run(&[]);
+        assert_eq!(run.code, Some(8), "{}", run.stderr);
+        assert_eq!(
+            run.stderr,
+            "error: rate limited by server (asked to wait 3600s)\n"
+        );
+
+        let server = front_page().replace("item/121003.json", Reply::RetryAfter(503, "3600"));
+        let run = server.run(&["--max-retry-after", "1"]);
+        assert_eq!(run.code, Some(8), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("item 121003 failed to fetch: rate limited by server"),
+            "{}",
+            run.stderr
+        );
+    }
+
+    /// Test that responses are requested gzipped and decoded transparently,
+    /// that `--no-compression` stops asking, and that servers ignoring the
+    /// header still work.
+    #[test]
+    fn test_e2e_gzip() {
+        let server = FixtureServer::start()
+            .route("topstories.json", Reply::Gzip(json!([8863])))
+            .route(
+                "item/8863.json",
+                Reply::Gzip(json!({
+                    "id": 8863, "title": "My YC app: Dropbox", "score": 111, "by": "dhouston"
+                })),
+            );
+
+        let run = server.run(&["--compact"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout, " 1. [111 ] My YC app: Dropbox by dhouston\n");
+        assert!(
+            server
+                .header_values("accept-encoding")
+                .iter()
+                .all(|accepted| accepted
+                    .as_deref()
+                    .is_some_and(|value| value.contains("gzip"))),
+            "{:?}",
+            server.header_values("accept-encoding")
+        );
+
+        let plain = FixtureServer::start()
+            .route("topstories.json", Reply::Gzip(json!([8863])))
+            .json(
+                "item/8863.json",
+                json!({
+                    "id": 8863, "title": "My YC app: Dropbox", "score": 111, "by": "dhouston"
+                }),
+            );
+        let run = plain.run(&["--compact", "--no-compression"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout, " 1. [111 ] My YC app: Dropbox by dhouston\n");
+        assert_eq!(plain.header_values("accept-encoding"), [None, None]);
+    }
+
+    /// Test that `--type-icons` tells stories, Ask HN posts and jobs apart,
+    /// in ASCII under `--ascii`, and that `--no-type-icons` overrides the
+    /// config file.
+    #[test]
+    fn test_e2e_type_icons() {
+        let server = front_page();
+        let titles = |run: &Run| -> Vec<String> {
+            run.stdout
+                .lines()
+                .filter_map(|line| line.split_once("] ").map(|(_, rest)| rest))
+                .filter_map(|rest| rest.split_whitespace().next().map(str::to_string))
+                .collect()
+        };
+
+        let run = server.run(&["--compact"]);
+        assert_eq!(titles(&run), ["My", "ASK", "Justin.tv"], "{}", run.stdout);
+        let run = server.run(&["--compact", "--type-icons"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(titles(&run), ["📰", "❓", "💼"], "{}", run.stdout);
+        let run = server.run(&["--type-icons", "--ascii"]);
+        assert_eq!(
+            titles(&run),
+            ["[story]", "[ask]", "[job]"],
+            "{}",
+            run.stdout
+        );
+
+        let config = std::env::temp_dir().join(format!("hn-cli-icons-{}.toml", std::process::id()));
+        std::fs::write(&config, "type_icons = true\n").unwrap();
+        let run = server.run(&["--compact", "--config", config.to_str().unwrap()]);
+        assert_eq!(titles(&run), ["📰", "❓", "💼"], "{}", run.stdout);
+        let run = server.run(&[
+            "--compact",
+            "--no-type-icons",
+            "--config",
+            config.to_str().unwrap(),
+        ]);
+        assert_eq!(titles(&run), ["My", "ASK", "Justin.tv"], "{}", run.stdout);
+        std::fs::remove_file(&config).unwrap();
+    }
+
+    /// Test that `--type-breakdown` prints how many of each type the results
+    /// hold to stderr, most common first, leaving stdout as it was.
+    #[test]
+    fn test_e2e_type_breakdown() {
+        let server = front_page();
+        let plain = server.run(&["--format", "json"]);
+        let run = server.run(&["--format", "json", "--type-breakdown"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout, plain.stdout);
+        assert!(
+            run.stderr.contains("Types: 2 stories, 1 job\n"),
+            "{}",
+            run.stderr
+        );
+
+        let run = server.run(&["--type-breakdown", "--min-score", "10", "--format", "json"]);
+        assert!(run.stderr.contains("Types: 2 stories\n"), "{}", run.stderr);
+        let run = server.run(&[
+            "--type-breakdown",
+            "--min-score",
+            "1000",
+            "--format",
+            "json",
+        ]);
+        assert!(run.stderr.contains("Types: none\n"), "{}", run.stderr);
+
+        let run = server.run(&["--type-breakdown", "--watch", "5"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+    }
+
+    /// Test that `--no-jobs` leaves out job postings, and that the JSON
+    /// output marks Ask HN and Show HN posts.
+    #[test]
+    fn test_e2e_no_jobs() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([121_003, 192_327, 5]))
+            .json(
+                "item/121003.json",
+                json!({ "id": 121_003, "type": "story", "title": "Ask HN: The Arc Effect" }),
+            )
+            .json(
+                "item/192327.json",
+                json!({ "id": 192_327, "type": "job", "title": "Justin.tv is hiring" }),
+            )
+            .json(
+                "item/5.json",
+                json!({
+                    "id": 5, "type": "story", "title": "show hn: A thing I made", "score": 3,
+                    "by": "maker", "url": "https://example.com/thing"
+                }),
+            );
+        let ids = |run: &Run| -> Vec<u64> {
+            let listing: Value = serde_json::from_str(&run.stdout).unwrap();
+            listing
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| s["id"].as_u64().unwrap())
+                .collect()
+        };
+
+        let run = server.run(&["--format", "json"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(ids(&run), [121_003, 192_327, 5]);
+        let listing: Value = serde_json::from_str(&run.stdout).unwrap();
+        let flags: Vec<_> = listing
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| (s["ask"].as_bool().unwrap(), s["show"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(flags, [(true, false), (false, false), (false, true)]);
+
+        let run = server.run(&["--format", "json", "--no-jobs"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(ids(&run), [121_003, 5]);
+
+        let run = server.run(&["--compact", "--no-jobs"]);
+        assert_eq!(
+            run.stdout,
+            " 1. [ 0  ] ASK Ask HN: The Arc Effect by \n 2. [ 3  ] SHOW show hn: A thing I made \
+             (example.com) by maker\n"
+        );
+        let run = server.run(&["--compact", "--no-jobs", "--type-icons"]);
+        assert!(!run.stdout.contains("SHOW"), "{}", run.stdout);
+
+        let run = server.run(&["--explain", "--no-jobs"]);
+        assert!(run.stdout.contains("no job postings"), "{}", run.stdout);
+    }
+
+    /// Test that `--format badge` prints one value of the top story and
+    /// nothing else, and that `--badge-field` needs it.
+    #[test]
+    fn test_e2e_badge() {
+        let server = front_page();
+        let run = server.run(&["--format", "badge"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout, "111\n");
+        for (field, value) in [
+            ("score", "111"),
+            ("title", "My YC app: Dropbox"),
+            ("comments", "71"),
+            ("url", "http://www.getdropbox.com/u/2/screencast.html"),
+            ("id", "8863"),
+            ("by", "dhouston"),
+        ] {
+            let run = server.run(&["--format", "badge", "--badge-field", field]);
+            assert_eq!(run.stdout, format!("{value}\n"), "{field}: {}", run.stderr);
+        }
+
+        let run = server.run(&["-f", "badge", "--badge-field", "url", "--text-only"]);
+        assert_eq!(run.stdout, "https://news.ycombinator.com/item?id=121003\n");
+        let run = server.run(&["-f", "badge", "--min-score", "1000"]);
+        assert_eq!(run.code, Some(4), "{}", run.stderr);
+        assert_eq!(run.stdout, "");
+
+        let run = server.run(&["--badge-field", "title"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        let run = server.run(&["-f", "badge", "user", "pg"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+    }
+
+    /// Test that `--min-karma` leaves out stories of low-karma submitters,
+    /// looking each one up once and reusing the cached profile on later
+    /// runs, that a failed lookup keeps the story, and that the summary
+    /// counts what it left out.
+    #[test]
+    fn test_e2e_min_karma() {
+        let server = front_page()
+            .json(
+                "user/dhouston.json",
+                json!({ "id": "dhouston", "created": 1_175_714_200, "karma": 5000 }),
+            )
+            .json(
+                "user/tel.json",
+                json!({ "id": "tel", "created": 1_175_714_200, "karma": 3 }),
+            );
+        let home = std::env::temp_dir().join(format!("hn-cli-karma-{}", std::process::id()));
+        let ids = |run: &Run| -> Vec<u64> {
+            let report: Value = serde_json::from_str(&run.stdout).unwrap();
+            report["stories"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| s["id"].as_u64().unwrap())
+                .collect()
+        };
+
+        let run = server.run_in(&home, &["--format", "json", "--min-karma", "100", "-v"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(ids(&run), [8863, 192_327]);
+        assert!(run.stderr.contains("karma lookup failed"), "{}", run.stderr);
+        for user in ["dhouston", "tel", "justin"] {
+            assert_eq!(server.requests(&format!("user/{user}.json")), 1, "{user}");
+        }
+
+        let run = server.run_in(&home, &["--min-karma", "100", "--no-quiet"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stdout.contains(
+                "Done! 2 stories, 1 left out by --min-karma · 30 requested, 4 available, 3 \
+                 fetched, 2 shown"
+            ),
+            "{}",
+            run.stdout
+        );
+        assert_eq!(server.requests("user/dhouston.json"), 1);
+        assert_eq!(server.requests("user/tel.json"), 1);
+        assert_eq!(server.requests("user/justin.json"), 2);
+
+        let run = server.run_in(&home, &["--format", "json", "--min-karma", "1"]);
+        assert_eq!(ids(&run), [8863, 121_003, 192_327]);
+        let run = server.run_in(&home, &[
+            "--format",
+            "json",
+            "--min-karma",
+            "100",
+            "--offline",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(ids(&run), [8863, 192_327]);
+        std::fs::remove_dir_all(&home).unwrap();
+
+        let run = server.run(&["--min-karma", "100", "--stream"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        let run = server.run(&["--explain", "--min-karma", "100"]);
+        assert!(
+            run.stdout.contains("submitter karma at least 100"),
+            "{}",
+            run.stdout
+        );
+    }
+
+    /// Test that `comments` prints the reply tree indented by level, keeps a
+    /// deleted comment only for its replies, honors `--depth`, and nests the
+    /// replies in JSON.
+    #[test]
+    fn test_e2e_comments() {
+        let server = front_page()
+            .replace(
+                "item/8863.json",
+                Reply::Json(json!({
+                    "id": 8863, "type": "story", "title": "My YC app: Dropbox", "score": 111,
+                    "by": "dhouston", "descendants": 4, "kids": [10, 11, 14]
+                })),
+            )
+            .json(
+                "item/10.json",
+                json!({ "id": 10, "type": "comment", "by": "alice", "text": "First!", "kids": [12] }),
+            )
+            .json(
+                "item/11.json",
+                json!({ "id": 11, "type": "comment", "deleted": true, "kids": [13] }),
+            )
+            .json(
+                "item/12.json",
+                json!({ "id": 12, "type": "comment", "by": "bob", "text": "Second<p>Paragraph" }),
+            )
+            .json(
+                "item/13.json",
+                json!({ "id": 13, "type": "comment", "by": "carol", "text": "Orphan" }),
+            )
+            .json("item/14.json", json!({ "id": 14, "type": "comment", "deleted": true }));
+
+        let run = server.run(&["comments", "8863"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            "My YC app: Dropbox\nby dhouston — 4 comments\n\nalice\nFirst!\n\n    bob\n    \
+             Second\n\n    Paragraph\n\n[deleted]\n\n    carol\n    Orphan\n"
+        );
+
+        let run = server.run(&["comments", "8863", "--depth", "1"]);
+        assert_eq!(
+            run.stdout,
+            "My YC app: Dropbox\nby dhouston — 4 comments\n\nalice\nFirst!\n"
+        );
+        assert_eq!(server.requests("item/12.json"), 1);
+
+        let run = server.run(&["comments", "8863", "--format", "json"]);
+        let discussion: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(discussion["id"], 8863);
+        assert_eq!(discussion["comments"][0]["by"], "alice");
+        assert_eq!(discussion["comments"][0]["replies"][0]["id"], 12);
+        assert_eq!(discussion["comments"][1]["deleted"], true);
+        assert_eq!(discussion["comments"][1]["replies"][0]["by"], "carol");
+        assert_eq!(discussion["comments"].as_array().unwrap().len(), 2);
+
+        let run = server.run(&["comments", "8863", "--format", "table"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+
+        let home =
+            std::env::temp_dir().join(format!("hn-cli-repl-comments-{}", std::process::id()));
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args(["--base-url", &server.url, "--deterministic", "repl"])
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("hn-cli binary runs");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"top 1\ncomments 1\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(output.status.code(), Some(0), "{stdout}");
+        assert!(
+            stdout.contains(
+                "My YC app: Dropbox\nby dhouston — 4 comments\n\nalice\nFirst!\n\n    bob\n"
+            ),
+            "{stdout}"
+        );
+        assert!(!stdout.contains("Opened"), "{stdout}");
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `--since-id` drops the ids at or below it from the newest
+    /// stories, without fetching them, and says where to resume.
+    #[test]
+    fn test_e2e_since_id() {
+        let story =
+            |id: u32| json!({ "id": id, "title": format!("Story {id}"), "score": 1, "by": "a" });
+        let server = FixtureServer::start()
+            .json("newstories.json", json!([10, 9, 8, 7]))
+            .json("item/10.json", story(10))
+            .json("item/9.json", story(9))
+            .json("item/8.json", story(8))
+            .json("item/7.json", story(7));
+
+        let run = server.run(&["--sort", "latest", "--since-id", "8", "--no-quiet"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("Story 10"), "{}", run.stdout);
+        assert!(run.stdout.contains("Story 9"), "{}", run.stdout);
+        assert!(!run.stdout.contains("Story 8"), "{}", run.stdout);
+        assert!(
+            run.stdout
+                .contains("Newest id: 10 (pass --since-id 10 next run)\n"),
+            "{}",
+            run.stdout
+        );
+        assert_eq!(server.requests("item/8.json"), 0);
+        assert_eq!(server.requests("item/7.json"), 0);
+
+        let run = server.run(&["--sort", "latest", "--since-id", "8", "--format", "json"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let report: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(report["newest"], 10);
+        assert_eq!(report["stories"][0]["id"], 10);
+        assert_eq!(report["stories"].as_array().unwrap().len(), 2);
+        let run = server.run(&["--sort", "latest", "--since-id", "10", "--format", "json"]);
+        let report: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(report["newest"], 10);
+        assert_eq!(report["stories"], json!([]));
+        let run = server.run(&["--sort", "latest", "--since-id", "8", "--format", "yaml"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.starts_with("stories:\n"), "{}", run.stdout);
+        assert!(run.stdout.ends_with("newest: 10\n"), "{}", run.stdout);
+
+        let run = server.run(&["--since-id", "8"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+    }
+
+    /// Test that `--backend algolia` looks the feed's stories up with one
+    /// search request, and falls back to the items endpoint for the ids the
+    /// search didn't return.
+    #[test]
+    fn test_e2e_algolia_backend() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([8863, 121_003, 5]))
+            .json(
+                "search?tags=(story_8863,story_121003,story_5)&hitsPerPage=3",
+                json!({ "hits": [
+                    { "objectID": "121003", "title": "Ask HN: The Arc Effect", "points": 25,
+                      "author": "tel", "_tags": ["story", "ask_hn"] },
+                    { "objectID": "8863", "title": "My YC app: Dropbox", "points": 111,
+                      "author": "dhouston", "num_comments": 71, "_tags": ["story"],
+                      "url": "http://www.getdropbox.com/u/2/screencast.html" },
+                ] }),
+            )
+            .json(
+                "items/5",
+                json!({ "id": 5, "type": "story", "title": "Five", "points": 3, "author": "e" }),
+            );
+        let algolia = server.url.clone();
+        let run = server.run(&[
+            "--algolia-url",
+            &algolia,
+            "--backend",
+            "algolia",
+            "--format",
+            "json",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let stories: Value = serde_json::from_str(&run.stdout).unwrap();
+        let ids: Vec<_> = stories
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|story| {
+                (
+                    story["id"].as_u64().unwrap(),
+                    story["score"].as_i64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(ids, [(8863, 111), (121_003, 25), (5, 3)]);
+        assert_eq!(server.requests("items/5"), 1);
+        assert_eq!(server.requests("items/8863"), 0);
+        assert_eq!(server.requests("item/8863.json"), 0);
+    }
+
+    /// Test that `search` goes through Algolia with the words as one query
+    /// and prints the hits in the story formats.
+    #[test]
+    fn test_e2e_search() {
+        let hits = json!({ "hits": [{
+            "objectID": "8863", "title": "My YC app: Dropbox", "points": 111,
+            "author": "dhouston", "num_comments": 71,
+            "url": "http://www.getdropbox.com/u/2/screencast.html"
+        }] });
+        let server = FixtureServer::start()
+            .json(
+                "search?query=dropbox+sync&tags=story&hitsPerPage=5&page=0",
+                hits.clone(),
+            )
+            .json(
+                "search?query=dropbox&tags=story&hitsPerPage=30&page=0",
+                hits,
+            );
+        let algolia = server.url.clone();
+        let run = server.run(&[
+            "--algolia-url",
+            &algolia,
+            "search",
+            "dropbox",
+            "sync",
+            "--count",
+            "5",
+            "--format",
+            "json",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let stories: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(stories[0]["id"], 8863);
+        assert_eq!(stories[0]["score"], 111);
+
+        let run = server.run(&["--algolia-url", &algolia, "search", "dropbox", "--no-quiet"]);
+        assert!(
+            run.stdout.contains("Found: 1 story matching 'dropbox'"),
+            "{}",
+            run.stdout
+        );
+        let run = server.run(&["search"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+    }
+
+    /// Test that `--color always` colors piped output, and `--color never` or
+    /// `--deterministic` keep it plain.
+    #[test]
+    fn test_e2e_color() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-color-{}", std::process::id()));
+        // Comments: `run` passes --deterministic, which means no colors.
+        let colored = |args: &[&str]| -> (Option<i32>, bool) {
+            let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+                .args(["--base-url", &server.url, "--no-pager", "--compact"])
+                .args(args)
+                .env("XDG_CACHE_HOME", &home)
+                .env("XDG_CONFIG_HOME", &home)
+                .env("XDG_STATE_HOME", &home)
+                .env_remove("NO_COLOR")
+                .output()
+                .expect("hn-cli binary runs");
+            (
+                output.status.code(),
+                String::from_utf8(output.stdout).unwrap().contains('\x1b'),
+            )
+        };
+
+        assert_eq!(colored(&[]), (Some(0), false));
+        assert_eq!(colored(&["--color", "always"]), (Some(0), true));
+        assert_eq!(colored(&["--color", "never"]), (Some(0), false));
+        assert_eq!(
+            colored(&["--color", "always", "--deterministic"]),
+            (Some(0), false)
+        );
+        assert_eq!(
+            colored(&["item", "8863", "--color", "always"]),
+            (Some(0), true)
+        );
+        assert_eq!(colored(&["--color", "sometimes"]).0, Some(2));
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that the config file's rate limit applies and shows up in the
+    /// timing report, and that a broken config file is a usage error.
+    #[test]
+    fn test_e2e_config_rate_limit() {
+        let server = front_page();
+        let config =
+            std::env::temp_dir().join(format!("hn-cli-config-{}.toml", std::process::id()));
+
+        std::fs::write(&config, "rate_limit = 2\n").unwrap();
+        let run = server.run(&["--config", config.to_str().unwrap(), "--timing"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stderr.contains("requests throttled by --rate-limit"),
+            "{}",
+            run.stderr
+        );
+
+        std::fs::write(&config, "rate_limt = 2\n").unwrap();
+        let run = server.run(&["--config", config.to_str().unwrap()]);
+        assert_eq!(run.code, Some(2));
+        assert!(
+            run.stderr.contains("unknown field `rate_limt`"),
+            "{}",
+            run.stderr
+        );
+        std::fs::remove_file(&config).unwrap();
+    }
+
+    /// Test that `config check` accepts a valid config file, reports a bad
+    /// value with its line even though a run would fail on it first, and
+    /// treats a missing default file as the defaults.
+    #[test]
+    fn test_e2e_config_check() {
+        let server = FixtureServer::start();
+        let home = std::env::temp_dir().join(format!("hn-cli-config-check-{}", std::process::id()));
+        let run = server.run_in(&home, &["config", "check"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("the defaults apply"), "{}", run.stdout);
+
+        let config = home.join("hn-cli").join("config.toml");
+        std::fs::create_dir_all(config.parent().unwrap()).unwrap();
+        std::fs::write(
+            &config,
+            "rate_limit = 5\n[profiles.rust]\nfilter = [\"rust\"]\n",
+        )
+        .unwrap();
+        let run = server.run_in(&home, &["config", "check"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("is valid, 1 profile"), "{}", run.stdout);
+
+        std::fs::write(&config, "[profiles.rust]\nsort = [\"top\"]\n").unwrap();
+        let run = server.run_in(&home, &["config", "check"]);
+        assert_eq!(run.code, Some(2));
+        assert!(run.stderr.contains("line 2, column 9"), "{}", run.stderr);
+        assert!(
+            run.stderr.contains("unknown variant `top`"),
+            "{}",
+            run.stderr
+        );
+        assert!(run.stdout.is_empty());
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `--user-agent` and repeated `--header` flags reach every
+    /// request, and that a malformed header is a usage error.
+    #[test]
+    fn test_e2e_headers() {
+        let server = front_page();
+        let run = server.run(&[
+            "--count",
+            "2",
+            "--user-agent",
+            "hn-bot/1.0",
+            "--header",
+            "X-Egress-Token: s3cret",
+            "--header",
+            "x-team:hn",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(server.header_values("user-agent"), vec![
+            Some(
+                "hn-bot/1.0".to_string()
+            );
+            3
+        ]);
+        assert_eq!(server.header_values("x-egress-token"), vec![
+            Some(
+                "s3cret".to_string()
+            );
+            3
+        ]);
+        assert_eq!(server.header_values("X-Team"), vec![
+            Some("hn".to_string());
+            3
+        ]);
+
+        let run = server.run(&["--header", "X Egress: s3cret"]);
+        assert_eq!(run.code, Some(2));
+        assert!(
+            run.stderr.contains("invalid header name 'X Egress'"),
+            "{}",
+            run.stderr
+        );
+        let run = server.run(&["--header", "X-Egress"]);
+        assert_eq!(run.code, Some(2));
+        assert!(
+            run.stderr.contains("expected 'Name: value'"),
+            "{}",
+            run.stderr
+        );
+    }
+
+    /// Test that `--ipv6` keeps the client off the IPv4-only fixture server
+    /// while `--ipv4` reaches it.
+    #[test]
+    fn test_e2e_ip_family() {
+        let server = front_page();
+
+        let run = server.run(&["--ipv4", "--count", "1"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+
+        let run = server.run(&["--ipv6", "--count", "1"]);
+        assert_eq!(run.code, Some(3), "{}", run.stderr);
+        assert_eq!(server.requests("topstories.json"), 1);
+    }
+
+    /// Test that `--timestamp` puts the fetch time in the header, in local
+    /// time or, with `--utc`, in UTC, and that `--utc` needs `--timestamp`.
+    #[test]
+    fn test_e2e_timestamp() {
+        let server = front_page();
+        let header = |args: &[&str]| -> String {
+            let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+                .args([
+                    "--base-url",
+                    &server.url,
+                    "--no-pager",
+                    "--no-quiet",
+                    "--count",
+                    "1",
+                ])
+                .args(args)
+                .env("TZ", "IST-5:30")
+                .env("NO_COLOR", "1")
+                .env(
+                    "XDG_CACHE_HOME",
+                    std::env::temp_dir().join("hn-cli-timestamp"),
+                )
+                .env(
+                    "XDG_STATE_HOME",
+                    std::env::temp_dir().join("hn-cli-timestamp"),
+                )
+                .output()
+                .expect("hn-cli binary runs");
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            String::from_utf8(output.stdout)
+                .unwrap()
+                .lines()
+                .nth(1)
+                .unwrap()
+                .to_string()
+        };
+        let time = |header: &str, format: &str| {
+            let time = header
+                .strip_prefix(" 🧡 Hacker News CLI — ")
+                .and_then(|rest| rest.strip_suffix(' '))
+                .unwrap_or_else(|| panic!("unexpected header {header:?}"));
+            chrono::NaiveDateTime::parse_from_str(time, format)
+                .unwrap_or_else(|e| panic!("{time:?}: {e}"))
+        };
+
+        assert_eq!(header(&[]), " 🧡 Hacker News CLI ");
+        let utc = time(&header(&["--timestamp", "--utc"]), "%Y-%m-%d %H:%M UTC");
+        let now = chrono::Utc::now().naive_utc();
+        assert!((now - utc).num_minutes().abs() <= 1, "{utc} vs {now}");
+        let local = time(&header(&["--timestamp"]), "%Y-%m-%d %H:%M");
+        let offset = (local - utc).num_minutes();
+        assert!((329..=331).contains(&offset), "{local} vs {utc}");
+
+        let run = server.run(&["--utc"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+    }
+
+    /// Test that `--deterministic` wins over flags that would make output
+    /// vary between runs: the header time is raw Unix seconds, color and the
+    /// progress bar stay off even when forced.
+    #[test]
+    fn test_e2e_deterministic() {
+        let run = front_page().run(&["--timestamp", "--no-quiet", "--progress", "--count", "1"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let header = run.stdout.lines().nth(1).unwrap();
+        let time = header
+            .strip_prefix(" 🧡 Hacker News CLI — ")
+            .and_then(|rest| rest.strip_suffix(' '))
+            .unwrap_or_else(|| panic!("unexpected header {header:?}"));
+        assert!(time.parse::<i64>().is_ok(), "{time:?}");
+        assert!(!run.stdout.contains('\x1b'), "{:?}", run.stdout);
+        assert!(run.stderr.is_empty(), "{:?}", run.stderr);
+    }
+
+    /// Test that `last` reprints the saved listing in another format without
+    /// asking the server again.
+    #[test]
+    fn test_e2e_last() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-last-{}", std::process::id()));
+
+        let run = server.run_in(&home, &["last"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stdout.starts_with("No saved listing yet."),
+            "{}",
+            run.stdout
+        );
+
+        let run = server.run_in(&home, &["--count", "2"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let requests = server.requests("item/8863.json");
+
+        let run = server.run_in(&home, &["--format", "json", "last"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let listing: Value = serde_json::from_str(&run.stdout).unwrap();
+        let ids: Vec<_> = listing
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| &s["id"])
+            .collect();
+        assert_eq!(ids, [8863, 121_003]);
+
+        let run = server.run_in(&home, &["--compact", "--quiet", "last"]);
+        assert_eq!(
+            run.stdout,
+            " 1. [111 ] My YC app: Dropbox (getdropbox.com) by dhouston — 71c\n 2. [ 25 ] ASK \
+             Ask HN: The Arc Effect by tel — 16c\n"
+        );
+        assert_eq!(server.requests("item/8863.json"), requests);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `diff` compares the two most recent runs of a feed, or two
+    /// named ones, reporting only moves beyond `--moved-by`.
+    #[test]
+    fn test_e2e_diff() {
+        let mut server =
+            FixtureServer::start().json("topstories.json", json!([1, 2, 3, 4, 5, 6, 7]));
+        for id in 1..=8 {
+            server = server.json(
+                &format!("item/{id}.json"),
+                json!({ "id": id, "title": format!("Story {id}"), "score": id, "by": "pg" }),
+            );
+        }
+        let home = std::env::temp_dir().join(format!("hn-cli-diff-{}", std::process::id()));
+
+        let run = server.run_in(&home, &["diff"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        assert!(run.stderr.contains("found 0"), "{}", run.stderr);
+        assert_eq!(server.run_in(&home, &[]).code, Some(0));
+        let server = server.replace("topstories.json", Reply::Json(json!([7, 1, 2, 3, 4, 5, 8])));
+        assert_eq!(server.run_in(&home, &[]).code, Some(0));
+
+        let run = server.run_in(&home, &["diff"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            "▸ Entered the list 1 story\n  7. Story 8 8 points\n\n▸ Dropped off 1 story\n  6. \
+             Story 6 6 points\n\n▸ Moved more than 5 places 1 story\n  1. Story 7 7 points ↑6 \
+             from 7\n\n"
+        );
+        let run = server.run_in(&home, &["diff", "--moved-by", "0"]);
+        assert_eq!(run.stdout.matches(" from ").count(), 6, "{}", run.stdout);
+
+        let runs = home.join("hn-cli/runs/topstories");
+        let mut saved: Vec<_> = std::fs::read_dir(&runs)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        saved.sort();
+        let last = home.join("hn-cli/last.json");
+        let run = server.run_in(&home, &[
+            "--format",
+            "json",
+            "diff",
+            saved[0].to_str().unwrap(),
+            last.to_str().unwrap(),
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let diff: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(diff["feed"], "topstories");
+        assert_eq!(diff["entered"][0]["to"], 7);
+        assert_eq!(diff["entered"][0]["story"]["id"], 8);
+        assert_eq!(diff["dropped"][0]["from"], 6);
+        assert_eq!(
+            (&diff["moved"][0]["from"], &diff["moved"][0]["to"]),
+            (&json!(7), &json!(1))
+        );
+
+        let run = server.run_in(&home, &["--format", "table", "diff"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `--changed-only` prints only stories no earlier run showed,
+    /// nothing at all with exit code 0 when there are none, and a story
+    /// filtered out before once it passes `--min-score`.
+    #[test]
+    fn test_e2e_changed_only() {
+        let story =
+            |id: u32, score: i32| json!({ "id": id, "title": "Story", "score": score, "by": "pg" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2]))
+            .json("item/1.json", story(1, 50))
+            .json("item/2.json", story(2, 5));
+        let home = std::env::temp_dir().join(format!("hn-cli-changed-{}", std::process::id()));
+        let args = ["--changed-only", "--url-only", "--min-score", "20"];
+
+        let run = server.run_in(&home, &args);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout, "https://news.ycombinator.com/item?id=1\n");
+
+        let run = server.run_in(&home, &args);
+        assert_eq!((run.code, run.stdout.as_str()), (Some(0), ""));
+        let run = server.run_in(&home, &[
+            "--changed-only",
+            "--format",
+            "json",
+            "--min-score",
+            "20",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!((run.stdout.as_str(), run.stderr.as_str()), ("", ""));
+
+        let server = server
+            .replace("topstories.json", Reply::Json(json!([1, 2, 3])))
+            .replace("item/2.json", Reply::Json(story(2, 30)))
+            .json("item/3.json", story(3, 40));
+        let run = server.run_in(&home, &args);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            "https://news.ycombinator.com/item?id=2\nhttps://news.ycombinator.com/item?id=3\n"
+        );
+
+        let server = server.json("newstories.json", json!([1]));
+        let run = server.run_in(&home, &[&args[..], &["--sort", "latest"]].concat());
+        assert_eq!(run.stdout, "https://news.ycombinator.com/item?id=1\n");
+        let run = server.run_in(&home, &["--changed-only", "--no-quiet"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `--count 0` is rejected, that a `--count` beyond the feed
+    /// warns or fails under `--strict`, and that the closing line then tells
+    /// requested, available, fetched and shown apart.
+    #[test]
+    fn test_e2e_count_limits() {
+        let server = front_page();
+        let run = server.run(&["--count", "0"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        assert!(run.stderr.contains("'--count <COUNT>'"), "{}", run.stderr);
+
+        let run = server.run(&["--no-quiet", "--count", "10"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("warning: topstories lists 4 stories, fewer than the 10 asked for"),
+            "{}",
+            run.stderr
+        );
+        assert!(
+            run.stdout
+                .contains("Done! 3 stories · 10 requested, 4 available, 3 fetched, 3 shown"),
+            "{}",
+            run.stdout
+        );
+
+        let run = server.run(&["--count", "10", "--strict"]);
+        assert_eq!(run.code, Some(7), "{}", run.stderr);
+        assert_eq!(server.requests("item/8863.json"), 1);
+
+        let run = server.run(&["--no-quiet", "--count", "2", "--min-score", "100"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(!run.stderr.contains("warning"), "{}", run.stderr);
+        assert!(
+            run.stdout
+                .contains("Done! 1 story · 2 requested, 2 available, 2 fetched, 1 shown"),
+            "{}",
+            run.stdout
+        );
+    }
+
+    /// Test that `--offline` shows the stories an online run cached without
+    /// a single request, and fails with exit code 6 before any were cached.
+    #[test]
+    fn test_e2e_offline() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-offline-{}", std::process::id()));
+
+        let run = server.run_in(&home, &["--offline"]);
+        assert_eq!(run.code, Some(6), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("is not in the offline cache; run once without --offline first"),
+            "{}",
+            run.stderr
+        );
+        assert_eq!(server.requests("topstories.json"), 0);
+
+        let online = server.run_in(&home, &["--format", "json"]);
+        assert_eq!(online.code, Some(0), "{}", online.stderr);
+        let requests = server.requests("topstories.json") + server.requests("item/8863.json");
+        let offline = server.run_in(&home, &["--format", "json", "--offline"]);
+        assert_eq!(offline.code, Some(0), "{}", offline.stderr);
+        assert_eq!(
+            server.requests("topstories.json") + server.requests("item/8863.json"),
+            requests
+        );
+        let ids = |stdout: &str| -> Vec<u64> {
+            let listing: Value = serde_json::from_str(stdout).unwrap();
+            listing["stories"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|story| story["id"].as_u64().unwrap())
+                .collect()
+        };
+        assert_eq!(ids(&offline.stdout), ids(&online.stdout));
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `--timing` summarizes the latencies on stderr, counting
+    /// item requests and retries, and adds a `timing` object to JSON.
+    #[test]
+    fn test_e2e_timing() {
+        let server = front_page().before("item/8863.json", [Reply::Status(500)]);
+        let run = server.run(&["--timing", "--count", "2"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let lines: Vec<&str> = run.stderr.lines().collect();
+        assert_eq!(lines.len(), 3, "{}", run.stderr);
+        assert!(lines[0].starts_with("Timing: "), "{}", lines[0]);
+        assert!(lines[0].contains(" ms for the id list"), "{}", lines[0]);
+        assert!(
+            lines[1].starts_with("  2 item requests: min "),
+            "{}",
+            lines[1]
+        );
+        assert!(
+            lines[2].starts_with("  0 cache hits, 1 retr"),
+            "{}",
+            lines[2]
+        );
+
+        let run = server.run(&["--timing", "--count", "2", "--format", "json"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let report: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(report["stories"].as_array().unwrap().len(), 2);
+        assert_eq!(report["errors"], json!([]));
+        assert_eq!(report["timing"]["items"]["requests"], 2);
+        assert_eq!(report["timing"]["retries"], 0);
+        assert!(report["timing"]["total_ms"].as_f64().unwrap() > 0.0);
+        assert!(run.stderr.is_empty(), "{}", run.stderr);
+    }
+
+    /// Test that `--prefetch` caches the page after the one listed, so an
+    /// `--offline` run can show it, and that a plain run doesn't.
+    #[test]
+    fn test_e2e_prefetch() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-prefetch-{}", std::process::id()));
+        let offline = ["--offline", "--count", "3", "--url-only"];
+
+        let run = server.run_in(&home, &["--count", "2", "--url-only"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let run = server.run_in(&home, &offline);
+        assert!(run.stderr.contains("192327 (not cached)"), "{}", run.stderr);
+
+        let run = server.run_in(&home, &["--count", "2", "--url-only", "--prefetch"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout.lines().count(), 2, "{}", run.stdout);
+        let run = server.run_in(&home, &offline);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.ends_with("item?id=192327\n"), "{}", run.stdout);
+
+        let run = server.run_in(&home, &["--prefetch", "--offline"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that a story that fails to fetch is shown from the cache, marked
+    /// `(cached)`, unless `--prefer-fresh` leaves it out.
+    #[test]
+    fn test_e2e_cached_fallback() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-fallback-{}", std::process::id()));
+        let run = server.run_in(&home, &["--compact"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(!run.stdout.contains("(cached)"), "{}", run.stdout);
+
+        let server = server.replace("item/8863.json", Reply::Malformed);
+        let run = server.run_in(&home, &["--compact"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let line = run
+            .stdout
+            .lines()
+            .find(|line| line.contains("Dropbox"))
+            .unwrap();
+        assert!(line.contains("· (cached)"), "{}", run.stdout);
+        assert!(
+            run.stderr.contains("1 item failed to fetch: 4 (missing)"),
+            "{}",
+            run.stderr
+        );
+
+        let run = server.run_in(&home, &["--format", "json"]);
+        let json: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(json["stories"][0]["id"], 8863);
+        assert_eq!(json["stories"][0]["cached"], true);
+        assert!(json["stories"][1].get("cached").is_none());
+
+        let run = server.run_in(&home, &["--compact", "--prefer-fresh"]);
+        assert!(!run.stdout.contains("Dropbox"), "{}", run.stdout);
+        assert!(
+            run.stderr.contains("2 items failed to fetch"),
+            "{}",
+            run.stderr
+        );
+        let run = server.run_in(&home, &[
+            "--compact",
+            "--prefer-fresh",
+            "--merge-cache-on-partial-failure",
+        ]);
+        assert!(run.stdout.contains("(cached)"), "{}", run.stdout);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `--append` grows the archive by one line per story across
+    /// runs, and that `log top` ranks each story once, skipping a torn line.
+    #[test]
+    fn test_e2e_append() {
+        let server = front_page();
+        let dir = std::env::temp_dir().join(format!("hn-cli-append-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.jsonl");
+        let archive = path.to_str().unwrap();
+
+        for count in ["2", "4"] {
+            let run = server.run(&["--quiet", "--count", count, "--append", archive]);
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+        }
+        let lines: Vec<Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines.iter().all(|line| line["fetched_at"].is_i64()));
+
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(br#"{"fetched_at":1,"id":"#)
+            .unwrap();
+        let run = server.run(&["--format", "json", "log", "top", archive, "--since", "7d"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stderr.contains("skipped 1 unreadable line"),
+            "{}",
+            run.stderr
+        );
+        let top: Value = serde_json::from_str(&run.stdout).unwrap();
+        let ids: Vec<_> = top.as_array().unwrap().iter().map(|s| &s["id"]).collect();
+        assert_eq!(ids, [8863, 121_003, 192_327]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test that `--check-links` annotates every story with its link status,
+    /// falls back from HEAD to a ranged GET, follows redirects and keeps dead
+    /// links in the list.
+    #[test]
+    fn test_e2e_check_links() {
+        let server = FixtureServer::start();
+        let base = server.url.clone();
+        let link = |path: &str| format!("{base}/links/{path}");
+        let story = |id: u32, url: String| json!({ "id": id, "title": format!("Story {id}"), "score": 10, "by": "a", "url": url });
+        let server = server
+            .json("topstories.json", json!([1, 2, 3, 4, 5]))
+            .json("item/1.json", story(1, link("ok")))
+            .json("item/2.json", story(2, link("moved")))
+            .json("item/3.json", story(3, link("head")))
+            .json("item/4.json", story(4, link("gone")))
+            .json("item/5.json", story(5, link("slow")))
+            .json("links/ok", json!(null))
+            .route("links/moved", Reply::Redirect("/v0/links/ok"))
+            .route("links/head", Reply::Status(405))
+            .json("links/head", json!(null))
+            .route("links/gone", Reply::Status(404))
+            .route("links/slow", Reply::Stall(Duration::from_secs(3)))
+            .json(
+                &wayback_path(&link("gone")),
+                json!({ "archived_snapshots": { "closest": {
+                    "available": true, "url": "http://web.archive.org/web/2020/gone"
+                } } }),
+            );
+        let wayback = format!("{base}/wayback");
+
+        let run = server.run(&[
+            "--format",
+            "json",
+            "--timeout",
+            "1",
+            "--user-agent",
+            "link-bot",
+            "--wayback-url",
+            &wayback,
+            "--check-links",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let stories: Value = serde_json::from_str(&run.stdout).unwrap();
+        let statuses: Vec<_> = stories
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|story| &story["link_status"])
+            .collect();
+        assert_eq!(statuses, [
+            &json!({ "status": 200 }),
+            &json!({ "status": 200, "final_url": link("ok") }),
+            &json!({ "status": 200 }),
+            &json!({ "status": 404 }),
+            &json!({ "error": "timeout" }),
+        ]);
+        assert_eq!(server.requests_to("links/head", "range"), [
+            ("HEAD".to_string(), None),
+            ("GET".to_string(), Some("bytes=0-0".to_string()))
+        ]);
+        assert!(
+            server
+                .requests_to("links/ok", "user-agent")
+                .iter()
+                .all(|(_, agent)| agent.as_deref() == Some("link-bot"))
+        );
+
+        let run = server.run(&["--timeout", "1", "--check-links"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        for badge in [
+            "✓ 200",
+            &format!("✓ 200 → {}", link("ok")),
+            "✗ 404",
+            "⚠ timeout",
+        ] {
+            assert!(run.stdout.contains(badge), "{badge}: {}", run.stdout);
+        }
+    }
+
+    /// Test that `--archive-links` looks up every link, caches the copies it
+    /// finds and shows them offline from the cache alone.
+    #[test]
+    fn test_e2e_archive_links() {
+        let link = "http://www.getdropbox.com/u/2/screencast.html";
+        let server = front_page().json(
+            &wayback_path(link),
+            json!({ "archived_snapshots": { "closest": {
+                "available": true, "url": "http://web.archive.org/web/2008/dropbox"
+            } } }),
+        );
+        let wayback = format!("{}/wayback", server.url);
+        let dir = std::env::temp_dir().join(format!("hn-cli-wayback-{}", std::process::id()));
+        let cache_dir = dir.to_str().unwrap();
+
+        for offline in [false, true] {
+            let mut args = vec!["--format", "json", "--cache-dir", cache_dir];
+            args.extend(["--wayback-url", &wayback, "--archive-links"]);
+            if offline {
+                args.push("--offline");
+            }
+            let run = server.run(&args);
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            let listing: Value = serde_json::from_str(&run.stdout).unwrap();
+            let dropbox = &listing["stories"][0];
+            assert_eq!(dropbox["id"], 8863);
+            assert_eq!(
+                dropbox["archive_url"], "http://web.archive.org/web/2008/dropbox",
+                "offline: {offline}"
+            );
+            assert!(listing["stories"][1].get("archive_url").is_none());
+        }
+        assert_eq!(server.requests(&wayback_path(link)), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test that `--pipe-each` feeds every story to the command as JSON, and
+    /// that failing runs are listed and set the exit code.
+    #[cfg(unix)]
+    #[test]
+    fn test_e2e_pipe_each() {
+        let server = front_page();
+        let dir = std::env::temp_dir().join(format!("hn-cli-pipe-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("out.jsonl");
+        let command = format!("cat >> '{}'", out.display());
+
+        let run = server.run(&["--quiet", "--pipe-each", &command]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let ids: Vec<_> = std::fs::read_to_string(&out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["id"].clone())
+            .collect();
+        assert_eq!(ids, [8863, 121_003, 192_327]);
+
+        let run = server.run(&["--quiet", "--pipe-each", r#"test "$HN_STORY_ID" != 121003"#]);
+        assert_eq!(run.code, Some(10), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("--pipe-each failed for 1 of 3 stories: 121003 (exit 1)"),
+            "{}",
+            run.stderr
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test that `--exec` runs the command once per story without a shell,
+    /// each placeholder one argument however many spaces it holds, that
+    /// failures are listed and set the exit code, that `--exec-fail-fast`
+    /// starts no more commands after one fails, and that a bad template is a
+    /// usage error.
+    #[cfg(unix)]
+    #[test]
+    fn test_e2e_exec() {
+        let server = front_page();
+        let dir = std::env::temp_dir().join(format!("hn-cli-exec-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("out.txt");
+        let command = format!(
+            r#"sh -c 'printf "%s|%s|%s\n" "$1" "$2" "$3" >> "$0"' {} {{id}} {{title}} "{{url}}""#,
+            out.display()
+        );
+
+        let run = server.run(&["--quiet", "--exec", &command]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let mut lines: Vec<String> = std::fs::read_to_string(&out)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        lines.sort();
+        assert_eq!(lines, [
+            "121003|Ask HN: The Arc Effect|https://news.ycombinator.com/item?id=121003",
+            "192327|Justin.tv is looking for a Lead Flash Engineer!|https://news.ycombinator.com/item?id=192327",
+            "8863|My YC app: Dropbox|http://www.getdropbox.com/u/2/screencast.html",
+        ]);
+
+        let run = server.run(&["--quiet", "--exec", "test {id} != 121003"]);
+        assert_eq!(run.code, Some(10), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("--exec failed for 1 of 3 stories: 121003 (exit 1)"),
+            "{}",
+            run.stderr
+        );
+        let run = server.run(&[
+            "--quiet",
+            "--jobs",
+            "1",
+            "--exec",
+            "false",
+            "--exec-fail-fast",
+        ]);
+        assert_eq!(run.code, Some(10), "{}", run.stderr);
+        assert!(
+            run.stderr.contains(
+                "--exec failed for 1 of 1 stories: 8863 (exit 1); 2 not run (--exec-fail-fast)"
+            ),
+            "{}",
+            run.stderr
+        );
+
+        let run = server.run(&["--exec", "echo {name}"]);
+        assert_eq!(run.code, Some(2));
+        assert!(
+            run.stderr.contains("unknown placeholder {name}"),
+            "{}",
+            run.stderr
+        );
+        let run = server.run(&["--exec", "echo 'open"]);
+        assert_eq!(run.code, Some(2));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test that `--cache-dir` creates and fills the given directory, that
+    /// `--offline` reads it back, and that an unusable one only warns.
+    #[test]
+    fn test_e2e_cache_dir() {
+        let server = front_page();
+        let dir = std::env::temp_dir().join(format!("hn-cli-cache-dir-{}", std::process::id()));
+        let cache = dir.join("nested").join("cache");
+        let cache_dir = cache.to_str().unwrap();
+
+        let run = server.run(&["--quiet", "--count", "2", "--cache-dir", cache_dir]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(cache.join("items").join("8863.json").is_file());
+
+        let requests = server.requests("topstories.json");
+        let run = server.run(&[
+            "--quiet",
+            "--count",
+            "2",
+            "--offline",
+            "--cache-dir",
+            cache_dir,
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("My YC app: Dropbox"), "{}", run.stdout);
+        assert_eq!(server.requests("topstories.json"), requests);
+
+        let file = dir.join("file");
+        std::fs::write(&file, "").unwrap();
+        let unusable = file.join("cache");
+        let run = server.run(&[
+            "--quiet",
+            "--count",
+            "1",
+            "--cache-dir",
+            unusable.to_str().unwrap(),
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stderr.contains("continuing without a cache"),
+            "{}",
+            run.stderr
+        );
+        assert!(run.stdout.contains("My YC app: Dropbox"), "{}", run.stdout);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test that `crawl` caches every item of its range, comments and deleted
+    /// ones included, skips what is already cached when run again, and that
+    /// `--offline` reads the stories back but not the other items.
+    #[test]
+    fn test_e2e_crawl() {
+        let server = front_page()
+            .json(
+                "item/8864.json",
+                json!({ "id": 8864, "type": "comment", "by": "pg", "parent": 8863, "text": "Nice." }),
+            )
+            .json("item/8865.json", json!({ "id": 8865, "deleted": true, "type": "story" }))
+            .json("item/8866.json", Value::Null);
+        let dir = std::env::temp_dir().join(format!("hn-cli-crawl-{}", std::process::id()));
+        let cache_dir = dir.to_str().unwrap();
+        let crawl = |from: &str| {
+            server.run(&[
+                "--cache-dir",
+                cache_dir,
+                "--no-quiet",
+                "crawl",
+                "--from",
+                from,
+                "--to",
+                "8866",
+                "--jobs",
+                "2",
+            ])
+        };
+
+        let run = crawl("8863");
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stdout
+                .contains("crawled 8863–8866: 3 fetched, 0 already cached, 1 never used, 0 failed"),
+            "{}",
+            run.stdout
+        );
+        for id in [8863, 8864, 8865] {
+            assert!(
+                dir.join("items").join(format!("{id}.json")).is_file(),
+                "{id}"
+            );
+        }
+        assert!(!dir.join("items").join("8866.json").exists());
+
+        let run = crawl("8862");
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stdout
+                .contains("crawled 8862–8866: 0 fetched, 3 already cached, 2 never used, 0 failed"),
+            "{}",
+            run.stdout
+        );
+        assert_eq!(server.requests("item/8863.json"), 1);
+        assert_eq!(server.requests("item/8866.json"), 2);
+
+        std::fs::create_dir_all(dir.join("lists")).unwrap();
+        std::fs::write(
+            dir.join("lists").join("topstories.json"),
+            "[8863, 8864, 8865]",
+        )
+        .unwrap();
+        let run = server.run(&["--cache-dir", cache_dir, "--offline", "--format", "json"]);
+        let listing: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(listing["stories"][0]["id"], 8863, "{listing}");
+        let failed: Vec<_> = listing["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|failure| (&failure["id"], &failure["category"]))
+            .collect();
+        assert_eq!(failed, [
+            (&json!(8864), &json!("decode")),
+            (&json!(8865), &json!("missing"))
+        ]);
+
+        let run = crawl("9000");
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+
+        let failing = FixtureServer::start().route("item/1.json", Reply::Status(500));
+        let run = failing.run(&["--retries", "0", "crawl", "--from", "1", "--to", "1"]);
+        assert_eq!(run.code, Some(7), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("1 item(s) failed to fetch; run the crawl again to retry them"),
+            "{}",
+            run.stderr
+        );
+        assert!(!run.stderr.contains("--strict"), "{}", run.stderr);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Test that `list` takes the same flags as the bare form, that `item`
+    /// and `user` follow the global `--format`, and that listing flags next
+    /// to another command are rejected.
+    #[test]
+    fn test_e2e_subcommands() {
+        let server = front_page().json(
+            "user/dhouston.json",
+            json!({ "id": "dhouston", "created": 1_175_289_467, "karma": 8580, "submitted": [8863] }),
+        );
+        let bare = server.run(&["--count", "2", "--compact", "--quiet"]);
+        let list = server.run(&["--compact", "list", "--count", "2", "--quiet"]);
+        assert_eq!(list.code, Some(0), "{}", list.stderr);
+        assert_eq!(list.stdout, bare.stdout);
+
+        let run = server.run(&["--format", "json", "item", "121003"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let item: Value = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(item["title"], "Ask HN: The Arc Effect");
+        assert_eq!(server.run(&["item", "4"]).code, Some(4));
+
+        let run = server.run(&["user", "dhouston"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            "dhouston [8,580 karma]\n      joined 1175289467, 1 submissions\n"
+        );
+
+        for args in [&["--offline", "doctor"][..], &["--count", "2", "list"]] {
+            let run = server.run(args);
+            assert_eq!(run.code, Some(2), "{args:?}");
+        }
+    }
+
+    /// Test that `bookmarks add` saves a story with its score, and that
+    /// `--refresh` shows each saved score next to the current one, with the
+    /// change, or that the story is gone.
+    #[test]
+    fn test_e2e_bookmarks_refresh() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-bookmarks-{}", std::process::id()));
+        let store = home.join("hn-cli").join("bookmarks.json");
+        let bookmark = |id: u32, title: &str, score: i32| {
+            json!({ "id": id, "title": title, "url": null, "by": "a",
+                    "saved_score": score, "saved_at": 1_700_000_000 })
+        };
+        std::fs::create_dir_all(store.parent().unwrap()).unwrap();
+        std::fs::write(
+            &store,
+            json!([
+                bookmark(8863, "My YC app: Dropbox", 100),
+                bookmark(121_003, "Ask HN: The Arc Effect", 30),
+                bookmark(
+                    192_327,
+                    "Justin.tv is looking for a Lead Flash Engineer!",
+                    6
+                ),
+                bookmark(4, "Deleted story", 5),
+            ])
+            .to_string(),
+        )
+        .unwrap();
+
+        let run = server.run_in(&home, &["bookmarks", "--refresh"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let scores: Vec<&str> = run
+            .stdout
+            .lines()
+            .filter(|line| line.contains(" → "))
+            .collect();
+        assert_eq!(scores, [
+            " 1. [ 100 → 111 ] +11 My YC app: Dropbox",
+            " 2. [  30 → 25  ] -5 Ask HN: The Arc Effect",
+            " 3. [   6 → 6   ] ±0 Justin.tv is looking for a Lead Flash Engineer!",
+            " 4. [   5 → gone] deleted Deleted story",
+        ]);
+
+        let run = server.run_in(&home, &["bookmarks", "remove", "4", "192327"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let run = server.run_in(&home, &["bookmarks", "add", "8863"]);
+        assert!(
+            run.stdout.contains("8863 is already saved"),
+            "{}",
+            run.stdout
+        );
+        let saved: Value = serde_json::from_slice(&std::fs::read(&store).unwrap()).unwrap();
+        let ids: Vec<_> = saved
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bookmark| {
+                (
+                    bookmark["id"].as_u64().unwrap(),
+                    bookmark["saved_score"].as_i64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(ids, [(8863, 100), (121_003, 30)]);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `doctor` walks through its checks against a plain-http base
+    /// URL, skipping the TLS handshake, and fails when a request does.
+    #[test]
+    fn test_e2e_doctor() {
+        let run = front_page().run(&["doctor"]);
+        assert_eq!(run.code, Some(0), "{}", run.stdout);
+        for line in [
+            " ✓ TCP connect on port",
+            " - TLS handshake (skipped)",
+            " ✓ GET topstories.json",
+            "4 ids",
+            "GET item 8863",
+            "My YC app: Dropbox",
+            "All checks passed!",
+        ] {
+            assert!(run.stdout.contains(line), "{line}: {}", run.stdout);
+        }
+
+        let server = FixtureServer::start().json("topstories.json", json!([1, 2]));
+        let run = server.run(&["doctor"]);
+        assert_eq!(run.code, Some(1), "{}", run.stdout);
+        assert!(run.stdout.contains(" ✗ GET item 8863"), "{}", run.stdout);
+        assert!(run.stdout.contains("1 check failed"), "{}", run.stdout);
+    }
+
+    /// Test that `--hot-at` highlights exactly the titles at or above the
+    /// threshold, and that nothing is highlighted without colors.
+    #[test]
+    fn test_e2e_hot_at() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-hot-at-{}", std::process::id()));
+        let colored = |extra: &[&str]| {
+            let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+                .args([
+                    "--base-url",
+                    &server.url,
+                    "--quiet",
+                    "--compact",
+                    "--hot-at",
+                    "25",
+                ])
+                .args(extra)
+                .env("CLICOLOR_FORCE", "1")
+                .env("XDG_CACHE_HOME", &home)
+                .env("XDG_STATE_HOME", &home)
+                .output()
+                .expect("hn-cli binary runs");
+            String::from_utf8(output.stdout).unwrap()
+        };
+        let highlighted = "\u{1b}[1;43;30m";
+
+        let stdout = colored(&[]);
+        let hot: Vec<_> = stdout
+            .lines()
+            .map(|line| line.contains(highlighted))
+            .collect();
+        assert_eq!(hot, [true, true, false], "{stdout}");
+        assert!(!colored(&["--deterministic"]).contains(highlighted));
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that the score column widens to the largest score shown, in
+    /// every section alike, and stays 4 wide for smaller scores.
+    #[test]
+    fn test_e2e_score_width() {
+        let story = |id: u32, score: i32| json!({ "id": id, "title": "A story", "score": score, "by": "a" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2]))
+            .json("newstories.json", json!([3]))
+            .json("item/1.json", story(1, 5))
+            .json("item/2.json", story(2, 12345))
+            .json("item/3.json", story(3, 42));
+
+        let run = server.run(&[
+            "--quiet",
+            "--compact",
+            "--sort",
+            "hottest",
+            "--sort",
+            "latest",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let scores: Vec<_> = run
+            .stdout
+            .lines()
+            .filter_map(|line| line.split_once(" A story").map(|(start, _)| start))
+            .collect();
+        assert_eq!(
+            scores,
+            [" 1. [  5   ]", " 2. [12,345]", " 1. [  42  ]"],
+            "{}",
+            run.stdout
+        );
+
+        let run = server.run(&["--quiet", "--compact", "--sort", "latest"]);
+        assert_eq!(run.stdout, " 1. [ 42 ] A story by a\n");
+    }
+
+    /// Test that score badges are dimmed, yellow or red by the default
+    /// thresholds or those of the config file, and that thresholds in the
+    /// wrong order are a usage error.
+    #[test]
+    fn test_e2e_score_colors() {
+        let story = |id: u32, score: i32| json!({ "id": id, "title": "A story", "score": score, "by": "a" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("item/1.json", story(1, 10))
+            .json("item/2.json", story(2, 60))
+            .json("item/3.json", story(3, 250));
+        let home = std::env::temp_dir().join(format!("hn-cli-score-colors-{}", std::process::id()));
+        let config = home.join("config.toml");
+        std::fs::create_dir_all(&home).unwrap();
+        let run = |extra: &[&str]| {
+            Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+                .args(["--base-url", &server.url, "--quiet", "--compact"])
+                .args(extra)
+                .env("CLICOLOR_FORCE", "1")
+                .env("XDG_CACHE_HOME", &home)
+                .env("XDG_STATE_HOME", &home)
+                .env("XDG_CONFIG_HOME", &home)
+                .output()
+                .expect("hn-cli binary runs")
+        };
+        let badges = |output: &std::process::Output| {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout
+                .lines()
+                .map(|line| {
+                    ["\u{1b}[2m[", "\u{1b}[1;33m[", "\u{1b}[1;91m["]
+                        .iter()
+                        .position(|style| line.contains(style))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(badges(&run(&[])), [Some(0), Some(1), Some(2)]);
+
+        std::fs::write(&config, "[score_colors]\nwarm = 5\nhot = 60\n").unwrap();
+        let configured = run(&["--config", config.to_str().unwrap()]);
+        assert_eq!(badges(&configured), [Some(1), Some(2), Some(2)]);
+
+        std::fs::write(&config, "[score_colors]\nwarm = 300\n").unwrap();
+        let output = run(&["--config", config.to_str().unwrap()]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(stderr.contains("warm (300) is above hot (200)"), "{stderr}");
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `--theme` picks a preset, that the config file's `[theme]`
+    /// picks one too and overrides single roles, the flag winning, and that
+    /// an unknown color is a usage error naming its key.
+    #[test]
+    fn test_e2e_theme() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1]))
+            .json(
+                "item/1.json",
+                json!({ "id": 1, "title": "A story", "score": 60, "by": "a", "url": "https://example.com/" }),
+            );
+        let home = std::env::temp_dir().join(format!("hn-cli-theme-{}", std::process::id()));
+        let config = home.join("config.toml");
+        std::fs::create_dir_all(&home).unwrap();
+        let run = |extra: &[&str]| {
+            Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+                .args(["--base-url", &server.url, "--quiet", "--compact"])
+                .args(["--config", config.to_str().unwrap()])
+                .args(extra)
+                .env("CLICOLOR_FORCE", "1")
+                .env("XDG_CACHE_HOME", &home)
+                .env("XDG_STATE_HOME", &home)
+                .output()
+                .expect("hn-cli binary runs")
+        };
+        let stdout = |output: std::process::Output| String::from_utf8(output.stdout).unwrap();
+        let bold_black_title = "\u{1b}[1;30mA story";
+        let orange_site = "\u{1b}[38;5;208m (example.com)";
+
+        std::fs::write(&config, "").unwrap();
+        let dark = stdout(run(&[]));
+        assert!(dark.contains("\u{1b}[1;37mA story"), "{dark}");
+        assert!(stdout(run(&["--theme", "light"])).contains(bold_black_title));
+        let colorblind = stdout(run(&["--theme", "cb"]));
+        assert!(
+            colorblind.contains("\u{1b}[1;38;5;39m[ 60 ]"),
+            "{colorblind}"
+        );
+        let mono = stdout(run(&["--theme", "mono"]));
+        assert!(mono.contains("\u{1b}[1m[ 60 ]"), "{mono}");
+        assert!(
+            !mono.contains("\u{1b}[3") && !mono.contains("\u{1b}[1;3"),
+            "{mono}"
+        );
+
+        std::fs::write(&config, "[theme]\npreset = \"light\"\nurl = 208\n").unwrap();
+        let configured = stdout(run(&[]));
+        assert!(configured.contains(bold_black_title), "{configured}");
+        assert!(configured.contains(orange_site), "{configured}");
+        let flagged = stdout(run(&["--theme", "dark"]));
+        assert!(!flagged.contains(bold_black_title), "{flagged}");
+        assert!(flagged.contains(orange_site), "{flagged}");
+
+        std::fs::write(&config, "[theme]\ntitle = \"blu\"\n").unwrap();
+        let output = run(&[]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(
+            stderr.contains("theme.title: unknown color 'blu'"),
+            "{stderr}"
+        );
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that stories show their age, fresh ones in green and old ones
+    /// dimmed, that `--order-by age` puts the newest first and that
+    /// `--deterministic` shows the raw submission time instead.
+    #[test]
+    fn test_e2e_story_age() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let story = |id: u32, time: u64| json!({ "id": id, "title": format!("Story {id}"), "score": 1, "by": "a", "time": time });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("item/1.json", story(1, now - 3 * 86_400))
+            .json("item/2.json", story(2, now - 600))
+            .json(
+                "item/3.json",
+                json!({ "id": 3, "title": "Story 3", "score": 1, "by": "a" }),
+            );
+        let home = std::env::temp_dir().join(format!("hn-cli-age-{}", std::process::id()));
+        let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args(["--base-url", &server.url, "--quiet", "--compact"])
+            .args(["--order-by", "age"])
+            .env("CLICOLOR_FORCE", "1")
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .output()
+            .expect("hn-cli binary runs");
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let lines: Vec<_> = stdout.lines().collect();
+
+        assert_eq!(lines.len(), 3, "{stdout}");
+        assert!(
+            lines[0].contains("Story 2") && lines[0].contains(" · \u{1b}[1;32m10m ago"),
+            "{stdout}"
+        );
+        assert!(
+            lines[1].contains("Story 1") && lines[1].contains(" · \u{1b}[2m3d ago"),
+            "{stdout}"
+        );
+        assert!(
+            lines[2].contains("Story 3") && !lines[2].contains(" · "),
+            "{stdout}"
+        );
+
+        let run = server.run(&["--quiet", "--compact"]);
+        assert_eq!(
+            run.stdout.lines().next(),
+            Some(format!(" 1. [ 1  ] Story 1 by a · {}", now - 3 * 86_400).as_str())
+        );
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `--format table` aligns its columns by display width, wide
+    /// characters included, that `--fields` picks and orders them, and that
+    /// `--fields` without the table format is a usage error.
+    #[test]
+    fn test_e2e_table() {
+        let story = |id: u32, title: &str, url: &str| json!({ "id": id, "title": title, "score": id * 50, "by": "a", "descendants": id, "url": url });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("item/1.json", story(1, "Rust 1.0", "https://www.rust-lang.org/"))
+            .json("item/2.json", story(2, "日本語のタイトル", "https://example.jp/"))
+            .json("item/3.json", json!({ "id": 3, "title": "Ask HN: Why?", "score": 7, "by": "b", "time": 1_175_714_200 }));
+
+        let run = server.run(&["--quiet", "--format", "table"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            "Rank  Score  Comments  Age         Title             Domain\n\
+             \x20  1     50         1              Rust 1.0          rust-lang.org\n\
+             \x20  2    100         2              日本語のタイトル  example.jp\n\
+             \x20  3      7            1175714200  Ask HN: Why?\n"
+        );
+
+        let run = server.run(&["--quiet", "--format", "table", "--fields", "title,score"]);
+        assert_eq!(
+            run.stdout,
+            "Title             Score\nRust 1.0             50\n日本語のタイトル    100\nAsk HN: Why?          7\n"
+        );
+
+        for args in [&["--fields", "title"][..], &[
+            "--format", "table", "user", "pg",
+        ]] {
+            let run = server.run(args);
+            assert_eq!(run.code, Some(2), "{args:?}");
+        }
+    }
+
+    /// Test that `--hot-rank` orders by `score / (age_hours + 2)^gravity`, ages
+    /// measured from the newest story under `--deterministic`, that
+    /// `--gravity 0` leaves only the score, and that it conflicts with
+    /// `--order-by`.
+    #[test]
+    fn test_e2e_hot_rank() {
+        let story = |id: u32, score: i32, hours: i64| json!({ "id": id, "title": format!("Story {id}"), "score": score, "by": "a", "time": 1_700_000_000 - hours * 3600 });
+        let server = FixtureServer::start()
+            .json("newstories.json", json!([1, 2, 3, 4]))
+            .json("item/1.json", story(1, 10, 0))
+            .json("item/2.json", story(2, 100, 10))
+            .json("item/3.json", story(3, 50, 1))
+            .json(
+                "item/4.json",
+                json!({ "id": 4, "title": "Story 4", "score": 500, "by": "a" }),
+            );
+        let ranked = |extra: &[&str]| -> Vec<u64> {
+            let run = server.run(&[&["--sort", "latest", "--format", "json"], extra].concat());
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            let stories: Vec<Value> = serde_json::from_str(&run.stdout).unwrap();
+            stories
+                .iter()
+                .map(|story| story["id"].as_u64().unwrap())
+                .collect()
+        };
+
+        assert_eq!(ranked(&["--hot-rank"]), [3, 1, 2, 4]);
+        assert_eq!(ranked(&["--order-by", "hot"]), [3, 1, 2, 4]);
+        assert_eq!(ranked(&["--hot-rank", "--gravity", "0"]), [2, 3, 1, 4]);
+
+        let run = server.run(&["--hot-rank", "--order-by", "score"]);
+        assert_eq!(run.code, Some(2));
+        let run = server.run(&["--hot-rank", "--gravity", "-1"]);
+        assert_eq!(run.code, Some(2));
+    }
+
+    /// Test that `--profile` and `profile NAME` run a listing from the config
+    /// file's `[profiles]`, flags given alongside overriding it, that
+    /// `profile list` shows each one as flags, and that an unknown name is a
+    /// usage error listing the known ones.
+    #[test]
+    fn test_e2e_profiles() {
+        let story = |id: u32, title: &str, score: i32| json!({ "id": id, "title": title, "score": score, "by": "a" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("newstories.json", json!([3]))
+            .json("item/1.json", story(1, "Rust 2024", 150))
+            .json("item/2.json", story(2, "Go news", 200))
+            .json("item/3.json", story(3, "Rust in Linux", 20));
+        let config =
+            std::env::temp_dir().join(format!("hn-cli-profiles-{}.toml", std::process::id()));
+        std::fs::write(
+            &config,
+            "[profiles.rust]\nsort = [\"latest\"]\nfilter = [\"rust\"]\ncount = 5\n\n[profiles.big]\nmin_score = 100\nformat = \"json\"\n",
+        )
+        .unwrap();
+        let config = config.to_str().unwrap();
+        let titles = |run: &Run| -> Vec<String> {
+            run.stdout
+                .lines()
+                .map(|line| line.split("] ").nth(1).unwrap_or_default().to_string())
+                .collect()
+        };
+
+        let run = server.run(&[
+            "--config",
+            config,
+            "--quiet",
+            "--compact",
+            "--profile",
+            "rust",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(titles(&run), ["Rust in Linux by a"]);
+        let run = server.run(&[
+            "--config",
+            config,
+            "p",
+            "rust",
+            "--sort",
+            "hottest",
+            "--quiet",
+            "--compact",
+        ]);
+        assert_eq!(titles(&run), ["Rust 2024 by a", "Rust in Linux by a"]);
+
+        let run = server.run(&["--config", config, "--profile", "big"]);
+        let stories: Vec<Value> = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(stories.len(), 2);
+        let run = server.run(&[
+            "--config",
+            config,
+            "profile",
+            "big",
+            "--format",
+            "pretty",
+            "--min-score",
+            "180",
+            "--quiet",
+            "--compact",
+        ]);
+        assert_eq!(titles(&run), ["Go news by a"]);
+
+        let run = server.run(&["--config", config, "profile", "list"]);
+        assert_eq!(
+            run.stdout,
+            "big   --min-score 100 --format json\nrust  --sort latest --count 5 --filter rust\n"
+        );
+        let run = server.run(&["--config", config, "--profile", "nope"]);
+        assert_eq!(run.code, Some(2));
+        assert!(
+            run.stderr
+                .contains("unknown profile 'nope'; known profiles: big, rust"),
+            "{}",
+            run.stderr
+        );
+        std::fs::remove_file(config).unwrap();
+    }
+
+    /// Test that `--top-comment` fetches only the first comment of each
+    /// story with comments and previews it on one line, decoded, in both
+    /// layouts and in JSON.
+    #[test]
+    fn test_e2e_top_comment() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2]))
+            .json("item/1.json", json!({ "id": 1, "title": "Talked about", "score": 50, "by": "a", "kids": [11, 12] }))
+            .json("item/2.json", json!({ "id": 2, "title": "Quiet", "score": 5, "by": "b" }))
+            .json(
+                "item/11.json",
+                json!({ "id": 11, "type": "comment", "by": "pg", "text": "Tabs &amp; spaces.<p>Both, really." }),
+            )
+            .json("item/12.json", json!({ "id": 12, "type": "comment", "by": "tel", "text": "No." }));
+
+        let run = server.run(&["--quiet", "--compact", "--top-comment"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            " 1. [ 50 ] Talked about by a\n    💬 pg: Tabs & spaces. Both, really.\n 2. [ 5  ] Quiet by b\n"
+        );
+        assert_eq!(server.requests("item/12.json"), 0);
+
+        let run = server.run(&["--quiet", "--top-comment"]);
+        assert!(
+            run.stdout
+                .contains("      by a\n      💬 pg: Tabs & spaces. Both, really.\n"),
+            "{}",
+            run.stdout
+        );
+
+        let run = server.run(&["--format", "json", "--top-comment"]);
+        let stories: Vec<Value> = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(stories[0]["top_comment"]["by"], "pg");
+        assert!(stories[1].get("top_comment").is_none());
+
+        let before = server.requests("item/11.json");
+        server.run(&["--quiet"]);
+        assert_eq!(server.requests("item/11.json"), before);
+    }
+
+    /// Test that `--url-only` prints bare links (discussion pages for text
+    /// posts), that `--print0` ends them with NUL bytes, that both reject
+    /// what they can't honor, and that a reader closing the pipe early
+    /// isn't an error.
+    #[test]
+    fn test_e2e_url_only_print0() {
+        let server = front_page();
+
+        let run = server.run(&["--url-only"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            "http://www.getdropbox.com/u/2/screencast.html\nhttps://news.ycombinator.com/item?id=121003\nhttps://news.ycombinator.com/item?id=192327\n"
+        );
+
+        let run = server.run(&["--url-only", "--print0"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            "http://www.getdropbox.com/u/2/screencast.html\0https://news.ycombinator.com/item?id=121003\0https://news.ycombinator.com/item?id=192327\0"
+        );
+
+        let run = server.run(&["--print0"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        let run = server.run(&["--url-only", "--print0", "--format", "table"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        assert!(
+            run.stderr.contains("--url-only prints bare links"),
+            "{}",
+            run.stderr
+        );
+
+        let home = std::env::temp_dir().join(format!("hn-cli-print0-{}", std::process::id()));
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args([
+                "--base-url",
+                &server.url,
+                "--deterministic",
+                "--url-only",
+                "--print0",
+            ])
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("hn-cli binary runs");
+        drop(child.stdout.take());
+        let output = child.wait_with_output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(0), "{stderr}");
+        assert!(
+            !stderr.contains("panicked") && !stderr.contains("could not write"),
+            "{stderr}"
+        );
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `--pick` offers `title<TAB>link<TAB>id` lines to
+    /// `$HN_PICKER` and prints every picked link with `--pick-print`, that
+    /// quitting the picker picks nothing, and that a missing picker fails
+    /// with a hint.
+    #[cfg(unix)]
+    #[test]
+    fn test_e2e_pick() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-pick-{}", std::process::id()));
+        let pick = |picker: &str| {
+            let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+                .args([
+                    "--base-url",
+                    &server.url,
+                    "--deterministic",
+                    "--pick",
+                    "--pick-print",
+                ])
+                .env("HN_PICKER", picker)
+                .env("XDG_CACHE_HOME", &home)
+                .env("XDG_STATE_HOME", &home)
+                .output()
+                .expect("hn-cli binary runs");
+            (
+                output.status.code(),
+                String::from_utf8(output.stdout).unwrap(),
+                String::from_utf8(output.stderr).unwrap(),
+            )
+        };
+
+        let (code, stdout, stderr) = pick("cat");
+        assert_eq!(code, Some(0), "{stderr}");
+        assert_eq!(
+            stdout,
+            "http://www.getdropbox.com/u/2/screencast.html\nhttps://news.ycombinator.com/item?id=121003\nhttps://news.ycombinator.com/item?id=192327\n"
+        );
+
+        let (code, stdout, _) = pick("grep -F 121003");
+        assert_eq!(code, Some(0));
+        assert_eq!(stdout, "https://news.ycombinator.com/item?id=121003\n");
+
+        let (code, stdout, stderr) = pick("false");
+        assert_eq!(code, Some(0), "{stderr}");
+        assert_eq!(stdout, "");
+
+        let (code, _, stderr) = pick("no-such-picker --multi");
+        assert_eq!(code, Some(2));
+        assert!(
+            stderr.contains("picker 'no-such-picker' not found"),
+            "{stderr}"
+        );
+        assert!(stderr.contains("`repl`"), "{stderr}");
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `serve` answers each feed with the JSON of `--format json`,
+    /// with CORS headers, keeps the last copy through an API outage, answers
+    /// unknown paths with 404 and stops cleanly on Ctrl-C.
+    #[cfg(all(unix, feature = "serve"))]
+    #[test]
+    fn test_e2e_serve() {
+        let server = front_page().json("askstories.json", json!([121_003]));
+        let expected = server.run(&["--format", "json"]).stdout.clone();
+        let home = std::env::temp_dir().join(format!("hn-cli-serve-{}", std::process::id()));
+        let (child, mut stdout, address) = start_serve(&server, &home);
+
+        let mut top = http(&address, "GET", "/top.json");
+        for _ in 0..50 {
+            if top.0.starts_with("HTTP/1.1 200") {
+                break;
+            }
+            assert!(top.0.starts_with("HTTP/1.1 503"), "{}", top.0);
+            thread::sleep(Duration::from_millis(100));
+            top = http(&address, "GET", "/top.json");
+        }
+        assert!(top.0.starts_with("HTTP/1.1 200"), "{}", top.0);
+        assert!(
+            top.0.contains("Access-Control-Allow-Origin: *"),
+            "{}",
+            top.0
+        );
+        assert_eq!(top.1, expected);
+
+        let (_, ask) = http(&address, "GET", "/ask.json?fresh=1");
+        let ask: Vec<Value> = serde_json::from_str(&ask).unwrap();
+        assert_eq!(ask.len(), 1);
+        assert_eq!(ask[0]["id"], 121_003);
+        let (head, _) = http(&address, "OPTIONS", "/top.json");
+        assert!(head.starts_with("HTTP/1.1 204"), "{head}");
+        assert!(head.contains("Access-Control-Allow-Methods: GET, HEAD, OPTIONS"));
+        let (head, _) = http(&address, "GET", "/best.json");
+        assert!(head.starts_with("HTTP/1.1 404"), "{head}");
+
+        let server = server.replace("topstories.json", Reply::Status(500));
+        let before = server.requests("topstories.json");
+        while server.requests("topstories.json") == before {
+            thread::sleep(Duration::from_millis(100));
+        }
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(http(&address, "GET", "/top.json").1, expected);
+
+        let killed = Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(killed.success());
+        let output = child.wait_with_output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(0), "{stderr}");
+        assert!(
+            stderr.contains("warning: refreshing /top.json failed"),
+            "{stderr}"
+        );
+        let mut rest = String::new();
+        std::io::Read::read_to_string(&mut stdout, &mut rest).unwrap();
+        assert!(rest.contains("Stopped."), "{rest}");
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `serve` answers an overlong request line with 400, overlong
+    /// or too many headers with 431 and connections past its cap with 503,
+    /// and that Ctrl-C doesn't wait on clients that never send a request.
+    #[cfg(all(unix, feature = "serve"))]
+    #[test]
+    fn test_e2e_serve_limits() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-serve-limits-{}", std::process::id()));
+        let (child, _stdout, address) = start_serve(&server, &home);
+        // The server answers before reading the rest, so the connection may
+        // end in a reset once the response is in.
+        let raw = |request: &str| {
+            let mut stream = TcpStream::connect(&address).expect("server accepts");
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            let _ = std::io::Read::read_to_string(&mut stream, &mut response);
+            response
+        };
+
+        let long = format!(
+            "GET /top.json HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+            "a".repeat(9000)
+        );
+        let response = raw(&long);
+        assert!(
+            response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+            "{response}"
+        );
+        let many = format!(
+            "GET /top.json HTTP/1.1\r\n{}\r\n",
+            "X-Many: 1\r\n".repeat(101)
+        );
+        let response = raw(&many);
+        assert!(response.starts_with("HTTP/1.1 431 "), "{response}");
+        let response = raw(&format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(9000)));
+        assert!(
+            response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+            "{response}"
+        );
+        let response = raw(&format!(
+            "GET /top.json HTTP/1.1\r\n{}\r\n",
+            "X-Many: 1\r\n".repeat(100)
+        ));
+        assert!(!response.starts_with("HTTP/1.1 431 "), "{response}");
+
+        let idle: Vec<TcpStream> = (0..64)
+            .map(|_| TcpStream::connect(&address).expect("server accepts"))
+            .collect();
+        let response = raw("GET /top.json HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 503 "), "{response}");
+        assert!(response.contains("Retry-After: 1"), "{response}");
+
+        let stopping = std::time::Instant::now();
+        let killed = Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(killed.success());
+        let output = child.wait_with_output().unwrap();
+        assert!(stopping.elapsed() < Duration::from_secs(2));
+        assert_eq!(output.status.code(), Some(0));
+        drop(idle);
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `--format html` renders a self-contained page with escaped
+    /// titles, and that `--with-favicons` embeds each site's icon once per
+    /// origin, skips sites without one and reuses cached icons.
+    #[test]
+    fn test_e2e_html_favicons() {
+        const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+        let server = FixtureServer::start();
+        let root = server.url.trim_end_matches("/v0").to_string();
+        let home = std::env::temp_dir().join(format!("hn-cli-favicons-{}", std::process::id()));
+        let server = server
+            .json("topstories.json", json!([1, 2, 3, 4]))
+            .json("item/1.json", json!({ "id": 1, "title": "First", "score": 10, "by": "a", "url": format!("{root}/first") }))
+            .json("item/2.json", json!({ "id": 2, "title": "Second", "score": 9, "by": "b", "url": format!("{root}/second") }))
+            .json("item/3.json", json!({ "id": 3, "title": "Gone", "score": 8, "by": "c", "url": "http://127.0.0.1:1/gone" }))
+            .json("item/4.json", json!({ "id": 4, "title": "Ask HN: <b> & \"quotes\"?", "score": 1, "by": "d", "descendants": 1 }))
+            .route("/favicon.ico", Reply::Bytes("image/png", PNG));
+        let icon =
+            "<img src=\"data:image/png;base64,iVBORw0KGgo=\" width=\"16\" height=\"16\" alt=\"\">";
+
+        let run = server.run_in(&home, &["--format", "html"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stdout.starts_with("<!DOCTYPE html>\n"),
+            "{}",
+            run.stdout
+        );
+        assert!(run.stdout.ends_with("</html>\n"));
+        assert!(run.stdout.contains(
+            "<a class=\"title\" href=\"https://news.ycombinator.com/item?id=4\">Ask HN: &lt;b&gt; &amp; &quot;quotes&quot;?</a>\n<div class=\"meta\">1 point by d · <a href=\"https://news.ycombinator.com/item?id=4\">1 comment</a></div></li>"
+        ), "{}", run.stdout);
+        assert!(!run.stdout.contains("<img"));
+        assert_eq!(server.requests("/favicon.ico"), 0);
+
+        let run = server.run_in(&home, &["--format", "html", "--with-favicons"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(run.stdout.matches(icon).count(), 2, "{}", run.stdout);
+        assert!(
+            run.stdout
+                .contains("<span class=\"site\">(127.0.0.1)</span>")
+        );
+        assert_eq!(server.requests("/favicon.ico"), 1);
+
+        let run = server.run_in(&home, &["--format", "html", "--with-favicons"]);
+        assert_eq!(run.stdout.matches(icon).count(), 2);
+        assert_eq!(server.requests("/favicon.ico"), 1);
+
+        let run = server.run(&["--with-favicons"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("--with-favicons only applies to '--format html'")
+        );
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    /// Test that `block` edits the blocklist file, that every listing leaves
+    /// out what it blocks (subdomains included) unless `--no-blocklist`, and
+    /// that a malformed line is warned about by number and skipped.
+    #[test]
+    fn test_e2e_blocklist() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-blocklist-{}", std::process::id()));
+        let file = home.join("hn-cli").join("blocklist.txt");
+        let titles = |run: &Run| -> Vec<String> {
+            run.stdout
+                .lines()
+                .map(|line| line.split("] ").nth(1).unwrap_or_default().to_string())
+                .collect()
+        };
+
+        let run = server.run_in(&home, &[
+            "block",
+            "add",
+            "domain:getdropbox.com",
+            "user:tel",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(
+            run.stdout.contains("✓ blocked domain:getdropbox.com"),
+            "{}",
+            run.stdout
+        );
+        let run = server.run_in(&home, &["block", "add", "domain:WWW.GetDropbox.com"]);
+        assert!(
+            run.stdout
+                .contains("domain:getdropbox.com is already blocked"),
+            "{}",
+            run.stdout
+        );
+        let run = server.run_in(&home, &["block", "add", "site:example.com"]);
+        assert_eq!(run.code, Some(2));
+
+        let mut text = std::fs::read_to_string(&file).unwrap();
+        text.push_str("# spam\nsite:example.com\n");
+        std::fs::write(&file, text).unwrap();
+
+        let run = server.run_in(&home, &["--quiet", "--compact"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(titles(&run), [
+            "Justin.tv is looking for a Lead Flash Engineer! by justin"
+        ]);
+        assert!(
+            run.stderr.contains(
+                "blocklist.txt:4: expected `domain:HOST` or `user:NAME`, got 'site:example.com'"
+            ),
+            "{}",
+            run.stderr
+        );
+        let run = server.run_in(&home, &["--quiet", "--compact", "--no-blocklist"]);
+        assert_eq!(titles(&run).len(), 3, "{}", run.stdout);
+
+        let run = server.run_in(&home, &["block", "rm", "user:tel", "user:nobody"]);
+        assert!(
+            run.stdout.contains("user:nobody isn't blocked"),
+            "{}",
+            run.stdout
+        );
+        let run = server.run_in(&home, &["block", "list"]);
+        assert_eq!(run.stdout, "domain:getdropbox.com\n");
+        assert!(std::fs::read_to_string(&file).unwrap().contains("# spam\n"));
+        let run = server.run_in(&home, &["--quiet", "--compact"]);
+        assert_eq!(titles(&run).len(), 2, "{}", run.stdout);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `--include-poll-results` fetches a poll's options and shows
+    /// the leading one with its share of the votes, in both layouts and in
+    /// JSON, and that polls cost no extra requests without it.
+    #[test]
+    fn test_e2e_poll_results() {
+        let option = |id: u32, text: &str, score: i32| json!({ "id": id, "type": "pollopt", "text": text, "score": score });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1]))
+            .json("item/1.json", json!({ "id": 1, "type": "poll", "title": "Tabs?", "score": 50, "by": "a", "descendants": 2, "parts": [11, 12, 13] }))
+            .json("item/11.json", option(11, "Tabs", 10))
+            .json("item/12.json", option(12, "Spaces", 30))
+            .json("item/13.json", option(13, "Both", 10));
+
+        let run = server.run(&["--quiet", "--compact"]);
+        assert_eq!(run.stdout, " 1. [ 50 ] Tabs? by a — 2c\n");
+        assert_eq!(server.requests("item/11.json"), 0);
+
+        let run = server.run(&["--quiet", "--compact", "--include-poll-results"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            " 1. [ 50 ] Tabs? by a · 📊 60% Spaces (50 votes) — 2c\n"
+        );
+        let run = server.run(&["--quiet", "--include-poll-results"]);
+        assert!(
+            run.stdout
+                .contains("      📊 ██████░░░░ 60% Spaces (50 votes)\n"),
+            "{}",
+            run.stdout
+        );
+
+        let run = server.run(&["--format", "json", "--include-poll-results"]);
+        let stories: Vec<Value> = serde_json::from_str(&run.stdout).unwrap();
+        assert_eq!(stories[0]["poll_options"][1]["score"], 30);
+    }
+
+    /// Test that `--max-per-domain` counts subdomains with their registrable
+    /// domain but shared hosts' users apart, fills the places it empties from
+    /// deeper in the feed without fetching past what it needs, and says in the
+    /// summary how many stories it left out.
+    #[test]
+    fn test_e2e_max_per_domain() {
+        let story = |id: u32, url: &str| json!({ "id": id, "title": format!("Story {id}"), "score": 1, "by": "a", "url": url });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3, 4, 5, 6, 7, 8]))
+            .json("item/1.json", story(1, "https://example.com/a"))
+            .json("item/2.json", story(2, "https://news.example.com/b"))
+            .json("item/3.json", story(3, "https://www.example.com/c"))
+            .json(
+                "item/4.json",
+                json!({ "id": 4, "title": "Story 4", "score": 1, "by": "a" }),
+            )
+            .json("item/5.json", story(5, "https://alice.github.io/"))
+            .json("item/6.json", story(6, "https://example.com/d"))
+            .json("item/7.json", story(7, "https://bob.github.io/"));
+
+        let run = server.run(&[
+            "--no-quiet",
+            "--compact",
+            "--count",
+            "4",
+            "--max-per-domain",
+            "1",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        let titles: Vec<_> = run
+            .stdout
+            .lines()
+            .filter_map(|line| line.split_once("] ").map(|(_, rest)| rest))
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                "Story 1 (example.com) by a",
+                "Story 4 by a",
+                "Story 5 (alice.github.io) by a",
+                "Story 7 (bob.github.io) by a"
+            ],
+            "{}",
+            run.stdout
+        );
+        assert!(
+            run.stdout
+                .contains("Done! 4 stories, 3 more left out by --max-per-domain"),
+            "{}",
+            run.stdout
+        );
+        assert_eq!(server.requests("item/8.json"), 0);
+    }
+
+    /// Test that `--numbers` (or `--humanize`, for compact) styles the scores
+    /// and comment counts of the pretty and table formats, grouped by
+    /// default, while JSON keeps plain numbers.
+    #[test]
+    fn test_e2e_numbers() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1]))
+            .json(
+                "item/1.json",
+                json!({ "id": 1, "title": "Big", "score": 1834, "by": "a", "descendants": 12_345 }),
+            );
+
+        let cases = [
+            (&[][..], " 1. [1,834] Big by a — 12,345c\n"),
+            (&["--numbers", "plain"], " 1. [1834] Big by a — 12345c\n"),
+            (&["--numbers", "compact"], " 1. [1.8k] Big by a — 12kc\n"),
+            (&["--humanize"], " 1. [1.8k] Big by a — 12kc\n"),
+        ];
+        for (numbers, expected) in cases {
+            let run = server.run(&[&["--quiet", "--compact"], numbers].concat());
+            assert_eq!(run.stdout, expected, "{numbers:?}");
+        }
+
+        let run = server.run(&["--quiet", "--format", "table", "--fields", "score,comments"]);
+        assert_eq!(run.stdout, "Score  Comments\n1,834    12,345\n");
+        let run = server.run(&[
+            "--quiet",
+            "--format",
+            "table",
+            "--fields",
+            "score,comments",
+            "--humanize",
+        ]);
+        assert_eq!(run.stdout, "Score  Comments\n 1.8k       12k\n");
+        assert_eq!(
+            server.run(&["--humanize", "--numbers", "plain"]).code,
+            Some(2)
+        );
+
+        for compact in [&["--numbers", "compact"][..], &["--humanize"]] {
+            let run = server.run(&[&["--format", "json"], compact].concat());
+            let stories: Vec<Value> = serde_json::from_str(&run.stdout).unwrap();
+            assert_eq!(stories[0]["score"], 1834);
+            assert_eq!(stories[0]["descendants"], 12_345);
+        }
+    }
+
+    /// Test that `--max-body-bytes` fails a story whose body is over the
+    /// limit like any other failed fetch, and the whole run, as a decode
+    /// error, when the id list is.
+    #[test]
+    fn test_e2e_max_body_bytes() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2]))
+            .json(
+                "item/1.json",
+                json!({ "id": 1, "title": "Small", "score": 1, "by": "a" }),
+            )
+            .json(
+                "item/2.json",
+                json!({ "id": 2, "title": "x".repeat(500), "score": 1, "by": "a" }),
+            );
+
+        let run = server.run(&["--quiet", "--compact", "--max-body-bytes", "200"]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert!(run.stdout.contains("Small"), "{}", run.stdout);
+        assert!(run.stderr.contains("2 (too large)"), "{}", run.stderr);
+
+        let run = server.run(&["--max-body-bytes", "4"]);
+        assert_eq!(run.code, Some(5), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("response body exceeds the 4-byte limit"),
+            "{}",
+            run.stderr
+        );
+    }
+
+    /// Test that `--group-by day` puts the pretty listing under a heading per
+    /// UTC day under `--deterministic`, newest day first with the
+    /// `--order-by` order inside each day and undated stories last, while
+    /// JSON stays flat.
+    #[test]
+    fn test_e2e_group_by_day() {
+        let story = |id: u32, score: i32, time: i64| json!({ "id": id, "title": format!("Story {id}"), "score": score, "by": "a", "time": time });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3, 4]))
+            .json("item/1.json", story(1, 10, 1_718_150_400))
+            .json("item/2.json", story(2, 20, 1_718_236_800))
+            .json(
+                "item/3.json",
+                json!({ "id": 3, "title": "Story 3", "score": 30, "by": "a" }),
+            )
+            .json("item/4.json", story(4, 40, 1_718_193_600));
+
+        let run = server.run(&[
+            "--quiet",
+            "--compact",
+            "--group-by",
+            "day",
+            "--order-by",
+            "score",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            "── 2024-06-13 ──\n \
+             1. [ 20 ] Story 2 by a · 1718236800\n\
+             \n\
+             ── 2024-06-12 ──\n \
+             2. [ 40 ] Story 4 by a · 1718193600\n \
+             3. [ 10 ] Story 1 by a · 1718150400\n\
+             \n\
+             ── unknown ──\n \
+             4. [ 30 ] Story 3 by a\n"
+        );
+
+        let run = server.run(&[
+            "--format",
+            "json",
+            "--group-by",
+            "day",
+            "--order-by",
+            "score",
+        ]);
+        let ids: Vec<u64> = serde_json::from_str::<Vec<Value>>(&run.stdout)
+            .unwrap()
+            .iter()
+            .map(|story| story["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, [4, 3, 2, 1]);
+    }
+
+    /// Test that `--explain` fetches the id lists but no stories, and
+    /// reports the feeds, requests, filters, order and output it worked out.
+    #[test]
+    fn test_e2e_explain() {
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("newstories.json", json!([4, 3]))
+            .json(
+                "item/1.json",
+                json!({ "id": 1, "title": "A", "score": 1, "by": "a" }),
+            );
+
+        let run = server.run(&[
+            "--explain",
+            "--sort",
+            "hottest",
+            "--sort",
+            "latest",
+            "--min-score",
+            "10",
+            "--filter",
+            "rust",
+            "--order-by",
+            "score",
+            "--jobs",
+            "4",
+            "--format",
+            "json",
+        ]);
+        assert_eq!(run.code, Some(0), "{}", run.stderr);
+        assert_eq!(
+            run.stdout,
+            format!(
+                "Feed     hottest from {url}/topstories.json: 3 stories\n\
+                 Feed     latest from {url}/newstories.json: 1 story, 1 already in an earlier feed\n\
+                 Items    4 requests to {url}/item/{{id}}.json, 4 at a time, up to 2 retries each, \
+                 no rate limit\n\
+                 Filters  score at least 10, title contains 'rust'\n\
+                 Order    by score\n\
+                 Output   json to stdout\n",
+                url = server.url
+            )
+        );
+        assert_eq!(server.requests("topstories.json"), 1);
+        assert_eq!(server.requests("item/1.json"), 0);
+
+        let run = server.run(&["--dry-run", "--count", "2", "--backend", "algolia"]);
+        assert!(
+            run.stdout.contains(
+                "Items    1 request to https://hn.algolia.com/api/v1/search, up to 50 stories each"
+            ),
+            "{}",
+            run.stdout
+        );
+    }
+
+    /// Test that `--min-score` and `--min-comments` leave out the stories
+    /// below them, counting a job's missing comment count as 0, and that
+    /// the closing line then says how many were fetched and shown.
+    #[test]
+    fn test_e2e_min_score_comments() {
+        let server = front_page();
+        let ids = |extra: &[&str]| -> Vec<u64> {
+            let run = server.run(&[&["--format", "json"], extra].concat());
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            let listing: Value = serde_json::from_str(&run.stdout).unwrap();
+            listing["stories"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|story| story["id"].as_u64().unwrap())
+                .collect()
+        };
+
+        assert_eq!(ids(&["--min-score", "6"]), [8863, 121_003, 192_327]);
+        assert_eq!(ids(&["--min-score", "20"]), [8863, 121_003]);
+        assert_eq!(ids(&["--min-comments", "16"]), [8863, 121_003]);
+        assert_eq!(ids(&["--min-comments", "17"]), [8863]);
+        assert_eq!(ids(&["--min-score", "30", "--min-comments", "10"]), [8863]);
+
+        let run = server.run(&["--no-quiet", "--min-score", "20"]);
+        assert!(
+            run.stdout
+                .contains("Done! 2 stories · 30 requested, 4 available, 3 fetched, 2 shown"),
+            "{}",
+            run.stdout
+        );
+    }
+
+    /// Test that `--filter` keeps the titles containing any term, ignoring
+    /// case, and highlights each match in place, overlapping matches merged
+    /// into one run; without colors the titles are left as they are.
+    #[test]
+    fn test_e2e_filter() {
+        let story = |id: u32, title: &str, score: i32| json!({ "id": id, "title": title, "score": score, "by": "a" });
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3]))
+            .json("item/1.json", story(1, "Trusting Rust", 5))
+            .json("item/2.json", story(2, "Go tips", 5))
+            .json("item/3.json", story(3, "ÉCOLE rust", 500));
+        let home = std::env::temp_dir().join(format!("hn-cli-filter-{}", std::process::id()));
+        let run = |extra: &[&str]| {
+            let output = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+                .args(["--base-url", &server.url, "--quiet", "--compact"])
+                .args(["--filter", "rust", "--filter", "ING R", "--filter", "école"])
+                .args(extra)
+                .env("CLICOLOR_FORCE", "1")
+                .env("XDG_CACHE_HOME", &home)
+                .env("XDG_STATE_HOME", &home)
+                .output()
+                .expect("hn-cli binary runs");
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        let stdout = run(&[]);
+        let lines: Vec<_> = stdout.lines().collect();
+        assert_eq!(lines.len(), 2, "{stdout}");
+        assert!(
+            lines[0].contains("\u{1b}[1;37mT\u{1b}[0m\u{1b}[1;7;37mrusting Rust\u{1b}[0m"),
+            "{stdout}"
+        );
+        assert!(
+            lines[1].contains(
+                "\u{1b}[1;7;43;30mÉCOLE\u{1b}[0m\u{1b}[1;43;30m \u{1b}[0m\u{1b}[1;7;43;30mrust\u{1b}[0m"
+            ),
+            "{stdout}"
+        );
+
+        let stdout = run(&["--deterministic"]);
+        assert!(!stdout.contains('\u{1b}'), "{stdout}");
+        assert!(stdout.contains("Trusting Rust") && stdout.contains("ÉCOLE rust"));
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// Test that `--where` keeps the stories its expression matches, with
+    /// `and` binding tighter than `or`, and rejects unknown fields and
+    /// mismatched comparisons as usage errors.
+    #[test]
+    fn test_e2e_where() {
+        let server = front_page();
+        let links = |expr: &str| {
+            let run = server.run(&["--url-only", "--where", expr]);
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            run.stdout.clone()
+        };
+
+        assert_eq!(
+            links("score > 100 and domain == \"GetDropbox.com\""),
+            "http://www.getdropbox.com/u/2/screencast.html\n"
+        );
+        assert_eq!(
+            links("not (author == 'dhouston') and comments >= 16"),
+            "https://news.ycombinator.com/item?id=121003\n"
+        );
+        assert_eq!(
+            links("title ~ \"arc\" or score < 10 and domain == ''"),
+            "https://news.ycombinator.com/item?id=121003\nhttps://news.ycombinator.com/item?id=192327\n"
+        );
+        assert_eq!(
+            links(&format!("{}score > 100{}", "(".repeat(64), ")".repeat(64))),
+            "http://www.getdropbox.com/u/2/screencast.html\n"
+        );
+
+        for (expr, message) in [
+            ("points > 10", "unknown field 'points' at column 1"),
+            ("author > \"pg\"", "author can't be compared with >"),
+            ("score == \"high\"", "score compares with a number"),
+            ("domain == github.com", "quote text as"),
+            ("score > 10 and", "expected a field at the end"),
+            ("age < 2y", "unknown unit 'y'"),
+            (
+                &format!("{}score > 1", "(".repeat(65)),
+                "expression nested too deeply at column 65",
+            ),
+            (
+                &format!("{}score > 1", "not ".repeat(65)),
+                "expression nested too deeply at column 257",
+            ),
+        ] {
+            let run = server.run(&["--where", expr]);
+            assert_eq!(run.code, Some(2), "{expr}: {}", run.stderr);
+            assert!(run.stderr.contains(message), "{expr}: {}", run.stderr);
+        }
+    }
+
+    /// Test that `--max-age` keeps stories submitted within the age and drops
+    /// those without a time, that `--min-age` keeps older stories and those
+    /// without one, and that nonsense ages are usage errors.
+    #[test]
+    fn test_e2e_age_filters() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let story = |id: u32, age: Option<u64>| {
+            let mut story =
+                json!({ "id": id, "type": "story", "title": format!("Story {id}"), "by": "a" });
+            if let Some(age) = age {
+                story["time"] = json!(now - age);
+            }
+            story
+        };
+        let server = FixtureServer::start()
+            .json("topstories.json", json!([1, 2, 3, 4]))
+            .json("item/1.json", story(1, Some(20 * 60)))
+            .json("item/2.json", story(2, Some(5 * 3600)))
+            .json("item/3.json", story(3, Some(3 * 86_400)))
+            .json("item/4.json", story(4, None));
+        let ids = |extra: &[&str]| -> Vec<u64> {
+            let run = server.run(&[&["--format", "json"], extra].concat());
+            assert_eq!(run.code, Some(0), "{}", run.stderr);
+            let stories: Value = serde_json::from_str(&run.stdout).unwrap();
+            stories
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|story| story["id"].as_u64().unwrap())
+                .collect()
+        };
+
+        assert_eq!(ids(&["--max-age", "30m"]), [1]);
+        assert_eq!(ids(&["--max-age", "12h"]), [1, 2]);
+        assert_eq!(ids(&["--min-age", "1h"]), [2, 3, 4]);
+        assert_eq!(ids(&["--min-age", "2d"]), [3, 4]);
+        assert_eq!(ids(&["--min-age", "1h", "--max-age", "1d"]), [2]);
+        assert_eq!(ids(&["--max-age", "2w"]), [1, 2, 3]);
+        assert_eq!(ids(&["--max-age", "3600s"]), [1]);
+
+        for (age, message) in [
+            ("12", "unknown unit in '12'"),
+            ("3y", "unknown unit in '3y'"),
+            ("+12h", "expected an age like '7d'"),
+            ("0d", "expected an age above zero"),
+            ("1.5h", "unknown unit in '1.5h'"),
+        ] {
+            let run = server.run(&["--max-age", age]);
+            assert_eq!(run.code, Some(2), "{age}: {}", run.stderr);
+            assert!(run.stderr.contains(message), "{age}: {}", run.stderr);
+        }
+        let run = server.run(&["--min-age", "2d", "--max-age", "12h"]);
+        assert_eq!(run.code, Some(2), "{}", run.stderr);
+        assert!(
+            run.stderr
+                .contains("--min-age must be shorter than --max-age")
+        );
+    }
+
+    /// Test a `repl` session fed through stdin: pages continue the ranks, a
+    /// failed or unknown command doesn't end the session, and EOF does.
+    #[test]
+    fn test_e2e_repl() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-repl-{}", std::process::id()));
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args(["--base-url", &server.url, "--deterministic", "repl"])
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("hn-cli binary runs");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"top 2\nopen 3\nnext\nbogus\nlatest\nnext\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+
+        assert_eq!(output.status.code(), Some(0), "{stderr}");
+        let ranks: Vec<_> = stdout
+            .lines()
+            .filter_map(|line| line.trim_start().split_once(". [").map(|(rank, _)| rank))
+            .collect();
+        assert_eq!(ranks, ["1", "2", "3"], "{stdout}");
+        assert!(stdout.contains("No more stories."), "{stdout}");
+        assert!(stdout.contains("Commands:"), "{stdout}");
+        assert_eq!(
+            stderr,
+            "error: no story 3 on this page\n1 item failed to fetch: 4 (missing)\nunknown \
+             command 'bogus'\nerror: network error: http status: 404\n"
+        );
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn test_e2e_repl_huge_count() {
+        let server = front_page();
+        let home = std::env::temp_dir().join(format!("hn-cli-repl-count-{}", std::process::id()));
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hn-cli"))
+            .args([
+                "--base-url",
+                &server.url,
+                "--deterministic",
+                "repl",
+                "--count",
+            ])
+            .arg(usize::MAX.to_string())
+            .env("XDG_CACHE_HOME", &home)
+            .env("XDG_STATE_HOME", &home)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .expect("hn-cli binary runs");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"top\nnext\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+
+        assert_eq!(output.status.code(), Some(0), "{stderr}");
+        assert!(stdout.contains("Page 1 ·"), "{stdout}");
+        assert!(!stdout.contains("Page 2"), "{stdout}");
+        assert!(
+            stderr.contains(&format!(
+                "error: page 2 of {} stories each is out of range; use a smaller count\n",
+                usize::MAX
+            )),
+            "{stderr}"
+        );
+        let _ = std::fs::remove_dir_all(&home);
     }
 
     /// Smoke test for the HN API.
     /// Note: This requires internet access and checks if the endpoint is still
-    /// alive.
+    /// alive; run it with `cargo test -- --ignored`.
     #[test]
+    #[ignore = "needs internet access"]
     fn test_api_endpoint_alive() {
-        let url = "https://hacker-news.firebaseio.com/v0/topstories.json";
-        let response = ureq::get(url).call();
-        assert!(response.is_ok(), "The HN API should be reachable");
+        let ids = HnClient::new().story_ids(SortMode::Hottest);
+        assert!(ids.is_ok(), "The HN API should be reachable");
     }
 }